@@ -0,0 +1,72 @@
+//! Compares the number of candidates that the Chomsky-Schützenberger parser
+//! enumerates until it finds a parse, with and without rejecting
+//! inconsistent candidates during the enumeration.
+//!
+//! Run with `cargo bench --bench consistency_check`.
+
+extern crate log_domain;
+extern crate rustomata;
+
+use log_domain::LogDomain;
+use rustomata::grammars::lcfrs::Lcfrs;
+use rustomata::grammars::lcfrs::csparsing::{CSRepresentation, DebugResult};
+
+use std::fs::File;
+use std::io::Read;
+use std::time::Duration;
+
+const SENTENCES: &[&str] = &[
+    "a c",
+    "b d",
+    "a b c d",
+    "a a b c c d",
+    "a b b c d d",
+    "a a b b c c d d",
+    "a a a b b c c c d d",
+    "a a a b b b c c c d d d",
+    "a a b b c d",
+];
+
+/// The number of enumerated candidates and the parse time in milliseconds.
+fn measure(
+    grammar: &CSRepresentation<String, String, LogDomain<f64>>,
+    sentence: &[String],
+    check_consistency: bool,
+) -> (String, f64) {
+    let mut generator = grammar.build_generator();
+    generator.set_candidates(10_000);
+    if !check_consistency {
+        generator.disable_consistency_check();
+    }
+    let (_, _, time, result) = generator.debug(sentence);
+    let candidates = match result {
+        DebugResult::Parse(_, candidates) => candidates.to_string(),
+        DebugResult::Fallback(_, candidates, _) => format!("{} (no parse)", candidates),
+        DebugResult::Noparse(_) => String::from("- (no parse)"),
+    };
+    (candidates, millis(time))
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1e3 + f64::from(duration.subsec_nanos()) / 1e6
+}
+
+fn main() {
+    let mut grammar_string = String::new();
+    File::open("examples/example.pmcfg")
+        .and_then(|mut file| file.read_to_string(&mut grammar_string))
+        .expect("could not read examples/example.pmcfg");
+    let lcfrs: Lcfrs<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+    let grammar = CSRepresentation::new(lcfrs, 0).unwrap();
+
+    println!("sentence\tcandidates (checked)\tcandidates (unchecked)\tms (checked)\tms (unchecked)");
+    for sentence in SENTENCES {
+        let words: Vec<String> = sentence.split_whitespace().map(String::from).collect();
+        let (checked, checked_time) = measure(&grammar, &words, true);
+        let (unchecked, unchecked_time) = measure(&grammar, &words, false);
+        println!(
+            "{}\t{}\t{}\t{:.3}\t{:.3}",
+            sentence, checked, unchecked, checked_time, unchecked_time
+        );
+    }
+}
@@ -0,0 +1,14 @@
+% A minimalist grammar for a fragment of English with wh-movement of
+% subjects and objects.
+% It has no items with an empty word, such that it can also be parsed
+% with a Chomsky-Schützenberger representation.
+initial: [c]
+
+John :: d                        # 0.4
+Mary :: d                        # 0.4
+who :: d -wh                     # 0.2
+sleeps :: =d c                   # 0.4
+sleeps :: =d +wh c               # 0.1
+sees :: =d =d c                  # 0.4
+sees :: =d +wh c                 # 0.1
+sees :: =d =d +wh c              # 0.1
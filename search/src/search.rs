@@ -139,16 +139,14 @@ pub mod unique {
         fn next(&mut self) -> Option<Self::Item> {
             let &mut Search{ref mut search, ref mut elements} = self;
             
-            if let Some(item) = search.agenda.pop() {
+            while let Some(item) = search.agenda.pop() {
+                // an item may be on the agenda several times
                 if elements.insert(item.clone()) {
                     search.agenda.extend((search.successors)(&item).into_iter().filter(|i| !elements.contains(i)));
-                    Some(item)
-                } else {
-                    None
+                    return Some(item);
                 }
-            } else {
-                None
             }
+            None
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,376 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::fmt::Display;
+use std::hash::Hash;
+
+use approximation::equivalence_classes::EquivalenceRelation;
+use grammars::cfg::{CFG, LetterT};
+use grammars::pmcfg::{PMCFG, VarT};
+
+/// Strategies to induce an `EquivalenceRelation` on the nonterminals of a grammar.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClassInduction {
+    /// Merges nonterminals whose labels coincide after removing annotations, see `strip_annotations`.
+    Prefix,
+    /// Merges nonterminals with the same fanout.
+    Fanout,
+    /// Merges nonterminals of the same fanout that are used similarly in the rules of the grammar
+    /// until (at most) the given number of classes remains.
+    Clustering(usize),
+}
+
+/// The parts of a rule that are relevant to the induction of equivalence classes.
+#[derive(Clone, Debug)]
+struct RuleSummary<N> {
+    head: N,
+    tail: Vec<N>,
+    fanout: usize,
+    lexical: bool,
+}
+
+/// A feature of a nonterminal that is counted for `ClassInduction::Clustering`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Feature {
+    /// occurs as head of a rule with the given rank and (non-)lexical composition
+    Head(usize, bool),
+    /// occurs as the successor at the given position of a rule with the given rank
+    Successor(usize, usize),
+}
+
+/// Removes the annotations that are added to a label during binarization and markovization
+/// (as done by disco-dop), i.e. everything starting from the first `'|'` or `'^'`.
+///
+/// ```
+/// use rustomata::approximation::class_induction::strip_annotations;
+///
+/// assert_eq!("NP", strip_annotations("NP|<DT,NN>"));
+/// assert_eq!("VP", strip_annotations("VP^<S>"));
+/// assert_eq!("S", strip_annotations("S"));
+/// ```
+pub fn strip_annotations(label: &str) -> &str {
+    match label.find(|c| c == '|' || c == '^') {
+        Some(0) | None => label,
+        Some(i) => &label[..i],
+    }
+}
+
+/// Induces an `EquivalenceRelation` on the nonterminals of a `PMCFG`.
+pub fn induce_from_pmcfg<N, T, W>(
+    grammar: &PMCFG<N, T, W>,
+    strategy: &ClassInduction,
+) -> EquivalenceRelation<N, String>
+where
+    N: Clone + Display + Eq + Hash + Ord,
+{
+    let summaries = grammar
+        .rules
+        .iter()
+        .map(|r| {
+            RuleSummary {
+                head: r.head.clone(),
+                tail: r.tail.clone(),
+                fanout: r.composition.composition.len(),
+                lexical: r.composition.composition.iter().any(|component| {
+                    component.iter().any(|x| match *x {
+                        VarT::T(_) => true,
+                        VarT::Var(_, _) => false,
+                    })
+                }),
+            }
+        })
+        .collect();
+    induce(summaries, strategy)
+}
+
+/// Induces an `EquivalenceRelation` on the nonterminals of a `CFG`.
+pub fn induce_from_cfg<N, T, W>(
+    grammar: &CFG<N, T, W>,
+    strategy: &ClassInduction,
+) -> EquivalenceRelation<N, String>
+where
+    N: Clone + Display + Eq + Hash + Ord,
+{
+    let summaries = grammar
+        .rules
+        .iter()
+        .map(|r| {
+            let mut tail = Vec::new();
+            let mut lexical = false;
+            for x in &r.composition.composition {
+                match *x {
+                    LetterT::Label(ref nt) => tail.push(nt.clone()),
+                    LetterT::Value(_) => lexical = true,
+                }
+            }
+            RuleSummary {
+                head: r.head.clone(),
+                tail,
+                fanout: 1,
+                lexical,
+            }
+        })
+        .collect();
+    induce(summaries, strategy)
+}
+
+fn induce<N>(rules: Vec<RuleSummary<N>>, strategy: &ClassInduction) -> EquivalenceRelation<N, String>
+where
+    N: Clone + Display + Eq + Hash + Ord,
+{
+    let mut fanouts = BTreeMap::new();
+    for rule in &rules {
+        fanouts.insert(rule.head.clone(), rule.fanout);
+    }
+
+    let classes: Vec<(String, Vec<N>)> = match *strategy {
+        ClassInduction::Prefix => {
+            let mut classes: BTreeMap<String, Vec<N>> = BTreeMap::new();
+            for nt in fanouts.keys() {
+                classes
+                    .entry(strip_annotations(&nt.to_string()).to_string())
+                    .or_insert_with(Vec::new)
+                    .push(nt.clone());
+            }
+            classes.into_iter().collect()
+        }
+        ClassInduction::Fanout => {
+            let mut classes: BTreeMap<usize, Vec<N>> = BTreeMap::new();
+            for (nt, &fanout) in &fanouts {
+                classes.entry(fanout).or_insert_with(Vec::new).push(nt.clone());
+            }
+            classes
+                .into_iter()
+                .map(|(fanout, nts)| (format!("f{}", fanout), nts))
+                .collect()
+        }
+        ClassInduction::Clustering(size) => {
+            cluster(&rules, &fanouts, size)
+                .into_iter()
+                .map(|nts| (nts[0].to_string(), nts))
+                .collect()
+        }
+    };
+
+    let labels: HashSet<_> = classes.iter().map(|&(ref label, _)| label.clone()).collect();
+    let mut default = String::from("default");
+    while labels.contains(&default) {
+        default.push('\'');
+    }
+
+    let map = classes
+        .into_iter()
+        .map(|(label, nts)| (label, nts.into_iter().collect()))
+        .collect();
+    EquivalenceRelation::new(map, default)
+}
+
+/// Agglomerative clustering of the nonterminals with respect to the relative frequencies of
+/// their `Feature`s. The two closest clusters (w.r.t. the L1 distance of their average
+/// frequencies) that have the same fanout are merged until at most `size` clusters remain or no
+/// more clusters can be merged.
+/// The distances of all pairs of clusters are kept in a priority queue, hence each merge only
+/// computes the distances to the merged cluster, i.e. it takes O(n² log n) time for `n`
+/// nonterminals.
+/// Returns the clusters in ascending order with their members in ascending order.
+fn cluster<N>(rules: &[RuleSummary<N>], fanouts: &BTreeMap<N, usize>, size: usize) -> Vec<Vec<N>>
+where
+    N: Clone + Eq + Hash + Ord,
+{
+    let mut head_counts: HashMap<&N, BTreeMap<Feature, f64>> = HashMap::new();
+    let mut successor_counts: HashMap<&N, BTreeMap<Feature, f64>> = HashMap::new();
+    for rule in rules {
+        *head_counts
+            .entry(&rule.head)
+            .or_insert_with(BTreeMap::new)
+            .entry(Feature::Head(rule.tail.len(), rule.lexical))
+            .or_insert(0.0) += 1.0;
+        for (i, nt) in rule.tail.iter().enumerate() {
+            *successor_counts
+                .entry(nt)
+                .or_insert_with(BTreeMap::new)
+                .entry(Feature::Successor(rule.tail.len(), i))
+                .or_insert(0.0) += 1.0;
+        }
+    }
+
+    let normalised = |counts: Option<&BTreeMap<Feature, f64>>| -> BTreeMap<Feature, f64> {
+        match counts {
+            Some(counts) => {
+                let total: f64 = counts.values().sum();
+                counts.iter().map(|(f, &c)| (f.clone(), c / total)).collect()
+            }
+            None => BTreeMap::new(),
+        }
+    };
+
+    // each cluster consists of its members, its fanout, and its average feature frequencies
+    let clusters: Vec<(Vec<N>, usize, BTreeMap<Feature, f64>)> = fanouts
+        .iter()
+        .map(|(nt, &fanout)| {
+            let mut features = normalised(head_counts.get(nt));
+            features.extend(normalised(successor_counts.get(nt)));
+            (vec![nt.clone()], fanout, features)
+        })
+        .collect();
+
+    let distance = |f1: &BTreeMap<Feature, f64>, f2: &BTreeMap<Feature, f64>| -> f64 {
+        let mut d: f64 = f1.iter()
+            .map(|(f, &v)| (v - f2.get(f).cloned().unwrap_or(0.0)).abs())
+            .sum();
+        d += f2.iter()
+            .filter(|&(f, _)| !f1.contains_key(f))
+            .map(|(_, &v)| v)
+            .sum::<f64>();
+        d
+    };
+
+    // candidate merges, closest first; candidates with a merged cluster are skipped
+    let mut candidates = BinaryHeap::new();
+    for i in 0..clusters.len() {
+        for j in i + 1..clusters.len() {
+            if clusters[i].1 == clusters[j].1 {
+                let d = distance(&clusters[i].2, &clusters[j].2);
+                candidates.push(Candidate { distance: d, i, j });
+            }
+        }
+    }
+
+    let mut clusters: Vec<Option<_>> = clusters.into_iter().map(Some).collect();
+    let mut remaining = clusters.len();
+    while remaining > size {
+        let (i, j) = match candidates.pop() {
+            Some(Candidate { i, j, .. }) => (i, j),
+            None => break,
+        };
+        if clusters[i].is_none() || clusters[j].is_none() {
+            continue;
+        }
+
+        let (mut members1, fanout, features1) = clusters[i].take().unwrap();
+        let (members2, _, features2) = clusters[j].take().unwrap();
+        let (n1, n2) = (members1.len() as f64, members2.len() as f64);
+        let mut merged = BTreeMap::new();
+        for (f, v) in features1 {
+            merged.insert(f, v * n1 / (n1 + n2));
+        }
+        for (f, v) in features2 {
+            *merged.entry(f).or_insert(0.0) += v * n2 / (n1 + n2);
+        }
+        members1.extend(members2);
+        members1.sort();
+
+        let k = clusters.len();
+        for (l, cluster) in clusters.iter().enumerate() {
+            if let Some((_, fanout_l, ref features_l)) = *cluster {
+                if fanout_l == fanout {
+                    let d = distance(features_l, &merged);
+                    candidates.push(Candidate { distance: d, i: l, j: k });
+                }
+            }
+        }
+        clusters.push(Some((members1, fanout, merged)));
+        remaining -= 1;
+    }
+
+    let mut clusters: Vec<_> = clusters
+        .into_iter()
+        .filter_map(|cluster| cluster.map(|(members, _, _)| members))
+        .collect();
+    clusters.sort();
+    clusters
+}
+
+/// A merge of the clusters `i` and `j` in `cluster`, ordered such that the closest clusters are
+/// the greatest element; ties are broken by the indices of the clusters.
+#[derive(Debug)]
+struct Candidate {
+    distance: f64,
+    i: usize,
+    j: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .distance
+            .partial_cmp(&self.distance)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| (other.i, other.j).cmp(&(self.i, self.j)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+
+    fn example_pmcfg() -> PMCFG<String, String, LogDomain<f64>> {
+        "initial: [S]\n\n\
+         S → [[Var 0 0, Var 1 0, Var 0 1, Var 1 1]] (\"A|<a>\", B^S)\n\
+         \"A|<a>\" → [[T a, Var 0 0], [T c, Var 0 1]] (A) # 0.5\n\
+         A → [[], []] () # 0.5\n\
+         B^S → [[T b, Var 0 0], [T d, Var 0 1]] (B) # 0.5\n\
+         B → [[], []] () # 0.5"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_induce_prefix() {
+        let rel = induce_from_pmcfg(&example_pmcfg(), &ClassInduction::Prefix);
+        let control_rel: EquivalenceRelation<String, String> =
+            "S [S]\nA [A, \"A|<a>\"]\nB [B, B^S]\ndefault *".parse().unwrap();
+        assert_eq!(control_rel, rel);
+    }
+
+    #[test]
+    fn test_induce_fanout() {
+        let rel = induce_from_pmcfg(&example_pmcfg(), &ClassInduction::Fanout);
+        let control_rel: EquivalenceRelation<String, String> =
+            "f1 [S]\nf2 [A, \"A|<a>\", B, B^S]\ndefault *".parse().unwrap();
+        assert_eq!(control_rel, rel);
+    }
+
+    #[test]
+    fn test_induce_clustering() {
+        let grammar = example_pmcfg();
+
+        // nonterminals of different fanouts are never merged
+        let rel = induce_from_pmcfg(&grammar, &ClassInduction::Clustering(1));
+        let control_rel = induce_from_pmcfg(&grammar, &ClassInduction::Fanout);
+        for nt in &["S", "A", "A|<a>", "B", "B^S"] {
+            let nt = nt.to_string();
+            assert_eq!(
+                rel.project(&nt) == rel.project(&String::from("S")),
+                control_rel.project(&nt) == control_rel.project(&String::from("S"))
+            );
+        }
+
+        // nonterminals that are used alike are merged first
+        let rel = induce_from_pmcfg(&grammar, &ClassInduction::Clustering(3));
+        let control_rel: EquivalenceRelation<String, String> =
+            "S [S]\nA [A, B]\n\"A|<a>\" [\"A|<a>\", B^S]\ndefault *".parse().unwrap();
+        assert_eq!(control_rel, rel);
+    }
+
+    #[test]
+    fn test_induced_classes_display_inverse() {
+        let rel = induce_from_pmcfg(&example_pmcfg(), &ClassInduction::Prefix);
+        let reparsed: EquivalenceRelation<String, String> = rel.to_string().parse().unwrap();
+        assert_eq!(rel, reparsed);
+    }
+}
@@ -1,7 +1,7 @@
 use nom::{IResult, is_space};
 use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Entry;
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::hash::Hash;
 use std::iter::FromIterator;
 use std::str::FromStr;
@@ -126,6 +126,38 @@ where
     }
 }
 
+/// Writes one class per line (in the format of classes files), the default class last.
+/// Labels and elements are delimited by `'"'` and escaped, see `util::parsing::escape`.
+impl<A, B> fmt::Display for EquivalenceRelation<A, B>
+where
+    A: Eq + Hash + fmt::Display,
+    B: Eq + Hash + fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut classes: HashMap<&B, Vec<String>> = HashMap::new();
+        for (element, label) in &self.map {
+            classes
+                .entry(label)
+                .or_insert_with(Vec::new)
+                .push(format!("\"{}\"", escape(&element.to_string())));
+        }
+
+        let mut lines: Vec<String> = classes
+            .into_iter()
+            .map(|(label, mut elements)| {
+                elements.sort();
+                format!("\"{}\" [{}]", escape(&label.to_string()), elements.join(", "))
+            })
+            .collect();
+        lines.sort();
+
+        for line in lines {
+            writeln!(f, "{}", line)?;
+        }
+        write!(f, "\"{}\" *", escape(&self.default.to_string()))
+    }
+}
+
 impl<A, B> FromStr for EquivalenceRelation<A, B>
 where
     A: Clone + Eq + Hash + FromStr,
@@ -191,7 +223,7 @@ where
 {
     do_parse!(
         input,
-        label: parse_token >>
+        label: parse_unescaped_token >>
             take_while!(is_space) >>
             set: alt!(
                 do_parse!(
@@ -216,7 +248,7 @@ where
 {
     do_parse!(
         input,
-        output: apply!(parse_vec, parse_token, "[", "]", ",") >>
+        output: apply!(parse_vec, parse_unescaped_token, "[", "]", ",") >>
         (HashSet::from_iter(output))
     )
 }
@@ -358,6 +390,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_equivalence_relation_display_inverse() {
+        let relation: EquivalenceRelation<String, String> = EquivalenceRelation::from(vec![
+            EquivalenceClass::from((String::from("A\"B"), Some(vec![String::from("C\\D"), String::from("\"")]))),
+            EquivalenceClass::from((String::from("\\"), None)),
+        ]);
+
+        assert_eq!(relation, relation.to_string().parse().unwrap());
+    }
+
     #[test]
     fn test_equivalence_relation_from_str_illegal_input() {
         let illegal_inputs = vec![
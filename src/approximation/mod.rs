@@ -6,9 +6,12 @@ use recognisable::{Instruction, Transition};
 use recognisable::automaton::Automaton;
 use util::push_down::Pushdown;
 
+pub mod class_induction;
 pub mod equivalence_classes;
+pub mod ptk;
 pub mod relabel;
 pub mod tts;
+pub mod tsu;
 
 /// Object defining the strategies used for `approximation`
 pub trait ApproximationStrategy<T, W>: Sized
@@ -30,21 +33,65 @@ where
         <Self::I1 as Instruction>::Storage,
     ) -> <Self::I2 as Instruction>::Storage;
 
-    fn approximate_instruction(&self, &Self::I1) -> Self::I2;
-
     fn approximate_automaton(
         self,
         automaton1: &Self::A1,
-    ) -> (Self::A2, ApproximationInstance<Self, T, W>) {
-        let mut instance = ApproximationInstance::new(self);
-        let transitions2: Vec<_> = automaton1
-            .transitions()
-            .map(|t| instance.approximate_transition(t.clone()))
-            .collect();
-        let initial2 = instance.approximate_storage(automaton1.initial());
-
-        (Self::A2::from_transitions(transitions2, initial2), instance)
-    }
+    ) -> (Self::A2, ApproximationInstance<Self, T, W>);
+}
+
+/// An `ApproximationStrategy` that approximates each instruction on its own, independently of
+/// the storages it is applied to.
+pub trait InstructionApproximation<T, W>: ApproximationStrategy<T, W>
+where
+    Self::I1: Clone + Eq + Instruction + Ord,
+    Self::I2: Clone + Eq + Instruction + Ord,
+    Self::A1: Automaton<T, W, I = Self::I1>,
+    Self::A2: Automaton<T, W, I = Self::I2> + Sized,
+    T: Clone + Eq + Ord,
+    W: Clone + MulAssign + One + Ord,
+{
+    fn approximate_instruction(&self, &Self::I1) -> Self::I2;
+}
+
+/// Approximates `automaton1` transition by transition, i.e. the `approximate_automaton` of an
+/// `InstructionApproximation`.
+pub fn approximate_instructionwise<Strategy, T, W>(
+    strategy: Strategy,
+    automaton1: &Strategy::A1,
+) -> (Strategy::A2, ApproximationInstance<Strategy, T, W>)
+where
+    Strategy: InstructionApproximation<T, W>,
+    Strategy::I1: Clone + Eq + Instruction + Ord,
+    Strategy::I2: Clone + Eq + Instruction + Ord,
+    Strategy::A1: Automaton<T, W, I = Strategy::I1>,
+    Strategy::A2: Automaton<T, W, I = Strategy::I2> + Sized,
+    T: Clone + Eq + Ord,
+    W: Clone + MulAssign + One + Ord,
+{
+    let mut instance = ApproximationInstance::new(strategy);
+    let transitions2: Vec<_> = automaton1
+        .transitions()
+        .map(|t| instance.approximate_transition(t.clone()))
+        .collect();
+    let initial2 = instance.approximate_storage(automaton1.initial());
+
+    (Strategy::A2::from_transitions(transitions2, initial2), instance)
+}
+
+/// An `ApproximationStrategy` whose resulting automaton recognises a subset of the runs of the
+/// original automaton, i.e. `ApproximationInstance::unapproximate_run` maps every run of the
+/// resulting automaton to at least one valid run of the original automaton.
+/// A `CoarseToFineRecogniser` with such a strategy never discards a candidate, but it may miss
+/// runs of the original automaton.
+pub trait SubsetApproximation<T, W>: ApproximationStrategy<T, W>
+where
+    Self::I1: Clone + Eq + Instruction + Ord,
+    Self::I2: Clone + Eq + Instruction + Ord,
+    Self::A1: Automaton<T, W, I = Self::I1>,
+    Self::A2: Automaton<T, W, I = Self::I2> + Sized,
+    T: Clone + Eq + Ord,
+    W: Clone + MulAssign + One + Ord,
+{
 }
 
 pub struct ApproximationInstance<Strategy, T, W>
@@ -81,24 +128,17 @@ where
         self.strategy.approximate_storage(s1)
     }
 
-    pub fn approximate_instruction(&self, i1: &Strategy::I1) -> Strategy::I2 {
-        self.strategy.approximate_instruction(i1)
-    }
-
-    pub fn approximate_transition(
+    /// Remembers that `t2` approximates `t1`.
+    /// Used by strategies that do not approximate transitions one by one.
+    pub fn add_transition(
         &mut self,
         t1: Transition<Strategy::I1, T, W>,
-    ) -> Transition<Strategy::I2, T, W> {
-        let t2 = Transition {
-            word: t1.word.clone(),
-            instruction: self.approximate_instruction(&t1.instruction),
-            weight: t1.weight.clone(),
-        };
+        t2: Transition<Strategy::I2, T, W>,
+    ) {
         self.reverse_transition_map
-            .entry(t2.clone())
+            .entry(t2)
             .or_insert(Vec::new())
             .push(t1);
-        t2
     }
 
     pub fn unapproximate_transition(
@@ -131,3 +171,29 @@ where
             .fold(initial_heap, f)
     }
 }
+
+impl<Strategy, T, W> ApproximationInstance<Strategy, T, W>
+where
+    Strategy: InstructionApproximation<T, W>,
+    Strategy::I2: Clone + Eq + Ord,
+    Strategy::I1: Clone + Eq + Ord,
+    T: Clone + Eq + Ord,
+    W: Clone + MulAssign + One + Ord,
+{
+    pub fn approximate_instruction(&self, i1: &Strategy::I1) -> Strategy::I2 {
+        self.strategy.approximate_instruction(i1)
+    }
+
+    pub fn approximate_transition(
+        &mut self,
+        t1: Transition<Strategy::I1, T, W>,
+    ) -> Transition<Strategy::I2, T, W> {
+        let t2 = Transition {
+            word: t1.word.clone(),
+            instruction: self.approximate_instruction(&t1.instruction),
+            weight: t1.weight.clone(),
+        };
+        self.add_transition(t1, t2.clone());
+        t2
+    }
+}
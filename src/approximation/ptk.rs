@@ -0,0 +1,312 @@
+use num_traits::Zero;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::ops::{AddAssign, MulAssign};
+
+use approximation::*;
+use automata::finite_state_automaton::{FiniteStateAutomaton, FiniteStateInstruction};
+use automata::push_down_automaton::*;
+
+/// The topmost elements of a `PushDown`.
+/// If `truncated` is set, then an unknown (possibly empty) sequence of symbols lies below
+/// `elements`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LimitedPushDown<A> {
+    elements: Vec<A>,
+    truncated: bool,
+}
+
+impl<A: Clone + PartialEq> LimitedPushDown<A> {
+    /// Keeps the topmost `size` of the given `elements` (bottom first).
+    fn new(mut elements: Vec<A>, truncated: bool, size: usize) -> Self {
+        if elements.len() > size {
+            let cut = elements.len() - size;
+            elements.drain(..cut);
+            LimitedPushDown { elements, truncated: true }
+        } else {
+            LimitedPushDown { elements, truncated }
+        }
+    }
+
+    /// Returns whether this represents a `PushDown` that contains only its empty symbol.
+    pub fn is_bottom(&self) -> bool {
+        self.elements.len() == 1 || self.truncated && self.elements.is_empty()
+    }
+
+    /// Applies `PushDownInstruction::Replace` to all `PushDown`s that are represented by `self`.
+    /// As in `PushDown::replace`, `current_val` is given topmost symbol first.
+    /// Symbols of `current_val` that lie below the known elements are accepted as they are.
+    fn replace(&self, current_val: &[A], new_val: &[A], size: usize) -> Option<Self> {
+        let n = self.elements.len();
+        let matches = current_val.iter().zip(self.elements.iter().rev()).all(|(a, b)| a == b);
+        if !matches || current_val.len() > n && !self.truncated {
+            return None;
+        }
+
+        let mut elements = self.elements[..n - current_val.len().min(n)].to_vec();
+        elements.extend(new_val.iter().cloned());
+        Some(LimitedPushDown::new(elements, self.truncated, size))
+    }
+}
+
+impl<A: fmt::Display> fmt::Display for LimitedPushDown<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut buffer = String::new();
+        if self.truncated {
+            buffer.push_str("…");
+        }
+        for a in &self.elements {
+            if !buffer.is_empty() {
+                buffer.push_str(",");
+            }
+            buffer.push_str(&a.to_string());
+        }
+        write!(f, "[{}]", buffer)
+    }
+}
+
+/// `ApproximationStrategy` that limits the pushdown of a `PushDownAutomaton` to its topmost
+/// `size` elements.
+/// Since there are only finitely many such pushdowns, the result is a `FiniteStateAutomaton`
+/// whose states are the reachable `LimitedPushDown`s.
+#[derive(Clone, Debug)]
+pub struct PDTopKElement<A> {
+    _dummy: PhantomData<A>,
+    pub size: usize,
+}
+
+impl<A> PDTopKElement<A> {
+    pub fn new(size: usize) -> Self {
+        assert!(size >= 1);
+        PDTopKElement {
+            _dummy: PhantomData,
+            size,
+        }
+    }
+}
+
+impl<A, T, W> ApproximationStrategy<T, W> for PDTopKElement<A>
+where
+    A: Clone + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: AddAssign + Copy + MulAssign + One + Ord + Zero,
+{
+    type I1 = PushDownInstruction<A>;
+    type I2 = FiniteStateInstruction<LimitedPushDown<A>>;
+    type A1 = PushDownAutomaton<A, T, W>;
+    type A2 = FiniteStateAutomaton<LimitedPushDown<A>, T, W>;
+
+    fn approximate_storage(&self, pd: PushDown<A>) -> LimitedPushDown<A> {
+        LimitedPushDown::new(pd.iter().cloned().collect(), false, self.size)
+    }
+
+    /// Explores the `LimitedPushDown`s that are reachable from the initial pushdown and adds a
+    /// transition to the `FiniteStateAutomaton` for each applicable transition of `automaton1`.
+    /// The final states are those `LimitedPushDown`s that may represent the empty pushdown.
+    fn approximate_automaton(
+        self,
+        automaton1: &PushDownAutomaton<A, T, W>,
+    ) -> (FiniteStateAutomaton<LimitedPushDown<A>, T, W>, ApproximationInstance<Self, T, W>) {
+        let size = self.size;
+        let mut by_top: HashMap<Option<A>, Vec<Transition<PushDownInstruction<A>, T, W>>> =
+            HashMap::new();
+        for t in automaton1.list_transitions() {
+            let top = match t.instruction {
+                PushDownInstruction::Replace { ref current_val, .. } => current_val.first().cloned(),
+            };
+            by_top.entry(top).or_insert_with(Vec::new).push(t);
+        }
+
+        let initial =
+            <Self as ApproximationStrategy<T, W>>::approximate_storage(&self, automaton1.initial());
+        let mut instance = ApproximationInstance::new(self);
+        let mut transitions2 = Vec::new();
+        let mut finals = Vec::new();
+        let mut discovered = HashSet::new();
+        discovered.insert(initial.clone());
+        let mut queue = vec![initial.clone()];
+
+        while let Some(q) = queue.pop() {
+            if q.is_bottom() {
+                finals.push(q.clone());
+            }
+
+            let applicable: Vec<&Transition<_, _, _>> = match q.elements.last() {
+                // the topmost symbol is unknown, hence every transition may be applicable
+                None if q.truncated => by_top.values().flat_map(|ts| ts.iter()).collect(),
+                None => by_top.get(&None).into_iter().flat_map(|ts| ts.iter()).collect(),
+                Some(top) => {
+                    let with_top = by_top.get(&Some(top.clone())).into_iter().flat_map(|ts| ts.iter());
+                    let without_top = by_top.get(&None).into_iter().flat_map(|ts| ts.iter());
+                    with_top.chain(without_top).collect()
+                }
+            };
+
+            for t1 in applicable {
+                let target = match t1.instruction {
+                    PushDownInstruction::Replace {
+                        ref current_val,
+                        ref new_val,
+                    } => q.replace(current_val, new_val, size),
+                };
+
+                if let Some(q1) = target {
+                    if discovered.insert(q1.clone()) {
+                        queue.push(q1.clone());
+                    }
+                    let t2 = Transition {
+                        word: t1.word.clone(),
+                        weight: t1.weight,
+                        instruction: FiniteStateInstruction::new(q.clone(), q1),
+                    };
+                    instance.add_transition(t1.clone(), t2.clone());
+                    transitions2.push(t2);
+                }
+            }
+        }
+
+        (FiniteStateAutomaton::new(transitions2, initial, finals), instance)
+    }
+}
+
+impl<A, T, W> InstructionApproximation<T, W> for PDTopKElement<A>
+where
+    A: Clone + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: AddAssign + Copy + MulAssign + One + Ord + Zero,
+{
+    /// Approximates the instruction as it acts on a pushdown whose lower part is unknown, i.e.
+    /// the resulting instruction is applicable to every `LimitedPushDown` that represents the
+    /// pushdowns the instruction is applicable to.
+    /// This is the instruction of `ApproximationInstance::approximate_transition`;
+    /// `approximate_automaton` instead constructs an instruction for each reachable
+    /// `LimitedPushDown`, which is more precise.
+    fn approximate_instruction(
+        &self,
+        instr: &PushDownInstruction<A>,
+    ) -> FiniteStateInstruction<LimitedPushDown<A>> {
+        match *instr {
+            PushDownInstruction::Replace {
+                ref current_val,
+                ref new_val,
+            } => {
+                let popped = current_val.iter().rev().cloned().collect();
+                FiniteStateInstruction::new(
+                    LimitedPushDown::new(popped, true, self.size),
+                    LimitedPushDown::new(new_val.clone(), true, self.size),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grammars::cfg::CFG;
+    use log_domain::LogDomain;
+    use recognisable::Recognisable;
+
+    #[test]
+    fn test_approximate_storage() {
+        let ptk = PDTopKElement::new(3);
+        let inputs = vec![
+            (vec!['@', 'a'], LimitedPushDown { elements: vec!['@', 'a'], truncated: false }),
+            (vec!['@', 'a', 'b'], LimitedPushDown { elements: vec!['@', 'a', 'b'], truncated: false }),
+            (vec!['@', 'a', 'b', 'c'], LimitedPushDown { elements: vec!['a', 'b', 'c'], truncated: true }),
+        ];
+
+        for (elements, control_pushdown) in inputs {
+            assert_eq!(
+                control_pushdown,
+                <PDTopKElement<_> as ApproximationStrategy<char, u8>>::approximate_storage(
+                    &ptk,
+                    PushDown::from(elements),
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_approximate_instruction() {
+        let ptk = PDTopKElement::new(3);
+        let instruction = PushDownInstruction::Replace {
+            current_val: vec!['b', 'a'],
+            new_val: vec!['c', 'd'],
+        };
+
+        assert_eq!(
+            FiniteStateInstruction::new(
+                LimitedPushDown { elements: vec!['a', 'b'], truncated: true },
+                LimitedPushDown { elements: vec!['c', 'd'], truncated: true },
+            ),
+            <PDTopKElement<_> as InstructionApproximation<char, u8>>::approximate_instruction(
+                &ptk,
+                &instruction,
+            )
+        );
+
+        // consistent with the instructions of `approximate_automaton`
+        let truncated = LimitedPushDown { elements: vec!['a', 'b'], truncated: true };
+        assert_eq!(
+            Some(LimitedPushDown { elements: vec!['c', 'd'], truncated: true }),
+            truncated.replace(&['b', 'a'], &['c', 'd'], 3)
+        );
+    }
+
+    #[test]
+    fn test_limited_push_down_replace() {
+        let exact = LimitedPushDown { elements: vec!['@', 'a', 'b'], truncated: false };
+        let truncated = LimitedPushDown { elements: vec!['a', 'b'], truncated: true };
+
+        assert_eq!(
+            Some(LimitedPushDown { elements: vec!['a', 'c', 'd'], truncated: true }),
+            exact.replace(&['b'], &['c', 'd'], 3)
+        );
+        assert_eq!(
+            Some(LimitedPushDown { elements: vec!['@'], truncated: false }),
+            exact.replace(&['b', 'a'], &[], 3)
+        );
+        assert_eq!(None, exact.replace(&['b', 'a', '@', 'x'], &[], 3));
+        assert_eq!(None, exact.replace(&['a'], &[], 3));
+        assert_eq!(
+            Some(LimitedPushDown { elements: vec!['c'], truncated: true }),
+            truncated.replace(&['b', 'a', 'x'], &['c'], 3)
+        );
+        assert_eq!(None, truncated.replace(&['b', 'x', 'a'], &['c'], 3));
+    }
+
+    #[test]
+    fn test_ptk_superset() {
+        let g: CFG<String, String, LogDomain<f64>> =
+            "initial: [A]\n\
+             \n\
+             A → [T a, Nt A, T b]  # 0.6\n\
+             A → []                # 0.4"
+                .parse()
+                .unwrap();
+        let a = PushDownAutomaton::from(g);
+        let ptk = PDTopKElement::new(3);
+        let (b, _) = ptk.approximate_automaton(&a);
+
+        let true_positives_and_true_negatives = vec!["", "ab", "aabb", "aaabbb", "ba", "bab"];
+
+        for input in true_positives_and_true_negatives {
+            let word: Vec<_> = input.chars().map(|x| x.to_string()).collect();
+            assert_eq!(
+                a.recognise(word.clone()).next().is_some(),
+                b.recognise(word).next().is_some()
+            );
+        }
+
+        let false_positives = vec!["aab", "aaabb"];
+
+        for input in false_positives {
+            let word: Vec<_> = input.chars().map(|x| x.to_string()).collect();
+            assert_eq!(false, a.recognise(word.clone()).next().is_some());
+            assert_eq!(true, b.recognise(word).next().is_some());
+        }
+    }
+}
@@ -35,6 +35,19 @@ impl<'a, A1, A2, T, W> ApproximationStrategy<T, W> for RlbElement<'a, A1, A2>
         pd.map(&self.mapping)
     }
 
+    fn approximate_automaton(self, automaton1: &PushDownAutomaton<A1, T, W>)
+                             -> (PushDownAutomaton<A2, T, W>, ApproximationInstance<Self, T, W>)
+    {
+        approximate_instructionwise(self, automaton1)
+    }
+}
+
+impl<'a, A1, A2, T, W> InstructionApproximation<T, W> for RlbElement<'a, A1, A2>
+    where A1: Clone + Hash + Ord,
+          A2: Clone + Hash + Ord,
+          T: Clone + Eq + Hash + Ord,
+          W: AddAssign + Copy + MulAssign + One + Ord + Zero,
+{
     fn approximate_instruction(&self, instr: &PushDownInstruction<A1>)
                                -> PushDownInstruction<A2>
     {
@@ -105,7 +118,7 @@ mod tests {
 
         assert_eq!(
             control_instruction,
-            <RlbElement<_, _> as ApproximationStrategy<char, u8>>::approximate_instruction(
+            <RlbElement<_, _> as InstructionApproximation<char, u8>>::approximate_instruction(
                 &rlb,
                 &instruction,
             )
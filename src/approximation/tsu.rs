@@ -0,0 +1,370 @@
+use num_traits::Zero;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::ops::{AddAssign, MulAssign};
+
+use approximation::*;
+use automata::push_down_automaton::*;
+use automata::tree_stack_automaton::*;
+use util::tree::GornTree;
+
+/// A node on the path from the root of a `TreeStack` to its stack pointer.
+/// `children` contains the occupied child positions of the node.
+/// The subtree below such a position is either remembered (`Some`) or forgotten (`None`).
+/// The child position that continues the path is always forgotten.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TreeStackFrame<A> {
+    position: Option<usize>,
+    value: A,
+    children: BTreeMap<usize, Option<TreeStackFrame<A>>>,
+}
+
+impl<A: Clone> TreeStackFrame<A> {
+    /// Returns the label of the node.
+    pub fn value(&self) -> &A {
+        &self.value
+    }
+
+    /// Builds the frame for the node at `address` in `tree`; its subtrees are remembered up to
+    /// `depth` levels, except for the child position `path_child`.
+    fn from_tree(
+        tree: &GornTree<A>,
+        address: &[usize],
+        path_child: Option<usize>,
+        depth: usize,
+    ) -> Self {
+        let mut children = BTreeMap::new();
+        for key in tree.keys() {
+            if key.len() == address.len() + 1 && key.starts_with(address) {
+                let n = key[address.len()];
+                let child = if Some(n) == path_child {
+                    None
+                } else {
+                    TreeStackFrame::from_tree(tree, key, None, depth).truncate(depth)
+                };
+                children.insert(n, child);
+            }
+        }
+
+        TreeStackFrame {
+            position: address.last().cloned(),
+            value: tree.get(&address.to_vec()).unwrap().clone(),
+            children,
+        }
+    }
+
+    /// Forgets everything that lies more than `depth` levels below (and including) this node.
+    fn truncate(self, depth: usize) -> Option<Self> {
+        if depth == 0 {
+            None
+        } else {
+            let children = self.children
+                .into_iter()
+                .map(|(n, child)| (n, child.and_then(|c| c.truncate(depth - 1))))
+                .collect();
+            Some(TreeStackFrame { children, ..self })
+        }
+    }
+}
+
+impl<A: fmt::Display> fmt::Display for TreeStackFrame<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)?;
+        if !self.children.is_empty() {
+            let mut buffer = String::new();
+            for (n, child) in &self.children {
+                if !buffer.is_empty() {
+                    buffer.push_str(", ");
+                }
+                match *child {
+                    Some(ref c) => buffer.push_str(&format!("{}: {}", n, c)),
+                    None => buffer.push_str(&format!("{}: …", n)),
+                }
+            }
+            write!(f, "({})", buffer)?;
+        }
+        Ok(())
+    }
+}
+
+/// `ApproximationStrategy` that unrolls a `TreeStackAutomaton` into a `PushDownAutomaton`.
+/// The pushdown holds the path from the root of the tree stack to its stack pointer.
+/// Subtrees that are left via `Down` are only remembered up to `depth` levels.
+/// Runs that go `Up` into a forgotten subtree are lost, hence this is a subset approximation.
+/// It is no `InstructionApproximation`, since the frames of an instruction depend on the frames
+/// that are reachable; e.g. the child position that a `Down` instruction leaves is only known
+/// from the frame below the top of the pushdown.
+#[derive(Clone, Debug)]
+pub struct TSUElement<A> {
+    _dummy: PhantomData<A>,
+    pub depth: usize,
+}
+
+impl<A> TSUElement<A> {
+    pub fn new(depth: usize) -> Self {
+        TSUElement {
+            _dummy: PhantomData,
+            depth,
+        }
+    }
+}
+
+impl<A, T, W> ApproximationStrategy<T, W> for TSUElement<A>
+where
+    A: Clone + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: AddAssign + Copy + MulAssign + One + Ord + Zero,
+{
+    type I1 = TreeStackInstruction<A>;
+    type I2 = PushDownInstruction<TreeStackFrame<A>>;
+    type A1 = TreeStackAutomaton<A, T, W>;
+    type A2 = PushDownAutomaton<TreeStackFrame<A>, T, W>;
+
+    fn approximate_storage(&self, ts: TreeStack<A>) -> PushDown<TreeStackFrame<A>> {
+        let (tree, pointer) = ts.to_tree();
+        let frames: Vec<_> = (0..pointer.len() + 1)
+            .map(|i| {
+                TreeStackFrame::from_tree(&tree, &pointer[..i], pointer.get(i).cloned(), self.depth)
+            })
+            .collect();
+        PushDown::from(frames)
+    }
+
+    /// Explores the frames that are reachable from the initial tree stack and adds a
+    /// transition to the `PushDownAutomaton` for each applicable transition of `automaton1`.
+    /// `Down` transitions are added for every pair of a reachable top frame and a reachable
+    /// frame that may lie directly below it.
+    fn approximate_automaton(
+        self,
+        automaton1: &TreeStackAutomaton<A, T, W>,
+    ) -> (PushDownAutomaton<TreeStackFrame<A>, T, W>, ApproximationInstance<Self, T, W>) {
+        let depth = self.depth;
+        let mut by_current: HashMap<A, Vec<Transition<TreeStackInstruction<A>, T, W>>> =
+            HashMap::new();
+        for t in automaton1.list_transitions() {
+            let current = match t.instruction {
+                TreeStackInstruction::Up { ref current_val, .. } |
+                TreeStackInstruction::Push { ref current_val, .. } |
+                TreeStackInstruction::Down { ref current_val, .. } => current_val.clone(),
+            };
+            by_current.entry(current).or_insert_with(Vec::new).push(t);
+        }
+
+        let initial = <Self as ApproximationStrategy<T, W>>::approximate_storage(
+            &self,
+            automaton1.initial(),
+        );
+        let mut instance = ApproximationInstance::new(self);
+        let mut transitions2 = Vec::new();
+
+        let mut tops = vec![initial.current_symbol().clone()];
+        let mut belows: Vec<TreeStackFrame<A>> = Vec::new();
+        let mut discovered_tops: HashSet<_> = tops.iter().cloned().collect();
+        let mut discovered_belows = HashSet::new();
+        for frame in initial.iter().rev().skip(1) {
+            if discovered_belows.insert(frame.clone()) {
+                belows.push(frame.clone());
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < tops.len() || j < belows.len() {
+            // each pair of a top and a below frame is considered exactly once, namely when the
+            // later of both is processed
+            let (pairs, mut new_tops, mut new_belows) = if i < tops.len() {
+                let top = tops[i].clone();
+                i += 1;
+
+                let mut new_tops = Vec::new();
+                let mut new_belows = Vec::new();
+                for t1 in by_current.get(&top.value).into_iter().flat_map(|ts| ts.iter()) {
+                    let successor = match t1.instruction {
+                        TreeStackInstruction::Up {
+                            n,
+                            ref old_val,
+                            ref new_val,
+                            ..
+                        } => match top.children.get(&n) {
+                            Some(&Some(ref child)) if &child.value == old_val => {
+                                let mut child = child.clone();
+                                child.value = new_val.clone();
+                                Some(child)
+                            }
+                            _ => None,
+                        },
+                        TreeStackInstruction::Push { n, ref new_val, .. } => {
+                            if top.children.contains_key(&n) {
+                                None
+                            } else {
+                                Some(TreeStackFrame {
+                                    position: Some(n),
+                                    value: new_val.clone(),
+                                    children: BTreeMap::new(),
+                                })
+                            }
+                        }
+                        TreeStackInstruction::Down { .. } => None,
+                    };
+
+                    if let Some(child) = successor {
+                        let mut parent = top.clone();
+                        parent.children.insert(child.position.unwrap(), None);
+                        let t2 = Transition {
+                            word: t1.word.clone(),
+                            weight: t1.weight,
+                            instruction: PushDownInstruction::Replace {
+                                current_val: vec![top.clone()],
+                                new_val: vec![parent.clone(), child.clone()],
+                            },
+                        };
+                        instance.add_transition(t1.clone(), t2.clone());
+                        transitions2.push(t2);
+                        new_belows.push(parent);
+                        new_tops.push(child);
+                    }
+                }
+
+                let pairs: Vec<_> = belows[..j].iter().map(|b| (top.clone(), b.clone())).collect();
+                (pairs, new_tops, new_belows)
+            } else {
+                let below = belows[j].clone();
+                j += 1;
+                let pairs = tops[..i].iter().map(|t| (t.clone(), below.clone())).collect();
+                (pairs, Vec::new(), Vec::new())
+            };
+
+            for (top, below) in pairs {
+                let n = match top.position {
+                    Some(n) if below.children.get(&n) == Some(&None) => n,
+                    _ => continue,
+                };
+
+                for t1 in by_current.get(&top.value).into_iter().flat_map(|ts| ts.iter()) {
+                    if let TreeStackInstruction::Down {
+                        ref old_val,
+                        ref new_val,
+                        ..
+                    } = t1.instruction
+                    {
+                        if &below.value != old_val {
+                            continue;
+                        }
+
+                        let mut parent = below.clone();
+                        parent.value = new_val.clone();
+                        parent.children.insert(n, top.clone().truncate(depth));
+                        let t2 = Transition {
+                            word: t1.word.clone(),
+                            weight: t1.weight,
+                            instruction: PushDownInstruction::Replace {
+                                current_val: vec![top.clone(), below.clone()],
+                                new_val: vec![parent.clone()],
+                            },
+                        };
+                        instance.add_transition(t1.clone(), t2.clone());
+                        transitions2.push(t2);
+                        new_tops.push(parent);
+                    }
+                }
+            }
+
+            for frame in new_tops.drain(..) {
+                if discovered_tops.insert(frame.clone()) {
+                    tops.push(frame);
+                }
+            }
+            for frame in new_belows.drain(..) {
+                if discovered_belows.insert(frame.clone()) {
+                    belows.push(frame);
+                }
+            }
+        }
+
+        (PushDownAutomaton::new(transitions2, initial), instance)
+    }
+}
+
+impl<A, T, W> SubsetApproximation<T, W> for TSUElement<A>
+where
+    A: Clone + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: AddAssign + Copy + MulAssign + One + Ord + Zero,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approximate_storage() {
+        let mut ts = TreeStack::new('@');
+        ts = ts.push(1, '1').unwrap();
+        ts = ts.push(0, '2').unwrap();
+        ts = ts.down().unwrap();
+        ts = ts.down().unwrap();
+        ts = ts.push(2, '3').unwrap();
+
+        let leaf = |position, value| {
+            TreeStackFrame {
+                position: Some(position),
+                value,
+                children: BTreeMap::new(),
+            }
+        };
+        let mut one = leaf(1, '1');
+        one.children.insert(0, Some(leaf(0, '2')));
+        let mut root = TreeStackFrame {
+            position: None,
+            value: '@',
+            children: BTreeMap::new(),
+        };
+        root.children.insert(1, Some(one));
+        root.children.insert(2, None);
+        let control_pushdown = PushDown::from(vec![root.clone(), leaf(2, '3')]);
+
+        let tsu = TSUElement::new(2);
+        assert_eq!(
+            control_pushdown,
+            <TSUElement<_> as ApproximationStrategy<char, u8>>::approximate_storage(
+                &tsu,
+                ts.clone(),
+            )
+        );
+
+        // with depth 1, the child of '1' is forgotten
+        let mut one = leaf(1, '1');
+        one.children.insert(0, None);
+        root.children.insert(1, Some(one));
+        let control_pushdown = PushDown::from(vec![root, leaf(2, '3')]);
+
+        let tsu = TSUElement::new(1);
+        assert_eq!(
+            control_pushdown,
+            <TSUElement<_> as ApproximationStrategy<char, u8>>::approximate_storage(&tsu, ts)
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut frame = TreeStackFrame {
+            position: None,
+            value: 'a',
+            children: BTreeMap::new(),
+        };
+        let mut child = frame.clone();
+        child.position = Some(3);
+        child.value = 'b';
+        child.children.insert(0, None);
+        frame.children.insert(3, Some(child.clone()));
+
+        let mut control_frame = frame.clone();
+        control_frame.children.insert(3, None);
+
+        assert_eq!(None, frame.clone().truncate(0));
+        assert_eq!(Some(control_frame), frame.clone().truncate(1));
+        assert_eq!(Some(frame.clone()), frame.truncate(2));
+    }
+}
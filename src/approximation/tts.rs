@@ -48,6 +48,25 @@ where
         PushDown::from(pd)
     }
 
+    fn approximate_automaton(
+        self,
+        automaton1: &TreeStackAutomaton<A, T, W>,
+    ) -> (PushDownAutomaton<A, T, W>, ApproximationInstance<Self, T, W>) {
+        approximate_instructionwise(self, automaton1)
+    }
+}
+
+impl<A, T, W> InstructionApproximation<T, W> for TTSElement<A>
+where
+    A: Clone + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: AddAssign
+        + Copy
+        + MulAssign
+        + One
+        + Ord
+        + Zero,
+{
     fn approximate_instruction(&self, instr: &TreeStackInstruction<A>) -> PushDownInstruction<A> {
         match *instr {
             TreeStackInstruction::Up {
@@ -145,7 +164,7 @@ mod tests {
         for (ts_instruction, pd_control_instruction) in inputs {
             assert_eq!(
                 pd_control_instruction,
-                <TTSElement<_> as ApproximationStrategy<char, u8>>::approximate_instruction(
+                <TTSElement<_> as InstructionApproximation<char, u8>>::approximate_instruction(
                     &tts,
                     &ts_instruction,
                 )
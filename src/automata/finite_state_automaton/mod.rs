@@ -2,12 +2,15 @@ extern crate bit_set;
 
 use integeriser::{HashIntegeriser, Integeriser};
 use num_traits::One;
-use recognisable::{automaton::Automaton,Configuration, Instruction, Item, Transition};
-use std::collections::{BinaryHeap, HashMap};
+use recognisable::{self, automaton::Automaton, Configuration, Instruction, Item, Recognisable,
+                   Transition};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap};
 use std::fmt::{self, Debug, Display, Formatter};
 use std::hash::Hash;
-use std::ops::MulAssign;
+use std::ops::{Mul, MulAssign};
 use std::rc::Rc;
+use util::budget::BudgetTracker;
+use util::dot::{graph_to_dot, transition_label, ToDot};
 use util::integerisable::{Integerisable1, Integerisable2};
 use util::push_down::Pushdown;
 
@@ -18,6 +21,10 @@ mod from_str;
 type TransitionMap<Q, T, W>
     = HashMap<Q, BinaryHeap<Transition<FiniteStateInstruction<Q>, T, W>>>;
 
+/// Transitions in their internal representation that read at most one terminal symbol,
+/// given as `(source_state, terminal, target_state, weight)`.
+type LetterTransitions<W> = Vec<(usize, Option<usize>, usize, W)>;
+
 #[derive(Clone, Debug)]
 pub struct FiniteStateAutomaton<Q, T, W>
 where
@@ -38,6 +45,20 @@ pub struct FiniteStateInstruction<Q> {
     target_state: Q,
 }
 
+impl<Q> FiniteStateInstruction<Q> {
+    pub fn new(source_state: Q, target_state: Q) -> Self {
+        FiniteStateInstruction { source_state, target_state }
+    }
+
+    pub fn source_state(&self) -> &Q {
+        &self.source_state
+    }
+
+    pub fn target_state(&self) -> &Q {
+        &self.target_state
+    }
+}
+
 impl<Q: Clone + Eq + Hash> Integerisable1 for FiniteStateInstruction<Q> {
     type AInt = FiniteStateInstruction<usize>;
     type I = HashIntegeriser<Q>;
@@ -118,6 +139,231 @@ where
         }))
     }
 
+    /// Applies `f` to every state of the automaton.
+    pub fn map_states<F, R>(&self, f: F) -> FiniteStateAutomaton<R, T, W>
+    where
+        F: Fn(&Q) -> R,
+        R: Clone + Eq + Hash + Ord,
+    {
+        let transitions = self.list_transitions().map(|t| Transition {
+            word: t.word,
+            weight: t.weight,
+            instruction: FiniteStateInstruction {
+                source_state: f(&t.instruction.source_state),
+                target_state: f(&t.instruction.target_state),
+            },
+        });
+        let finals: Vec<_> = self.final_states
+            .iter()
+            .map(|q| f(self.q_integeriser.find_value(q).unwrap()))
+            .collect();
+
+        FiniteStateAutomaton::new(
+            transitions,
+            f(self.q_integeriser.find_value(self.initial_state).unwrap()),
+            finals,
+        )
+    }
+}
+
+impl<Q, T, W> FiniteStateAutomaton<Q, T, W>
+where
+    Q: Clone + Eq + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: Clone + One + Ord,
+{
+    /// Splits every transition into transitions that read at most one terminal symbol.
+    /// Returns the number of states (including the fresh intermediate states) and the split
+    /// transitions.
+    /// The weight of a transition is kept by the first of its parts.
+    fn letter_transitions(&self) -> (usize, LetterTransitions<W>) {
+        let mut states = self.q_integeriser.size();
+        let mut result = Vec::new();
+
+        for t in self.transitions.values().flat_map(|h| h.iter()) {
+            let FiniteStateInstruction { source_state, target_state } = t.instruction;
+            if t.word.is_empty() {
+                result.push((source_state, None, target_state, t.weight.clone()));
+                continue;
+            }
+
+            let mut source = source_state;
+            let mut weight = Some(t.weight.clone());
+            for (i, &terminal) in t.word.iter().enumerate() {
+                let target = if i + 1 == t.word.len() {
+                    target_state
+                } else {
+                    states += 1;
+                    states - 1
+                };
+                result.push((source, Some(terminal), target, weight.take().unwrap_or_else(W::one)));
+                source = target;
+            }
+        }
+
+        (states, result)
+    }
+
+    /// Constructs a deterministic automaton for the support of this automaton using the subset
+    /// construction.
+    /// The resulting automaton reads exactly one terminal symbol with each transition and all of
+    /// its weights are `W::one()`; its states are numbered in the order of their discovery.
+    pub fn determinize(&self) -> FiniteStateAutomaton<usize, T, W> {
+        let (states, transitions) = self.letter_transitions();
+        let mut epsilons: Vec<Vec<usize>> = vec![Vec::new(); states];
+        let mut letters: Vec<BTreeMap<usize, Vec<usize>>> = vec![BTreeMap::new(); states];
+        for (source, terminal, target, _) in transitions {
+            match terminal {
+                None => epsilons[source].push(target),
+                Some(t) => letters[source].entry(t).or_insert_with(Vec::new).push(target),
+            }
+        }
+
+        let closure = |mut subset: BTreeSet<usize>| {
+            let mut stack: Vec<usize> = subset.iter().cloned().collect();
+            while let Some(q) = stack.pop() {
+                for &p in &epsilons[q] {
+                    if subset.insert(p) {
+                        stack.push(p);
+                    }
+                }
+            }
+            subset
+        };
+
+        let mut subsets = HashIntegeriser::new();
+        let mut initial = BTreeSet::new();
+        initial.insert(self.initial_state);
+        let mut queue = vec![subsets.integerise(closure(initial))];
+        let mut new_transitions = Vec::new();
+
+        while let Some(i) = queue.pop() {
+            let mut successors: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+            for &q in subsets.find_value(i).unwrap() {
+                for (&t, targets) in &letters[q] {
+                    successors
+                        .entry(t)
+                        .or_insert_with(BTreeSet::new)
+                        .extend(targets.iter().cloned());
+                }
+            }
+
+            for (t, targets) in successors {
+                let discovered = subsets.size();
+                let j = subsets.integerise(closure(targets));
+                if j == discovered {
+                    queue.push(j);
+                }
+                new_transitions.push(Transition {
+                    word: vec![self.t_integeriser.find_value(t).unwrap().clone()],
+                    weight: W::one(),
+                    instruction: FiniteStateInstruction {
+                        source_state: i,
+                        target_state: j,
+                    },
+                });
+            }
+        }
+
+        let finals: Vec<usize> = subsets
+            .values()
+            .iter()
+            .enumerate()
+            .filter(|&(_, subset)| subset.iter().any(|&q| self.final_states.contains(q)))
+            .map(|(i, _)| i)
+            .collect();
+
+        FiniteStateAutomaton::new(new_transitions, 0, finals)
+    }
+
+    /// Constructs an automaton for the intersection of the languages of `self` and `other` using
+    /// the product construction.
+    /// The weight of a run of the resulting automaton is the product of the weights of the
+    /// corresponding runs of `self` and `other`; its states are numbered in the order of their
+    /// discovery.
+    pub fn intersect<Q2>(
+        &self,
+        other: &FiniteStateAutomaton<Q2, T, W>,
+    ) -> FiniteStateAutomaton<usize, T, W>
+    where
+        Q2: Clone + Eq + Hash + Ord,
+        W: Mul<Output = W>,
+    {
+        fn by_source<'a, T, W>(
+            transitions: LetterTransitions<W>,
+            states: usize,
+            t_integeriser: &'a HashIntegeriser<T>,
+        ) -> Vec<Vec<(Option<&'a T>, usize, W)>>
+        where
+            T: Clone + Eq + Hash,
+            W: Clone,
+        {
+            let mut result = vec![Vec::new(); states];
+            for (source, terminal, target, weight) in transitions {
+                let terminal = terminal.map(|t| t_integeriser.find_value(t).unwrap());
+                result[source].push((terminal, target, weight));
+            }
+            result
+        }
+
+        let (states1, transitions1) = self.letter_transitions();
+        let (states2, transitions2) = other.letter_transitions();
+        let transitions1 = by_source(transitions1, states1, &self.t_integeriser);
+        let transitions2 = by_source(transitions2, states2, &other.t_integeriser);
+
+        let mut pairs = HashIntegeriser::new();
+        let mut queue = vec![pairs.integerise((self.initial_state, other.initial_state))];
+        let mut new_transitions = Vec::new();
+
+        while let Some(i) = queue.pop() {
+            let (p, q) = *pairs.find_value(i).unwrap();
+            let mut successors = Vec::new();
+
+            for &(t1, p1, ref w1) in &transitions1[p] {
+                match t1 {
+                    None => successors.push((None, (p1, q), w1.clone())),
+                    Some(t) => {
+                        for &(t2, q1, ref w2) in &transitions2[q] {
+                            if t2 == Some(t) {
+                                successors.push((Some(t), (p1, q1), w1.clone() * w2.clone()));
+                            }
+                        }
+                    }
+                }
+            }
+            for &(t2, q1, ref w2) in &transitions2[q] {
+                if t2.is_none() {
+                    successors.push((None, (p, q1), w2.clone()));
+                }
+            }
+
+            for (terminal, pair, weight) in successors {
+                let discovered = pairs.size();
+                let j = pairs.integerise(pair);
+                if j == discovered {
+                    queue.push(j);
+                }
+                new_transitions.push(Transition {
+                    word: terminal.into_iter().cloned().collect(),
+                    weight,
+                    instruction: FiniteStateInstruction {
+                        source_state: i,
+                        target_state: j,
+                    },
+                });
+            }
+        }
+
+        let finals: Vec<usize> = pairs
+            .values()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(p, q))| self.final_states.contains(p) && other.final_states.contains(q))
+            .map(|(i, _)| i)
+            .collect();
+
+        FiniteStateAutomaton::new(new_transitions, 0, finals)
+    }
 }
 
 impl<Q, T, W> Automaton<T, W> for FiniteStateAutomaton<Q, T, W>
@@ -203,12 +449,45 @@ where
     }
 }
 
+impl<Q, T, W> Recognisable<T, W> for FiniteStateAutomaton<Q, T, W>
+where
+    Q: Clone + Debug + Hash + Ord,
+    T: Clone + Debug + Eq + Hash + Ord,
+    W: Copy + Debug + Mul<Output = W> + MulAssign + One + Ord,
+{
+    type Parse = Item<Q, FiniteStateInstruction<Q>, T, W>;
+
+    fn recognise<'a>(&'a self, word: Vec<T>) -> Box<Iterator<Item = Self::Parse> + 'a> {
+        Box::new(recognisable::automaton::recognise(self, word))
+    }
+
+    fn recognise_beam_search<'a>(
+        &'a self,
+        beam: usize,
+        word: Vec<T>,
+    ) -> Box<Iterator<Item = Self::Parse> + 'a> {
+        Box::new(recognisable::automaton::recognise_beam(self, beam, word))
+    }
+
+    fn recognise_with_budget<'a>(
+        &'a self,
+        beam: Option<usize>,
+        budget: BudgetTracker,
+        word: Vec<T>,
+    ) -> Box<Iterator<Item = Self::Parse> + 'a>
+    where
+        Self::Parse: 'a,
+    {
+        Box::new(recognisable::automaton::recognise_with_budget(self, beam, budget, word))
+    }
+}
+
 impl<Q> Display for FiniteStateInstruction<Q>
 where
     Q: Display,
 {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "{} → {}", self.source_state, self.target_state)
+        write!(f, "({} → {})", self.source_state, self.target_state)
     }
 }
 
@@ -221,18 +500,19 @@ where
 {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         let mut buffer = "".to_string();
-        buffer.push_str(&format!("initial: {}\n", self.initial_state));
+        buffer.push_str(&format!(
+            "initial: {}\n",
+            self.q_integeriser.find_value(self.initial_state).unwrap()
+        ));
 
         buffer.push_str("final: [");
         let mut first = true;
-        for i in 0 .. self.final_states.len() {
-            if self.final_states.contains(i) {
-                if first {
-                    buffer.push_str(", ");
-                    first = false;
-                }
-                buffer.push_str(&format!("\"{}\"", i));
+        for i in self.final_states.iter() {
+            if !first {
+                buffer.push_str(", ");
             }
+            first = false;
+            buffer.push_str(&format!("\"{}\"", self.q_integeriser.find_value(i).unwrap()));
         }
         buffer.push_str("]\n\n");
         for t in self.list_transitions() {
@@ -242,3 +522,151 @@ where
         write!(f, "{}", buffer)
     }
 }
+
+impl<Q, T, W> ToDot for FiniteStateAutomaton<Q, T, W>
+where
+    Q: Clone + Display + Hash + Ord,
+    T: Clone + Display + Eq + Hash + Ord,
+    W: Clone + Display + Ord,
+{
+    fn to_dot(&self) -> String {
+        let state = |i| self.q_integeriser.find_value(i).unwrap().to_string();
+        let finals: Vec<String> = self.final_states.iter().map(&state).collect();
+        let edges = self.list_transitions().map(|t| {
+            ( t.instruction.source_state.to_string()
+            , t.instruction.target_state.to_string()
+            , transition_label(&t.word, "", &t.weight)
+            )
+        });
+        graph_to_dot(&[state(self.initial_state)], &finals, edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+
+    fn example_automaton() -> FiniteStateAutomaton<usize, String, LogDomain<f64>> {
+        let transitions = vec![
+            (vec![], 0, 1, 0.5),
+            (vec!['a'], 1, 1, 0.5),
+            (vec!['b'], 1, 2, 1.0),
+            (vec![], 0, 3, 0.5),
+            (vec!['a', 'a'], 3, 4, 1.0),
+            (vec!['b'], 4, 4, 0.5),
+        ];
+
+        FiniteStateAutomaton::new(
+            transitions.into_iter().map(|(word, source_state, target_state, weight)| {
+                Transition {
+                    word: word.into_iter().map(|t: char| t.to_string()).collect(),
+                    weight: LogDomain::new(weight).unwrap(),
+                    instruction: FiniteStateInstruction { source_state, target_state },
+                }
+            }),
+            0,
+            vec![2, 4],
+        )
+    }
+
+    fn words() -> Vec<(&'static str, bool)> {
+        vec![
+            ("", false),
+            ("b", true),
+            ("ab", true),
+            ("aab", true),
+            ("aa", true),
+            ("aabbb", true),
+            ("a", false),
+            ("abb", false),
+            ("ba", false),
+        ]
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = example_automaton().to_dot();
+        assert!(dot.contains("__initial0 -> \"0\";"));
+        assert!(dot.contains("\"2\" [shape=doublecircle];"));
+        assert!(dot.contains("\"0\" -> \"1\" [label=\"ε # 0.5\"];"));
+        assert!(dot.contains("\"3\" -> \"4\" [label=\"a a # 1\"];"));
+        assert_eq!(6, dot.matches(" -> \"").count() - 1);
+    }
+
+    #[test]
+    fn test_determinize() {
+        let automaton = example_automaton();
+        let deterministic = automaton.determinize();
+
+        for t in deterministic.list_transitions() {
+            assert_eq!(1, t.word.len());
+            assert_eq!(
+                1,
+                deterministic
+                    .list_transitions()
+                    .filter(|t1| {
+                        t1.word == t.word &&
+                            t1.instruction.source_state == t.instruction.source_state
+                    })
+                    .count()
+            );
+        }
+
+        for (word, control_acceptance) in words() {
+            let word: Vec<_> = word.chars().map(|t| t.to_string()).collect();
+            assert_eq!(control_acceptance, automaton.recognise(word.clone()).next().is_some());
+            assert_eq!(control_acceptance, deterministic.recognise(word).next().is_some());
+        }
+    }
+
+    #[test]
+    fn test_intersect() {
+        let automaton = example_automaton();
+        // words over {a, b} with an even number of symbols
+        let even: FiniteStateAutomaton<bool, String, LogDomain<f64>> = FiniteStateAutomaton::new(
+            vec!['a', 'b'].into_iter().flat_map(|t| {
+                vec![(false, true), (true, false)].into_iter().map(move |(source_state, target_state)| {
+                    Transition {
+                        word: vec![t.to_string()],
+                        weight: LogDomain::one(),
+                        instruction: FiniteStateInstruction { source_state, target_state },
+                    }
+                })
+            }),
+            false,
+            vec![false],
+        );
+        let intersection = automaton.intersect(&even);
+
+        for (word, control_acceptance) in words() {
+            let word: Vec<_> = word.chars().map(|t| t.to_string()).collect();
+            assert_eq!(
+                control_acceptance && word.len() % 2 == 0,
+                intersection.recognise(word).next().is_some()
+            );
+        }
+
+        let best = intersection.recognise("aaab".chars().map(|t| t.to_string()).collect()).next().unwrap();
+        assert_eq!(LogDomain::new(0.5f64.powi(4)).unwrap(), (best.0).weight);
+    }
+
+    #[test]
+    fn test_display_from_str_inverse() {
+        let automaton = example_automaton();
+        let reparsed: FiniteStateAutomaton<usize, String, LogDomain<f64>>
+            = automaton.to_string().parse().unwrap();
+
+        assert_eq!(automaton.initial(), reparsed.initial());
+        let mut transitions: Vec<_> = automaton.list_transitions().collect();
+        let mut reparsed_transitions: Vec<_> = reparsed.list_transitions().collect();
+        transitions.sort();
+        reparsed_transitions.sort();
+        assert_eq!(transitions, reparsed_transitions);
+
+        for (word, control_acceptance) in words() {
+            let word: Vec<_> = word.chars().map(|t| t.to_string()).collect();
+            assert_eq!(control_acceptance, reparsed.recognise(word).next().is_some());
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod finite_state_automaton;
 pub mod push_down_automaton;
+pub mod tree_automaton;
 pub mod tree_stack_automaton;
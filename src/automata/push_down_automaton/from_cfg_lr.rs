@@ -0,0 +1,707 @@
+use num_traits::{One, Zero};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::hash::Hash;
+use std::ops::AddAssign;
+
+use grammars::cfg::{CFG, CFGRule, LetterT};
+use recognisable::Transition;
+use super::{PushDown, PushDownAutomaton, PushDownInstruction};
+
+/// The constructions of LR parse tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LRKind {
+    /// reduce regardless of the next symbol of the input
+    LR0,
+    /// reduce if the next symbol of the input may follow the head of the rule
+    SLR,
+    /// reduce if the next symbol of the input may follow the head of the rule
+    /// in the state of the LR(0) automaton that the reduction leads to
+    LALR1,
+}
+
+/// Symbols of a `PushDown` created from an `LRTable`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum LRSymbol<T> {
+    Bottom,
+    /// a state of the LR(0) automaton
+    State(usize),
+    /// a state of the LR(0) automaton in which the next symbol of the input
+    /// (or its end, if `None`) has already been read
+    Lookahead(usize, Option<T>),
+}
+
+impl<T: fmt::Display> fmt::Display for LRSymbol<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LRSymbol::Bottom => write!(f, "@"),
+            LRSymbol::State(q) => write!(f, "q{}", q),
+            LRSymbol::Lookahead(q, Some(ref t)) => write!(f, "q{}/{}", q, t),
+            LRSymbol::Lookahead(q, None) => write!(f, "q{}/$", q),
+        }
+    }
+}
+
+/// An action of an LR parser.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum LRAction<N, T, W> {
+    /// read the next symbol of the input and go to the given state
+    Shift(usize),
+    /// replace the body of the rule on top of the stack by its head
+    Reduce(CFGRule<N, T, W>),
+    /// accept the input, which is derived from the given initial nonterminal
+    Accept(N),
+}
+
+/// Several actions of an LR parser for the same state and next symbol of the
+/// input (`None` for its end).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LRConflict<N, T, W> {
+    pub state: usize,
+    pub lookahead: Option<T>,
+    pub actions: Vec<LRAction<N, T, W>>,
+}
+
+impl<N, T, W> fmt::Display for LRConflict<N, T, W>
+where
+    N: fmt::Display,
+    T: fmt::Display,
+    W: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.lookahead {
+            Some(ref t) => write!(f, "conflict in state {} on \"{}\":", self.state, t)?,
+            None => write!(f, "conflict in state {} at the end of the input:", self.state)?,
+        }
+        for action in &self.actions {
+            match *action {
+                LRAction::Shift(q) => write!(f, "\n  shift to state {}", q)?,
+                LRAction::Reduce(ref rule) => write!(f, "\n  reduce {}", rule)?,
+                LRAction::Accept(ref n) => write!(f, "\n  accept \"{}\"", n)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Actions of the table; rules are referred to by their index in the
+/// grammar.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Action {
+    Shift(usize),
+    Reduce(usize),
+    Accept(usize),
+}
+
+/// An LR(0) item: a production and the position of the dot in its body.
+/// The productions are the rules of the grammar followed by one rule
+/// `S' → S` for every initial nonterminal `S`.
+type LRItem = (usize, usize);
+
+/// The parse table of a shift-reduce parser for a `CFG`, built on the
+/// canonical collection of LR(0) item sets.
+///
+/// The table may contain conflicts, which are listed by `conflicts`.
+/// The `PushDownAutomaton` constructed by `automaton` contains every action
+/// of the table, i.e. it is deterministic (apart from guessing the end of the
+/// input) if there are no conflicts, and otherwise recognising with it is
+/// generalised LR parsing.
+///
+/// ```
+/// extern crate log_domain;
+/// extern crate rustomata;
+///
+/// use std::str::FromStr;
+/// use log_domain::LogDomain;
+/// use rustomata::automata::push_down_automaton::{LRKind, LRTable};
+/// use rustomata::grammars::cfg::CFG;
+/// use rustomata::recognisable::Recognisable;
+///
+/// # fn main() {
+/// let grammar: CFG<String, String, LogDomain<f64>> = CFG::from_str(
+///     "initial: [S]\n\n\
+///      S → [T a, Nt S, T b] # 0.4\n\
+///      S → [] # 0.6"
+/// ).unwrap();
+///
+/// let table = LRTable::new(grammar, LRKind::LALR1);
+/// assert!(table.conflicts().is_empty());
+///
+/// let automaton = table.automaton();
+/// let word: Vec<String> = vec!["a", "a", "b", "b"].into_iter().map(String::from).collect();
+/// assert_eq!(1, automaton.recognise(word).take(2).count());
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LRTable<N, T, W> {
+    kind: LRKind,
+    grammar: CFG<N, T, W>,
+    /// the bodies of the rules `S' → S`
+    starts: Vec<Vec<LetterT<N, T>>>,
+    nullable: BTreeSet<N>,
+    first: BTreeMap<N, BTreeSet<T>>,
+    /// the kernels of the LR(0) item sets
+    states: Vec<BTreeSet<LRItem>>,
+    gotos: BTreeMap<(usize, LetterT<N, T>), usize>,
+    actions: BTreeMap<(usize, Option<T>), Vec<Action>>,
+}
+
+impl<N, T, W> LRTable<N, T, W>
+where
+    N: Clone + Ord + Hash,
+    T: Clone + Ord + Hash,
+    W: Clone,
+{
+    pub fn new(grammar: CFG<N, T, W>, kind: LRKind) -> Self {
+        let starts = grammar.initial.iter().map(|n| vec![LetterT::Label(n.clone())]).collect();
+        let mut table = LRTable {
+            kind,
+            grammar,
+            starts,
+            nullable: BTreeSet::new(),
+            first: BTreeMap::new(),
+            states: Vec::new(),
+            gotos: BTreeMap::new(),
+            actions: BTreeMap::new(),
+        };
+        let (nullable, first) = table.first();
+        table.nullable = nullable;
+        table.first = first;
+        table.collection();
+
+        let shifts: Vec<_> = table
+            .gotos
+            .iter()
+            .filter_map(|(&(q, ref symbol), &target)| match *symbol {
+                LetterT::Value(ref t) => Some((q, t.clone(), target)),
+                LetterT::Label(_) => None,
+            })
+            .collect();
+        for (q, t, target) in shifts {
+            table.add_action(q, Some(t), Action::Shift(target));
+        }
+
+        match kind {
+            LRKind::LR0 => {
+                let mut lookaheads: BTreeSet<_> = table.terminals().into_iter().map(Some).collect();
+                lookaheads.insert(None);
+                for q in 0..table.states.len() {
+                    for (p, d) in table.closure(&table.states[q]) {
+                        if d == table.body(p).len() {
+                            for lookahead in &lookaheads {
+                                table.add_reduction(q, p, lookahead.clone());
+                            }
+                        }
+                    }
+                }
+            }
+            LRKind::SLR => {
+                let follow = table.follow();
+                for q in 0..table.states.len() {
+                    for (p, d) in table.closure(&table.states[q]) {
+                        if d == table.body(p).len() {
+                            let lookaheads = match table.head(p) {
+                                Some(n) => follow.get(n).cloned().unwrap_or_default(),
+                                None => vec![None].into_iter().collect(),
+                            };
+                            for lookahead in lookaheads {
+                                table.add_reduction(q, p, lookahead);
+                            }
+                        }
+                    }
+                }
+            }
+            LRKind::LALR1 => {
+                let lookaheads = table.lalr_lookaheads();
+                for q in 0..table.states.len() {
+                    let kernel = lookaheads[q]
+                        .iter()
+                        .flat_map(|(&(p, d), ts)| ts.iter().map(move |t| (p, d, Some(t.clone()))))
+                        .collect();
+                    for (p, d, lookahead) in table.closure1(kernel) {
+                        if d == table.body(p).len() {
+                            table.add_reduction(q, p, lookahead.unwrap());
+                        }
+                    }
+                }
+            }
+        }
+
+        table
+    }
+
+    /// The construction the table was built with.
+    pub fn kind(&self) -> LRKind {
+        self.kind
+    }
+
+    /// The number of states of the LR(0) automaton.
+    pub fn states(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Lists the entries of the table that contain more than one action.
+    pub fn conflicts(&self) -> Vec<LRConflict<N, T, W>> {
+        self.actions
+            .iter()
+            .filter(|&(_, actions)| actions.len() > 1)
+            .map(|(&(state, ref lookahead), actions)| LRConflict {
+                state,
+                lookahead: lookahead.clone(),
+                actions: actions
+                    .iter()
+                    .map(|action| match *action {
+                        Action::Shift(q) => LRAction::Shift(q),
+                        Action::Reduce(p) => LRAction::Reduce(self.grammar.rules[p].clone()),
+                        Action::Accept(p) => {
+                            LRAction::Accept(self.grammar.initial[p - self.grammar.rules.len()].clone())
+                        }
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// The head of a production, `None` for `S' → S`.
+    fn head(&self, p: usize) -> Option<&N> {
+        self.grammar.rules.get(p).map(|rule| &rule.head)
+    }
+
+    fn body(&self, p: usize) -> &[LetterT<N, T>] {
+        match self.grammar.rules.get(p) {
+            Some(rule) => &rule.composition.composition,
+            None => &self.starts[p - self.grammar.rules.len()],
+        }
+    }
+
+    /// The symbol after the dot of an item.
+    fn next_symbol(&self, (p, d): LRItem) -> Option<LetterT<N, T>> {
+        self.body(p).get(d).cloned()
+    }
+
+    fn productions_of<'a>(&'a self, n: &'a N) -> Box<Iterator<Item = usize> + 'a> {
+        Box::new(
+            self.grammar
+                .rules
+                .iter()
+                .enumerate()
+                .filter(move |&(_, rule)| rule.head == *n)
+                .map(|(p, _)| p),
+        )
+    }
+
+    fn terminals(&self) -> BTreeSet<T> {
+        let mut terminals = BTreeSet::new();
+        for rule in &self.grammar.rules {
+            for symbol in &rule.composition.composition {
+                if let LetterT::Value(ref t) = *symbol {
+                    terminals.insert(t.clone());
+                }
+            }
+        }
+        terminals
+    }
+
+    fn closure(&self, kernel: &BTreeSet<LRItem>) -> BTreeSet<LRItem> {
+        let mut items = kernel.clone();
+        let mut agenda: Vec<_> = kernel.iter().cloned().collect();
+        while let Some(item) = agenda.pop() {
+            if let Some(LetterT::Label(n)) = self.next_symbol(item) {
+                for p in self.productions_of(&n) {
+                    if items.insert((p, 0)) {
+                        agenda.push((p, 0));
+                    }
+                }
+            }
+        }
+        items
+    }
+
+    /// Builds the canonical collection of LR(0) item sets and the transitions
+    /// between them.
+    fn collection(&mut self) {
+        let rules = self.grammar.rules.len();
+        let initial: BTreeSet<_> = (0..self.grammar.initial.len()).map(|i| (rules + i, 0)).collect();
+        let mut index = BTreeMap::new();
+        index.insert(initial.clone(), 0);
+        self.states.push(initial);
+
+        let mut q = 0;
+        while q < self.states.len() {
+            let mut successors: BTreeMap<LetterT<N, T>, BTreeSet<LRItem>> = BTreeMap::new();
+            for (p, d) in self.closure(&self.states[q]) {
+                if let Some(symbol) = self.next_symbol((p, d)) {
+                    successors.entry(symbol).or_insert_with(BTreeSet::new).insert((p, d + 1));
+                }
+            }
+            for (symbol, kernel) in successors {
+                let target = match index.get(&kernel) {
+                    Some(&target) => target,
+                    None => {
+                        self.states.push(kernel.clone());
+                        self.states.len() - 1
+                    }
+                };
+                index.insert(kernel, target);
+                self.gotos.insert((q, symbol), target);
+            }
+            q += 1;
+        }
+    }
+
+    fn add_action(&mut self, q: usize, lookahead: Option<T>, action: Action) {
+        let actions = self.actions.entry((q, lookahead)).or_insert_with(Vec::new);
+        if !actions.contains(&action) {
+            actions.push(action);
+        }
+    }
+
+    /// Adds the reduction with production `p`, which is an acceptance for
+    /// `S' → S`.
+    fn add_reduction(&mut self, q: usize, p: usize, lookahead: Option<T>) {
+        if p < self.grammar.rules.len() {
+            self.add_action(q, lookahead, Action::Reduce(p));
+        } else if lookahead.is_none() {
+            self.add_action(q, lookahead, Action::Accept(p));
+        }
+    }
+
+    /// The nullable nonterminals and the terminals that the derivations of
+    /// each nonterminal start with.
+    fn first(&self) -> (BTreeSet<N>, BTreeMap<N, BTreeSet<T>>) {
+        let mut nullable = BTreeSet::new();
+        let mut first: BTreeMap<N, BTreeSet<T>> = BTreeMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in &self.grammar.rules {
+                let (ts, empty) = first_of(&rule.composition.composition, &nullable, &first);
+                if empty && nullable.insert(rule.head.clone()) {
+                    changed = true;
+                }
+                let entry = first.entry(rule.head.clone()).or_insert_with(BTreeSet::new);
+                for t in ts {
+                    changed |= entry.insert(t);
+                }
+            }
+        }
+        (nullable, first)
+    }
+
+    /// The symbols of the input (`None` for its end) that may follow each
+    /// nonterminal.
+    fn follow(&self) -> BTreeMap<N, BTreeSet<Option<T>>> {
+        let mut follow: BTreeMap<N, BTreeSet<Option<T>>> = BTreeMap::new();
+        for n in &self.grammar.initial {
+            follow.entry(n.clone()).or_insert_with(BTreeSet::new).insert(None);
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in &self.grammar.rules {
+                let body = &rule.composition.composition;
+                for (i, symbol) in body.iter().enumerate() {
+                    if let LetterT::Label(ref n) = *symbol {
+                        let (ts, empty) = first_of(&body[i + 1..], &self.nullable, &self.first);
+                        let mut new: BTreeSet<_> = ts.into_iter().map(Some).collect();
+                        if empty {
+                            new.extend(follow.get(&rule.head).cloned().unwrap_or_default());
+                        }
+                        let entry = follow.entry(n.clone()).or_insert_with(BTreeSet::new);
+                        for t in new {
+                            changed |= entry.insert(t);
+                        }
+                    }
+                }
+            }
+        }
+        follow
+    }
+
+    /// The closure of a set of LR(1) items whose lookaheads are either
+    /// symbols of the input (`Some`) or a placeholder (`None`).
+    fn closure1(
+        &self,
+        kernel: BTreeSet<(usize, usize, Option<Option<T>>)>,
+    ) -> BTreeSet<(usize, usize, Option<Option<T>>)> {
+        let mut items = kernel.clone();
+        let mut agenda: Vec<_> = kernel.into_iter().collect();
+        while let Some((p, d, lookahead)) = agenda.pop() {
+            if let Some(LetterT::Label(n)) = self.next_symbol((p, d)) {
+                let (ts, empty) = first_of(&self.body(p)[d + 1..], &self.nullable, &self.first);
+                let mut lookaheads: Vec<_> = ts.into_iter().map(|t| Some(Some(t))).collect();
+                if empty {
+                    lookaheads.push(lookahead);
+                }
+                for new in lookaheads {
+                    for p1 in self.productions_of(&n) {
+                        if items.insert((p1, 0, new.clone())) {
+                            agenda.push((p1, 0, new.clone()));
+                        }
+                    }
+                }
+            }
+        }
+        items
+    }
+
+    /// Computes the LALR(1) lookaheads of the kernel items by determining
+    /// which lookaheads are generated spontaneously and which are propagated
+    /// along the transitions of the LR(0) automaton.
+    fn lalr_lookaheads(&self) -> Vec<BTreeMap<LRItem, BTreeSet<Option<T>>>> {
+        let mut lookaheads: Vec<BTreeMap<LRItem, BTreeSet<Option<T>>>> = self.states
+            .iter()
+            .map(|kernel| kernel.iter().map(|&item| (item, BTreeSet::new())).collect())
+            .collect();
+        let mut propagation: BTreeMap<(usize, LRItem), Vec<(usize, LRItem)>> = BTreeMap::new();
+
+        for &item in &self.states[0] {
+            lookaheads[0].get_mut(&item).unwrap().insert(None);
+        }
+        for (q, kernel) in self.states.iter().enumerate() {
+            for &(p, d) in kernel {
+                let items = self.closure1(vec![(p, d, None)].into_iter().collect());
+                for (p1, d1, lookahead) in items {
+                    let symbol = match self.next_symbol((p1, d1)) {
+                        Some(symbol) => symbol,
+                        None => continue,
+                    };
+                    let target = self.gotos[&(q, symbol)];
+                    match lookahead {
+                        Some(t) => {
+                            lookaheads[target].get_mut(&(p1, d1 + 1)).unwrap().insert(t);
+                        }
+                        None => {
+                            propagation
+                                .entry((q, (p, d)))
+                                .or_insert_with(Vec::new)
+                                .push((target, (p1, d1 + 1)));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (&(q, item), targets) in &propagation {
+                let source = lookaheads[q][&item].clone();
+                for &(target, target_item) in targets {
+                    let entry = lookaheads[target].get_mut(&target_item).unwrap();
+                    for t in &source {
+                        changed |= entry.insert(t.clone());
+                    }
+                }
+            }
+        }
+
+        lookaheads
+    }
+
+    /// The states `q₀` whose item sets contain `A → .α` for production `p`
+    /// together with the states reached from `q₀` by reading `α`.
+    fn paths(&self, p: usize) -> Vec<Vec<usize>> {
+        let mut paths = Vec::new();
+        for q in 0..self.states.len() {
+            if !self.closure(&self.states[q]).contains(&(p, 0)) {
+                continue;
+            }
+            let mut path = vec![q];
+            for d in 0..self.body(p).len() {
+                let symbol = self.next_symbol((p, d)).unwrap();
+                let next = self.gotos[&(*path.last().unwrap(), symbol)];
+                path.push(next);
+            }
+            paths.push(path);
+        }
+        paths
+    }
+
+    /// Constructs a shift-reduce `PushDownAutomaton` that performs the
+    /// actions of the table.
+    /// Its pushdown holds the states of the LR(0) automaton, and the topmost
+    /// state is paired with the next symbol of the input after it has been
+    /// read; the end of the input is guessed.
+    /// Reductions are weighted with the weights of their rules.
+    pub fn automaton(&self) -> PushDownAutomaton<LRSymbol<T>, T, W>
+    where
+        W: AddAssign + Ord + One + Zero,
+    {
+        let mut paths = BTreeMap::new();
+        let mut transitions = Vec::new();
+
+        for (&(q, ref lookahead), actions) in &self.actions {
+            let read = LRSymbol::Lookahead(q, lookahead.clone());
+            transitions.push(Transition {
+                word: lookahead.iter().cloned().collect(),
+                weight: W::one(),
+                instruction: PushDownInstruction::Replace {
+                    current_val: vec![LRSymbol::State(q)],
+                    new_val: vec![read.clone()],
+                },
+            });
+
+            for action in actions {
+                let (p, weight) = match *action {
+                    Action::Shift(target) => {
+                        transitions.push(Transition {
+                            word: Vec::new(),
+                            weight: W::one(),
+                            instruction: PushDownInstruction::Replace {
+                                current_val: vec![read.clone()],
+                                new_val: vec![LRSymbol::State(q), LRSymbol::State(target)],
+                            },
+                        });
+                        continue;
+                    }
+                    Action::Reduce(p) => (p, self.grammar.rules[p].weight.clone()),
+                    Action::Accept(p) => (p, W::one()),
+                };
+
+                if !paths.contains_key(&p) {
+                    paths.insert(p, self.paths(p));
+                }
+                for path in paths[&p].iter().filter(|path| *path.last().unwrap() == q) {
+                    let mut current_val = vec![read.clone()];
+                    current_val.extend(path[..path.len() - 1].iter().rev().map(|&q1| LRSymbol::State(q1)));
+                    let new_val = match self.head(p) {
+                        Some(n) => {
+                            let target = self.gotos[&(path[0], LetterT::Label(n.clone()))];
+                            vec![LRSymbol::State(path[0]), LRSymbol::Lookahead(target, lookahead.clone())]
+                        }
+                        None => Vec::new(),
+                    };
+                    transitions.push(Transition {
+                        word: Vec::new(),
+                        weight: weight.clone(),
+                        instruction: PushDownInstruction::Replace { current_val, new_val },
+                    });
+                }
+            }
+        }
+
+        PushDownAutomaton::new(transitions, PushDown::new(LRSymbol::Bottom, LRSymbol::State(0)))
+    }
+}
+
+/// The terminals that the derivations of a sequence of symbols start with and
+/// whether it derives the empty word.
+fn first_of<N, T>(
+    symbols: &[LetterT<N, T>],
+    nullable: &BTreeSet<N>,
+    first: &BTreeMap<N, BTreeSet<T>>,
+) -> (BTreeSet<T>, bool)
+where
+    N: Ord,
+    T: Clone + Ord,
+{
+    let mut ts = BTreeSet::new();
+    for symbol in symbols {
+        match *symbol {
+            LetterT::Value(ref t) => {
+                ts.insert(t.clone());
+                return (ts, false);
+            }
+            LetterT::Label(ref n) => {
+                if let Some(first) = first.get(n) {
+                    ts.extend(first.iter().cloned());
+                }
+                if !nullable.contains(n) {
+                    return (ts, false);
+                }
+            }
+        }
+    }
+    (ts, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+    use std::str::FromStr;
+
+    use automata::push_down_automaton::TabularRecogniser;
+    use recognisable::Recognisable;
+
+    fn grammar(rules: &str) -> CFG<String, String, LogDomain<f64>> {
+        CFG::from_str(&format!("initial: [S]\n\n{}", rules)).unwrap()
+    }
+
+    fn word(input: &str) -> Vec<String> {
+        input.split_whitespace().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_expressions() {
+        let expressions = grammar(
+            "S → [Nt S, T +, Nt P] # 0.3\n\
+             S → [Nt P] # 0.7\n\
+             P → [Nt P, T *, Nt F] # 0.2\n\
+             P → [Nt F] # 0.8\n\
+             F → [T \"(\", Nt S, T \")\"] # 0.1\n\
+             F → [T a] # 0.9",
+        );
+
+        assert!(!LRTable::new(expressions.clone(), LRKind::LR0).conflicts().is_empty());
+        assert!(LRTable::new(expressions.clone(), LRKind::SLR).conflicts().is_empty());
+        let table = LRTable::new(expressions, LRKind::LALR1);
+        assert!(table.conflicts().is_empty());
+        assert_eq!(12, table.states());
+
+        let automaton = table.automaton();
+        let runs: Vec<_> = automaton.recognise(word("a + a * ( a )")).take(2).collect();
+        assert_eq!(1, runs.len());
+        let weight = 0.3 * 0.7 * 0.8 * 0.9 * 0.2 * 0.8 * 0.9 * 0.1 * 0.7 * 0.8 * 0.9;
+        assert!(((runs[0].0).weight.ln() - f64::ln(weight)).abs() < 1e-9);
+
+        assert!(automaton.recognise(word("a + * a")).next().is_none());
+        assert!(automaton.recognise(word("( a")).next().is_none());
+    }
+
+    #[test]
+    fn test_lalr_not_slr() {
+        let assignments = grammar(
+            "S → [Nt L, T =, Nt R] # 0.5\n\
+             S → [Nt R] # 0.5\n\
+             L → [T *, Nt R] # 0.5\n\
+             L → [T id] # 0.5\n\
+             R → [Nt L] # 1",
+        );
+
+        let conflicts = LRTable::new(assignments.clone(), LRKind::SLR).conflicts();
+        assert_eq!(1, conflicts.len());
+        assert_eq!(Some("=".to_string()), conflicts[0].lookahead);
+        assert_eq!(LRAction::Shift(6), conflicts[0].actions[0]);
+
+        let table = LRTable::new(assignments, LRKind::LALR1);
+        assert!(table.conflicts().is_empty());
+        let automaton = table.automaton();
+        assert_eq!(1, automaton.recognise(word("* id = id")).take(2).count());
+        assert_eq!(1, automaton.recognise(word("* * id")).take(2).count());
+        assert!(automaton.recognise(word("id = = id")).next().is_none());
+    }
+
+    #[test]
+    fn test_generalised() {
+        let binary = grammar(
+            "S → [Nt S, Nt S] # 0.5\n\
+             S → [T a] # 0.5\n\
+             S → [] # 0.1",
+        );
+
+        let table = LRTable::new(binary, LRKind::LALR1);
+        assert!(!table.conflicts().is_empty());
+
+        // the derivations with 4 leaves and no empty subtrees form the
+        // binary trees with 4 leaves
+        let recogniser = TabularRecogniser::new(table.automaton());
+        let best: Vec<_> = recogniser.recognise(word("a a a a")).take(5).collect();
+        assert_eq!(5, best.len());
+        for run in best {
+            assert!(((run.0).weight.ln() - f64::ln(0.5f64.powi(7))).abs() < 1e-9);
+        }
+        assert!(recogniser.recognise(Vec::new()).next().is_some());
+    }
+}
@@ -0,0 +1,206 @@
+use nom::{IResult, is_space};
+use num_traits::{One, Zero};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::AddAssign;
+use std::vec::Vec;
+use std::str::FromStr;
+
+use recognisable::Transition;
+use automata::push_down_automaton::{PushDown, PushDownAutomaton, PushDownInstruction};
+use util::parsing::{parse_token, parse_vec};
+
+impl<A, T, W> FromStr for PushDownAutomaton<A, T, W>
+where
+    A: Clone + FromStr + Hash + Ord + PartialEq,
+    A::Err: Debug,
+    T: Clone + Eq + FromStr + Hash + Ord,
+    T::Err: Debug,
+    W: AddAssign + Clone + Eq + FromStr + One + Ord + Zero,
+    W::Err: Debug,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut initial = None;
+        let mut transitions: Vec<Transition<PushDownInstruction<A>, T, W>> = Vec::new();
+
+        let mut it = s.lines();
+
+        while let Some(l) = it.next() {
+            if l.trim_start().starts_with("initial:") {
+                match parse_initial_pushdown(l.trim_start().as_bytes()) {
+                    IResult::Done(_, (elements, ref empty)) if elements.first() == Some(empty) => {
+                        initial = Some(PushDown::from(elements));
+                    }
+                    _ => return Err(format!("Malformed initial declaration: {}", l)),
+                }
+            } else if !l.trim().is_empty() && !l.trim_start().starts_with("%") {
+                transitions.push(l.trim().parse()?);
+            }
+        }
+
+        match initial {
+            Some(init) => Ok(PushDownAutomaton::new(transitions, init)),
+            None => Err(String::from("No initial configuration found.")),
+        }
+    }
+}
+
+impl<A: FromStr> FromStr for PushDownInstruction<A>
+where
+    A::Err: Debug,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_replace(s.trim().as_bytes()) {
+            IResult::Done(rest, result) => {
+                if rest.is_empty() {
+                    Ok(result)
+                } else {
+                    Err(format!("PushDownInstruction malformed: {}", s))
+                }
+            }
+            _ => Err(format!("PushDownInstruction malformed: {}", s)),
+        }
+    }
+}
+
+/// Parses a string of the form `initial: stack: [⟨token⟩ …], empty:⟨token⟩` as the elements of
+/// the initial `PushDown` (bottom-up) and its empty symbol.
+fn parse_initial_pushdown<A>(input: &[u8]) -> IResult<&[u8], (Vec<A>, A)>
+where
+    A: FromStr,
+    A::Err: Debug,
+{
+    do_parse!(
+        input,
+        tag!("initial:") >>
+            take_while!(is_space) >>
+            tag!("stack:") >>
+            take_while!(is_space) >>
+            elements: call!(|x| parse_vec(x, parse_token, "[", "]", ",")) >>
+            take_while!(is_space) >>
+            tag!(",") >>
+            take_while!(is_space) >>
+            tag!("empty:") >>
+            take_while!(is_space) >>
+            empty: parse_token >>
+            take_while!(|_| true) >>
+            ((elements, empty))
+    )
+}
+
+/// Parses a string of the form `Replace ⟨token⟩, … // ⟨token⟩, …` as a
+/// `PushDownInstruction::Replace`.
+fn parse_replace<A>(input: &[u8]) -> IResult<&[u8], PushDownInstruction<A>>
+where
+    A: FromStr,
+    A::Err: Debug,
+{
+    do_parse!(
+        input,
+        tag!("Replace") >>
+            take_while!(is_space) >>
+            current_val: parse_symbols >>
+            tag!("//") >>
+            take_while!(is_space) >>
+            new_val: parse_symbols >>
+            (PushDownInstruction::Replace { current_val, new_val })
+    )
+}
+
+/// Parses a (possibly empty) sequence of tokens that are separated by `','` and whitespace.
+/// Stops at `"//"` and at the end of the input.
+fn parse_symbols<A>(input: &[u8]) -> IResult<&[u8], Vec<A>>
+where
+    A: FromStr,
+    A::Err: Debug,
+{
+    many0!(
+        input,
+        complete!(
+            do_parse!(
+                opt!(tag!(",")) >>
+                    take_while!(is_space) >>
+                    not!(tag!("//")) >>
+                    symbol: parse_token >>
+                    take_while!(is_space) >>
+                    (symbol)
+            )
+        )
+    )
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use recognisable::automaton::Automaton;
+
+    #[test]
+    fn test_pushdown_instruction_from_str() {
+        let legal_inputs = vec![
+            ("Replace \"(a)\" // ", vec!["(a)"], vec![]),
+            ("Replace I // \"(S)\"", vec!["I"], vec!["(S)"]),
+            ("Replace \"(S)\" // \"(b)\", \"(S)\", \"(a)\"", vec!["(S)"], vec!["(b)", "(S)", "(a)"]),
+            ("Replace a, b // c d", vec!["a", "b"], vec!["c", "d"]),
+            ("Replace // ", vec![], vec![]),
+        ];
+
+        for (legal_input, current_val, new_val) in legal_inputs {
+            let control_instruction = PushDownInstruction::Replace {
+                current_val: current_val.into_iter().map(String::from).collect(),
+                new_val: new_val.into_iter().map(String::from).collect(),
+            };
+            assert_eq!(Ok(control_instruction), legal_input.parse());
+        }
+
+        let illegal_inputs = vec!["Replace a b", "Push a // b", "Replace a // b )"];
+
+        for illegal_input in illegal_inputs {
+            assert!(illegal_input.parse::<PushDownInstruction<String>>().is_err());
+        }
+    }
+
+    #[test]
+    fn test_push_down_automaton_from_str_initial() {
+        let automaton_string = "initial: stack: [@ I], empty:@ % end-of-line comment\n\n\
+                                Transition [] (Replace I // \"(S)\") # 1";
+        let automaton: PushDownAutomaton<String, String, usize> = automaton_string.parse().unwrap();
+        assert_eq!(
+            PushDown::new(String::from("@"), String::from("I")),
+            automaton.initial()
+        );
+
+        let illegal_inputs = vec![
+            "initial: stack: [@ I], empty:I\n",
+            "initial: [@ I]\n",
+            "Transition [] (Replace I // \"(S)\") # 1",
+        ];
+
+        for illegal_input in illegal_inputs {
+            assert!(illegal_input.parse::<PushDownAutomaton<String, String, usize>>().is_err());
+        }
+    }
+
+    #[test]
+    fn test_push_down_automaton_from_str_display_inverse() {
+        let automaton_string = "% leading comment\n\
+                                initial: stack: [@ I], empty:@\n\n\
+                                Transition [a] (Replace \"(a)\" // ) # 1\n\
+                                Transition [] (Replace \"(S)\" // \"(a)\", \"(S)\") # 2\n\
+                                Transition [] (Replace I // \"(S)\") # 1\n\
+                                % trailing comment";
+        let automaton: PushDownAutomaton<String, String, usize> = automaton_string.parse().unwrap();
+        let reparsed: PushDownAutomaton<String, String, usize> =
+            automaton.to_string().parse().unwrap();
+
+        assert_eq!(automaton.initial(), reparsed.initial());
+        let mut transitions: Vec<_> = automaton.list_transitions().collect();
+        let mut reparsed_transitions: Vec<_> = reparsed.list_transitions().collect();
+        transitions.sort();
+        reparsed_transitions.sort();
+        assert_eq!(transitions, reparsed_transitions);
+    }
+}
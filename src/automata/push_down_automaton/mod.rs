@@ -13,13 +13,21 @@ use std::rc::Rc;
 use std::slice::Iter;
 use std::vec::Vec;
 
+use util::budget::BudgetTracker;
+use util::dot::{graph_to_dot, transition_label, ToDot};
 use util::integerisable::{Integerisable1, Integerisable2};
 use util::push_down::Pushdown;
 
 mod from_cfg;
-// TODO: mod from_str;
+mod from_cfg_lr;
+mod from_str;
+mod steps;
+mod tabular;
 
 pub use self::from_cfg::*;
+pub use self::from_cfg_lr::{LRAction, LRConflict, LRKind, LRSymbol, LRTable};
+pub use self::steps::{elementary_steps, StepKind};
+pub use self::tabular::TabularRecogniser;
 
 type TransitionMap<A, T, W> = HashMap<A, BinaryHeap<Transition<PushDownInstruction<A>, T, W>>>;
 
@@ -335,6 +343,18 @@ where
     ) -> Box<Iterator<Item = Self::Parse> + 'a> {
         Box::new(recognisable::automaton::recognise_beam(self, beam, word))
     }
+
+    fn recognise_with_budget<'a>(
+        &'a self,
+        beam: Option<usize>,
+        budget: BudgetTracker,
+        word: Vec<T>,
+    ) -> Box<Iterator<Item = Self::Parse> + 'a>
+    where
+        Self::Parse: 'a,
+    {
+        Box::new(recognisable::automaton::recognise_with_budget(self, beam, budget, word))
+    }
 }
 
 impl<A> PushDown<A> {
@@ -500,6 +520,29 @@ where
     }
 }
 
+/// The nodes are the top-most symbols of the pushdown before and after a
+/// transition; `ε` stands for the (unknown) top-most symbol after a
+/// transition that only pops.
+impl<A, T, W> ToDot for PushDownAutomaton<A, T, W>
+where
+    A: Clone + Display + Hash + Ord,
+    T: Clone + Display + Hash + Eq + Ord,
+    W: Clone + Display + Ord,
+{
+    fn to_dot(&self) -> String {
+        let top = |symbols: &[A]| symbols.last().map_or(String::from("ε"), |a| a.to_string());
+        let edges = self.list_transitions().map(|t| {
+            let PushDownInstruction::Replace { ref current_val, ref new_val } = t.instruction;
+            ( current_val.first().map_or(String::from("ε"), |a| a.to_string())
+            , top(new_val)
+            , transition_label(&t.word, &t.instruction.to_string(), &t.weight)
+            )
+        });
+        let initial = self.a_integeriser.find_value(*self.initial.current_symbol()).unwrap().to_string();
+        graph_to_dot(&[initial], &[], edges)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
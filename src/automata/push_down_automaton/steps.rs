@@ -0,0 +1,69 @@
+/// What an elementary step of a pushdown automaton does with the top-most
+/// symbol of the pushdown.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StepKind<A> {
+    /// replaces it by a symbol
+    Swap(A),
+    /// replaces it by the first symbol and pushes the second one
+    Push(A, A),
+    /// removes it
+    Pop,
+    /// removes it and replaces the first symbol below by the second one
+    PopSwap(A, A),
+}
+
+/// Splits a transition that replaces `current_val` by `new_val` into
+/// elementary steps that push or pop at most one symbol, each given by the
+/// top-most symbol it expects and what it does with it.
+/// Intermediate symbols are created by `fresh`; as they only occur in these
+/// steps, the steps are applied one after another without interruption.
+pub fn elementary_steps<A, F>(current_val: &[A], new_val: &[A], mut fresh: F) -> Vec<(A, StepKind<A>)>
+where
+    A: Clone,
+    F: FnMut() -> A,
+{
+    let mut steps = Vec::new();
+
+    let mut top = current_val[0].clone();
+    for below in &current_val[1..] {
+        let marker = fresh();
+        steps.push((top, StepKind::PopSwap(below.clone(), marker.clone())));
+        top = marker;
+    }
+    match new_val.len() {
+        0 => steps.push((top, StepKind::Pop)),
+        1 => steps.push((top, StepKind::Swap(new_val[0].clone()))),
+        q => for l in 0..q - 1 {
+            let pushed = if l + 2 == q { new_val[q - 1].clone() } else { fresh() };
+            steps.push((top, StepKind::Push(new_val[l].clone(), pushed.clone())));
+            top = pushed;
+        },
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elementary_steps() {
+        let mut fresh = 10;
+        let steps = elementary_steps(&[1, 2], &[3, 4, 5], || {
+            fresh += 1;
+            fresh - 1
+        });
+        assert_eq!(
+            vec![
+                (1, StepKind::PopSwap(2, 10)),
+                (10, StepKind::Push(3, 11)),
+                (11, StepKind::Push(4, 5)),
+            ],
+            steps
+        );
+        assert_eq!(12, fresh);
+
+        assert_eq!(vec![(1, StepKind::Pop)], elementary_steps(&[1], &[], || 0));
+        assert_eq!(vec![(1, StepKind::Swap(2))], elementary_steps(&[1], &[2], || 0));
+    }
+}
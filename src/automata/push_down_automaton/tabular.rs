@@ -0,0 +1,451 @@
+use num_traits::One;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::{AddAssign, Mul, MulAssign};
+
+use num_traits::Zero;
+use recognisable::{Configuration, Item, Recognisable, Transition};
+use recognisable::automaton::Automaton;
+use recognisable::chart::{Chart, Edge};
+use util::budget::BudgetTracker;
+use util::push_down::Pushdown;
+
+use super::{elementary_steps, PushDown, PushDownAutomaton, PushDownInstruction, StepKind};
+
+/// Recognises words with a `PushDownAutomaton` using Lang's dynamic
+/// programming with a graph-structured stack.
+/// Instead of whole configurations, it stores how a level of the pushdown
+/// evolves between two input positions; hence recognition takes polynomial
+/// time even if the automaton is highly ambiguous, and the runs are
+/// enumerated best-first by lazy k-best extraction.
+///
+/// The weights of the transitions must not be greater than `W::one()`.
+/// Runs may loop without reading input (e.g. for automata of grammars with
+/// ε- or unary cycles); the items then form cycles, which the enumeration
+/// follows, see `Chart::kth`.
+///
+/// ```
+/// extern crate log_domain;
+/// extern crate rustomata;
+///
+/// use std::str::FromStr;
+/// use log_domain::LogDomain;
+/// use rustomata::automata::push_down_automaton::{PushDownAutomaton, TabularRecogniser};
+/// use rustomata::grammars::cfg::CFG;
+/// use rustomata::recognisable::Recognisable;
+///
+/// # fn main() {
+/// let grammar: CFG<String, String, LogDomain<f64>> = CFG::from_str(
+///     "initial: [S]\n\n\
+///      S → [Nt S, Nt S] # 0.5\n\
+///      S → [T a] # 0.5"
+/// ).unwrap();
+/// let recogniser = TabularRecogniser::new(PushDownAutomaton::from(grammar));
+///
+/// let word: Vec<String> = vec!["a"; 4].into_iter().map(String::from).collect();
+/// // there are 5 binary trees with 4 leaves
+/// assert_eq!(5, recogniser.recognise(word).take(10).count());
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TabularRecogniser<A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Eq + Hash,
+    W: Ord,
+{
+    automaton: PushDownAutomaton<A, T, W>,
+    transitions: Vec<Transition<PushDownInstruction<usize>, usize, W>>,
+    steps: Vec<Step<W>>,
+    steps_by_top: HashMap<usize, Vec<usize>>,
+    /// the symbols of the automaton are `0 .. symbols`, greater ones are
+    /// introduced by `normalise`
+    symbols: usize,
+    /// the bottom-most symbol that is replaced by the initial pushdown
+    bottom: usize,
+}
+
+/// An elementary operation on a pushdown; each transition is split into
+/// a sequence of steps, only the first of which reads its word and carries
+/// its weight and its index.
+#[derive(Debug, Clone)]
+struct Step<W> {
+    top: usize,
+    kind: StepKind<usize>,
+    word: Vec<usize>,
+    weight: W,
+    transition: Option<usize>,
+}
+
+/// Splits a transition that replaces `current_val` by `new_val` into steps,
+/// see `elementary_steps`; intermediate symbols are taken from `fresh`.
+fn normalise<W: Copy + One>(
+    current_val: &[usize],
+    new_val: &[usize],
+    word: &[usize],
+    weight: W,
+    transition: Option<usize>,
+    fresh: &mut usize,
+) -> Vec<Step<W>> {
+    let next_fresh = || {
+        *fresh += 1;
+        *fresh - 1
+    };
+    elementary_steps(current_val, new_val, next_fresh)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (top, kind))| if i == 0 {
+            Step { top, kind, word: word.to_vec(), weight, transition }
+        } else {
+            Step { top, kind, word: Vec::new(), weight: W::one(), transition: None }
+        })
+        .collect()
+}
+
+impl<A, T, W> TabularRecogniser<A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Eq + Hash,
+    W: Copy + One + Ord,
+{
+    pub fn new(automaton: PushDownAutomaton<A, T, W>) -> Self {
+        let transitions: Vec<_> = automaton.transitions.values().flat_map(|h| h.iter().cloned()).collect();
+
+        let symbols = transitions
+            .iter()
+            .flat_map(|t| {
+                let PushDownInstruction::Replace { ref current_val, ref new_val } = t.instruction;
+                current_val.iter().chain(new_val)
+            })
+            .chain(automaton.initial.iter());
+        let mut fresh = symbols.max().map_or(0, |a| a + 1);
+        let bottom = fresh;
+        fresh += 1;
+
+        let mut steps = Vec::new();
+        for (i, t) in transitions.iter().enumerate() {
+            let PushDownInstruction::Replace { ref current_val, ref new_val } = t.instruction;
+            steps.extend(normalise(current_val, new_val, &t.word, t.weight, Some(i), &mut fresh));
+        }
+        // the initial pushdown is built on top of `bottom`
+        steps.extend(normalise(&[bottom], &automaton.initial.elements, &[], W::one(), None, &mut fresh));
+
+        let mut steps_by_top = HashMap::new();
+        for (i, step) in steps.iter().enumerate() {
+            steps_by_top.entry(step.top).or_insert_with(Vec::new).push(i);
+        }
+
+        TabularRecogniser {
+            automaton,
+            transitions,
+            steps,
+            steps_by_top,
+            symbols: bottom,
+            bottom,
+        }
+    }
+
+    pub fn automaton(&self) -> &PushDownAutomaton<A, T, W> {
+        &self.automaton
+    }
+}
+
+impl<A, T, W> From<PushDownAutomaton<A, T, W>> for TabularRecogniser<A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Eq + Hash,
+    W: Copy + One + Ord,
+{
+    fn from(automaton: PushDownAutomaton<A, T, W>) -> Self {
+        TabularRecogniser::new(automaton)
+    }
+}
+
+/// The items of the chart.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Node {
+    /// `Level(b, i, c, j)`: a level of the pushdown that started with `b` on
+    /// top at position `i` has `c` on top at position `j`; the pushdown
+    /// below was not touched in between.
+    Level(usize, usize, usize, usize),
+    /// `Suspended(b, i, c, d, j)`: a level that started with `b` at `i` has
+    /// `c` on top and `d` was pushed on top of it at `j`.
+    Suspended(usize, usize, usize, usize, usize),
+    /// `Popped(b, i, j)`: a level that started with `b` at `i` was removed at `j`.
+    Popped(usize, usize, usize),
+    /// the pushdown consists of a single level that starts with the bottom
+    /// symbol at the first position and the word was read completely
+    Goal,
+}
+
+impl<A, T, W> TabularRecogniser<A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Eq + Hash,
+    W: Copy + Mul<Output = W> + One + Ord,
+{
+    /// Fills the chart for `word` with Knuth's algorithm; at most `beam`
+    /// items are kept for each position of the word.
+    fn chart(&self, word: &[usize], beam: Option<usize>, budget: BudgetTracker) -> Chart<Node, W> {
+        use self::Node::*;
+
+        let mut chart = Chart::new(budget.clone());
+        let mut agenda = BinaryHeap::new();
+        let mut started = HashSet::new();
+        let mut kept = vec![0; word.len() + 1];
+
+        // finished items by the start of the level they belong to, or by the
+        // start of the level that was pushed onto them
+        let mut levels: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        let mut suspended: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        let mut popped: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+
+        let matches = |step: &Step<W>, j: usize| word[j..].starts_with(&step.word);
+        let no_steps = Vec::new();
+
+        started.insert((self.bottom, 0));
+        agenda.extend(chart.propose(Level(self.bottom, 0, self.bottom, 0), Edge { tails: Vec::new(), label: None, weight: W::one() }));
+
+        while let Some((weight, i)) = agenda.pop() {
+            if chart.nodes[i].finished || weight != chart.nodes[i].weight {
+                continue;
+            }
+            if !budget.pop() {
+                break;
+            }
+            let node = chart.nodes[i].node.clone();
+            let position = match node {
+                Level(_, _, _, j) | Suspended(_, _, _, _, j) | Popped(_, _, j) => j,
+                Goal => word.len(),
+            };
+            if node != Goal && beam.map_or(false, |beam| kept[position] >= beam) {
+                continue;
+            }
+            kept[position] += 1;
+            chart.nodes[i].finished = true;
+
+            let mut new_edges = Vec::new();
+            match node {
+                Level(b, p, c, j) => {
+                    if b == self.bottom && p == 0 && j == word.len() && c < self.symbols {
+                        new_edges.push((Goal, vec![i], None));
+                    }
+                    levels.entry((b, p)).or_insert_with(Vec::new).push(i);
+                    for &s in self.steps_by_top.get(&c).unwrap_or(&no_steps) {
+                        let step = &self.steps[s];
+                        if !matches(step, j) {
+                            continue;
+                        }
+                        let k = j + step.word.len();
+                        match step.kind {
+                            StepKind::Swap(d) => new_edges.push((Level(b, p, d, k), vec![i], Some(s))),
+                            StepKind::Push(c1, d) => {
+                                new_edges.push((Suspended(b, p, c1, d, k), vec![i], Some(s)));
+                                if started.insert((d, k)) {
+                                    new_edges.push((Level(d, k, d, k), Vec::new(), None));
+                                }
+                            }
+                            StepKind::Pop => new_edges.push((Popped(b, p, k), vec![i], Some(s))),
+                            StepKind::PopSwap(below, d) => {
+                                for &l in suspended.get(&(b, p)).unwrap_or(&no_steps) {
+                                    if let Suspended(b0, p0, c0, _, _) = chart.nodes[l].node {
+                                        if c0 == below {
+                                            new_edges.push((Level(b0, p0, d, k), vec![l, i], Some(s)));
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                Suspended(b0, p0, c0, d, j) => {
+                    suspended.entry((d, j)).or_insert_with(Vec::new).push(i);
+                    for &l in popped.get(&(d, j)).unwrap_or(&no_steps) {
+                        if let Popped(_, _, k) = chart.nodes[l].node {
+                            new_edges.push((Level(b0, p0, c0, k), vec![i, l], None));
+                        }
+                    }
+                    for &l in levels.get(&(d, j)).unwrap_or(&no_steps) {
+                        if let Level(_, _, c, k) = chart.nodes[l].node {
+                            for &s in self.steps_by_top.get(&c).unwrap_or(&no_steps) {
+                                let step = &self.steps[s];
+                                if let StepKind::PopSwap(below, d1) = step.kind {
+                                    if below == c0 && matches(step, k) {
+                                        new_edges.push((Level(b0, p0, d1, k + step.word.len()), vec![i, l], Some(s)));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                Popped(b, p, k) => {
+                    popped.entry((b, p)).or_insert_with(Vec::new).push(i);
+                    for &l in suspended.get(&(b, p)).unwrap_or(&no_steps) {
+                        if let Suspended(b0, p0, c0, _, _) = chart.nodes[l].node {
+                            new_edges.push((Level(b0, p0, c0, k), vec![l, i], None));
+                        }
+                    }
+                }
+                Goal => (),
+            }
+
+            for (node, tails, step) in new_edges {
+                let weight = step.map_or(W::one(), |s| self.steps[s].weight);
+                agenda.extend(chart.propose(node, Edge { tails, label: step, weight }));
+            }
+        }
+
+        chart
+    }
+}
+
+/// Enumerates the runs of a `TabularRecogniser` best-first.
+struct TabularRuns<'a, A, T, W>
+where
+    A: 'a + Clone + Hash + Ord,
+    T: 'a + Eq + Hash,
+    W: 'a + Ord,
+{
+    recogniser: &'a TabularRecogniser<A, T, W>,
+    chart: Chart<Node, W>,
+    k: usize,
+    budget: BudgetTracker,
+}
+
+impl<'a, A, T, W> Iterator for TabularRuns<'a, A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: AddAssign + Copy + Mul<Output = W> + MulAssign + One + Ord + Zero,
+{
+    type Item = Item<PushDown<A>, PushDownInstruction<A>, T, W>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let goal = *self.chart.index.get(&Node::Goal)?;
+        if !self.chart.nodes[goal].finished {
+            return None;
+        }
+        let weight = self.chart.kth(goal, self.k)?;
+        if !self.budget.candidate() {
+            return None;
+        }
+
+        let mut steps = Vec::new();
+        self.chart.read(goal, self.k, &mut steps);
+        let (e, _, _) = self.chart.derivations[&goal].derivations[self.k];
+        let top = match self.chart.nodes[self.chart.nodes[goal].edges[e].tails[0]].node {
+            Node::Level(_, _, c, _) => c,
+            _ => unreachable!(),
+        };
+        self.k += 1;
+
+        let mut run = Pushdown::new();
+        for s in steps {
+            if let Some(t) = self.recogniser.steps[s].transition {
+                run = run.push(self.recogniser.transitions[t].clone());
+            }
+        }
+        let item = Item(
+            Configuration { word: Vec::new(), storage: PushDown::from(vec![top]), weight },
+            run,
+        );
+        Some(self.recogniser.automaton.item_map(&item))
+    }
+}
+
+impl<A, T, W> TabularRecogniser<A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: AddAssign + Copy + Mul<Output = W> + MulAssign + One + Ord + Zero,
+{
+    fn runs<'a>(
+        &'a self,
+        beam: Option<usize>,
+        budget: BudgetTracker,
+        word: Vec<T>,
+    ) -> Box<Iterator<Item = Item<PushDown<A>, PushDownInstruction<A>, T, W>> + 'a> {
+        let word: Option<Vec<_>> = word.iter().map(|t| self.automaton.terminal_to_int(t)).collect();
+        let chart = match word {
+            Some(word) => self.chart(&word, beam, budget.clone()),
+            None => return Box::new(::std::iter::empty()),
+        };
+        Box::new(TabularRuns { recogniser: self, chart, k: 0, budget })
+    }
+}
+
+impl<A, T, W> Recognisable<T, W> for TabularRecogniser<A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: AddAssign + Copy + Mul<Output = W> + MulAssign + One + Ord + Zero,
+{
+    type Parse = Item<PushDown<A>, PushDownInstruction<A>, T, W>;
+
+    fn recognise<'a>(&'a self, word: Vec<T>) -> Box<Iterator<Item = Self::Parse> + 'a> {
+        self.runs(None, BudgetTracker::unlimited(), word)
+    }
+
+    /// The `beam` limits the number of items for each position of the word.
+    fn recognise_beam_search<'a>(
+        &'a self,
+        beam: usize,
+        word: Vec<T>,
+    ) -> Box<Iterator<Item = Self::Parse> + 'a> {
+        self.runs(Some(beam), BudgetTracker::unlimited(), word)
+    }
+
+    /// Each item taken from the agenda of the chart and each candidate for a
+    /// derivation counts as an agenda pop.
+    fn recognise_with_budget<'a>(
+        &'a self,
+        beam: Option<usize>,
+        budget: BudgetTracker,
+        word: Vec<T>,
+    ) -> Box<Iterator<Item = Self::Parse> + 'a>
+    where
+        Self::Parse: 'a,
+    {
+        self.runs(beam, budget, word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use log_domain::LogDomain;
+    use grammars::cfg::CFG;
+    use recognisable::automaton::recognise;
+
+    #[test]
+    fn test_ambiguous_grammar() {
+        let grammar: CFG<String, String, LogDomain<f64>> = CFG::from_str(
+            "initial: [S]\n\n\
+             S → [Nt S, Nt S] # 0.4\n\
+             S → [Nt A] # 0.2\n\
+             S → [T a] # 0.4\n\
+             A → [Nt S] # 0.5"
+        ).unwrap();
+        let automaton = PushDownAutomaton::from(grammar);
+        let recogniser = TabularRecogniser::new(automaton.clone());
+        let word: Vec<String> = vec!["a"; 3].into_iter().map(String::from).collect();
+
+        // the unary cycle S → A → S yields infinitely many runs
+        let tabular: Vec<_> = recogniser.recognise(word.clone()).take(20).collect();
+        let agenda: Vec<_> = recognise(&automaton, word.clone()).take(20).collect();
+        assert_eq!(20, tabular.len());
+        for (t, a) in tabular.iter().zip(&agenda) {
+            assert_eq!(a.0.weight, t.0.weight);
+            assert_eq!(a.0.storage, t.0.storage);
+            assert_eq!(automaton.check_run(t.1.clone())[0].0.storage, t.0.storage);
+        }
+        assert!(tabular.windows(2).all(|w| w[0].0.weight >= w[1].0.weight));
+
+        let runs: HashSet<_> = tabular.into_iter().map(|i| i.1.to_vec()).collect();
+        assert_eq!(20, runs.len());
+
+        assert!(recogniser.recognise(vec![String::from("b")]).next().is_none());
+        assert!(recogniser.recognise(Vec::new()).next().is_none());
+    }
+}
@@ -0,0 +1,97 @@
+use std::hash::Hash;
+
+use automata::tree_automaton::{TreeAutomaton, TreeTransition};
+use grammars::pmcfg::{PMCFG, PMCFGRule};
+
+/// Constructs a tree automaton for the derivation trees of a PMCFG, whose
+/// nodes are labelled with rules.
+/// The states are the nonterminals; each rule `A → f(B₁, …, Bₖ)` becomes the
+/// transition `(A → f(B₁, …, Bₖ))(B₁, …, Bₖ) → A` with the weight of the rule,
+/// and the initial nonterminals are final.
+/// Hence the weight of a derivation tree is the weight of the derivation.
+impl<N, T, W> From<PMCFG<N, T, W>> for TreeAutomaton<N, PMCFGRule<N, T, W>, W>
+where
+    N: Clone + Eq + Hash + Ord,
+    T: Clone + Eq + Hash,
+    W: Clone,
+{
+    fn from(grammar: PMCFG<N, T, W>) -> Self {
+        let PMCFG { initial, rules } = grammar;
+        let transitions: Vec<_> = rules
+            .into_iter()
+            .map(|rule| {
+                TreeTransition {
+                    source_states: rule.tail.clone(),
+                    target_state: rule.head.clone(),
+                    weight: rule.weight.clone(),
+                    symbol: rule,
+                }
+            })
+            .collect();
+
+        TreeAutomaton::new(transitions, initial)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+    use util::tree::GornTree;
+
+    #[test]
+    fn test_from_pmcfg() {
+        let grammar: PMCFG<String, String, LogDomain<f64>> = "initial: [S]\n\
+             S → [[Var 0 0, Var 1 0]] (A, B) # 0.5\n\
+             A → [[T a, Var 0 0]] (A) # 0.4\n\
+             A → [[T a]] () # 0.6\n\
+             B → [[T b]] ()"
+            .parse()
+            .unwrap();
+        let rules = grammar.rules.clone();
+        let automaton = TreeAutomaton::from(grammar);
+
+        let derivation: GornTree<_> = vec![
+            (vec![], rules[0].clone()),
+            (vec![0], rules[1].clone()),
+            (vec![0, 0], rules[2].clone()),
+            (vec![1], rules[3].clone()),
+        ].into_iter()
+            .collect();
+        assert!((automaton.weight(&derivation).unwrap().value() - 0.5 * 0.4 * 0.6).abs() < 1e-9);
+
+        let ill_formed: GornTree<_> = vec![
+            (vec![], rules[0].clone()),
+            (vec![0], rules[3].clone()),
+            (vec![1], rules[2].clone()),
+        ].into_iter()
+            .collect();
+        assert!(!automaton.accepts(&ill_formed));
+
+        let best = automaton.k_best(1);
+        assert_eq!(1, best.len());
+        assert!((best[0].1.value() - 0.5 * 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_from_pmcfg_display_from_str_inverse() {
+        // the rules are written with quoted nonterminals and terminals
+        let grammar: PMCFG<String, String, LogDomain<f64>> = "initial: [S]\n\
+             S → [[T a, Var 0 0]] (A) # 0.5\n\
+             A → [[T \"b c\"]] ()"
+            .parse()
+            .unwrap();
+        let automaton = TreeAutomaton::from(grammar);
+        let reparsed: TreeAutomaton<String, PMCFGRule<String, String, LogDomain<f64>>, LogDomain<f64>> =
+            automaton.to_string().parse().unwrap();
+
+        assert_eq!(automaton.final_states(), reparsed.final_states());
+        assert_eq!(automaton.transitions().len(), reparsed.transitions().len());
+        for (t1, t2) in automaton.transitions().iter().zip(reparsed.transitions()) {
+            assert_eq!(
+                (&t1.symbol, &t1.source_states, &t1.target_state),
+                (&t2.symbol, &t2.source_states, &t2.target_state)
+            );
+        }
+    }
+}
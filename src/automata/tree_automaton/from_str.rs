@@ -0,0 +1,198 @@
+use nom::{IResult, is_space};
+use num_traits::One;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::str::{FromStr, from_utf8};
+
+use automata::tree_automaton::{TreeAutomaton, TreeTransition};
+use util::parsing::{parse_unescaped_token, parse_vec, unescape};
+
+impl<Q, T, W> FromStr for TreeAutomaton<Q, T, W>
+where
+    Q: Clone + Eq + FromStr + Hash + Ord,
+    Q::Err: Debug,
+    T: Clone + Eq + FromStr + Hash,
+    T::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut finals = Vec::new();
+        let mut transitions: Vec<TreeTransition<Q, T, W>> = Vec::new();
+
+        for l in s.lines() {
+            if l.trim_start().starts_with("final:") {
+                match parse_final_states(l.trim_start().as_bytes()) {
+                    IResult::Done(_, mut result) => finals.append(&mut result),
+                    _ => return Err(format!("Malformed final declaration: {}", l)),
+                }
+            } else if !l.trim().is_empty() && !l.trim_start().starts_with("%") {
+                transitions.push(l.trim().parse()?);
+            }
+        }
+
+        if finals.is_empty() {
+            Err("No final states found.".to_string())
+        } else {
+            Ok(TreeAutomaton::new(transitions, finals))
+        }
+    }
+}
+
+/// Parses a transition of the form `σ(q₁, …, qₖ) → q`, followed by an
+/// optional weight, e.g. `f(q, p) → q # 0.5`.
+/// The parentheses may be omitted for nullary symbols.
+impl<Q, T, W> FromStr for TreeTransition<Q, T, W>
+where
+    Q: FromStr,
+    Q::Err: Debug,
+    T: FromStr,
+    T::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_tree_transition(s.as_bytes()) {
+            IResult::Done(_, result) => Ok(result),
+            _ => Err(format!("Could not parse {}", s)),
+        }
+    }
+}
+
+/// Parses a string of the form `final: [...]` like `parse_finals`, but
+/// removes the escaping of the final states.
+fn parse_final_states<Q>(input: &[u8]) -> IResult<&[u8], Vec<Q>>
+where
+    Q: FromStr,
+    Q::Err: Debug,
+{
+    do_parse!(
+        input,
+        tag!("final:") >>
+        take_while!(is_space) >>
+        result: call!(|x| parse_vec(x, parse_unescaped_token, "[", "]", ",")) >>
+        (result)
+    )
+}
+
+fn parse_tree_transition<Q, T, W>(input: &[u8]) -> IResult<&[u8], TreeTransition<Q, T, W>>
+where
+    Q: FromStr,
+    Q::Err: Debug,
+    T: FromStr,
+    T::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    do_parse!(
+        input,
+        symbol: parse_symbol >>
+        take_while!(is_space) >>
+        source_states: opt!(call!(|x| parse_vec(x, parse_unescaped_token, "(", ")", ","))) >>
+        take_while!(is_space) >>
+        alt!(tag!("→") | tag!("->")) >>
+        take_while!(is_space) >>
+        target_state: parse_unescaped_token >>
+        take_while!(is_space) >>
+        weight_o: opt!(
+            complete!(
+                do_parse!(
+                    tag!("#") >>
+                    take_while!(is_space) >>
+                    weight_s: map_res!(is_not!(" "), from_utf8) >>
+                    weight: expr_res!(weight_s.parse()) >>
+                    (weight)
+                )
+            )
+        ) >>
+        take_while!(is_space) >>
+        alt!(
+            eof!() |
+            preceded!(tag!("%"), take_while!(|_| true))
+        ) >>
+        (TreeTransition {
+            symbol,
+            source_states: source_states.unwrap_or_else(Vec::new),
+            target_state,
+            weight: weight_o.unwrap_or(W::one()),
+        })
+    )
+}
+
+/// Parses the symbol of a transition like `parse_unescaped_token`, except
+/// that a symbol that is not delimited by `'"'` also ends with `'('`.
+fn parse_symbol<T>(input: &[u8]) -> IResult<&[u8], T>
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    named!(
+        parse_symbol_s<&str>,
+        map_res!(
+            alt!(
+                delimited!(
+                    tag!("\""),
+                    escaped!(is_not!("\"\\"), '\\', one_of!("\\\"")),
+                    tag!("\"")
+                ) |
+                is_not!(" \\\"-→,;()]%#")
+            ),
+            from_utf8
+        )
+    );
+
+    do_parse!(
+        input,
+        output: parse_symbol_s >>
+        symbol: expr_res!(unescape(output).parse()) >>
+        (symbol)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_transition_from_str() {
+        let control = TreeTransition {
+            symbol: 'f',
+            source_states: vec![1, 2],
+            target_state: 3,
+            weight: 2,
+        };
+        let inputs = vec![
+            "f(1, 2) → 3 # 2",
+            "f (1,2) -> 3 # 2 % comment",
+            "\"f\"(\"1\", \"2\") → \"3\"  # 2",
+        ];
+        for input in inputs {
+            assert_eq!(Ok(control.clone()), input.parse::<TreeTransition<usize, char, usize>>());
+        }
+
+        let nullary: TreeTransition<usize, char, usize> = TreeTransition {
+            symbol: 'a',
+            source_states: Vec::new(),
+            target_state: 1,
+            weight: 1,
+        };
+        assert_eq!(Ok(nullary.clone()), "a → 1".parse());
+        assert_eq!(Ok(nullary), "a() → 1".parse());
+
+        assert!("f(1, 2)".parse::<TreeTransition<usize, char, usize>>().is_err());
+    }
+
+    #[test]
+    fn test_tree_automaton_from_str_comments() {
+        let automaton_string = "% leading comment\n\
+                                final: [1] % end-of-line comment\n\n\
+                                a() → 1\n\
+                                % trailing comment";
+        let automaton: TreeAutomaton<usize, char, usize> = automaton_string.parse().unwrap();
+        assert_eq!(1, automaton.transitions().len());
+    }
+}
@@ -0,0 +1,568 @@
+use num_traits::One;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::hash::Hash;
+use std::ops::{Mul, Range};
+
+use recognisable::chart::{Chart, Edge};
+use util::budget::BudgetTracker;
+use util::parsing::escape;
+use util::tree::{child_address, GornTree};
+
+mod from_pmcfg;
+mod from_str;
+
+/// A transition `σ(q₁, …, qₖ) → q` of a bottom-up tree automaton, which
+/// assigns the state `q` to a node with the symbol `σ` whose children were
+/// assigned the states `q₁, …, qₖ`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TreeTransition<Q, T, W> {
+    pub symbol: T,
+    pub source_states: Vec<Q>,
+    pub target_state: Q,
+    pub weight: W,
+}
+
+impl<Q: Display, T: Display, W: Display> Display for TreeTransition<Q, T, W> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let sources: Vec<String> = self.source_states.iter().map(quote).collect();
+        write!(
+            f,
+            "{}({}) → {}  # {}",
+            quote(&self.symbol),
+            sources.join(", "),
+            quote(&self.target_state),
+            self.weight
+        )
+    }
+}
+
+/// A weighted bottom-up tree automaton over trees whose nodes are labelled
+/// with symbols of type `T`.
+/// A run assigns a state to each node of a tree by applying transitions
+/// from the leaves to the root; it is accepting if the root is assigned a
+/// final state.
+/// The weight of a run is the product of the weights of its transitions,
+/// and the weight of a tree is the weight of its best accepting run.
+/// Weights should not be greater than `W::one()`, so that the best run is
+/// found by Knuth's algorithm.
+///
+/// ```
+/// # extern crate log_domain;
+/// # extern crate rustomata;
+/// use log_domain::LogDomain;
+/// use rustomata::automata::tree_automaton::TreeAutomaton;
+///
+/// # fn main() {
+/// // trees over f and a with an even number of nodes
+/// let automaton: TreeAutomaton<String, String, LogDomain<f64>> = "final: [even]\n\
+///                                                                 a() → odd\n\
+///                                                                 f(odd) → even # 0.5\n\
+///                                                                 f(even) → odd # 0.5".parse().unwrap();
+///
+/// let best = automaton.k_best(2);
+/// assert_eq!(2, best.len());
+/// assert_eq!("f\n└── a", best[0].0.to_string());
+/// assert_eq!(Some(best[0].1), automaton.weight(&best[0].0));
+/// assert!((best[1].1.value() - 0.125).abs() < 1e-9);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TreeAutomaton<Q, T, W> {
+    transitions: Vec<TreeTransition<Q, T, W>>,
+    final_states: BTreeSet<Q>,
+    by_symbol: HashMap<T, Vec<usize>>,
+}
+
+/// The nodes of the chart for the enumeration of the best trees.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Node<Q> {
+    /// the state is assigned to the root of a tree
+    State(Q),
+    /// a final state is assigned to the root of a tree
+    Goal,
+}
+
+impl<Q, T, W> TreeAutomaton<Q, T, W>
+where
+    Q: Clone + Eq + Hash + Ord,
+    T: Clone + Eq + Hash,
+{
+    pub fn new<TransIt, FinalsIt>(transitions: TransIt, finals: FinalsIt) -> Self
+    where
+        TransIt: IntoIterator<Item = TreeTransition<Q, T, W>>,
+        FinalsIt: IntoIterator<Item = Q>,
+    {
+        let transitions: Vec<_> = transitions.into_iter().collect();
+        let mut by_symbol = HashMap::new();
+        for (i, transition) in transitions.iter().enumerate() {
+            by_symbol.entry(transition.symbol.clone()).or_insert_with(Vec::new).push(i);
+        }
+
+        TreeAutomaton {
+            transitions,
+            final_states: finals.into_iter().collect(),
+            by_symbol,
+        }
+    }
+
+    pub fn transitions(&self) -> &[TreeTransition<Q, T, W>] {
+        &self.transitions
+    }
+
+    pub fn final_states(&self) -> &BTreeSet<Q> {
+        &self.final_states
+    }
+
+    /// The transitions for the node at `address` whose children have the
+    /// given number.
+    fn transitions_at<'a>(&'a self, tree: &GornTree<T>, address: &[usize], arity: usize) -> Vec<&'a TreeTransition<Q, T, W>> {
+        tree.get(&address.to_vec())
+            .and_then(|symbol| self.by_symbol.get(symbol))
+            .map(|ts| ts.iter().map(|&i| &self.transitions[i]).filter(|t| t.source_states.len() == arity).collect())
+            .unwrap_or_else(Vec::new)
+    }
+}
+
+impl<Q, T, W> TreeAutomaton<Q, T, W>
+where
+    Q: Clone + Eq + Hash + Ord,
+    T: Clone + Eq + Hash,
+    W: Copy + Mul<Output = W> + One + Ord,
+{
+    /// The weights of the best runs on the subtree at `address`, for each
+    /// state that can be assigned to its root.
+    fn run_weights(&self, tree: &GornTree<T>, address: &[usize]) -> HashMap<&Q, W> {
+        let children: Vec<_> = tree.children(address)
+            .iter()
+            .map(|child| self.run_weights(tree, child))
+            .collect();
+
+        let mut weights: HashMap<&Q, W> = HashMap::new();
+        for transition in self.transitions_at(tree, address, children.len()) {
+            let weight = transition
+                .source_states
+                .iter()
+                .zip(&children)
+                .fold(Some(transition.weight), |w, (q, child)| Some(w? * *child.get(q)?));
+            if let Some(weight) = weight {
+                let best = weights.entry(&transition.target_state).or_insert(weight);
+                if weight > *best {
+                    *best = weight;
+                }
+            }
+        }
+        weights
+    }
+
+    /// The weight of the best accepting run on `tree`, if there is one.
+    pub fn weight(&self, tree: &GornTree<T>) -> Option<W> {
+        if tree.is_empty() {
+            return None;
+        }
+        self.run_weights(tree, &[])
+            .into_iter()
+            .filter(|&(q, _)| self.final_states.contains(q))
+            .map(|(_, w)| w)
+            .max()
+    }
+
+    /// Decides whether there is an accepting run on `tree`.
+    pub fn accepts(&self, tree: &GornTree<T>) -> bool {
+        self.weight(tree).is_some()
+    }
+
+    /// Constructs an automaton for the intersection of the languages of
+    /// `self` and `other` using the product construction.
+    /// The weight of a run of the resulting automaton is the product of the
+    /// weights of the corresponding runs of `self` and `other`.
+    /// Only pairs of states that can be assigned to some tree are kept.
+    pub fn intersect<Q2>(&self, other: &TreeAutomaton<Q2, T, W>) -> TreeAutomaton<(Q, Q2), T, W>
+    where
+        Q2: Clone + Eq + Hash + Ord,
+    {
+        let mut reachable: HashSet<(&Q, &Q2)> = HashSet::new();
+        let mut done = HashSet::new();
+        let mut transitions = Vec::new();
+        let no_transitions = Vec::new();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (symbol, ts1) in &self.by_symbol {
+                let ts2 = other.by_symbol.get(symbol).unwrap_or(&no_transitions);
+                for &i1 in ts1 {
+                    for &i2 in ts2 {
+                        let (t1, t2) = (&self.transitions[i1], &other.transitions[i2]);
+                        if t1.source_states.len() != t2.source_states.len() || done.contains(&(i1, i2)) {
+                            continue;
+                        }
+                        let sources: Vec<_> = t1.source_states.iter().zip(&t2.source_states).collect();
+                        if !sources.iter().all(|pair| reachable.contains(pair)) {
+                            continue;
+                        }
+
+                        done.insert((i1, i2));
+                        reachable.insert((&t1.target_state, &t2.target_state));
+                        changed = true;
+                        transitions.push(TreeTransition {
+                            symbol: t1.symbol.clone(),
+                            source_states: sources.into_iter().map(|(p, q)| (p.clone(), q.clone())).collect(),
+                            target_state: (t1.target_state.clone(), t2.target_state.clone()),
+                            weight: t1.weight * t2.weight,
+                        });
+                    }
+                }
+            }
+        }
+
+        let finals: Vec<_> = reachable
+            .into_iter()
+            .filter(|&(p, q)| self.final_states.contains(p) && other.final_states.contains(q))
+            .map(|(p, q)| (p.clone(), q.clone()))
+            .collect();
+
+        TreeAutomaton::new(transitions, finals)
+    }
+
+    /// Constructs a deterministic automaton for the support of this
+    /// automaton using the subset construction, i.e. every tree has at most
+    /// one run.
+    /// All of the weights of the resulting automaton are `W::one()`; its
+    /// states are the non-empty subsets of states that can be assigned to
+    /// some tree, numbered in the order of their discovery.
+    pub fn determinize(&self) -> TreeAutomaton<usize, T, W> {
+        let mut subsets: Vec<BTreeSet<&Q>> = Vec::new();
+        let mut indices: HashMap<BTreeSet<&Q>, usize> = HashMap::new();
+        let mut transitions: HashMap<(&T, Vec<usize>), usize> = HashMap::new();
+
+        // the arities with which each symbol occurs
+        let mut ranks: Vec<(&T, usize)> = self.transitions
+            .iter()
+            .map(|t| (&t.symbol, t.source_states.len()))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        ranks.sort_by_key(|&(_, arity)| arity);
+
+        // in each pass, only the tuples of subsets that contain a subset
+        // discovered in the previous pass are new
+        let mut known = None;
+        while known != Some(subsets.len()) {
+            let discovered = subsets.len();
+            for &(symbol, arity) in &ranks {
+                for sources in tuples(known, discovered, arity) {
+                    let target: BTreeSet<&Q> = self.by_symbol[symbol]
+                        .iter()
+                        .map(|&i| &self.transitions[i])
+                        .filter(|t| {
+                            t.source_states.len() == arity &&
+                                t.source_states.iter().zip(&sources).all(|(q, &s)| subsets[s].contains(q))
+                        })
+                        .map(|t| &t.target_state)
+                        .collect();
+                    if target.is_empty() {
+                        continue;
+                    }
+
+                    let j = match indices.get(&target) {
+                        Some(&j) => j,
+                        None => {
+                            indices.insert(target.clone(), subsets.len());
+                            subsets.push(target);
+                            subsets.len() - 1
+                        }
+                    };
+                    transitions.insert((symbol, sources), j);
+                }
+            }
+            known = Some(discovered);
+        }
+
+        let finals: Vec<usize> = subsets
+            .iter()
+            .enumerate()
+            .filter(|&(_, subset)| subset.iter().any(|q| self.final_states.contains(q)))
+            .map(|(i, _)| i)
+            .collect();
+
+        TreeAutomaton::new(
+            transitions.into_iter().map(|((symbol, source_states), target_state)| {
+                TreeTransition {
+                    symbol: symbol.clone(),
+                    source_states,
+                    target_state,
+                    weight: W::one(),
+                }
+            }),
+            finals,
+        )
+    }
+
+    /// The hypergraph of the states, whose edges are the transitions, with
+    /// the best run for each state found by Knuth's algorithm.
+    fn chart(&self) -> Chart<Node<Q>, W> {
+        let mut chart = Chart::new(BudgetTracker::unlimited());
+        let mut agenda = BinaryHeap::new();
+
+        // the number of source states of each transition that are not
+        // finished yet, and the transitions for each source state
+        let mut waiting: Vec<usize> = self.transitions.iter().map(|t| t.source_states.len()).collect();
+        let mut by_source: HashMap<&Q, Vec<usize>> = HashMap::new();
+        for (i, transition) in self.transitions.iter().enumerate() {
+            for q in &transition.source_states {
+                by_source.entry(q).or_insert_with(Vec::new).push(i);
+            }
+            if transition.source_states.is_empty() {
+                let edge = Edge { tails: Vec::new(), label: Some(i), weight: transition.weight };
+                agenda.extend(chart.propose(Node::State(transition.target_state.clone()), edge));
+            }
+        }
+
+        while let Some((weight, i)) = agenda.pop() {
+            if chart.nodes[i].finished || weight != chart.nodes[i].weight {
+                continue;
+            }
+            chart.nodes[i].finished = true;
+
+            if let Node::State(q) = chart.nodes[i].node.clone() {
+                if self.final_states.contains(&q) {
+                    let edge = Edge { tails: vec![i], label: None, weight: W::one() };
+                    agenda.extend(chart.propose(Node::Goal, edge));
+                }
+                for &t in by_source.get(&q).unwrap_or(&Vec::new()) {
+                    waiting[t] -= 1;
+                    if waiting[t] == 0 {
+                        let transition = &self.transitions[t];
+                        let tails = transition
+                            .source_states
+                            .iter()
+                            .map(|p| chart.index[&Node::State(p.clone())])
+                            .collect();
+                        let edge = Edge { tails, label: Some(t), weight: transition.weight };
+                        agenda.extend(chart.propose(Node::State(transition.target_state.clone()), edge));
+                    }
+                }
+            }
+        }
+
+        chart
+    }
+
+    /// Writes the tree of the `k`-th best run of a node of the chart to
+    /// `address`.
+    fn read(&self, chart: &mut Chart<Node<Q>, W>, node: usize, k: usize, tree: &mut GornTree<T>, address: Vec<usize>) {
+        let (edge, ranks) = chart.derivation(node, k);
+        tree.insert(address.clone(), self.transitions[edge.label.unwrap()].symbol.clone());
+        for (c, (tail, rank)) in edge.tails.into_iter().zip(ranks).enumerate() {
+            self.read(chart, tail, rank, tree, child_address(&address, c));
+        }
+    }
+
+    /// The `k` best trees of the language of this automaton, together with
+    /// their weights, best first.
+    /// Runs on a tree that was already enumerated are skipped.
+    pub fn k_best(&self, k: usize) -> Vec<(GornTree<T>, W)> {
+        let mut chart = self.chart();
+        let goal = match chart.index.get(&Node::Goal) {
+            Some(&goal) => goal,
+            None => return Vec::new(),
+        };
+
+        let mut trees: Vec<(GornTree<T>, W)> = Vec::new();
+        let mut rank = 0;
+        while trees.len() < k {
+            let weight = match chart.kth(goal, rank) {
+                Some(weight) => weight,
+                None => break,
+            };
+            let (edge, ranks) = chart.derivation(goal, rank);
+            let mut tree = GornTree::new();
+            self.read(&mut chart, edge.tails[0], ranks[0], &mut tree, Vec::new());
+            if trees.iter().all(|&(ref t, _)| *t != tree) {
+                trees.push((tree, weight));
+            }
+            rank += 1;
+        }
+        trees
+    }
+}
+
+/// All tuples of the given length over `0 .. n` that are not tuples over
+/// `0 .. known`, i.e. that contain some element of `known .. n`.
+/// If `known` is `None`, all tuples over `0 .. n` are returned.
+fn tuples(known: Option<usize>, n: usize, length: usize) -> Vec<Vec<usize>> {
+    match known {
+        None => product(&vec![0..n; length]),
+        Some(known) => {
+            // grouped by the position of the first element of `known .. n`
+            (0..length)
+                .flat_map(|first| {
+                    let ranges: Vec<_> = (0..length)
+                        .map(|position| if position < first {
+                            0..known
+                        } else if position == first {
+                            known..n
+                        } else {
+                            0..n
+                        })
+                        .collect();
+                    product(&ranges)
+                })
+                .collect()
+        }
+    }
+}
+
+/// All tuples whose elements are taken from the respective ranges.
+fn product(ranges: &[Range<usize>]) -> Vec<Vec<usize>> {
+    let mut result = vec![Vec::new()];
+    for range in ranges {
+        result = result
+            .into_iter()
+            .flat_map(|tuple| {
+                range.clone().map(move |i| {
+                    let mut tuple = tuple.clone();
+                    tuple.push(i);
+                    tuple
+                })
+            })
+            .collect();
+    }
+    result
+}
+
+/// Writes `x` between `'"'`s such that it is read by `parse_unescaped_token`.
+fn quote<A: Display>(x: &A) -> String {
+    format!("\"{}\"", escape(&x.to_string()))
+}
+
+impl<Q, T, W> Display for TreeAutomaton<Q, T, W>
+where
+    Q: Display,
+    T: Display,
+    W: Display,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let finals: Vec<String> = self.final_states.iter().map(quote).collect();
+        writeln!(f, "final: [{}]\n", finals.join(", "))?;
+        for transition in &self.transitions {
+            writeln!(f, "{}", transition)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+
+    /// Trees over `f`, `g` and `a` in which `g` occurs at most once; the
+    /// states count the occurrences of `g`.
+    fn example_automaton() -> TreeAutomaton<String, String, LogDomain<f64>> {
+        "final: [zero, one]\n\
+         a() → zero            # 0.5\n\
+         f(zero, zero) → zero  # 0.2\n\
+         f(zero, one) → one    # 0.2\n\
+         f(one, zero) → one    # 0.2\n\
+         g(zero) → one         # 0.3"
+            .parse()
+            .unwrap()
+    }
+
+    fn tree(nodes: &[(&[usize], &str)]) -> GornTree<String> {
+        nodes.iter().map(|&(address, symbol)| (address.to_vec(), symbol.to_string())).collect()
+    }
+
+    #[test]
+    fn test_weight() {
+        let automaton = example_automaton();
+        let t = tree(&[(&[], "f"), (&[0], "g"), (&[0, 0], "a"), (&[1], "a")]);
+        assert!((automaton.weight(&t).unwrap().value() - 0.2 * 0.3 * 0.5 * 0.5).abs() < 1e-9);
+
+        let t = tree(&[(&[], "g"), (&[0], "g"), (&[0, 0], "a")]);
+        assert!(!automaton.accepts(&t));
+        let t = tree(&[(&[], "f"), (&[0], "a")]);
+        assert!(!automaton.accepts(&t));
+        assert!(!automaton.accepts(&GornTree::new()));
+    }
+
+    #[test]
+    fn test_k_best() {
+        let automaton = example_automaton();
+        let best = automaton.k_best(4);
+        assert_eq!(4, best.len());
+        assert_eq!(tree(&[(&[], "a")]), best[0].0);
+        assert_eq!(tree(&[(&[], "g"), (&[0], "a")]), best[1].0);
+        for window in best.windows(2) {
+            assert!(window[0].1 >= window[1].1);
+        }
+        for &(ref t, w) in &best {
+            assert!((automaton.weight(t).unwrap().value() - w.value()).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_intersect_and_determinize() {
+        let automaton = example_automaton();
+        // trees in which every `f` has a leaf as its first child
+        let other: TreeAutomaton<String, String, LogDomain<f64>> = "final: [leaf, inner]\n\
+             a() → leaf\n\
+             f(leaf, leaf) → inner\n\
+             f(leaf, inner) → inner\n\
+             g(leaf) → inner\n\
+             g(inner) → inner"
+            .parse()
+            .unwrap();
+        let intersection = automaton.intersect(&other);
+        let deterministic = intersection.determinize();
+
+        let trees = vec![
+            (tree(&[(&[], "f"), (&[0], "a"), (&[1], "g"), (&[1, 0], "a")]), true),
+            (tree(&[(&[], "f"), (&[0], "g"), (&[0, 0], "a"), (&[1], "a")]), false),
+            (tree(&[(&[], "g"), (&[0], "g"), (&[0, 0], "a")]), false),
+            (tree(&[(&[], "a")]), true),
+        ];
+        for (t, control_acceptance) in trees {
+            assert_eq!(control_acceptance, intersection.accepts(&t));
+            assert_eq!(control_acceptance, deterministic.accepts(&t));
+            assert_eq!(automaton.weight(&t).filter(|_| control_acceptance), intersection.weight(&t));
+        }
+
+        for t1 in deterministic.transitions() {
+            assert_eq!(
+                1,
+                deterministic
+                    .transitions()
+                    .iter()
+                    .filter(|t2| t1.symbol == t2.symbol && t1.source_states == t2.source_states)
+                    .count()
+            );
+        }
+    }
+
+    #[test]
+    fn test_tuples() {
+        assert_eq!(vec![Vec::<usize>::new()], tuples(None, 0, 0));
+        assert!(tuples(Some(2), 2, 0).is_empty());
+        assert_eq!(4, tuples(None, 2, 2).len());
+        assert_eq!(vec![vec![1, 0], vec![1, 1], vec![0, 1]], tuples(Some(1), 2, 2));
+    }
+
+    #[test]
+    fn test_display_from_str_inverse() {
+        let automaton = example_automaton();
+        let reparsed: TreeAutomaton<String, String, LogDomain<f64>> = automaton.to_string().parse().unwrap();
+
+        assert_eq!(automaton.final_states(), reparsed.final_states());
+        assert_eq!(automaton.transitions().len(), reparsed.transitions().len());
+        for (t1, t2) in automaton.transitions().iter().zip(reparsed.transitions()) {
+            assert_eq!(
+                (&t1.symbol, &t1.source_states, &t1.target_state),
+                (&t2.symbol, &t2.source_states, &t2.target_state)
+            );
+            assert!((t1.weight.value() - t2.weight.value()).abs() < 1e-9);
+        }
+    }
+}
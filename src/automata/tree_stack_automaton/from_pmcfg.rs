@@ -209,30 +209,16 @@ impl<
 pub fn to_abstract_syntax_tree<A>(
     (tree_map, _): (GornTree<PosState<A>>, Vec<usize>),
 ) -> GornTree<A> {
-    let mut abstract_syntax_tree = GornTree::new();
-
-    for (address, pos_state) in tree_map {
-        let (curr_root_child, relative_address) =
-            if let Some((first, rest)) = address.split_first() {
-                (first.clone(), rest.to_vec())
-            } else {
-                continue;
-            };
-
-        if curr_root_child != 0 {
-            continue;
-        }
-
-        if let PosState::Position(value, _, _) = pos_state {
-            abstract_syntax_tree.insert(relative_address, value);
-        } else {
-            panic!(
+    tree_map
+        .into_subtree(&[0])
+        .into_iter()
+        .map(|(address, pos_state)| match pos_state {
+            PosState::Position(value, _, _) => (address, value),
+            _ => panic!(
                 "The given tree map contains 'designated' or 'initial' nodes that are not the root!"
-            );
-        }
-    }
-
-    abstract_syntax_tree
+            ),
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -12,16 +12,20 @@ use num_traits::One;
 
 use integeriser::{HashIntegeriser, Integeriser};
 use recognisable::{Configuration, Item, Recognisable, Transition};
-use recognisable::automaton::{Automaton, recognise, recognise_beam};
+use recognisable::automaton::{Automaton, recognise, recognise_beam, recognise_with_budget};
+use util::budget::BudgetTracker;
+use util::dot::{graph_to_dot, transition_label, ToDot};
 use util::integerisable::{Integerisable1, Integerisable2};
 use util::push_down::Pushdown;
 
 mod from_pmcfg;
 mod from_str;
+mod tabular;
 mod tree_stack;
 mod tree_stack_instruction;
 
 pub use self::from_pmcfg::*;
+pub use self::tabular::TabularRecogniser;
 pub use self::tree_stack::*;
 pub use self::tree_stack_instruction::*;
 
@@ -145,6 +149,18 @@ where
     ) -> Box<Iterator<Item = Self::Parse> + 'a> {
         Box::new(recognise_beam(self, beam, word))
     }
+
+    fn recognise_with_budget<'a>(
+        &'a self,
+        beam: Option<usize>,
+        budget: BudgetTracker,
+        word: Vec<T>,
+    ) -> Box<Iterator<Item = Self::Parse> + 'a>
+    where
+        Self::Parse: 'a,
+    {
+        Box::new(recognise_with_budget(self, beam, budget, word))
+    }
 }
 
 
@@ -240,6 +256,31 @@ where
 }
 
 
+/// The nodes are the symbols at the pointer of the tree stack before and
+/// after a transition.
+impl<A, T, W> ToDot for TreeStackAutomaton<A, T, W>
+where
+    A: Clone + Display + Hash + Ord,
+    T: Clone + Display + Eq + Hash + Ord,
+    W: Clone + Display + Ord,
+{
+    fn to_dot(&self) -> String {
+        let edges = self.list_transitions().map(|t| {
+            let (current, new) = match t.instruction {
+                TreeStackInstruction::Up { ref current_val, ref new_val, .. } |
+                TreeStackInstruction::Push { ref current_val, ref new_val, .. } |
+                TreeStackInstruction::Down { ref current_val, ref new_val, .. } => (current_val, new_val),
+            };
+            ( current.to_string()
+            , new.to_string()
+            , transition_label(&t.word, &t.instruction.to_string(), &t.weight)
+            )
+        });
+        let initial = self.a_integeriser.find_value(*self.initial.current_symbol()).unwrap().to_string();
+        graph_to_dot(&[initial], &[], edges)
+    }
+}
+
 impl<A, T, W> Display for TreeStackAutomaton<A, T, W>
 where
     A: Ord + PartialEq + Clone + Hash + Display,
@@ -0,0 +1,598 @@
+use num_traits::One;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::{Mul, MulAssign};
+
+use recognisable::{Configuration, Instruction, Item, Recognisable, Transition};
+use recognisable::automaton::Automaton;
+use recognisable::chart::{Chart, Edge};
+use util::budget::BudgetTracker;
+use util::push_down::Pushdown;
+
+use super::{TreeStack, TreeStackAutomaton, TreeStackInstruction};
+
+/// Recognises words with a `TreeStackAutomaton` by dynamic programming over
+/// the subtrees of the tree stack.
+/// A node of the tree stack is visited several times; its items record the
+/// visits (where they started and ended, with which symbols, and which
+/// `Down` transition left the node), but not the subtrees below the node.
+/// Items for the same visits are shared by all the runs that use them, which
+/// makes recognition polynomial for automata obtained from a `PMCFG`, where
+/// each node is visited once per component of its rule.
+/// The runs are enumerated best-first by lazy k-best extraction.
+///
+/// The weights of the transitions must not be greater than `W::one()`.
+/// Runs that visit a node more than `max_visits` times are not found, so the
+/// enumeration is only complete if no run does that.
+/// By default, a node may be revisited once per symbol that `Up` transitions
+/// write, which suffices for automata obtained from a `PMCFG`.
+///
+/// ```
+/// extern crate log_domain;
+/// extern crate rustomata;
+///
+/// use std::str::FromStr;
+/// use log_domain::LogDomain;
+/// use rustomata::automata::tree_stack_automaton::{TabularRecogniser, TreeStackAutomaton};
+/// use rustomata::grammars::pmcfg::PMCFG;
+/// use rustomata::recognisable::Recognisable;
+///
+/// # fn main() {
+/// let grammar: PMCFG<String, String, LogDomain<f64>> = PMCFG::from_str(
+///     "initial: [S]\n\n\
+///      S → [[Var 0 0, Var 0 1]] (A) # 1\n\
+///      A → [[T a, Var 0 0], [T b, Var 0 1]] (A) # 0.5\n\
+///      A → [[], []] () # 0.5"
+/// ).unwrap();
+/// let recogniser = TabularRecogniser::new(TreeStackAutomaton::from(grammar)).unwrap();
+///
+/// let word: Vec<String> = "a a b b".split_whitespace().map(String::from).collect();
+/// assert_eq!(1, recogniser.recognise(word).count());
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TabularRecogniser<A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Eq + Hash,
+    W: Ord,
+{
+    automaton: TreeStackAutomaton<A, T, W>,
+    transitions: Vec<Transition<TreeStackInstruction<usize>, usize, W>>,
+    by_current: HashMap<usize, Vec<usize>>,
+    /// `Push` transitions by the symbol they write
+    pushes: HashMap<usize, Vec<usize>>,
+    /// `Up` transitions by the symbol they expect at the child
+    ups: HashMap<usize, Vec<usize>>,
+    root: usize,
+    max_visits: usize,
+}
+
+impl<A, T, W> TabularRecogniser<A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Eq + Hash,
+    W: Copy + One + Ord,
+{
+    /// By default, `max_visits` is one more than the number of symbols that
+    /// `Up` transitions write.
+    /// Returns `Err` if the initial tree stack of the `automaton` does not
+    /// consist of a single node.
+    pub fn new(automaton: TreeStackAutomaton<A, T, W>) -> Result<Self, String> {
+        if !automaton.initial.is_at_bottom() || !automaton.initial.clone().ups().is_empty() {
+            return Err(String::from(
+                "The initial tree stack of a tabular recogniser must consist of a single node.",
+            ));
+        }
+        let transitions: Vec<_> = automaton.transitions.values().flat_map(|h| h.iter().cloned()).collect();
+
+        let mut by_current = HashMap::new();
+        let mut pushes = HashMap::new();
+        let mut ups = HashMap::new();
+        let mut revisited = HashSet::new();
+        for (i, t) in transitions.iter().enumerate() {
+            let current_val = match t.instruction {
+                TreeStackInstruction::Push { current_val, new_val, .. } => {
+                    pushes.entry(new_val).or_insert_with(Vec::new).push(i);
+                    current_val
+                }
+                TreeStackInstruction::Up { current_val, old_val, new_val, .. } => {
+                    ups.entry(old_val).or_insert_with(Vec::new).push(i);
+                    revisited.insert(new_val);
+                    current_val
+                }
+                TreeStackInstruction::Down { current_val, .. } => current_val,
+            };
+            by_current.entry(current_val).or_insert_with(Vec::new).push(i);
+        }
+
+        Ok(TabularRecogniser {
+            root: *automaton.initial.current_symbol(),
+            automaton,
+            transitions,
+            by_current,
+            pushes,
+            ups,
+            max_visits: revisited.len() + 1,
+        })
+    }
+
+    pub fn automaton(&self) -> &TreeStackAutomaton<A, T, W> {
+        &self.automaton
+    }
+
+    pub fn set_max_visits(&mut self, max_visits: usize) {
+        self.max_visits = max_visits;
+    }
+}
+
+/// A finished visit of a node: it was entered with `entry` on it at `start`
+/// and left with the `Down` transition `down` at `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Visit {
+    entry: usize,
+    start: usize,
+    down: usize,
+    end: usize,
+}
+
+/// The child positions of a node; an occupied position refers to the
+/// `History` of the child and tells how many of its visits happened so far
+/// and how many there are.
+type Children = Vec<Option<(usize, usize, usize)>>;
+
+/// The items of the chart.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Node {
+    /// a node after its finished `visits` was entered with `entry` at `start`
+    /// and now has `value` on it at `position`
+    Active {
+        visits: Vec<Visit>,
+        entry: usize,
+        start: usize,
+        value: usize,
+        position: usize,
+        children: Children,
+    },
+    /// a node that was left after its `visits`
+    Suspended { visits: Vec<Visit>, children: Children },
+    /// a node that is never visited again after its `visits`
+    History(Vec<Visit>),
+    /// the initial node was not left and the word was read completely
+    Goal,
+}
+
+/// The transitions of a run (in the order in which they are applied) for
+/// each visit of a node, and the same for its children.
+struct Segments {
+    visits: Vec<Vec<usize>>,
+    children: Vec<Option<(Vec<Vec<usize>>, usize)>>,
+}
+
+fn consumed(children: &Children) -> bool {
+    children.iter().all(|c| c.map_or(true, |(_, used, len)| used == len))
+}
+
+fn with_child(children: &Children, n: usize, child: (usize, usize, usize)) -> Children {
+    let mut children = children.clone();
+    if children.len() <= n {
+        children.resize(n + 1, None);
+    }
+    children[n] = Some(child);
+    children
+}
+
+impl<A, T, W> TabularRecogniser<A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Eq + Hash,
+    W: Copy + Mul<Output = W> + One + Ord,
+{
+    fn down_of(&self, visit: &Visit) -> (usize, usize, usize) {
+        match self.transitions[visit.down].instruction {
+            TreeStackInstruction::Down { current_val, old_val, new_val } => (current_val, old_val, new_val),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Fills the chart for `word` with Knuth's algorithm; at most `beam`
+    /// items are kept for each position of the word.
+    fn chart(&self, word: &[usize], beam: Option<usize>, budget: BudgetTracker) -> Chart<Node, W> {
+        use self::Node::*;
+
+        let mut chart = Chart::new(budget.clone());
+        let mut agenda = BinaryHeap::new();
+        let mut started = HashSet::new();
+        let mut kept = vec![0; word.len() + 1];
+
+        // finished `Active` items by their value and position, and finished
+        // histories by the symbol and position their first visit started with
+        let mut actives: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        let mut histories: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+
+        let matches = |t: &Transition<_, usize, W>, j: usize| word[j..].starts_with(&t.word);
+        let none = Vec::new();
+        let axiom = |entry, start| Active {
+            visits: Vec::new(),
+            entry,
+            start,
+            value: entry,
+            position: start,
+            children: Vec::new(),
+        };
+
+        started.insert((self.root, 0));
+        agenda.extend(chart.propose(axiom(self.root, 0), Edge { tails: Vec::new(), label: None, weight: W::one() }));
+
+        while let Some((weight, i)) = agenda.pop() {
+            if chart.nodes[i].finished || weight != chart.nodes[i].weight {
+                continue;
+            }
+            if !budget.pop() {
+                break;
+            }
+            let node = chart.nodes[i].node.clone();
+            let position = match node {
+                Active { position, .. } => position,
+                Suspended { ref visits, .. } | History(ref visits) => visits.last().unwrap().end,
+                Goal => word.len(),
+            };
+            if node != Goal && beam.map_or(false, |beam| kept[position] >= beam) {
+                continue;
+            }
+            kept[position] += 1;
+            chart.nodes[i].finished = true;
+
+            let mut new_edges = Vec::new();
+            match node {
+                Active { ref visits, entry, start, value, position: j, ref children } => {
+                    if visits.is_empty() && entry == self.root && start == 0 && j == word.len() && consumed(children) {
+                        new_edges.push((Goal, vec![i], None));
+                    }
+                    actives.entry((value, j)).or_insert_with(Vec::new).push(i);
+
+                    for &t in self.by_current.get(&value).unwrap_or(&none) {
+                        let transition = &self.transitions[t];
+                        if !matches(transition, j) {
+                            continue;
+                        }
+                        let k = j + transition.word.len();
+                        match transition.instruction {
+                            TreeStackInstruction::Push { n, new_val, .. } => {
+                                if children.get(n).map_or(false, |c| c.is_some()) {
+                                    continue;
+                                }
+                                if started.insert((new_val, k)) {
+                                    new_edges.push((axiom(new_val, k), Vec::new(), None));
+                                }
+                                for &h in histories.get(&(new_val, k)).unwrap_or(&none) {
+                                    if let History(ref child) = chart.nodes[h].node {
+                                        let (_, old_val, new_val) = self.down_of(&child[0]);
+                                        if old_val == value {
+                                            new_edges.push((
+                                                Active {
+                                                    visits: visits.clone(),
+                                                    entry,
+                                                    start,
+                                                    value: new_val,
+                                                    position: child[0].end,
+                                                    children: with_child(children, n, (h, 1, child.len())),
+                                                },
+                                                vec![i, h],
+                                                Some(t),
+                                            ));
+                                        }
+                                    }
+                                }
+                            }
+                            TreeStackInstruction::Up { n, old_val, new_val, .. } => {
+                                if let Some(&Some((h, used, len))) = children.get(n) {
+                                    if used == len {
+                                        continue;
+                                    }
+                                    if let History(ref child) = chart.nodes[h].node {
+                                        let (left_with, _, _) = self.down_of(&child[used - 1]);
+                                        let (_, expected, written) = self.down_of(&child[used]);
+                                        if left_with == old_val && child[used].entry == new_val
+                                            && child[used].start == k && expected == value
+                                        {
+                                            new_edges.push((
+                                                Active {
+                                                    visits: visits.clone(),
+                                                    entry,
+                                                    start,
+                                                    value: written,
+                                                    position: child[used].end,
+                                                    children: with_child(children, n, (h, used + 1, len)),
+                                                },
+                                                vec![i],
+                                                Some(t),
+                                            ));
+                                        }
+                                    }
+                                }
+                            }
+                            TreeStackInstruction::Down { .. } => {
+                                let mut visits = visits.clone();
+                                visits.push(Visit { entry, start, down: t, end: k });
+                                new_edges.push((Suspended { visits, children: children.clone() }, vec![i], Some(t)));
+                            }
+                        }
+                    }
+                }
+                Suspended { ref visits, ref children } => {
+                    if consumed(children) {
+                        new_edges.push((History(visits.clone()), vec![i], None));
+                    }
+                    if visits.len() < self.max_visits {
+                        // the next visit starts whenever the parent goes up again
+                        let (left_with, _, _) = self.down_of(visits.last().unwrap());
+                        let mut entries = HashSet::new();
+                        for &u in self.ups.get(&left_with).unwrap_or(&none) {
+                            let up = &self.transitions[u];
+                            let new_val = match up.instruction {
+                                TreeStackInstruction::Up { new_val, .. } => new_val,
+                                _ => unreachable!(),
+                            };
+                            for j in position..word.len() + 1 {
+                                if j >= position + up.word.len() && word[..j].ends_with(&up.word)
+                                    && entries.insert((new_val, j))
+                                {
+                                    let next = Active {
+                                        visits: visits.clone(),
+                                        entry: new_val,
+                                        start: j,
+                                        value: new_val,
+                                        position: j,
+                                        children: children.clone(),
+                                    };
+                                    new_edges.push((next, vec![i], None));
+                                }
+                            }
+                        }
+                    }
+                }
+                History(ref child) => {
+                    let first = child[0];
+                    histories.entry((first.entry, first.start)).or_insert_with(Vec::new).push(i);
+                    let (_, old_val, written) = self.down_of(&first);
+                    for &t in self.pushes.get(&first.entry).unwrap_or(&none) {
+                        let transition = &self.transitions[t];
+                        let (n, current_val) = match transition.instruction {
+                            TreeStackInstruction::Push { n, current_val, .. } => (n, current_val),
+                            _ => unreachable!(),
+                        };
+                        if current_val != old_val || first.start < transition.word.len() {
+                            continue;
+                        }
+                        let j = first.start - transition.word.len();
+                        if !matches(transition, j) {
+                            continue;
+                        }
+                        for &a in actives.get(&(current_val, j)).unwrap_or(&none) {
+                            if let Active { ref visits, entry, start, ref children, .. } = chart.nodes[a].node {
+                                if children.get(n).map_or(false, |c| c.is_some()) {
+                                    continue;
+                                }
+                                new_edges.push((
+                                    Active {
+                                        visits: visits.clone(),
+                                        entry,
+                                        start,
+                                        value: written,
+                                        position: first.end,
+                                        children: with_child(children, n, (i, 1, child.len())),
+                                    },
+                                    vec![a, i],
+                                    Some(t),
+                                ));
+                            }
+                        }
+                    }
+                }
+                Goal => (),
+            }
+
+            for (node, tails, label) in new_edges {
+                let weight = label.map_or(W::one(), |t| self.transitions[t].weight);
+                agenda.extend(chart.propose(node, Edge { tails, label, weight }));
+            }
+        }
+
+        chart
+    }
+
+    /// The transitions of the `k`-th best derivation of a node, split into
+    /// the visits of the node.
+    fn segments(&self, chart: &mut Chart<Node, W>, node: usize, k: usize) -> Segments {
+        let (edge, ranks) = chart.derivation(node, k);
+        let mut tails = edge.tails.iter().zip(ranks).map(|(&tail, rank)| (tail, rank));
+
+        let mut segments = match tails.next() {
+            Some((tail, rank)) => self.segments(chart, tail, rank),
+            None => return Segments { visits: vec![Vec::new()], children: Vec::new() },
+        };
+        match (&chart.nodes[node].node, edge.label) {
+            (&Node::Active { .. }, Some(t)) => {
+                let n = match self.transitions[t].instruction {
+                    TreeStackInstruction::Push { n, .. } | TreeStackInstruction::Up { n, .. } => n,
+                    _ => unreachable!(),
+                };
+                if let Some((tail, rank)) = tails.next() {
+                    let child = self.segments(chart, tail, rank).visits;
+                    if segments.children.len() <= n {
+                        segments.children.resize(n + 1, None);
+                    }
+                    segments.children[n] = Some((child, 0));
+                }
+                let (ref child, ref mut used) = *segments.children[n].as_mut().unwrap();
+                let current = segments.visits.last_mut().unwrap();
+                current.push(t);
+                current.extend(&child[*used]);
+                *used += 1;
+            }
+            (&Node::Active { .. }, None) => segments.visits.push(Vec::new()),
+            (&Node::Suspended { .. }, Some(t)) => segments.visits.last_mut().unwrap().push(t),
+            _ => (),
+        }
+        segments
+    }
+}
+
+/// Enumerates the runs of a `TabularRecogniser` best-first.
+struct TabularRuns<'a, A, T, W>
+where
+    A: 'a + Clone + Hash + Ord,
+    T: 'a + Eq + Hash,
+    W: 'a + Ord,
+{
+    recogniser: &'a TabularRecogniser<A, T, W>,
+    chart: Chart<Node, W>,
+    k: usize,
+    budget: BudgetTracker,
+}
+
+impl<'a, A, T, W> Iterator for TabularRuns<'a, A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: Copy + Mul<Output = W> + MulAssign + One + Ord,
+{
+    type Item = Item<TreeStack<A>, TreeStackInstruction<A>, T, W>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let goal = *self.chart.index.get(&Node::Goal)?;
+        if !self.chart.nodes[goal].finished {
+            return None;
+        }
+        let weight = self.chart.kth(goal, self.k)?;
+        if !self.budget.candidate() {
+            return None;
+        }
+
+        let segments = self.recogniser.segments(&mut self.chart, goal, self.k);
+        self.k += 1;
+
+        let mut run = Pushdown::new();
+        let mut storage = self.recogniser.automaton.initial.clone();
+        for &t in &segments.visits[0] {
+            let transition = &self.recogniser.transitions[t];
+            storage = transition.instruction.apply(storage).remove(0);
+            run = run.push(transition.clone());
+        }
+        let item = Item(Configuration { word: Vec::new(), storage, weight }, run);
+        Some(self.recogniser.automaton.item_map(&item))
+    }
+}
+
+impl<A, T, W> TabularRecogniser<A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: Copy + Mul<Output = W> + MulAssign + One + Ord,
+{
+    fn runs<'a>(
+        &'a self,
+        beam: Option<usize>,
+        budget: BudgetTracker,
+        word: Vec<T>,
+    ) -> Box<Iterator<Item = Item<TreeStack<A>, TreeStackInstruction<A>, T, W>> + 'a> {
+        let word: Option<Vec<_>> = word.iter().map(|t| self.automaton.terminal_to_int(t)).collect();
+        let chart = match word {
+            Some(word) => self.chart(&word, beam, budget.clone()),
+            None => return Box::new(::std::iter::empty()),
+        };
+        Box::new(TabularRuns { recogniser: self, chart, k: 0, budget })
+    }
+}
+
+impl<A, T, W> Recognisable<T, W> for TabularRecogniser<A, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: Copy + Mul<Output = W> + MulAssign + One + Ord,
+{
+    type Parse = Item<TreeStack<A>, TreeStackInstruction<A>, T, W>;
+
+    fn recognise<'a>(&'a self, word: Vec<T>) -> Box<Iterator<Item = Self::Parse> + 'a> {
+        self.runs(None, BudgetTracker::unlimited(), word)
+    }
+
+    /// The `beam` limits the number of items for each position of the word.
+    fn recognise_beam_search<'a>(
+        &'a self,
+        beam: usize,
+        word: Vec<T>,
+    ) -> Box<Iterator<Item = Self::Parse> + 'a> {
+        self.runs(Some(beam), BudgetTracker::unlimited(), word)
+    }
+
+    /// Each item taken from the agenda of the chart and each candidate for a
+    /// derivation counts as an agenda pop.
+    fn recognise_with_budget<'a>(
+        &'a self,
+        beam: Option<usize>,
+        budget: BudgetTracker,
+        word: Vec<T>,
+    ) -> Box<Iterator<Item = Self::Parse> + 'a>
+    where
+        Self::Parse: 'a,
+    {
+        self.runs(beam, budget, word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use log_domain::LogDomain;
+    use grammars::pmcfg::PMCFG;
+    use recognisable::automaton::recognise;
+
+    #[test]
+    fn test_ambiguous_grammar() {
+        // A is visited three times, D revisits its child, and the unary
+        // cycle S → B → S yields infinitely many runs
+        let grammar: PMCFG<String, String, LogDomain<f64>> = PMCFG::from_str(
+            "initial: [S]\n\n\
+             S → [[Var 0 0, Var 0 1, Var 0 1]] (A) # 0.3\n\
+             S → [[Var 0 0, Var 0 1]] (D) # 0.3\n\
+             S → [[Var 0 0]] (B) # 0.4\n\
+             B → [[Var 0 0]] (S) # 0.5\n\
+             A → [[T a], [T b]] () # 0.6\n\
+             A → [[T a], [T c]] () # 0.4\n\
+             D → [[T a, Var 0 0], [T b, Var 0 1]] (D) # 0.5\n\
+             D → [[], []] () # 0.5"
+        ).unwrap();
+        let automaton = TreeStackAutomaton::from(grammar);
+        let recogniser = TabularRecogniser::new(automaton.clone()).unwrap();
+
+        for sentence in &["a b b", "a a b b"] {
+            let word: Vec<String> = sentence.split_whitespace().map(String::from).collect();
+            let tabular: Vec<_> = recogniser.recognise(word.clone()).take(10).collect();
+            let agenda: Vec<_> = recognise(&automaton, word.clone()).take(10).collect();
+            assert_eq!(10, tabular.len());
+            for (t, a) in tabular.iter().zip(&agenda) {
+                // the products are taken in a different order
+                assert!((a.0.weight.ln() - t.0.weight.ln()).abs() < 1e-9);
+                let replayed = automaton.check_run(t.1.clone());
+                assert_eq!(t.0.storage, replayed[0].0.storage);
+                assert!((replayed[0].0.weight.ln() - t.0.weight.ln()).abs() < 1e-9);
+            }
+            let runs: HashSet<_> = tabular.into_iter().map(|i| i.1.to_vec()).collect();
+            assert_eq!(10, runs.len());
+        }
+
+        assert!(recogniser.recognise(vec![String::from("b")]).next().is_none());
+        assert!(recogniser.recognise(vec![String::from("a"), String::from("c")]).next().is_none());
+    }
+
+    #[test]
+    fn test_initial_tree_stack_with_several_nodes() {
+        let initial = TreeStack::new(0).push(0, 1).unwrap();
+        let automaton: TreeStackAutomaton<usize, char, LogDomain<f64>> =
+            TreeStackAutomaton::new(Vec::new(), initial);
+        assert!(TabularRecogniser::new(automaton).is_err());
+    }
+}
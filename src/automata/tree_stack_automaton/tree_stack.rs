@@ -5,6 +5,7 @@ use std::hash::Hash;
 
 use integeriser::{HashIntegeriser, Integeriser};
 use util::integerisable::Integerisable1;
+use util::dot::{tree_to_dot, ToDot};
 use util::tree::GornTree;
 
 /// upside-down tree with a designated position (the *stack pointer*) and *nodes* of type `A`.
@@ -139,6 +140,14 @@ impl<A> TreeStack<A> {
     }
 }
 
+/// Draws the tree with the node at the stack pointer in bold.
+impl<A: Clone + fmt::Display> ToDot for TreeStack<A> {
+    fn to_dot(&self) -> String {
+        let (tree, pointer) = self.to_tree();
+        tree_to_dot(&tree, |a| a.to_string(), Some(&pointer))
+    }
+}
+
 impl<A: Clone + fmt::Display> fmt::Display for TreeStack<A> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let (tree, pointer) = self.to_tree();
@@ -345,6 +354,20 @@ pub mod tests {
         assert_eq!(&0, ts.current_symbol());
     }
 
+    #[test]
+    fn test_to_dot() {
+        let ts = TreeStack::new('@').push(0, 'a').unwrap().down().unwrap().push(1, 'b').unwrap();
+        assert_eq!(
+            "digraph {\n    node [shape=none];\n\
+             \x20   n [label=\"@\"];\n\
+             \x20   n_0 [label=\"a\"];\n\
+             \x20   n_1 [label=\"b\", style=bold, shape=box];\n\
+             \x20   n -> n_0;\n    n -> n_1;\n\
+             }\n",
+            ts.to_dot()
+        );
+    }
+
     #[test]
     fn test_to_tree() {
         let mut ts: TreeStack<char> = TreeStack::new('@');
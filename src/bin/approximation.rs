@@ -6,8 +6,11 @@ use rustomata::recognisable::Recognisable;
 use rustomata::automata::tree_stack_automaton::TreeStackAutomaton;
 use rustomata::automata::push_down_automaton::{PushDownAutomaton, PushState};
 use rustomata::approximation::ApproximationStrategy;
+use rustomata::approximation::class_induction::{ClassInduction, induce_from_cfg, induce_from_pmcfg};
+use rustomata::approximation::ptk::PDTopKElement;
 use rustomata::approximation::relabel::RlbElement;
 use rustomata::approximation::tts::TTSElement;
+use rustomata::approximation::tsu::TSUElement;
 use rustomata::approximation::equivalence_classes::EquivalenceRelation;
 
 use std::io::{self, Read};
@@ -52,7 +55,7 @@ pub fn get_sub_command() -> App<'static, 'static> {
                 ),
         )
         .subcommand(
-            SubCommand::with_name("topk")
+            SubCommand::with_name("ptk")
                 .about("maps pushdown to its topmost k elements")
                 .subcommand(
                     SubCommand::with_name("parse")
@@ -105,6 +108,81 @@ pub fn get_sub_command() -> App<'static, 'static> {
                     ),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("tsu")
+                .about("unrolls tree-stack into pushdown automata with subtrees of bounded depth")
+                .subcommand(
+                    SubCommand::with_name("parse")
+                        .arg(
+                            Arg::with_name("grammar")
+                                .help("mcfg-grammar file to use")
+                                .index(1)
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("depth")
+                                .help("depth of remembered subtrees")
+                                .index(2)
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("automaton")
+                        .arg(
+                            Arg::with_name("grammar")
+                                .help("mcfg-grammar file to use")
+                                .index(1)
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("depth")
+                                .help("depth of remembered subtrees")
+                                .index(2)
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("classes")
+                .about("induces a classes file for the relabel approximation from a grammar")
+                .subcommand(
+                    SubCommand::with_name("prefix")
+                        .about("merges nonterminals that are equal after stripping annotations (e.g. \"|<…>\" and \"^…\")")
+                        .arg(grammar_arg())
+                        .arg(cfg_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("fanout")
+                        .about("merges nonterminals of equal fanout")
+                        .arg(grammar_arg())
+                        .arg(cfg_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("cluster")
+                        .about("merges nonterminals of equal fanout with similar rule usage")
+                        .arg(grammar_arg())
+                        .arg(
+                            Arg::with_name("size")
+                                .help("maximum number of classes")
+                                .index(2)
+                                .required(true),
+                        )
+                        .arg(cfg_arg()),
+                ),
+        )
+}
+
+fn grammar_arg() -> Arg<'static, 'static> {
+    Arg::with_name("grammar")
+        .help("mcfg-grammar file to use")
+        .index(1)
+        .required(true)
+}
+
+fn cfg_arg() -> Arg<'static, 'static> {
+    Arg::with_name("cfg")
+        .help("read the grammar as cfg-grammar")
+        .long("cfg")
 }
 
 pub fn handle_sub_matches(r_matches: &ArgMatches) {
@@ -169,6 +247,53 @@ pub fn handle_sub_matches(r_matches: &ArgMatches) {
                 _ => (),
             }
         }
+        ("ptk", Some(ptk_matches)) => {
+            match ptk_matches.subcommand() {
+                ("parse", Some(parse_matches)) => {
+                    let grammar_file_name = parse_matches.value_of("grammar").unwrap();
+                    let mut grammar_file = File::open(grammar_file_name).unwrap();
+                    let mut grammar_string = String::new();
+                    let _ = grammar_file.read_to_string(&mut grammar_string);
+                    let g: CFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+
+                    let a = PushDownAutomaton::from(g);
+
+                    let size = parse_matches.value_of("size").unwrap().parse().unwrap();
+                    let ptk = PDTopKElement::new(size);
+
+                    let (b, _) = ptk.approximate_automaton(&a);
+
+                    let mut corpus = String::new();
+                    let _ = io::stdin().read_to_string(&mut corpus);
+
+                    for sentence in corpus.lines() {
+                        println!(
+                            "{:?}: {}",
+                            b.recognise(
+                                sentence.split_whitespace().map(|x| x.to_string()).collect(),
+                            ).next(),
+                            sentence
+                        );
+                    }
+                }
+                ("automaton", Some(parse_matches)) => {
+                    let grammar_file_name = parse_matches.value_of("grammar").unwrap();
+                    let mut grammar_file = File::open(grammar_file_name).unwrap();
+                    let mut grammar_string = String::new();
+                    let _ = grammar_file.read_to_string(&mut grammar_string);
+                    let g: CFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+
+                    let a = PushDownAutomaton::from(g);
+
+                    let size = parse_matches.value_of("size").unwrap().parse().unwrap();
+                    let ptk = PDTopKElement::new(size);
+
+                    let (b, _) = ptk.approximate_automaton(&a);
+                    println!("{}", b);
+                }
+                _ => (),
+            }
+        }
         ("tts", Some(tts_matches)) => {
             match tts_matches.subcommand() {
                 ("parse", Some(parse_matches)) => {
@@ -213,6 +338,79 @@ pub fn handle_sub_matches(r_matches: &ArgMatches) {
                 _ => (),
             }
         }
+        ("tsu", Some(tsu_matches)) => {
+            match tsu_matches.subcommand() {
+                ("parse", Some(parse_matches)) => {
+                    let grammar_file_name = parse_matches.value_of("grammar").unwrap();
+                    let mut grammar_file = File::open(grammar_file_name).unwrap();
+                    let mut grammar_string = String::new();
+                    let _ = grammar_file.read_to_string(&mut grammar_string);
+                    let g: PMCFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+
+                    let a = TreeStackAutomaton::from(g);
+
+                    let depth = parse_matches.value_of("depth").unwrap().parse().unwrap();
+                    let tsu = TSUElement::new(depth);
+
+                    let (b, _) = tsu.approximate_automaton(&a);
+
+                    let mut corpus = String::new();
+                    let _ = io::stdin().read_to_string(&mut corpus);
+
+                    for sentence in corpus.lines() {
+                        println!(
+                            "{:?}: {}",
+                            b.recognise(
+                                sentence.split_whitespace().map(|x| x.to_string()).collect(),
+                            ).next(),
+                            sentence
+                        );
+                    }
+                }
+                ("automaton", Some(parse_matches)) => {
+                    let grammar_file_name = parse_matches.value_of("grammar").unwrap();
+                    let mut grammar_file = File::open(grammar_file_name).unwrap();
+                    let mut grammar_string = String::new();
+                    let _ = grammar_file.read_to_string(&mut grammar_string);
+                    let g: PMCFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+
+                    let a = TreeStackAutomaton::from(g);
+
+                    let depth = parse_matches.value_of("depth").unwrap().parse().unwrap();
+                    let tsu = TSUElement::new(depth);
+
+                    let (b, _) = tsu.approximate_automaton(&a);
+                    println!("{}", b);
+                }
+                _ => (),
+            }
+        }
+        ("classes", Some(classes_matches)) => {
+            let (strategy, induce_matches) = match classes_matches.subcommand() {
+                ("prefix", Some(m)) => (ClassInduction::Prefix, m),
+                ("fanout", Some(m)) => (ClassInduction::Fanout, m),
+                ("cluster", Some(m)) => {
+                    let size = m.value_of("size").unwrap().parse().unwrap();
+                    (ClassInduction::Clustering(size), m)
+                }
+                _ => return,
+            };
+
+            let grammar_file_name = induce_matches.value_of("grammar").unwrap();
+            let mut grammar_file = File::open(grammar_file_name).unwrap();
+            let mut grammar_string = String::new();
+            let _ = grammar_file.read_to_string(&mut grammar_string);
+
+            let e = if induce_matches.is_present("cfg") {
+                let g: CFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+                induce_from_cfg(&g, &strategy)
+            } else {
+                let g: PMCFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+                induce_from_pmcfg(&g, &strategy)
+            };
+
+            println!("{}", e);
+        }
         _ => (),
     }
 }
@@ -0,0 +1,42 @@
+use clap::{Arg, ArgMatches};
+use rustomata::util::budget::Budget;
+use std::time::Duration;
+
+/// Arguments that limit the work spent on a single sentence.
+pub fn budget_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("timeout")
+            .help("gives up on a sentence after the given number of milliseconds")
+            .long("timeout")
+            .value_name("milliseconds")
+            .required(false),
+        Arg::with_name("max-pops")
+            .help("gives up on a sentence after the given number of agenda pops")
+            .long("max-pops")
+            .value_name("pops")
+            .required(false),
+    ]
+}
+
+/// Argument that limits the number of parses that are enumerated for a single sentence
+/// (`csparsing parse` has its own `--candidates` instead).
+pub fn max_candidates_arg() -> Arg<'static, 'static> {
+    Arg::with_name("max-candidates")
+        .help("gives up on a sentence after the given number of enumerated parses")
+        .long("max-candidates")
+        .value_name("candidates")
+        .required(false)
+}
+
+/// Reads the `Budget` for each sentence from the arguments given by `budget_args`.
+pub fn budget_from_matches(matches: &ArgMatches) -> Budget {
+    Budget {
+        time: matches
+            .value_of("timeout")
+            .map(|ms| Duration::from_millis(ms.parse().unwrap())),
+        candidates: matches
+            .value_of("max-candidates")
+            .map(|c| c.parse().unwrap()),
+        pops: matches.value_of("max-pops").map(|p| p.parse().unwrap()),
+    }
+}
@@ -1,11 +1,17 @@
 use clap::{Arg, ArgMatches, App, SubCommand};
 use log_domain::LogDomain;
 use rustomata::grammars::cfg::CFG;
-use rustomata::recognisable::Recognisable;
-use rustomata::automata::push_down_automaton::PushDownAutomaton;
+use rustomata::grammars::cfg::cnf::CNF;
+use rustomata::recognisable::{Item, Recognisable};
+use rustomata::automata::push_down_automaton::{LRKind, LRTable, PushDown, PushDownAutomaton,
+                                                PushDownInstruction, TabularRecogniser};
+use rustomata::util::dot::ToDot;
 
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
 use std::io::{self, Read};
 use std::fs::File;
+use std::process;
 
 pub fn get_sub_command() -> App<'static, 'static> {
     SubCommand::with_name("cfg")
@@ -36,6 +42,25 @@ pub fn get_sub_command() -> App<'static, 'static> {
                         .long("beam")
                         .value_name("beam-width")
                         .required(false),
+                )
+                .arg(
+                    Arg::with_name("tabular")
+                        .help("recognises with a tabular algorithm, which takes polynomial time")
+                        .long("tabular"),
+                )
+                .arg(
+                    Arg::with_name("algorithm")
+                        .help("parsing algorithm to use")
+                        .long("algorithm")
+                        .possible_values(&["automaton", "cyk"])
+                        .default_value("automaton"),
+                )
+                .arg(
+                    Arg::with_name("lr")
+                        .help("constructs a shift-reduce automaton from the given LR parse table")
+                        .long("lr")
+                        .takes_value(true)
+                        .possible_values(&["lr0", "slr", "lalr"]),
                 ),
         )
         .subcommand(
@@ -49,6 +74,18 @@ pub fn get_sub_command() -> App<'static, 'static> {
                         .help("grammar file to use")
                         .index(1)
                         .required(true),
+                )
+                .arg(
+                    Arg::with_name("dot")
+                        .help("prints the automaton in the DOT language of Graphviz")
+                        .long("dot"),
+                )
+                .arg(
+                    Arg::with_name("lr")
+                        .help("constructs a shift-reduce automaton from the given LR parse table")
+                        .long("lr")
+                        .takes_value(true)
+                        .possible_values(&["lr0", "slr", "lalr"]),
                 ),
         )
 }
@@ -67,29 +104,44 @@ pub fn handle_sub_matches(cfg_matches: &ArgMatches) {
             let _ = grammar_file.read_to_string(&mut grammar_string);
             let grammar: CFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
 
-            let automaton = PushDownAutomaton::from(grammar);
-
             let mut corpus = String::new();
             let _ = io::stdin().read_to_string(&mut corpus);
 
-            for sentence in corpus.lines() {
-                let word = sentence.split_whitespace().map(|x| x.to_string()).collect();
-                match cfg_parse_matches.value_of("beam-width") {
-                    Some(b) => {
-                        for parse in automaton
-                            .recognise_beam_search(b.parse().unwrap(), word)
-                            .take(n)
-                        {
-                            println!("{:?}", parse.0);
-                        }
+            if cfg_parse_matches.value_of("algorithm") == Some("cyk") {
+                if ["beam-width", "tabular", "lr"].iter().any(|arg| cfg_parse_matches.is_present(arg)) {
+                    eprintln!("[ERR] The CYK algorithm cannot be combined with --beam, --tabular or --lr.");
+                    process::exit(1);
+                }
+                let cnf = CNF::from(grammar);
+                for sentence in corpus.lines() {
+                    let word: Vec<_> = sentence.split_whitespace().map(|x| x.to_string()).collect();
+                    for (tree, weight) in cnf.parse(&word).take(n) {
+                        println!("{}\n{}", weight, tree);
+                    }
+                    println!();
+                }
+                return;
+            }
+
+            let beam = cfg_parse_matches.value_of("beam-width").map(|b| b.parse().unwrap());
+            let tabular = cfg_parse_matches.is_present("tabular");
+            match cfg_parse_matches.value_of("lr") {
+                Some(kind) => {
+                    let automaton = lr_table(grammar, kind).automaton();
+                    if tabular {
+                        parse_corpus(&TabularRecogniser::new(automaton), n, beam, &corpus);
+                    } else {
+                        parse_corpus(&automaton, n, beam, &corpus);
                     }
-                    None => {
-                        for parse in automaton.recognise(word).take(n) {
-                            println!("{:?}", parse.0);
-                        }
+                }
+                None => {
+                    let automaton = PushDownAutomaton::from(grammar);
+                    if tabular {
+                        parse_corpus(&TabularRecogniser::new(automaton), n, beam, &corpus);
+                    } else {
+                        parse_corpus(&automaton, n, beam, &corpus);
                     }
-                };
-                println!();
+                }
             }
         }
         ("automaton", Some(cfg_automaton_matches)) => {
@@ -99,9 +151,60 @@ pub fn handle_sub_matches(cfg_matches: &ArgMatches) {
             let _ = grammar_file.read_to_string(&mut grammar_string);
             let grammar: CFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
 
-            let automaton = PushDownAutomaton::from(grammar);
-            println!("{}", automaton);
+            let dot = cfg_automaton_matches.is_present("dot");
+            match cfg_automaton_matches.value_of("lr") {
+                Some(kind) => print_automaton(&lr_table(grammar, kind).automaton(), dot),
+                None => print_automaton(&PushDownAutomaton::from(grammar), dot),
+            }
         }
         _ => (),
     }
 }
+
+/// Builds an LR parse table and reports its conflicts on the standard error.
+fn lr_table(grammar: CFG<String, String, LogDomain<f64>>, kind: &str) -> LRTable<String, String, LogDomain<f64>> {
+    let kind = match kind {
+        "lr0" => LRKind::LR0,
+        "slr" => LRKind::SLR,
+        _ => LRKind::LALR1,
+    };
+    let table = LRTable::new(grammar, kind);
+    for conflict in table.conflicts() {
+        eprintln!("{}", conflict);
+    }
+    table
+}
+
+fn print_automaton<A>(automaton: &PushDownAutomaton<A, String, LogDomain<f64>>, dot: bool)
+where
+    A: Clone + Display + Hash + Ord,
+{
+    if dot {
+        print!("{}", automaton.to_dot());
+    } else {
+        println!("{}", automaton);
+    }
+}
+
+fn parse_corpus<R, A>(recogniser: &R, n: usize, beam: Option<usize>, corpus: &str)
+where
+    R: Recognisable<String, LogDomain<f64>, Parse = Item<PushDown<A>, PushDownInstruction<A>, String, LogDomain<f64>>>,
+    A: Debug,
+{
+    for sentence in corpus.lines() {
+        let word = sentence.split_whitespace().map(|x| x.to_string()).collect();
+        match beam {
+            Some(b) => {
+                for parse in recogniser.recognise_beam_search(b, word).take(n) {
+                    println!("{:?}", parse.0);
+                }
+            }
+            None => {
+                for parse in recogniser.recognise(word).take(n) {
+                    println!("{:?}", parse.0);
+                }
+            }
+        };
+        println!();
+    }
+}
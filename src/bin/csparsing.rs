@@ -2,12 +2,20 @@ extern crate bincode;
 use clap::{App, Arg, ArgMatches, SubCommand};
 use flate2::{read, write, Compression};
 use log_domain::LogDomain;
+use num_traits::One;
 use std::{fs::File,
-          io::{stdin, stdout, Read}};
+          io::{stdin, stdout, Read},
+          process};
 use rustomata::grammars::lcfrs::from_discodop::DiscoDopGrammar;
-use rustomata::grammars::{lcfrs::{csparsing::{CSRepresentation, DebugResult},
+use rustomata::grammars::{lcfrs::{csparsing::{CSRepresentation, DebugResult, GeneratorBuilder, FORMAT_VERSION},
                                   Lcfrs},
                           pmcfg::negra::{to_negra, DumpMode, noparse}};
+use rustomata::grammars::pmcfg::PMCFGRule;
+use rustomata::util::{budget::Exhaustion, dot::ToDot, tree::GornTree};
+use serde_json::Value;
+
+use budget::{budget_args, budget_from_matches};
+use serve;
 
 pub fn get_sub_command(name: &str) -> App {
     SubCommand::with_name(name)
@@ -50,6 +58,11 @@ pub fn get_sub_command(name: &str) -> App {
                         .takes_value(false)
                         .help("if the provided grammar file is not Gzipped (default for raw grammars)")
                         .conflicts_with("gzipped")
+                ).arg(
+                    Arg::with_name("dot")
+                        .long("dot")
+                        .takes_value(false)
+                        .help("Prints the automaton of the representation in the DOT language of Graphviz instead.")
                 ).arg(
                     Arg::with_name("grammar")
                         .index(1)
@@ -96,6 +109,7 @@ pub fn get_sub_command(name: &str) -> App {
                         .takes_value(true)
                         .help("Maximum number of candidates to enumerate."),
                 )
+                .args(&budget_args())
                 .arg(
                     Arg::with_name("with-lines")
                         .short("l")
@@ -112,7 +126,14 @@ pub fn get_sub_command(name: &str) -> App {
                     Arg::with_name("debugmode")
                         .short("d")
                         .long("debug")
-                        .help("Prints debug information instead of parse trees."),
+                        .help("Prints debug information instead of parse trees: the number of rules, the length of the sentence, the parse time and the number of enumerated candidates. Inconsistent candidates are rejected while they are enumerated and are not counted."),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .short("j")
+                        .long("json")
+                        .conflicts_with("debugmode")
+                        .help("Prints one JSON object per sentence with the parse trees, their weights and statistics."),
                 )
                 .arg(
                     Arg::with_name("fallback")
@@ -121,6 +142,7 @@ pub fn get_sub_command(name: &str) -> App {
                         .help("Will output an incorrect parse tree if the parse does not find a correct one."),
                 ),
         )
+        .subcommand(serve::get_sub_command())
 }
 
 enum FileReader<R: Read> {
@@ -174,11 +196,22 @@ pub fn handle_sub_matches(submatches: &ArgMatches) {
                     .expect("Could not decode the grammar provided via stdin.")
             };
 
-            bincode::serialize_into(
-                &mut write::GzEncoder::new(stdout(), Compression::best()),
-                &CSRepresentation::new(gmr, sxlen),
-                bincode::Infinite,
-            ).unwrap()
+            let csrep = match CSRepresentation::new(gmr, sxlen) {
+                Ok(csrep) => csrep,
+                Err(error) => {
+                    eprintln!("{}", error);
+                    process::exit(1);
+                }
+            };
+            if params.is_present("dot") {
+                print!("{}", csrep.to_dot());
+            } else {
+                bincode::serialize_into(
+                    &mut write::GzEncoder::new(stdout(), Compression::best()),
+                    &(FORMAT_VERSION, &csrep),
+                    bincode::Infinite,
+                ).unwrap()
+            }
         }
 
         ("parse", Some(params)) => {
@@ -199,21 +232,22 @@ pub fn handle_sub_matches(submatches: &ArgMatches) {
                 = params.value_of("candidates").map(|s| s.parse().unwrap());
 
 
-            let csfile = File::open(params.value_of("csfile").unwrap()).unwrap();
-
-            let csrep: CSRepresentation<String, String, LogDomain<f64>> =
-                bincode::deserialize_from(&mut read::GzDecoder::new(csfile), bincode::Infinite)
-                    .unwrap();
+            let csrep = read_csrep(params.value_of("csfile").unwrap()).unwrap();
             let mut parser = csrep.build_generator();
             if let Some(beam) = beam_width { parser.set_beam(beam) };
             if let Some(delta) = beam_threshold { parser.set_delta(delta) };
-            if let Some(candidates) = candidates { parser.set_candidates(candidates) };
+            let mut budget = budget_from_matches(params);
+            budget.candidates = candidates;
+            parser.set_budget(budget);
+            let reason = |exhaustion: Option<Exhaustion>| exhaustion.map_or(String::new(), |e| format!(" ({})", e));
 
             for (i, sentence) in word_strings.lines().enumerate() {
                 let (i, words) = split_line(sentence, params.is_present("with-lines"), i);
                 let (words, negra_mode) = split_pos(words, params.is_present("with-pos"));
 
-                if params.is_present("debugmode") {
+                if params.is_present("json") {
+                    println!("{}", parse_json(&parser, &words, i, k, params.is_present("fallback"), negra_mode));
+                } else if params.is_present("debugmode") {
                     let tuple = parser.debug(words.as_slice());
                     eprint!("{} {} {:?} ", tuple.0, tuple.1, tuple.2);
                     match tuple.3 {
@@ -221,18 +255,18 @@ pub fn handle_sub_matches(submatches: &ArgMatches) {
                             eprintln!("parse {}", n);
                             println!("{}", to_negra(&t, i, negra_mode));
                         },
-                        DebugResult::Fallback(t, n) => {
-                            eprintln!("fallback {}", n);
+                        DebugResult::Fallback(t, n, exhaustion) => {
+                            eprintln!("fallback {}{}", n, reason(exhaustion));
                             println!("{}", to_negra(&t, i, negra_mode));
                         },
-                        DebugResult::Noparse => {
-                            eprintln!("noparse 0");
+                        DebugResult::Noparse(exhaustion) => {
+                            eprintln!("noparse 0{}", reason(exhaustion));
                             println!("{}", noparse(&words, i, negra_mode));
                         },
                     }
                 } else {
                     let mut found_trees = false;
-                    let (iterator, fallback) = parser.with_fallback(words.as_slice());
+                    let (iterator, fallback, budget) = parser.with_fallback(words.as_slice());
                     for derivation in iterator.take(k) {
                         found_trees = true;
                         println!(
@@ -247,6 +281,9 @@ pub fn handle_sub_matches(submatches: &ArgMatches) {
                             )
                         );
                     }
+                    if let (false, Some(exhaustion)) = (found_trees, budget.exhausted()) {
+                        eprintln!("sentence {}: {}", i, exhaustion);
+                    }
                     if !found_trees && params.is_present("fallback") {
                         if let Some(tree) = fallback {
                             println!("{}", to_negra(&tree, i, negra_mode));
@@ -259,10 +296,76 @@ pub fn handle_sub_matches(submatches: &ArgMatches) {
                 }
             }
         }
+        ("serve", Some(params)) => serve::handle_sub_matches(params),
         _ => (),
     }
 }
 
+/// Reads the CS representation of a grammar from a gzipped file; the file
+/// starts with the `FORMAT_VERSION` it was written with.
+pub fn read_csrep(path: &str) -> Result<CSRepresentation<String, String, LogDomain<f64>>, String> {
+    let csfile = File::open(path).map_err(|e| format!("could not open {}: {}", path, e))?;
+    let mut reader = read::GzDecoder::new(csfile);
+    let version: u32 = bincode::deserialize_from(&mut reader, bincode::Infinite)
+        .map_err(|e| format!("could not read {}: {}", path, e))?;
+    if version != FORMAT_VERSION {
+        return Err(format!(
+            "{} was written in an incompatible format, please extract it again",
+            path
+        ));
+    }
+    bincode::deserialize_from(&mut reader, bincode::Infinite)
+        .map_err(|e| format!("could not read {}: {}", path, e))
+}
+
+/// Parses a sentence and describes the `k` best parse trees (or the fallback
+/// tree) with their weights and some statistics as a JSON object.
+pub fn parse_json(
+    parser: &GeneratorBuilder<String, String, LogDomain<f64>>,
+    words: &[String],
+    sentence_id: usize,
+    k: usize,
+    with_fallback: bool,
+    mode: DumpMode<String>
+) -> Value {
+    let (iterator, fallback, budget) = parser.with_fallback(words);
+    let mut parses: Vec<Value> = iterator
+        .take(k)
+        .map(|derivation| {
+            let tree = derivation.into_iter().map(|(k, v)| (k, v.clone())).collect();
+            json_parse(&tree, sentence_id, mode.clone())
+        })
+        .collect();
+    let time = budget.elapsed();
+    let mut used_fallback = false;
+    if parses.is_empty() && with_fallback {
+        if let Some(tree) = fallback {
+            parses.push(json_parse(&tree, sentence_id, mode));
+            used_fallback = true;
+        }
+    }
+    json!({
+        "id": sentence_id,
+        "tokens": words,
+        "parses": parses,
+        "fallback": used_fallback,
+        "candidates": budget.candidates(),
+        "rules": parser.rules(),
+        "time": time.as_secs() as f64 + f64::from(time.subsec_nanos()) * 1e-9,
+        "exhausted": budget.exhausted().map(|e| e.to_string()),
+    })
+}
+
+/// A parse tree in NEGRA format with its weight.
+fn json_parse(tree: &GornTree<PMCFGRule<String, String, LogDomain<f64>>>, sentence_id: usize, mode: DumpMode<String>) -> Value {
+    let weight = tree.values().fold(LogDomain::one(), |w, rule| w * rule.weight);
+    json!({
+        "tree": to_negra(tree, sentence_id, mode),
+        "weight": weight.value(),
+        "logweight": weight.ln(),
+    })
+}
+
 fn split_line<'a>(line: &'a str, with_line_number: bool, default_line_number: usize) -> (usize, impl Iterator<Item=&'a str> + 'a) {
     let mut word_iterator = line.split_whitespace();
     
@@ -278,7 +381,7 @@ fn split_line<'a>(line: &'a str, with_line_number: bool, default_line_number: us
     (line_number, word_iterator)
 }
 
-fn split_pos<'a>(words: impl Iterator<Item=&'a str> + 'a, with_pos: bool) -> (Vec<String>, DumpMode<String>) {
+pub fn split_pos<'a>(words: impl Iterator<Item=&'a str> + 'a, with_pos: bool) -> (Vec<String>, DumpMode<String>) {
     if with_pos {
         let (pos, ws) = words.map(
             |wp| {
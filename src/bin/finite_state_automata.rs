@@ -0,0 +1,164 @@
+use clap::{Arg, ArgMatches, App, SubCommand};
+use log_domain::LogDomain;
+use rustomata::recognisable::{Item, Recognisable};
+use rustomata::util::dot::ToDot;
+use rustomata::automata::finite_state_automaton::{FiniteStateAutomaton, FiniteStateInstruction};
+use std::fmt::Debug;
+use std::io::{self, Read};
+use std::fs::File;
+
+pub fn get_sub_command() -> App<'static, 'static> {
+    SubCommand::with_name("fsa")
+        .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+        .about("functions related to finite-state automata")
+        .subcommand(
+            SubCommand::with_name("recognise")
+                .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+                .about("recognises from stdin with a finite-state automaton")
+                .arg(
+                    Arg::with_name("automaton")
+                        .help("automaton file to use")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("beam-width")
+                        .help("maximum number of frontier nodes in the search space")
+                        .short("b")
+                        .long("beam")
+                        .value_name("beam-width")
+                        .required(false),
+                )
+                .arg(
+                    Arg::with_name("number-of-runs")
+                        .help("number of runs that should be returned")
+                        .short("n")
+                        .long("number")
+                        .default_value("1")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("print")
+                .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+                .about("reads a finite-state automaton and prints it")
+                .arg(
+                    Arg::with_name("automaton")
+                        .help("automaton file to use")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("dot")
+                        .help("prints the automaton in the DOT language of Graphviz")
+                        .long("dot"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("determinize")
+                .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+                .about("prints a deterministic finite-state automaton for the support of the given automaton")
+                .arg(
+                    Arg::with_name("automaton")
+                        .help("automaton file to use")
+                        .index(1)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("intersect")
+                .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+                .about("prints a finite-state automaton for the intersection of two automata")
+                .arg(
+                    Arg::with_name("automaton1")
+                        .help("first automaton file to use")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("automaton2")
+                        .help("second automaton file to use")
+                        .index(2)
+                        .required(true),
+                ),
+        )
+}
+
+pub fn handle_sub_matches(fsa_matches: &ArgMatches) {
+    match fsa_matches.subcommand() {
+        ("recognise", Some(fsa_recognise_matches)) => {
+            let automaton = automaton_from_file(fsa_recognise_matches.value_of("automaton").unwrap());
+
+            let mut corpus_raw = String::new();
+            let _ = io::stdin().read_to_string(&mut corpus_raw);
+            let corpus = corpus_raw
+                .lines()
+                .map(|s| s.split_whitespace().map(|x| x.to_string()).collect())
+                .collect();
+
+            let n = fsa_recognise_matches
+                .value_of("number-of-runs")
+                .unwrap()
+                .parse()
+                .unwrap();
+
+            let beam = match fsa_recognise_matches.value_of("beam-width") {
+                Some(b) => Some(b.parse().unwrap()),
+                None => None,
+            };
+
+            recognise_corpus(automaton, n, beam, corpus)
+        }
+        ("print", Some(fsa_print_matches)) => {
+            let automaton = automaton_from_file(fsa_print_matches.value_of("automaton").unwrap());
+            if fsa_print_matches.is_present("dot") {
+                print!("{}", automaton.to_dot());
+            } else {
+                println!("{}", automaton);
+            }
+        }
+        ("determinize", Some(fsa_determinize_matches)) => {
+            let automaton =
+                automaton_from_file(fsa_determinize_matches.value_of("automaton").unwrap());
+            println!("{}", automaton.determinize());
+        }
+        ("intersect", Some(fsa_intersect_matches)) => {
+            let automaton1 = automaton_from_file(fsa_intersect_matches.value_of("automaton1").unwrap());
+            let automaton2 = automaton_from_file(fsa_intersect_matches.value_of("automaton2").unwrap());
+            println!("{}", automaton1.intersect(&automaton2));
+        }
+        _ => (),
+    }
+}
+
+fn automaton_from_file(automaton_file_name: &str) -> FiniteStateAutomaton<String, String, LogDomain<f64>> {
+    let mut automaton_file = File::open(automaton_file_name).unwrap();
+    let mut automaton_string = String::new();
+    let _ = automaton_file.read_to_string(&mut automaton_string);
+    automaton_string.parse().unwrap()
+}
+
+fn recognise_corpus<Q, Rec, T, W>(rec: Rec, n: usize, beam: Option<usize>, corpus: Vec<Vec<T>>)
+where
+    Rec: Recognisable<T, W, Parse = Item<Q, FiniteStateInstruction<Q>, T, W>>,
+    Q: Debug,
+    W: Debug,
+    T: Debug,
+{
+    for sentence in corpus {
+        println!("{:?}:", sentence);
+        match beam {
+            Some(b) => {
+                for run in rec.recognise_beam_search(b, sentence).take(n) {
+                    println!("  {:?}", run.1);
+                }
+            }
+            None => {
+                for run in rec.recognise(sentence).take(n) {
+                    println!("  {:?}", run.1)
+                }
+            }
+        }
+        println!();
+    }
+}
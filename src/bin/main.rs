@@ -1,6 +1,11 @@
 extern crate clap;
 extern crate log_domain;
+extern crate num_traits;
 extern crate flate2;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
 
 #[macro_use]
 extern crate rustomata;
@@ -8,8 +13,16 @@ extern crate rustomata;
 use clap::App;
 
 mod approximation;
+mod budget;
 mod cfg;
+mod finite_state_automata;
+mod mg;
 mod pmcfg;
+mod push_down_automata;
+mod repl;
+mod serve;
+mod tag;
+mod tree_automata;
 mod tree_stack_automata;
 mod csparsing;
 
@@ -20,17 +33,29 @@ fn main() {
         .about("Framework for (weighted) automata with storage")
         .subcommand(pmcfg::get_sub_command())
         .subcommand(cfg::get_sub_command())
+        .subcommand(tag::get_sub_command())
+        .subcommand(mg::get_sub_command())
         .subcommand(tree_stack_automata::get_sub_command())
+        .subcommand(push_down_automata::get_sub_command())
+        .subcommand(finite_state_automata::get_sub_command())
+        .subcommand(tree_automata::get_sub_command())
         .subcommand(approximation::get_sub_command())
         .subcommand(csparsing::get_sub_command("csparsing"))
+        .subcommand(repl::get_sub_command())
         .get_matches();
 
     match matches.subcommand() {
         ("mcfg", Some(mcfg_matches)) => pmcfg::handle_sub_matches(mcfg_matches),
         ("cfg", Some(cfg_matches)) => cfg::handle_sub_matches(cfg_matches),
+        ("tag", Some(tag_matches)) => tag::handle_sub_matches(tag_matches),
+        ("mg", Some(mg_matches)) => mg::handle_sub_matches(mg_matches),
         ("tsa", Some(tsa_matches)) => tree_stack_automata::handle_sub_matches(tsa_matches),
+        ("pda", Some(pda_matches)) => push_down_automata::handle_sub_matches(pda_matches),
+        ("fsa", Some(fsa_matches)) => finite_state_automata::handle_sub_matches(fsa_matches),
+        ("ta", Some(ta_matches)) => tree_automata::handle_sub_matches(ta_matches),
         ("approximation", Some(r_matches)) => approximation::handle_sub_matches(r_matches),
         ("csparsing", Some(r_matches)) => csparsing::handle_sub_matches(r_matches),
+        ("repl", Some(r_matches)) => repl::handle_sub_matches(r_matches),
         _ => (),
     }
 
@@ -0,0 +1,149 @@
+use clap::{Arg, ArgMatches, App, SubCommand};
+use log_domain::LogDomain;
+use num_traits::One;
+use rustomata::automata::tree_stack_automaton::{to_abstract_syntax_tree, TreeStackAutomaton};
+use rustomata::grammars::lcfrs::Lcfrs;
+use rustomata::grammars::lcfrs::csparsing::CSRepresentation;
+use rustomata::grammars::mcfg::Mcfg;
+use rustomata::grammars::mg::{to_derivation_tree, ToMGNonterminal, MG};
+use rustomata::grammars::pmcfg::{PMCFG, PMCFGRule};
+use rustomata::recognisable::Recognisable;
+use rustomata::util::tree::GornTree;
+
+use std::borrow::Borrow;
+use std::io::{self, Read};
+use std::fs::File;
+use std::process;
+
+pub fn get_sub_command() -> App<'static, 'static> {
+    SubCommand::with_name("mg")
+        .about("functions related to minimalist grammars")
+        .subcommand(
+            SubCommand::with_name("parse")
+                .about("parses from stdin with a minimalist grammar and prints the derivation trees")
+                .arg(
+                    Arg::with_name("grammar")
+                        .help("grammar file to use")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("number-of-parses")
+                        .help("number of parses that should be returned")
+                        .short("n")
+                        .long("number")
+                        .value_name("number-of-parses")
+                        .default_value("1")
+                        .required(false),
+                )
+                .arg(
+                    Arg::with_name("beam-width")
+                        .help("maximum number of frontier nodes in the search space")
+                        .short("b")
+                        .long("beam")
+                        .value_name("beam-width")
+                        .required(false),
+                )
+                .arg(
+                    Arg::with_name("csparsing")
+                        .help("parses with a Chomsky-Schützenberger representation of the LCFRS that is obtained from the MCFG instead of a tree-stack automaton")
+                        .long("csparsing"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("mcfg")
+                .about("compiles the given minimalist grammar into an MCFG")
+                .arg(
+                    Arg::with_name("grammar")
+                        .help("grammar file to use")
+                        .index(1)
+                        .required(true),
+                ),
+        )
+}
+
+pub fn handle_sub_matches(mg_matches: &ArgMatches) {
+    match mg_matches.subcommand() {
+        ("parse", Some(mg_parse_matches)) => {
+            let grammar = read_grammar(mg_parse_matches.value_of("grammar").unwrap());
+            let n = mg_parse_matches
+                .value_of("number-of-parses")
+                .unwrap()
+                .parse()
+                .unwrap();
+
+            let mut corpus = String::new();
+            let _ = io::stdin().read_to_string(&mut corpus);
+
+            if mg_parse_matches.is_present("csparsing") {
+                let cs = match CSRepresentation::new(Lcfrs::from(Mcfg::from(grammar)), 0) {
+                    Ok(cs) => cs,
+                    Err(error) => {
+                        eprintln!("{}", error);
+                        process::exit(1);
+                    }
+                };
+                let generator = cs.build_generator();
+                for sentence in corpus.lines() {
+                    let word: Vec<String> = sentence.split_whitespace().map(|x| x.to_string()).collect();
+                    for derivation in generator.with_fallback(&word).0.take(n) {
+                        let weight = derivation.values().fold(LogDomain::one(), |w, rule| w * rule.weight);
+                        print_derivation_tree(weight, &derivation);
+                    }
+                    println!();
+                }
+            } else {
+                let beam: Option<usize> = mg_parse_matches
+                    .value_of("beam-width")
+                    .map(|b| b.parse().unwrap());
+                let automaton = TreeStackAutomaton::from(PMCFG::from(Mcfg::from(grammar)));
+                for sentence in corpus.lines() {
+                    let word: Vec<String> = sentence.split_whitespace().map(|x| x.to_string()).collect();
+                    let parses = match beam {
+                        Some(b) => automaton.recognise_beam_search(b, word),
+                        None => automaton.recognise(word),
+                    };
+                    for parse in parses.take(n) {
+                        let derivation = to_abstract_syntax_tree(parse.0.storage.to_tree());
+                        print_derivation_tree(parse.0.weight, &derivation);
+                    }
+                    println!();
+                }
+            }
+        }
+        ("mcfg", Some(mg_mcfg_matches)) => {
+            let grammar = read_grammar(mg_mcfg_matches.value_of("grammar").unwrap());
+            print!("{}", PMCFG::from(Mcfg::from(grammar)));
+        }
+        _ => (),
+    }
+}
+
+/// Prints the weight and the derivation tree of the minimalist grammar for
+/// a derivation, or an error if the derivation was not obtained from a
+/// minimalist grammar.
+fn print_derivation_tree<R, N>(weight: LogDomain<f64>, derivation: &GornTree<R>)
+where
+    R: Borrow<PMCFGRule<N, String, LogDomain<f64>>>,
+    N: ToMGNonterminal<String>,
+{
+    match to_derivation_tree(derivation) {
+        Ok(tree) => println!("{}\n{}", weight, tree),
+        Err(error) => eprintln!("could not construct the derivation tree: {}", error),
+    }
+}
+
+fn read_grammar(grammar_file_name: &str) -> MG<String, String, LogDomain<f64>> {
+    let mut grammar_string = String::new();
+    if let Err(error) = File::open(grammar_file_name).and_then(|mut f| f.read_to_string(&mut grammar_string)) {
+        eprintln!("could not read {}: {}", grammar_file_name, error);
+        process::exit(1);
+    }
+    match grammar_string.parse() {
+        Ok(grammar) => grammar,
+        Err(error) => {
+            eprintln!("could not parse {}: {}", grammar_file_name, error);
+            process::exit(1);
+        }
+    }
+}
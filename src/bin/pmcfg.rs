@@ -1,13 +1,21 @@
 use clap::{Arg, ArgMatches, App, SubCommand};
 use log_domain::LogDomain;
-use rustomata::grammars::pmcfg::PMCFG;
-use rustomata::grammars::pmcfg::negra::{to_negra, DumpMode};
-use rustomata::recognisable::Recognisable;
-use rustomata::automata::tree_stack_automaton::TreeStackAutomaton;
+use rustomata::grammars::pmcfg::{PMCFG, PMCFGRule, SynchronousPMCFG};
+use rustomata::grammars::pmcfg::negra::{to_negra, noparse, DumpMode};
+use rustomata::recognisable::{Item, Recognisable};
+use rustomata::automata::tree_stack_automaton::{PosState, TabularRecogniser, TreeStack, TreeStackAutomaton,
+                                                TreeStackInstruction};
 use rustomata::automata::tree_stack_automaton::to_abstract_syntax_tree;
+use rustomata::util::budget::BudgetTracker;
+use rustomata::util::dot::{tree_to_dot, ToDot};
+
+use budget::{budget_args, budget_from_matches, max_candidates_arg};
 
 use std::io::{self, Read};
 use std::fs::File;
+use std::process;
+
+type Symbol = PosState<PMCFGRule<String, String, LogDomain<f64>>>;
 
 pub fn get_sub_command() -> App<'static, 'static> {
     SubCommand::with_name("mcfg")
@@ -44,6 +52,47 @@ pub fn get_sub_command() -> App<'static, 'static> {
                     Arg::with_name("negra")
                         .help("turn on output in NeGra export format")
                         .long("negra"),
+                )
+                .arg(
+                    Arg::with_name("dot")
+                        .help("prints the parse trees in the DOT language of Graphviz")
+                        .long("dot")
+                        .conflicts_with("negra"),
+                )
+                .arg(
+                    Arg::with_name("tabular")
+                        .help("parses with a tabular algorithm, which takes polynomial time")
+                        .long("tabular"),
+                )
+                .args(&budget_args())
+                .arg(max_candidates_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("translate")
+                .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+                .about("translates from stdin with a synchronous multiple context-free grammar")
+                .arg(
+                    Arg::with_name("grammar")
+                        .help("grammar file to use")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("number-of-translations")
+                        .help("number of translations that should be returned")
+                        .short("n")
+                        .long("number")
+                        .value_name("number-of-translations")
+                        .default_value("1")
+                        .required(false),
+                )
+                .arg(
+                    Arg::with_name("beam-width")
+                        .help("maximum number of frontier nodes in the search space")
+                        .short("b")
+                        .long("beam")
+                        .value_name("beam-width")
+                        .required(false),
                 ),
         )
         .subcommand(
@@ -55,6 +104,11 @@ pub fn get_sub_command() -> App<'static, 'static> {
                         .help("grammar file to use")
                         .index(1)
                         .required(true),
+                )
+                .arg(
+                    Arg::with_name("dot")
+                        .help("prints the automaton in the DOT language of Graphviz")
+                        .long("dot"),
                 ),
         )
 }
@@ -78,33 +132,65 @@ pub fn handle_sub_matches(mcfg_matches: &ArgMatches) {
             let mut corpus = String::new();
             let _ = io::stdin().read_to_string(&mut corpus);
 
-            for (i, sentence) in corpus.lines().enumerate() {
-                let word = sentence.split_whitespace().map(|x| x.to_string()).collect();
-                match mcfg_parse_matches.value_of("beam-width") {
-                    Some(b) => {
-                        for parse in automaton
-                            .recognise_beam_search(b.parse().unwrap(), word)
-                            .take(n)
-                        {
-                            let ast = to_abstract_syntax_tree(parse.0.storage.to_tree());
-                            if mcfg_parse_matches.is_present("negra") {
-                                println!("{}", to_negra(&ast, i + 1, DumpMode::Default));
-                            } else {
-                                println!("{}", parse.0);
-                            }
-                        }
-                    }
-                    None => {
-                        for parse in automaton.recognise(word).take(n) {
-                            let ast = to_abstract_syntax_tree(parse.0.storage.to_tree());
-                            if mcfg_parse_matches.is_present("negra") {
-                                println!("{}", to_negra(&ast, i + 1, DumpMode::Default));
-                            } else {
-                                println!("{}", parse.0);
-                            }
-                        }
-                    }
+            let beam = mcfg_parse_matches
+                .value_of("beam-width")
+                .map(|b| b.parse().unwrap());
+
+            if mcfg_parse_matches.is_present("tabular") {
+                // the initial tree stack of an automaton obtained from a PMCFG is a single node
+                let recogniser = TabularRecogniser::new(automaton).unwrap();
+                parse_corpus(&recogniser, mcfg_parse_matches, n, beam, &corpus);
+            } else {
+                parse_corpus(&automaton, mcfg_parse_matches, n, beam, &corpus);
+            }
+        }
+        ("translate", Some(mcfg_translate_matches)) => {
+            let grammar_file_name = mcfg_translate_matches.value_of("grammar").unwrap();
+            let n = mcfg_translate_matches
+                .value_of("number-of-translations")
+                .unwrap()
+                .parse()
+                .unwrap();
+            let mut grammar_string = String::new();
+            if let Err(error) = File::open(grammar_file_name).and_then(|mut f| f.read_to_string(&mut grammar_string)) {
+                eprintln!("could not read {}: {}", grammar_file_name, error);
+                process::exit(1);
+            }
+            let grammar: SynchronousPMCFG<String, String, LogDomain<f64>> = match grammar_string.parse() {
+                Ok(grammar) => grammar,
+                Err(error) => {
+                    eprintln!("could not parse {}: {}", grammar_file_name, error);
+                    process::exit(1);
+                }
+            };
+
+            let automaton = TreeStackAutomaton::from(grammar.source());
+
+            let mut corpus = String::new();
+            let _ = io::stdin().read_to_string(&mut corpus);
+
+            let beam: Option<usize> = mcfg_translate_matches
+                .value_of("beam-width")
+                .map(|b| b.parse().unwrap());
+
+            for sentence in corpus.lines() {
+                let word: Vec<String> = sentence.split_whitespace().map(|x| x.to_string()).collect();
+                let parses = match beam {
+                    Some(b) => automaton.recognise_beam_search(b, word),
+                    None => automaton.recognise(word),
                 };
+                // several derivations of the source word may yield the
+                // same translation
+                let derivations = parses.take(n).map(|parse| to_abstract_syntax_tree(parse.0.storage.to_tree()));
+                match grammar.translate_all(derivations, n) {
+                    Ok(translations) => for (translation, weight) in translations {
+                        println!("{}  # {}", translation.join(" "), weight);
+                    },
+                    Err(error) => {
+                        eprintln!("could not translate \"{}\": {}", sentence, error);
+                        process::exit(1);
+                    }
+                }
                 println!();
             }
         }
@@ -115,8 +201,49 @@ pub fn handle_sub_matches(mcfg_matches: &ArgMatches) {
             let _ = grammar_file.read_to_string(&mut grammar_string);
             let grammar: PMCFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
             let automaton = TreeStackAutomaton::from(grammar);
-            println!("{}", automaton);
+            if mcfg_automaton_matches.is_present("dot") {
+                print!("{}", automaton.to_dot());
+            } else {
+                println!("{}", automaton);
+            }
         }
         _ => (),
     }
 }
+
+fn parse_corpus<R>(recogniser: &R, mcfg_parse_matches: &ArgMatches, n: usize, beam: Option<usize>, corpus: &str)
+where
+    R: Recognisable<String, LogDomain<f64>, Parse = Item<TreeStack<Symbol>, TreeStackInstruction<Symbol>, String, LogDomain<f64>>>,
+{
+    let budget = budget_from_matches(mcfg_parse_matches);
+
+    for (i, sentence) in corpus.lines().enumerate() {
+        let word: Vec<String> = sentence.split_whitespace().map(|x| x.to_string()).collect();
+        let tracker = BudgetTracker::new(budget);
+        let mut found_parse = false;
+        for parse in recogniser
+            .recognise_with_budget(beam, tracker.clone(), word.clone())
+            .take(n)
+        {
+            found_parse = true;
+            let ast = to_abstract_syntax_tree(parse.0.storage.to_tree());
+            if mcfg_parse_matches.is_present("negra") {
+                println!("{}", to_negra(&ast, i + 1, DumpMode::Default));
+            } else if mcfg_parse_matches.is_present("dot") {
+                print!("{}", tree_to_dot(&ast, |r| r.label(), None));
+            } else {
+                println!("{}", parse.0);
+            }
+        }
+        if let (false, Some(exhaustion)) = (found_parse, tracker.exhausted()) {
+            if mcfg_parse_matches.is_present("negra") {
+                eprintln!("sentence {}: {}", i + 1, exhaustion);
+                println!("{}", noparse(&word, i + 1, DumpMode::Default));
+            } else {
+                let comment = if mcfg_parse_matches.is_present("dot") { "// " } else { "" };
+                println!("{}NOPARSE ({})", comment, exhaustion);
+            }
+        }
+        println!();
+    }
+}
@@ -0,0 +1,162 @@
+use clap::{Arg, ArgMatches, App, SubCommand};
+use log_domain::LogDomain;
+use rustomata::grammars::cfg::CFG;
+use rustomata::recognisable::{Item, Recognisable};
+use rustomata::util::dot::ToDot;
+use rustomata::automata::push_down_automaton::{PushDownAutomaton, PushDown,
+                                               PushDownInstruction, TabularRecogniser};
+use std::fmt::Debug;
+use std::io::{self, Read};
+use std::fs::File;
+
+pub fn get_sub_command() -> App<'static, 'static> {
+    SubCommand::with_name("pda")
+        .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+        .about("functions related to pushdown automata")
+        .subcommand(
+            SubCommand::with_name("recognise")
+                .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+                .about("recognises from stdin with a pushdown automaton")
+                .arg(
+                    Arg::with_name("automaton")
+                        .help("automaton file to use")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("beam-width")
+                        .help("maximum number of frontier nodes in the search space")
+                        .short("b")
+                        .long("beam")
+                        .value_name("beam-width")
+                        .required(false),
+                )
+                .arg(
+                    Arg::with_name("number-of-runs")
+                        .help("number of runs that should be returned")
+                        .short("n")
+                        .long("number")
+                        .default_value("1")
+                        .required(false),
+                )
+                .arg(
+                    Arg::with_name("tabular")
+                        .help("recognises with a tabular algorithm, which takes polynomial time")
+                        .long("tabular"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("print")
+                .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+                .about("reads a pushdown automaton and prints it")
+                .arg(
+                    Arg::with_name("automaton")
+                        .help("automaton file to use")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("dot")
+                        .help("prints the automaton in the DOT language of Graphviz")
+                        .long("dot"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("grammar")
+                .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+                .about("constructs a context-free grammar from the given pushdown automaton")
+                .arg(
+                    Arg::with_name("automaton")
+                        .help("automaton file to use")
+                        .index(1)
+                        .required(true),
+                ),
+        )
+}
+
+pub fn handle_sub_matches(pda_matches: &ArgMatches) {
+    match pda_matches.subcommand() {
+        ("recognise", Some(pda_recognise_matches)) => {
+            let automaton_file_name = pda_recognise_matches.value_of("automaton").unwrap();
+            let mut automaton_file = File::open(automaton_file_name).unwrap();
+            let mut automaton_string = String::new();
+            let _ = automaton_file.read_to_string(&mut automaton_string);
+            let automaton: PushDownAutomaton<String, String, LogDomain<f64>> =
+                automaton_string.parse().unwrap();
+
+            let mut corpus_raw = String::new();
+            let _ = io::stdin().read_to_string(&mut corpus_raw);
+            let corpus = corpus_raw
+                .lines()
+                .map(|s| s.split_whitespace().map(|x| x.to_string()).collect())
+                .collect();
+
+            let n = pda_recognise_matches
+                .value_of("number-of-runs")
+                .unwrap()
+                .parse()
+                .unwrap();
+
+            let beam = match pda_recognise_matches.value_of("beam-width") {
+                Some(b) => Some(b.parse().unwrap()),
+                None => None,
+            };
+
+            if pda_recognise_matches.is_present("tabular") {
+                recognise_corpus(TabularRecogniser::new(automaton), n, beam, corpus)
+            } else {
+                recognise_corpus(automaton, n, beam, corpus)
+            }
+        }
+        ("print", Some(pda_print_matches)) => {
+            let automaton_file_name = pda_print_matches.value_of("automaton").unwrap();
+            let mut automaton_file = File::open(automaton_file_name).unwrap();
+            let mut automaton_string = String::new();
+            let _ = automaton_file.read_to_string(&mut automaton_string);
+            let automaton: PushDownAutomaton<String, String, LogDomain<f64>> =
+                automaton_string.parse().unwrap();
+
+            if pda_print_matches.is_present("dot") {
+                print!("{}", automaton.to_dot());
+            } else {
+                println!("{}", automaton);
+            }
+        }
+        ("grammar", Some(pda_grammar_matches)) => {
+            let automaton_file_name = pda_grammar_matches.value_of("automaton").unwrap();
+            let mut automaton_file = File::open(automaton_file_name).unwrap();
+            let mut automaton_string = String::new();
+            let _ = automaton_file.read_to_string(&mut automaton_string);
+            let automaton: PushDownAutomaton<String, String, LogDomain<f64>> =
+                automaton_string.parse().unwrap();
+
+            print!("{}", CFG::from(automaton));
+        }
+        _ => (),
+    }
+}
+
+fn recognise_corpus<A, Rec, T, W>(rec: Rec, n: usize, beam: Option<usize>, corpus: Vec<Vec<T>>)
+where
+    Rec: Recognisable<T, W, Parse = Item<PushDown<A>, PushDownInstruction<A>, T, W>>,
+    A: Debug,
+    W: Debug,
+    T: Debug,
+{
+    for sentence in corpus {
+        println!("{:?}:", sentence);
+        match beam {
+            Some(b) => {
+                for run in rec.recognise_beam_search(b, sentence).take(n) {
+                    println!("  {:?}", run.1);
+                }
+            }
+            None => {
+                for run in rec.recognise(sentence).take(n) {
+                    println!("  {:?}", run.1)
+                }
+            }
+        }
+        println!();
+    }
+}
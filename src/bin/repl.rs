@@ -0,0 +1,320 @@
+use clap::{Arg, ArgMatches, App, SubCommand};
+use log_domain::LogDomain;
+use num_traits::One;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{stdin, stdout, BufRead, Read, Write};
+use std::num::ParseIntError;
+use std::process;
+
+use rustomata::automata::push_down_automaton::{PushDownAutomaton, PushDownInstruction, PushState};
+use rustomata::automata::tree_stack_automaton::{to_abstract_syntax_tree, PosState, TreeStackAutomaton};
+use rustomata::grammars::cfg::CFG;
+use rustomata::grammars::lcfrs::csparsing::CSRepresentation;
+use rustomata::grammars::pmcfg::{PMCFG, PMCFGRule};
+use rustomata::recognisable::{Recognisable, Transition};
+use rustomata::util::budget::{Budget, BudgetTracker};
+use rustomata::util::tree::{child_address, GornTree};
+
+use csparsing::read_csrep;
+
+type W = LogDomain<f64>;
+
+pub fn get_sub_command() -> App<'static, 'static> {
+    SubCommand::with_name("repl")
+        .about("Loads a grammar or automaton and parses the sentences that are typed in.")
+        .arg(
+            Arg::with_name("file")
+                .required(true)
+                .index(1)
+                .help("MCFG, CFG, tree-stack automaton or CS representation of a grammar"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["mcfg", "cfg", "tsa", "cs"])
+                .help("format of the file; guessed from its extension if not given \
+                       (`.cfg`, `.tsa` and `.cs`, everything else is read as MCFG)"),
+        )
+}
+
+/// The grammar or automaton that is explored.
+enum Loaded {
+    Mcfg(PMCFG<String, String, W>, TreeStackAutomaton<PosState<PMCFGRule<String, String, W>>, String, W>),
+    Cfg(CFG<String, String, W>, PushDownAutomaton<PushState<String, String>, String, W>),
+    Tsa(TreeStackAutomaton<String, String, W>),
+    Cs(CSRepresentation<String, String, W>),
+}
+
+struct Settings {
+    k: usize,
+    beam: Option<usize>,
+    candidates: Option<usize>,
+    states: bool,
+}
+
+const HELP: &str = "\
+Type a sentence (space separated) to parse it, or one of the commands
+  :k <n>                  number of parse trees that are shown
+  :beam <n>|off           beam width
+  :candidates <n>|off     maximum number of enumerated candidates
+  :rules [<nonterminal>]  rules with the given left-hand side, or all left-hand sides
+  :states                 toggles the output of the visited states (CS representations only)
+  :settings               shows the current settings
+  :help                   shows this message
+  :quit                   ends the session";
+
+fn read_file(path: &str) -> Result<String, String> {
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .map_err(|e| format!("could not read {}: {}", path, e))?;
+    Ok(content)
+}
+
+fn load(path: &str, format: &str) -> Result<Loaded, String> {
+    match format {
+        "cs" => read_csrep(path).map(Loaded::Cs),
+        "cfg" => {
+            let grammar: CFG<String, String, W> = read_file(path)?.parse()?;
+            let automaton = PushDownAutomaton::from(grammar.clone());
+            Ok(Loaded::Cfg(grammar, automaton))
+        }
+        "tsa" => Ok(Loaded::Tsa(read_file(path)?.parse()?)),
+        _ => {
+            let grammar: PMCFG<String, String, W> = read_file(path)?.parse()?;
+            let automaton = TreeStackAutomaton::from(grammar.clone());
+            Ok(Loaded::Mcfg(grammar, automaton))
+        }
+    }
+}
+
+/// Reads the argument of `:beam` and `:candidates`.
+fn parse_limit(argument: &str) -> Result<Option<usize>, ParseIntError> {
+    if argument == "off" {
+        Ok(None)
+    } else {
+        argument.parse().map(Some)
+    }
+}
+
+pub fn handle_sub_matches(params: &ArgMatches) {
+    let path = params.value_of("file").unwrap();
+    let format = params.value_of("format").unwrap_or_else(|| {
+        if path.ends_with(".cfg") {
+            "cfg"
+        } else if path.ends_with(".tsa") {
+            "tsa"
+        } else if path.ends_with(".cs") {
+            "cs"
+        } else {
+            "mcfg"
+        }
+    });
+    let loaded = match load(path, format) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let mut settings = Settings { k: 1, beam: None, candidates: None, states: false };
+
+    println!("{}\n", HELP);
+    let input = stdin();
+    let mut lines = input.lock().lines();
+    loop {
+        print!("> ");
+        stdout().flush().unwrap();
+        let line = match lines.next() {
+            Some(line) => line.unwrap(),
+            None => break,
+        };
+        let mut words = line.split_whitespace();
+        match words.next() {
+            None => (),
+            Some(":quit") => break,
+            Some(":help") => println!("{}", HELP),
+            Some(":settings") => println!(
+                "k: {}, beam: {}, candidates: {}, states: {}",
+                settings.k,
+                settings.beam.map_or("off".to_string(), |b| b.to_string()),
+                settings.candidates.map_or("off".to_string(), |c| c.to_string()),
+                if settings.states { "on" } else { "off" }
+            ),
+            Some(":k") => match words.next().and_then(|n| n.parse().ok()) {
+                Some(k) => settings.k = k,
+                None => println!("usage: :k <n>"),
+            },
+            Some(":beam") => match words.next().map(parse_limit) {
+                Some(Ok(beam)) => settings.beam = beam,
+                _ => println!("usage: :beam <n>|off"),
+            },
+            Some(":candidates") => match words.next().map(parse_limit) {
+                Some(Ok(candidates)) => settings.candidates = candidates,
+                _ => println!("usage: :candidates <n>|off"),
+            },
+            Some(":states") => {
+                settings.states = !settings.states;
+                println!("states: {}", if settings.states { "on" } else { "off" });
+            }
+            Some(":rules") => print_rules(&loaded, words.next()),
+            Some(command) if command.starts_with(':') => {
+                println!("unknown command {}; type :help for a list of commands", command)
+            }
+            Some(first) => {
+                let word = ::std::iter::once(first).chain(words).map(|w| w.to_string()).collect();
+                parse(&loaded, &settings, word);
+            }
+        }
+    }
+    println!();
+}
+
+fn print_rules(loaded: &Loaded, head: Option<&str>) {
+    match *loaded {
+        Loaded::Mcfg(ref grammar, _) => {
+            print_rules_by_head(grammar.rules.iter().map(|r| (&r.head, r.to_string())), head)
+        }
+        Loaded::Cfg(ref grammar, _) => {
+            print_rules_by_head(grammar.rules.iter().map(|r| (&r.head, r.to_string())), head)
+        }
+        Loaded::Cs(ref csrep) => {
+            print_rules_by_head(csrep.rules().iter().map(|r| (&r.head, r.to_string())), head)
+        }
+        Loaded::Tsa(_) => println!("a tree-stack automaton has no rules"),
+    }
+}
+
+/// Prints the rules with the given left-hand side, or each left-hand side
+/// with its number of rules.
+fn print_rules_by_head<'a, I>(rules: I, head: Option<&str>)
+where
+    I: Iterator<Item = (&'a String, String)>,
+{
+    match head {
+        Some(head) => {
+            let mut found = false;
+            for (_, rule) in rules.filter(|&(h, _)| h == head) {
+                found = true;
+                println!("{}", rule);
+            }
+            if !found {
+                println!("there are no rules for {}", head);
+            }
+        }
+        None => {
+            let mut counts: BTreeMap<&String, usize> = BTreeMap::new();
+            for (h, _) in rules {
+                *counts.entry(h).or_insert(0) += 1;
+            }
+            for (h, count) in counts {
+                println!("{}\t{} rule(s)", h, count);
+            }
+        }
+    }
+}
+
+fn parse(loaded: &Loaded, settings: &Settings, word: Vec<String>) {
+    let tracker = BudgetTracker::new(Budget { candidates: settings.candidates, ..Budget::default() });
+    let mut found = false;
+    match *loaded {
+        Loaded::Mcfg(_, ref automaton) => {
+            for parse in automaton.recognise_with_budget(settings.beam, tracker.clone(), word).take(settings.k) {
+                found = true;
+                let tree = to_abstract_syntax_tree(parse.0.storage.to_tree());
+                println!("weight {}\n{}\n", parse.0.weight, tree.to_ascii(|r| r.label()));
+            }
+        }
+        Loaded::Cfg(_, ref automaton) => {
+            for parse in automaton.recognise_with_budget(settings.beam, tracker.clone(), word).take(settings.k) {
+                found = true;
+                let run = parse.1.to_vec();
+                println!("weight {}\n{}\n", parse.0.weight, cfg_tree(run).to_ascii(|l| l.clone()));
+            }
+        }
+        Loaded::Tsa(ref automaton) => {
+            for parse in automaton.recognise_with_budget(settings.beam, tracker.clone(), word).take(settings.k) {
+                found = true;
+                let (tree_stack, _) = parse.0.storage.to_tree();
+                println!("weight {}\n{}\n", parse.0.weight, tree_stack.to_ascii(|l| l.clone()));
+            }
+        }
+        Loaded::Cs(ref csrep) => {
+            let mut parser = csrep.build_generator();
+            if let Some(beam) = settings.beam {
+                parser.set_beam(beam);
+            }
+            if let Some(candidates) = settings.candidates {
+                parser.set_candidates(candidates);
+            }
+            if settings.states {
+                for (i, j, q, nonterminal, w) in parser.chart(&word) {
+                    match nonterminal {
+                        Some((nt, component)) => println!("{}-{}\t{} ({}/{})\t{}", i, j, q, nt, component, w),
+                        None => println!("{}-{}\t{}\t{}", i, j, q, w),
+                    }
+                }
+                println!();
+            }
+            let (parses, fallback, budget) = parser.with_fallback(&word);
+            for tree in parses.take(settings.k) {
+                found = true;
+                let weight = tree.values().fold(W::one(), |w, rule| w * rule.weight);
+                println!("weight {}\n{}\n", weight, tree.to_ascii(|r| r.label()));
+            }
+            if let (false, Some(tree)) = (found, fallback) {
+                println!("fallback\n{}\n", tree.to_ascii(|r| r.label()));
+            }
+            if let (false, Some(exhaustion)) = (found, budget.exhausted()) {
+                println!("{}", exhaustion);
+            }
+            return;
+        }
+    }
+    if !found {
+        match tracker.exhausted() {
+            Some(exhaustion) => println!("no parse ({})", exhaustion),
+            None => println!("no parse"),
+        }
+    }
+}
+
+/// Reconstructs the derivation tree of a CFG from a run of its pushdown
+/// automaton.
+/// The run is a left-most derivation, thus the nonterminal expansions occur
+/// in pre-order.
+fn cfg_tree(run: Vec<Transition<PushDownInstruction<PushState<String, String>>, String, W>>) -> GornTree<String> {
+    let mut expansions = run.into_iter().filter_map(|transition| {
+        let PushDownInstruction::Replace { current_val, new_val } = transition.instruction;
+        match current_val.first() {
+            Some(&PushState::Nt(ref head)) => Some((head.clone(), new_val)),
+            _ => None,
+        }
+    });
+    let mut tree = GornTree::new();
+    expand(&mut expansions, Vec::new(), &mut tree);
+    tree
+}
+
+fn expand<I>(expansions: &mut I, address: Vec<usize>, tree: &mut GornTree<String>)
+where
+    I: Iterator<Item = (String, Vec<PushState<String, String>>)>,
+{
+    if let Some((head, successors)) = expansions.next() {
+        tree.insert(address.clone(), head);
+        // the right-hand side of a rule is pushed in reverse order
+        for (i, successor) in successors.into_iter().rev().enumerate() {
+            let child = child_address(&address, i);
+            match successor {
+                PushState::T(t) => {
+                    tree.insert(child, t);
+                }
+                _ => expand(expansions, child, tree),
+            }
+        }
+    }
+}
+
@@ -0,0 +1,245 @@
+use clap::{Arg, ArgMatches, App, SubCommand};
+use log_domain::LogDomain;
+use serde_json::{self, Value};
+use std::collections::BTreeMap;
+use std::io::{self, stdin, stdout, BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use rustomata::grammars::lcfrs::csparsing::CSRepresentation;
+use rustomata::util::budget::Budget;
+
+use csparsing::{parse_json, read_csrep, split_pos};
+
+type Grammar = CSRepresentation<String, String, LogDomain<f64>>;
+
+pub fn get_sub_command() -> App<'static, 'static> {
+    SubCommand::with_name("serve")
+        .about(
+            "Loads CS representations of grammars once and answers parse requests,
+                                       one JSON object per line, on stdin/stdout or a socket.",
+        )
+        .arg(
+            Arg::with_name("grammars")
+                .required(true)
+                .multiple(true)
+                .index(1)
+                .help("CS representations of grammars, given as `name=file` or `file`; \
+                       the first one is used if a request names no grammar."),
+        )
+        .arg(
+            Arg::with_name("unix")
+                .long("unix")
+                .takes_value(true)
+                .value_name("path")
+                .conflicts_with("tcp")
+                .help("Listens on a Unix socket instead of stdin (only on Unix); \
+                       each connection is answered in its own thread."),
+        )
+        .arg(
+            Arg::with_name("tcp")
+                .long("tcp")
+                .takes_value(true)
+                .value_name("address")
+                .help("Listens on a TCP address, e.g. `127.0.0.1:4242`, instead of stdin; \
+                       each connection is answered in its own thread."),
+        )
+}
+
+/// A request to the server.
+/// All fields except for `sentence` (when parsing) and `grammar` (when
+/// reloading) are optional.
+#[derive(Deserialize, Debug)]
+struct Request {
+    #[serde(default)]
+    command: Command,
+    grammar: Option<String>,
+    id: Option<usize>,
+    sentence: Option<String>,
+    #[serde(default)]
+    with_pos: bool,
+    k: Option<usize>,
+    beam: Option<usize>,
+    threshold: Option<f64>,
+    candidates: Option<usize>,
+    timeout: Option<u64>,
+    max_pops: Option<usize>,
+    #[serde(default)]
+    fallback: bool,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Command {
+    /// parses `sentence` with `grammar`
+    Parse,
+    /// reads the file of `grammar` again; the old grammar is kept if that fails
+    Reload,
+    /// lists the names of the loaded grammars
+    Grammars,
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Command::Parse
+    }
+}
+
+/// The loaded grammars with their files, and the name of the default grammar.
+/// The server is shared by all connections; a grammar that is being reloaded
+/// is only replaced once it was read completely, so parses that already
+/// started keep using the old one.
+struct Server {
+    grammars: RwLock<BTreeMap<String, (String, Arc<Grammar>)>>,
+    default: String,
+    sentences: AtomicUsize,
+}
+
+impl Server {
+    fn new<'a, I: Iterator<Item = &'a str>>(specs: I) -> Result<Self, String> {
+        let mut grammars = BTreeMap::new();
+        let mut default = None;
+        for spec in specs {
+            let (name, path) = match spec.find('=') {
+                Some(pos) => (spec[..pos].to_string(), spec[pos + 1..].to_string()),
+                None => (spec.to_string(), spec.to_string()),
+            };
+            let grammar = read_csrep(&path)?;
+            default = default.or_else(|| Some(name.clone()));
+            grammars.insert(name, (path, Arc::new(grammar)));
+        }
+        Ok(Server {
+            grammars: RwLock::new(grammars),
+            default: default.ok_or("no grammar given")?,
+            sentences: AtomicUsize::new(0),
+        })
+    }
+
+    fn handle(&self, line: &str) -> Value {
+        match serde_json::from_str::<Request>(line) {
+            Ok(request) => {
+                let id = request.id;
+                self.respond(request).unwrap_or_else(|e| json!({ "id": id, "error": e }))
+            }
+            Err(e) => json!({ "error": format!("invalid request: {}", e) }),
+        }
+    }
+
+    fn respond(&self, request: Request) -> Result<Value, String> {
+        let name = request.grammar.clone().unwrap_or_else(|| self.default.clone());
+        match request.command {
+            Command::Grammars => {
+                let grammars = self.grammars.read().unwrap();
+                Ok(json!({ "grammars": grammars.keys().collect::<Vec<_>>() }))
+            }
+            Command::Reload => {
+                let path = self.grammar(&name)?.0;
+                let grammar = read_csrep(&path)?;
+                if let Some(entry) = self.grammars.write().unwrap().get_mut(&name) {
+                    entry.1 = Arc::new(grammar);
+                }
+                Ok(json!({ "reloaded": name }))
+            }
+            Command::Parse => {
+                let grammar = self.grammar(&name)?.1;
+                let sentence = request.sentence.ok_or("missing sentence")?;
+
+                let mut parser = grammar.build_generator();
+                if let Some(beam) = request.beam { parser.set_beam(beam) };
+                if let Some(threshold) = request.threshold {
+                    parser.set_delta(LogDomain::new(threshold).map_err(|_| format!("invalid threshold {}", threshold))?)
+                };
+                parser.set_budget(Budget {
+                    time: request.timeout.map(Duration::from_millis),
+                    candidates: request.candidates,
+                    pops: request.max_pops,
+                });
+
+                let number = self.sentences.fetch_add(1, Ordering::SeqCst);
+                let id = request.id.unwrap_or(number);
+                let (words, negra_mode) = split_pos(sentence.split_whitespace(), request.with_pos);
+                Ok(parse_json(&parser, &words, id, request.k.unwrap_or(1), request.fallback, negra_mode))
+            }
+        }
+    }
+
+    /// The file and the current version of the grammar called `name`.
+    fn grammar(&self, name: &str) -> Result<(String, Arc<Grammar>), String> {
+        self.grammars
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|&(ref path, ref grammar)| (path.clone(), Arc::clone(grammar)))
+            .ok_or(format!("unknown grammar {}", name))
+    }
+
+    /// Answers each line of `input` with a line in `output`.
+    fn serve<R: BufRead, W: Write>(&self, input: R, mut output: W) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            writeln!(output, "{}", self.handle(&line))?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+pub fn handle_sub_matches(params: &ArgMatches) {
+    let server = match Server::new(params.values_of("grammars").unwrap()) {
+        Ok(server) => Arc::new(server),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    if let Some(path) = params.value_of("unix") {
+        listen_unix(&server, path);
+    } else if let Some(address) = params.value_of("tcp") {
+        let listener = TcpListener::bind(address).expect("could not bind address");
+        for stream in listener.incoming() {
+            connect(&server, stream.and_then(|s| s.try_clone().map(|r| (r, s))));
+        }
+    } else {
+        let input = stdin();
+        server.serve(input.lock(), stdout()).unwrap();
+    }
+}
+
+#[cfg(unix)]
+fn listen_unix(server: &Arc<Server>, path: &str) {
+    let listener = UnixListener::bind(path).expect("could not bind socket");
+    for stream in listener.incoming() {
+        connect(server, stream.and_then(|s| s.try_clone().map(|r| (r, s))));
+    }
+}
+
+#[cfg(not(unix))]
+fn listen_unix(_: &Arc<Server>, _: &str) {
+    eprintln!("Unix sockets are not supported on this platform.");
+    process::exit(1);
+}
+
+/// Answers the requests of a connection, given as a reader and a writer, in a new thread.
+fn connect<S: Read + Write + Send + 'static>(server: &Arc<Server>, connection: io::Result<(S, S)>) {
+    match connection {
+        Ok((reader, writer)) => {
+            let server = Arc::clone(server);
+            thread::spawn(move || {
+                if let Err(e) = server.serve(BufReader::new(reader), writer) {
+                    eprintln!("connection failed: {}", e);
+                }
+            });
+        }
+        Err(e) => eprintln!("connection failed: {}", e),
+    }
+}
@@ -0,0 +1,154 @@
+use clap::{Arg, ArgMatches, App, SubCommand};
+use log_domain::LogDomain;
+use rustomata::automata::tree_automaton::{TreeAutomaton, TreeTransition};
+use rustomata::grammars::pmcfg::PMCFG;
+
+use std::fmt::Debug;
+use std::io::Read;
+use std::fs::File;
+use std::process;
+use std::str::FromStr;
+
+pub fn get_sub_command() -> App<'static, 'static> {
+    SubCommand::with_name("ta")
+        .about("functions related to bottom-up tree automata")
+        .subcommand(
+            SubCommand::with_name("print")
+                .about("reads a tree automaton and prints it")
+                .arg(
+                    Arg::with_name("automaton")
+                        .help("automaton file to use")
+                        .index(1)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("best")
+                .about("prints the best trees of the language of a tree automaton with their weights")
+                .arg(
+                    Arg::with_name("automaton")
+                        .help("automaton file to use")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("number-of-trees")
+                        .help("number of trees that should be returned")
+                        .short("n")
+                        .long("number")
+                        .value_name("number-of-trees")
+                        .default_value("1")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("determinize")
+                .about("prints a deterministic tree automaton for the support of the given automaton")
+                .arg(
+                    Arg::with_name("automaton")
+                        .help("automaton file to use")
+                        .index(1)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("intersect")
+                .about("prints a tree automaton for the intersection of two automata")
+                .arg(
+                    Arg::with_name("automaton1")
+                        .help("first automaton file to use")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("automaton2")
+                        .help("second automaton file to use")
+                        .index(2)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("derivations")
+                .about("prints the tree automaton for the derivation trees of a multiple context-free grammar")
+                .arg(
+                    Arg::with_name("grammar")
+                        .help("grammar file to use")
+                        .index(1)
+                        .required(true),
+                ),
+        )
+}
+
+pub fn handle_sub_matches(ta_matches: &ArgMatches) {
+    match ta_matches.subcommand() {
+        ("print", Some(ta_print_matches)) => {
+            let automaton = automaton_from_file(ta_print_matches.value_of("automaton").unwrap());
+            print!("{}", automaton);
+        }
+        ("best", Some(ta_best_matches)) => {
+            let automaton = automaton_from_file(ta_best_matches.value_of("automaton").unwrap());
+            let n = match ta_best_matches.value_of("number-of-trees").unwrap().parse() {
+                Ok(n) => n,
+                Err(error) => {
+                    eprintln!("invalid number of trees: {}", error);
+                    process::exit(1);
+                }
+            };
+            for (tree, weight) in automaton.k_best(n) {
+                println!("{}\n{}", weight, tree);
+            }
+        }
+        ("determinize", Some(ta_determinize_matches)) => {
+            let automaton = automaton_from_file(ta_determinize_matches.value_of("automaton").unwrap());
+            print!("{}", automaton.determinize());
+        }
+        ("intersect", Some(ta_intersect_matches)) => {
+            let automaton1 = automaton_from_file(ta_intersect_matches.value_of("automaton1").unwrap());
+            let automaton2 = automaton_from_file(ta_intersect_matches.value_of("automaton2").unwrap());
+            let intersection = automaton1.intersect(&automaton2);
+
+            // pairs of states are written as `(p, q)`
+            let pair = |&(ref p, ref q): &(String, String)| format!("({}, {})", p, q);
+            let transitions = intersection.transitions().iter().map(|t| {
+                TreeTransition {
+                    symbol: t.symbol.clone(),
+                    source_states: t.source_states.iter().map(&pair).collect(),
+                    target_state: pair(&t.target_state),
+                    weight: t.weight,
+                }
+            });
+            let finals = intersection.final_states().iter().map(&pair);
+            print!("{}", TreeAutomaton::new(transitions, finals));
+        }
+        ("derivations", Some(ta_derivations_matches)) => {
+            let grammar: PMCFG<String, String, LogDomain<f64>> =
+                from_file(ta_derivations_matches.value_of("grammar").unwrap());
+            print!("{}", TreeAutomaton::from(grammar));
+        }
+        _ => (),
+    }
+}
+
+fn automaton_from_file(automaton_file_name: &str) -> TreeAutomaton<String, String, LogDomain<f64>> {
+    from_file(automaton_file_name)
+}
+
+/// Reads and parses a file, or exits with an error.
+fn from_file<A>(file_name: &str) -> A
+where
+    A: FromStr,
+    A::Err: Debug,
+{
+    let mut string = String::new();
+    if let Err(error) = File::open(file_name).and_then(|mut f| f.read_to_string(&mut string)) {
+        eprintln!("could not read {}: {}", file_name, error);
+        process::exit(1);
+    }
+    match string.parse() {
+        Ok(a) => a,
+        Err(error) => {
+            eprintln!("could not parse {}: {:?}", file_name, error);
+            process::exit(1);
+        }
+    }
+}
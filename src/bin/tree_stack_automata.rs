@@ -1,15 +1,24 @@
 use clap::{Arg, ArgMatches, App, SubCommand};
 use log_domain::LogDomain;
+use rustomata::grammars::pmcfg::PMCFG;
 use rustomata::recognisable::{Item, Recognisable};
+use rustomata::automata::tree_stack_automaton;
 use rustomata::automata::tree_stack_automaton::{TreeStackAutomaton, TreeStack,
                                                 TreeStackInstruction};
 use rustomata::approximation::ApproximationStrategy;
+use rustomata::automata::push_down_automaton::TabularRecogniser;
+use rustomata::approximation::ptk::PDTopKElement;
 use rustomata::approximation::tts::TTSElement;
 use rustomata::recognisable::coarse_to_fine::CoarseToFineRecogniser;
-use std::fmt::Debug;
+use rustomata::util::budget::{Budget, BudgetTracker};
+use rustomata::util::dot::ToDot;
+use std::fmt::{Debug, Display};
 use std::io::{self, Read};
 use std::rc::Rc;
 use std::fs::File;
+use std::process;
+
+use budget::{budget_args, budget_from_matches, max_candidates_arg};
 
 pub fn get_sub_command() -> App<'static, 'static> {
     SubCommand::with_name("tsa")
@@ -43,12 +52,59 @@ pub fn get_sub_command() -> App<'static, 'static> {
                 )
                 .arg(
                     Arg::with_name("coarse-to-fine")
-                        .help("use coarse-to-parsing")
+                        .help("use coarse-to-fine parsing with the given approximations: \"tts\" (to a pushdown automaton) or \"tts,ptk\" (additionally to a finite state automaton)")
                         .short("ctf")
                         .long("coarse-to-fine")
                         .value_name("strategies")
                         .default_value("")
                         .required(false),
+                )
+                .arg(
+                    Arg::with_name("ptk-size")
+                        .help("maximum height of the pushdown for the \"ptk\" approximation")
+                        .long("ptk-size")
+                        .value_name("k")
+                        .default_value("3")
+                        .required(false),
+                )
+                .arg(
+                    Arg::with_name("tabular")
+                        .help("recognises with a tabular algorithm (with coarse-to-fine parsing, only the coarsest approximation; not available for \"tts,ptk\"; without coarse-to-fine parsing, it only finds runs that visit each node of the tree stack at most k + 1 times, where k is the number of symbols that up-transitions write)")
+                        .long("tabular"),
+                )
+                .args(&budget_args())
+                .arg(max_candidates_arg())
+                .arg(
+                    Arg::with_name("dot")
+                        .help("prints the tree stack of each run in the DOT language of Graphviz")
+                        .long("dot"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("print")
+                .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+                .about("reads a tree-stack automaton and prints it")
+                .arg(
+                    Arg::with_name("automaton")
+                        .help("automaton file to use")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("dot")
+                        .help("prints the automaton in the DOT language of Graphviz")
+                        .long("dot"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("grammar")
+                .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+                .about("constructs a PMCFG from the given tree-stack automaton")
+                .arg(
+                    Arg::with_name("automaton")
+                        .help("automaton file to use")
+                        .index(1)
+                        .required(true),
                 ),
         )
 }
@@ -80,42 +136,110 @@ pub fn handle_sub_matches(tsa_matches: &ArgMatches) {
                 Some(b) => Some(b.parse().unwrap()),
                 None => None,
             };
+            let budget = budget_from_matches(tsa_recognise_matches);
+            let dot = tsa_recognise_matches.is_present("dot");
 
-            match tsa_recognise_matches.value_of("strategies") {
+            match tsa_recognise_matches.value_of("coarse-to-fine") {
+                Some("tts") if tsa_recognise_matches.is_present("tabular") => {
+                    let (aut0, strat_instance) = TTSElement::new().approximate_automaton(&automaton);
+                    let rec = CoarseToFineRecogniser {
+                        sublevel: Rc::new(TabularRecogniser::new(aut0)),
+                        recogniser: Rc::new(automaton),
+                        approximation_instance: Rc::new(strat_instance),
+                    };
+
+                    recognise_corpus(rec, n, beam, budget, dot, corpus)
+                }
                 Some("tts") => {
                     let rec = coarse_to_fine_recogniser!(automaton; TTSElement::new());
 
-                    recognise_corpus(rec, n, beam, corpus)
+                    recognise_corpus(rec, n, beam, budget, dot, corpus)
                 }
+                Some("tts,ptk") if tsa_recognise_matches.is_present("tabular") => {
+                    eprintln!("[ERR] The tabular algorithm is not available for finite state automata.");
+                    process::exit(1);
+                }
+                Some("tts,ptk") => {
+                    let size = tsa_recognise_matches
+                        .value_of("ptk-size")
+                        .unwrap()
+                        .parse()
+                        .unwrap();
+                    let rec = coarse_to_fine_recogniser!(automaton; TTSElement::new(), PDTopKElement::new(size));
+
+                    recognise_corpus(rec, n, beam, budget, dot, corpus)
+                }
+                Some("") | None if tsa_recognise_matches.is_present("tabular") => {
+                    match tree_stack_automaton::TabularRecogniser::new(automaton.clone()) {
+                        Ok(rec) => recognise_corpus(rec, n, beam, budget, dot, corpus),
+                        Err(error) => {
+                            eprintln!("{} Falling back to the agenda-based recogniser.", error);
+                            recognise_corpus(automaton, n, beam, budget, dot, corpus)
+                        }
+                    }
+                }
+                Some("") | None => recognise_corpus(automaton, n, beam, budget, dot, corpus),
                 Some(e) => panic!("[ERR] Strategy \"{}\" unknown.", e),
-                None => recognise_corpus(automaton, n, beam, corpus),
+            }
+        }
+        ("print", Some(tsa_print_matches)) => {
+            let automaton_file_name = tsa_print_matches.value_of("automaton").unwrap();
+            let mut automaton_file = File::open(automaton_file_name).unwrap();
+            let mut automaton_string = String::new();
+            let _ = automaton_file.read_to_string(&mut automaton_string);
+            let automaton: TreeStackAutomaton<String, String, LogDomain<f64>> =
+                automaton_string.parse().unwrap();
+
+            if tsa_print_matches.is_present("dot") {
+                print!("{}", automaton.to_dot());
+            } else {
+                println!("{}", automaton);
+            }
+        }
+        ("grammar", Some(tsa_grammar_matches)) => {
+            let automaton_file_name = tsa_grammar_matches.value_of("automaton").unwrap();
+            let mut automaton_file = File::open(automaton_file_name).unwrap();
+            let mut automaton_string = String::new();
+            let _ = automaton_file.read_to_string(&mut automaton_string);
+            let automaton: TreeStackAutomaton<String, String, LogDomain<f64>> =
+                automaton_string.parse().unwrap();
+
+            match PMCFG::from_tree_stack_automaton(&automaton) {
+                Ok(grammar) => print!("{}", grammar),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    process::exit(1);
+                }
             }
         }
         _ => (),
     }
 }
 
-fn recognise_corpus<A, Rec, T, W>(rec: Rec, n: usize, beam: Option<usize>, corpus: Vec<Vec<T>>)
+fn recognise_corpus<A, Rec, T, W>(rec: Rec, n: usize, beam: Option<usize>, budget: Budget, dot: bool, corpus: Vec<Vec<T>>)
 where
     Rec: Recognisable<T, W, Parse = Item<TreeStack<A>, TreeStackInstruction<A>, T, W>>,
-    A: Debug,
+    A: Clone + Debug + Display,
     W: Debug,
     T: Debug,
 {
+    // in DOT mode, everything except for the graphs is printed as comments
+    let comment = if dot { "// " } else { "" };
     for sentence in corpus {
-        println!("{:?}:", sentence);
-        match beam {
-            Some(b) => {
-                for run in rec.recognise_beam_search(b, sentence).take(n) {
-                    println!("  {:?}", run.1);
-                }
-            }
-            None => {
-                for run in rec.recognise(sentence).take(n) {
-                    println!("  {:?}", run.1)
-                }
+        println!("{}{:?}:", comment, sentence);
+        let tracker = BudgetTracker::new(budget);
+        let mut found_run = false;
+        for run in rec.recognise_with_budget(beam, tracker.clone(), sentence).take(n) {
+            found_run = true;
+            if dot {
+                print!("{}", run.0.storage.to_dot());
+            } else {
+                println!("  {:?}", run.1);
             }
         }
+        if let (false, Some(exhaustion)) = (found_run, tracker.exhausted()) {
+            println!("{}  NOPARSE ({})", comment, exhaustion);
+        }
         println!();
     }
 }
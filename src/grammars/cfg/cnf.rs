@@ -0,0 +1,539 @@
+use num_traits::One;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::Mul;
+
+use grammars::cfg::{CFG, CFGRule, LetterT};
+use util::tree::{child_address, GornTree};
+
+/// A nonterminal of a grammar in Chomsky normal form.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum CNFNonterminal<N, T> {
+    /// a nonterminal of the original grammar
+    Original(N),
+    /// derives only the terminal, which occurred in a longer right-hand side
+    Terminal(T),
+    /// derives a suffix of a right-hand side that was binarised
+    Fresh(usize),
+}
+
+/// The right-hand side of a rule in Chomsky normal form.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum CNFBody<N, T> {
+    Binary(CNFNonterminal<N, T>, CNFNonterminal<N, T>),
+    /// a unit rule, which is kept such that chains of unit rules are
+    /// closed by the parser
+    Unit(CNFNonterminal<N, T>),
+    Lexical(T),
+}
+
+/// A node of a derivation of a `CNF`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum CNFLabel {
+    /// the rule in Chomsky normal form with the given index
+    Rule(usize),
+    /// the rule of the original grammar with the given index, in a
+    /// derivation of the empty word from an erased nonterminal
+    Empty(usize),
+}
+
+/// An original rule in which the nonterminals at the positions `erased`
+/// (counted among the nonterminals of the rule) derive the empty word.
+#[derive(Debug, Clone, PartialEq)]
+struct Variant {
+    rule: usize,
+    erased: Vec<usize>,
+}
+
+/// Where a rule in Chomsky normal form comes from.
+#[derive(Debug, Clone, PartialEq)]
+enum Origin {
+    /// a variant of a rule of the original grammar
+    Rule(Variant),
+    /// a rule for a `Fresh` nonterminal
+    Intermediate,
+    /// a rule for a `Terminal` nonterminal
+    Preterminal,
+}
+
+/// A rule of a weighted grammar in Chomsky normal form.
+#[derive(Debug, Clone)]
+pub struct CNFRule<N, T, W> {
+    pub head: CNFNonterminal<N, T>,
+    pub body: CNFBody<N, T>,
+    pub weight: W,
+    /// the nonterminals of the original rule that are erased, i.e. that
+    /// derive the empty word, from left to right
+    pub erased: Vec<N>,
+    origin: Origin,
+}
+
+/// A weighted CFG in Chomsky normal form, obtained from a `CFG` by removing
+/// ε-rules and by binarising the remaining rules.
+/// Unit rules are kept, such that the parser closes each cell under them.
+/// Each derivation of the grammar in Chomsky normal form, together with
+/// derivations of the empty word for the `erased` nonterminals of its
+/// rules, is mapped back to a derivation of the original grammar by
+/// `original_derivation`; thus each derivation of the original grammar of
+/// a non-empty word is represented exactly once.
+///
+/// The weight of a rule in Chomsky normal form is the weight of its original
+/// rule; the weights of the derivations of the erased nonterminals are
+/// added by the parser.
+/// A rule with n nullable nonterminals has up to 2ⁿ variants in which some
+/// of them are erased.
+/// The weights of the rules must not be greater than `W::one()`.
+///
+/// ```
+/// extern crate log_domain;
+/// extern crate rustomata;
+///
+/// use std::str::FromStr;
+/// use log_domain::LogDomain;
+/// use rustomata::grammars::cfg::CFG;
+/// use rustomata::grammars::cfg::cnf::{CNF, CNFBody};
+///
+/// # fn main() {
+/// let grammar: CFG<String, String, LogDomain<f64>> = CFG::from_str(
+///     "initial: [S]\n\n\
+///      S → [T a, Nt S, T b] # 0.4\n\
+///      S → [] # 0.6"
+/// ).unwrap();
+/// let cnf = CNF::from(grammar);
+///
+/// // S → a S b is split into two rules, and there are rules for S → a b
+/// // and for each of the terminals
+/// assert_eq!(3, cnf.rules.iter().filter(|r| match r.body {
+///     CNFBody::Binary(..) => true,
+///     CNFBody::Lexical(_) | CNFBody::Unit(_) => false,
+/// }).count());
+/// assert_eq!(2, cnf.rules.len() - 3);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CNF<N, T, W> {
+    pub initial: Vec<N>,
+    pub rules: Vec<CNFRule<N, T, W>>,
+    original: Vec<CFGRule<N, T, W>>,
+    /// the best derivation of the empty word for each nullable nonterminal
+    epsilon: HashMap<N, (W, GornTree<usize>)>,
+}
+
+/// Builds the rules in Chomsky normal form for right-hand sides with at
+/// least two symbols.
+struct Binariser<N, T, W> {
+    rules: Vec<CNFRule<N, T, W>>,
+    fresh: HashMap<Vec<CNFNonterminal<N, T>>, usize>,
+    preterminals: HashSet<T>,
+}
+
+impl<N, T, W> Binariser<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Copy + One,
+{
+    fn symbol(&mut self, letter: &LetterT<N, T>) -> CNFNonterminal<N, T> {
+        match *letter {
+            LetterT::Label(ref n) => CNFNonterminal::Original(n.clone()),
+            LetterT::Value(ref t) => {
+                if self.preterminals.insert(t.clone()) {
+                    self.rules.push(CNFRule {
+                        head: CNFNonterminal::Terminal(t.clone()),
+                        body: CNFBody::Lexical(t.clone()),
+                        weight: W::one(),
+                        erased: Vec::new(),
+                        origin: Origin::Preterminal,
+                    });
+                }
+                CNFNonterminal::Terminal(t.clone())
+            }
+        }
+    }
+
+    /// Adds rules that derive `symbols` from `head`.
+    fn add(
+        &mut self,
+        head: CNFNonterminal<N, T>,
+        symbols: &[CNFNonterminal<N, T>],
+        weight: W,
+        erased: Vec<N>,
+        origin: Origin,
+    ) {
+        let right = if symbols.len() == 2 {
+            symbols[1].clone()
+        } else {
+            self.suffix(&symbols[1..])
+        };
+        self.rules.push(CNFRule {
+            head,
+            body: CNFBody::Binary(symbols[0].clone(), right),
+            weight,
+            erased,
+            origin,
+        });
+    }
+
+    /// A `Fresh` nonterminal that derives `symbols`; the same one is used for
+    /// equal suffixes.
+    fn suffix(&mut self, symbols: &[CNFNonterminal<N, T>]) -> CNFNonterminal<N, T> {
+        if let Some(&i) = self.fresh.get(symbols) {
+            return CNFNonterminal::Fresh(i);
+        }
+        let i = self.fresh.len();
+        self.fresh.insert(symbols.to_vec(), i);
+        self.add(CNFNonterminal::Fresh(i), symbols, W::one(), Vec::new(), Origin::Intermediate);
+        CNFNonterminal::Fresh(i)
+    }
+}
+
+/// Computes the best derivation of the empty word for each nonterminal that
+/// has one.
+fn epsilon<N, T, W>(rules: &[CFGRule<N, T, W>]) -> HashMap<N, (W, GornTree<usize>)>
+where
+    N: Clone + Eq + Hash,
+    W: Copy + Mul<Output = W> + Ord,
+{
+    let mut epsilon: HashMap<N, (W, GornTree<usize>)> = HashMap::new();
+    let mut changed = true;
+    // as weights are not greater than one, the best derivations do not
+    // contain cycles and the weights do not improve forever
+    while changed {
+        changed = false;
+        for (r, rule) in rules.iter().enumerate() {
+            let mut weight = rule.weight;
+            let mut tree = GornTree::new();
+            tree.insert(Vec::new(), r);
+            let mut nullable = true;
+            for (c, letter) in rule.composition.composition.iter().enumerate() {
+                match *letter {
+                    LetterT::Label(ref n) if epsilon.contains_key(n) => {
+                        weight = weight * epsilon[n].0;
+                        tree.graft(&[c], epsilon[n].1.clone());
+                    }
+                    _ => {
+                        nullable = false;
+                        break;
+                    }
+                }
+            }
+            if nullable && epsilon.get(&rule.head).map_or(true, |&(w, _)| weight > w) {
+                epsilon.insert(rule.head.clone(), (weight, tree));
+                changed = true;
+            }
+        }
+    }
+    epsilon
+}
+
+impl<N, T, W> From<CFG<N, T, W>> for CNF<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Copy + Mul<Output = W> + One + Ord,
+{
+    fn from(grammar: CFG<N, T, W>) -> Self {
+        let epsilon = epsilon(&grammar.rules);
+
+        // remove ε-rules: every nonterminal that derives the empty word may be
+        // erased, which is recorded in the variants of the rules
+        let mut binariser = Binariser { rules: Vec::new(), fresh: HashMap::new(), preterminals: HashSet::new() };
+        for (r, rule) in grammar.rules.iter().enumerate() {
+            // the right-hand sides of the variants, built letter by letter,
+            // with their erased nonterminals and their positions
+            let mut variants: Vec<(Vec<LetterT<N, T>>, Vec<N>, Vec<usize>)> =
+                vec![(Vec::new(), Vec::new(), Vec::new())];
+            let mut c = 0;
+            for letter in &rule.composition.composition {
+                let erasing: Vec<_> = match *letter {
+                    LetterT::Label(ref n) if epsilon.contains_key(n) => variants
+                        .iter()
+                        .map(|&(ref body, ref erased, ref positions)| {
+                            let mut erased = erased.clone();
+                            let mut positions = positions.clone();
+                            erased.push(n.clone());
+                            positions.push(c);
+                            (body.clone(), erased, positions)
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                for variant in &mut variants {
+                    variant.0.push(letter.clone());
+                }
+                variants.extend(erasing);
+                if let LetterT::Label(_) = *letter {
+                    c += 1;
+                }
+            }
+
+            for (body, erased, positions) in variants {
+                // the derivations of the empty word are not represented by
+                // rules in Chomsky normal form
+                if body.is_empty() {
+                    continue;
+                }
+                let origin = Origin::Rule(Variant { rule: r, erased: positions });
+                let head = CNFNonterminal::Original(rule.head.clone());
+                match body[0] {
+                    LetterT::Value(ref t) if body.len() == 1 => binariser.rules.push(CNFRule {
+                        head,
+                        body: CNFBody::Lexical(t.clone()),
+                        weight: rule.weight,
+                        erased,
+                        origin,
+                    }),
+                    LetterT::Label(ref n) if body.len() == 1 => binariser.rules.push(CNFRule {
+                        head,
+                        body: CNFBody::Unit(CNFNonterminal::Original(n.clone())),
+                        weight: rule.weight,
+                        erased,
+                        origin,
+                    }),
+                    _ => {
+                        let symbols: Vec<_> = body.iter().map(|letter| binariser.symbol(letter)).collect();
+                        binariser.add(head, &symbols, rule.weight, erased, origin);
+                    }
+                }
+            }
+        }
+
+        CNF {
+            initial: grammar.initial,
+            rules: binariser.rules,
+            original: grammar.rules,
+            epsilon,
+        }
+    }
+}
+
+impl<N, T, W> CNF<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone,
+    W: Copy,
+{
+    /// The rules of the original grammar, which are referred to by
+    /// `CNFLabel::Empty`.
+    pub fn original_rules(&self) -> &[CFGRule<N, T, W>] {
+        &self.original
+    }
+
+    /// The best derivation of the empty word from `nonterminal` in the
+    /// original grammar, and its weight.
+    pub fn empty_derivation(&self, nonterminal: &N) -> Option<(GornTree<CFGRule<N, T, W>>, W)> {
+        self.epsilon
+            .get(nonterminal)
+            .map(|&(weight, ref tree)| (tree.map_values(|&r| self.original[r].clone()), weight))
+    }
+
+    /// Maps a derivation to the derivation of the original grammar that it
+    /// stands for.
+    /// The children of a node `CNFLabel::Rule(r)` are the derivations of the
+    /// nonterminals in the body of the `r`-th rule, from left to right,
+    /// followed by the derivations of its `erased` nonterminals.
+    /// The children of a node `CNFLabel::Empty(r)` are the derivations of
+    /// the nonterminals of the `r`-th original rule.
+    pub fn original_derivation(&self, derivation: &GornTree<CNFLabel>) -> GornTree<CFGRule<N, T, W>> {
+        let mut tree = GornTree::new();
+        self.write(derivation, &[], &mut tree, Vec::new());
+        tree
+    }
+
+    fn write(
+        &self,
+        derivation: &GornTree<CNFLabel>,
+        node: &[usize],
+        tree: &mut GornTree<CFGRule<N, T, W>>,
+        address: Vec<usize>,
+    ) {
+        let variant = match *derivation.get(&node.to_vec()).unwrap() {
+            CNFLabel::Empty(r) => {
+                tree.insert(address.clone(), self.original[r].clone());
+                for (c, child) in derivation.children(node).into_iter().enumerate() {
+                    self.write(derivation, &child, tree, child_address(&address, c));
+                }
+                return;
+            }
+            CNFLabel::Rule(r) => match self.rules[r].origin {
+                Origin::Rule(ref variant) => variant,
+                _ => panic!("The derivation does not start with a rule of the original grammar!"),
+            },
+        };
+        let rule = &self.original[variant.rule];
+        tree.insert(address.clone(), rule.clone());
+
+        let mut arguments = Vec::new();
+        let mut erased = Vec::new();
+        for child in derivation.children(node) {
+            match *derivation.get(&child).unwrap() {
+                CNFLabel::Empty(_) => erased.push(child),
+                CNFLabel::Rule(_) => self.arguments(derivation, child, &mut arguments),
+            }
+        }
+
+        let labels = rule.composition.composition.iter().filter(|letter| match **letter {
+            LetterT::Label(_) => true,
+            LetterT::Value(_) => false,
+        });
+        let (mut arguments, mut erased) = (arguments.into_iter(), erased.into_iter());
+        for (c, _) in labels.enumerate() {
+            let child = if variant.erased.contains(&c) {
+                erased.next()
+            } else {
+                arguments.next()
+            };
+            self.write(derivation, &child.unwrap(), tree, child_address(&address, c));
+        }
+    }
+
+    /// Collects the nodes at or below `node` that stand for nonterminals of
+    /// an original rule, skipping the nodes that were introduced by
+    /// binarisation.
+    fn arguments(&self, derivation: &GornTree<CNFLabel>, node: Vec<usize>, arguments: &mut Vec<Vec<usize>>) {
+        if let CNFLabel::Rule(r) = *derivation.get(&node).unwrap() {
+            match self.rules[r].origin {
+                Origin::Rule(_) => arguments.push(node),
+                Origin::Intermediate => {
+                    for child in derivation.children(&node) {
+                        self.arguments(derivation, child, arguments);
+                    }
+                }
+                Origin::Preterminal => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use log_domain::LogDomain;
+
+    fn rule(s: &str) -> CFGRule<String, String, LogDomain<f64>> {
+        CFGRule::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_epsilon_and_units() {
+        let grammar: CFG<String, String, LogDomain<f64>> = CFG::from_str(
+            "initial: [S]\n\n\
+             S → [Nt A, T a, Nt A] # 1\n\
+             A → [Nt B] # 0.5\n\
+             A → [] # 0.2\n\
+             B → [] # 0.8\n\
+             B → [T b] # 0.2"
+        ).unwrap();
+        let cnf = CNF::from(grammar);
+
+        // A derives ε best by A → B → ε
+        let (tree, weight) = cnf.empty_derivation(&String::from("A")).unwrap();
+        assert!((weight.ln() - 0.4f64.ln()).abs() < 1e-9);
+        assert_eq!(
+            vec![(vec![], rule("A → [Nt B] # 0.5")), (vec![0], rule("B → [] # 0.8"))],
+            tree.into_iter().collect::<Vec<_>>()
+        );
+        assert!(cnf.empty_derivation(&String::from("S")).is_none());
+
+        // S → a is the rule S → A a A with both A erased
+        let lexical: Vec<_> = cnf.rules
+            .iter()
+            .enumerate()
+            .filter(|&(_, r)| r.head == CNFNonterminal::Original(String::from("S")))
+            .filter(|&(_, r)| r.body == CNFBody::Lexical(String::from("a")))
+            .collect();
+        assert_eq!(1, lexical.len());
+        assert_eq!(LogDomain::one(), lexical[0].1.weight);
+        assert_eq!(vec![String::from("A"), String::from("A")], lexical[0].1.erased);
+
+        // both A derive ε by A → B → ε
+        let mut derivation = GornTree::new();
+        derivation.insert(vec![], CNFLabel::Rule(lexical[0].0));
+        for c in 0..2 {
+            derivation.insert(vec![c], CNFLabel::Empty(1));
+            derivation.insert(vec![c, 0], CNFLabel::Empty(3));
+        }
+        let original = cnf.original_derivation(&derivation);
+        assert_eq!(Ok(()), original.validate());
+        assert_eq!(
+            vec![
+                rule("S → [Nt A, T a, Nt A] # 1"),
+                rule("A → [Nt B] # 0.5"),
+                rule("B → [] # 0.8"),
+                rule("A → [Nt B] # 0.5"),
+                rule("B → [] # 0.8"),
+            ],
+            original.into_iter().map(|(_, r)| r).collect::<Vec<_>>()
+        );
+
+        // A → b by the unit rule A → B, which is kept
+        assert!(cnf.rules.iter().any(|r| {
+            r.head == CNFNonterminal::Original(String::from("A"))
+                && r.body == CNFBody::Unit(CNFNonterminal::Original(String::from("B")))
+                && r.origin == Origin::Rule(Variant { rule: 1, erased: Vec::new() })
+        }));
+        assert!(!cnf.rules.iter().any(|r| {
+            r.head == CNFNonterminal::Original(String::from("A")) && r.body == CNFBody::Lexical(String::from("b"))
+        }));
+    }
+
+    #[test]
+    fn test_binarisation() {
+        let grammar: CFG<String, String, LogDomain<f64>> = CFG::from_str(
+            "initial: [S]\n\n\
+             S → [Nt A, Nt A, T a, Nt A] # 0.5\n\
+             S → [Nt A, T a, Nt A] # 0.5\n\
+             A → [T a] # 1"
+        ).unwrap();
+        let cnf = CNF::from(grammar);
+
+        // the suffix [a, A] is shared
+        let fresh: HashSet<_> = cnf.rules
+            .iter()
+            .filter_map(|r| match r.head {
+                CNFNonterminal::Fresh(i) => Some(i),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(2, fresh.len());
+        assert_eq!(6, cnf.rules.len());
+        assert!(cnf.rules.iter().all(|r| match r.body {
+            CNFBody::Binary(..) => true,
+            CNFBody::Lexical(ref t) => t == "a",
+            CNFBody::Unit(_) => false,
+        }));
+    }
+
+    #[test]
+    fn test_nullable_expansion() {
+        let grammar: CFG<String, String, LogDomain<f64>> = CFG::from_str(
+            "initial: [S]\n\n\
+             S → [Nt A, Nt A, Nt A] # 1\n\
+             A → [T a] # 0.5\n\
+             A → [] # 0.5"
+        ).unwrap();
+        let cnf = CNF::from(grammar);
+
+        // S → A A A, three times S → A A and three times the unit rule
+        // S → A, besides A → a and the rule for the suffix A A
+        assert_eq!(9, cnf.rules.len());
+        let binary: Vec<_> = cnf.rules
+            .iter()
+            .filter(|r| r.head == CNFNonterminal::Original(String::from("S")))
+            .filter_map(|r| match r.origin {
+                Origin::Rule(ref variant) if variant.erased.len() == 1 => Some(variant.erased[0]),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![0, 1, 2], binary);
+        let units: Vec<_> = cnf.rules
+            .iter()
+            .filter_map(|r| match r.body {
+                CNFBody::Unit(_) => Some(r.erased.len()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![2, 2, 2], units);
+    }
+}
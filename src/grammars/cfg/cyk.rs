@@ -0,0 +1,274 @@
+use num_traits::One;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+use std::ops::Mul;
+
+use grammars::cfg::{CFGRule, LetterT};
+use grammars::cfg::cnf::{CNF, CNFBody, CNFLabel, CNFNonterminal};
+use recognisable::chart::{Chart, Edge};
+use util::budget::BudgetTracker;
+use util::tree::{child_address, GornTree};
+
+/// The items of the chart.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Node<N, T> {
+    /// the nonterminal derives the word between the two positions
+    Span(CNFNonterminal<N, T>, usize, usize),
+    /// the nonterminal of the original grammar derives the empty word
+    Empty(N),
+    /// an initial nonterminal derives the whole word
+    Goal,
+}
+
+impl<N, T, W> CNF<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Copy + Mul<Output = W> + One + Ord,
+{
+    /// Parses `word` with the Cocke-Younger-Kasami algorithm and enumerates
+    /// the derivations of the original grammar best-first, together with
+    /// their weights.
+    /// Each cell of the chart is closed under the unit rules, and the
+    /// derivations of the empty word are shared by all cells; as the chart
+    /// may have cycles, there may be infinitely many derivations.
+    ///
+    /// ```
+    /// extern crate log_domain;
+    /// extern crate rustomata;
+    ///
+    /// use std::str::FromStr;
+    /// use log_domain::LogDomain;
+    /// use rustomata::grammars::cfg::CFG;
+    /// use rustomata::grammars::cfg::cnf::CNF;
+    ///
+    /// # fn main() {
+    /// let grammar: CFG<String, String, LogDomain<f64>> = CFG::from_str(
+    ///     "initial: [S]\n\n\
+    ///      S → [Nt S, Nt S] # 0.5\n\
+    ///      S → [T a] # 0.5"
+    /// ).unwrap();
+    /// let cnf = CNF::from(grammar);
+    ///
+    /// let word: Vec<String> = vec!["a"; 4].into_iter().map(String::from).collect();
+    /// // there are 5 binary trees with 4 leaves
+    /// let parses: Vec<_> = cnf.parse(&word).collect();
+    /// assert_eq!(5, parses.len());
+    /// assert_eq!(7, parses[0].0.len());
+    /// # }
+    /// ```
+    pub fn parse<'a>(&'a self, word: &[T]) -> Box<Iterator<Item = (GornTree<CFGRule<N, T, W>>, W)> + 'a> {
+        let chart = self.chart(word);
+        Box::new(CYKParses { cnf: self, chart, k: 0 })
+    }
+
+    /// Adds the nodes for the nonterminals that derive the empty word, in
+    /// the order of Knuth's algorithm.
+    fn empty(&self, chart: &mut Chart<Node<N, T>, W>) {
+        let original = self.original_rules();
+        // the number of nonterminals of each rule that are not finished yet,
+        // if the right-hand side consists of nonterminals only
+        let mut waiting: Vec<Option<usize>> = Vec::new();
+        let mut occurrences: HashMap<&N, Vec<usize>> = HashMap::new();
+        let mut agenda = BinaryHeap::new();
+        for (r, rule) in original.iter().enumerate() {
+            let nonterminals: Option<Vec<&N>> = rule.composition
+                .composition
+                .iter()
+                .map(|letter| match *letter {
+                    LetterT::Label(ref n) => Some(n),
+                    LetterT::Value(_) => None,
+                })
+                .collect();
+            waiting.push(nonterminals.as_ref().map(|ns| ns.len()));
+            for n in nonterminals.into_iter().flat_map(|ns| ns) {
+                occurrences.entry(n).or_insert_with(Vec::new).push(r);
+            }
+            if waiting[r] == Some(0) {
+                let edge = Edge { tails: Vec::new(), label: Some(r), weight: rule.weight };
+                agenda.extend(chart.propose(Node::Empty(rule.head.clone()), edge));
+            }
+        }
+
+        while let Some((_, node)) = agenda.pop() {
+            if chart.nodes[node].finished {
+                continue;
+            }
+            chart.nodes[node].finished = true;
+            let nonterminal = match chart.nodes[node].node {
+                Node::Empty(ref n) => n.clone(),
+                _ => unreachable!(),
+            };
+            for &r in occurrences.get(&nonterminal).into_iter().flat_map(|rs| rs) {
+                let remaining = waiting[r].unwrap() - 1;
+                waiting[r] = Some(remaining);
+                if remaining == 0 {
+                    let tails = original[r]
+                        .composition
+                        .composition
+                        .iter()
+                        .map(|letter| match *letter {
+                            LetterT::Label(ref n) => chart.index[&Node::Empty(n.clone())],
+                            LetterT::Value(_) => unreachable!(),
+                        })
+                        .collect();
+                    let edge = Edge { tails, label: Some(r), weight: original[r].weight };
+                    agenda.extend(chart.propose(Node::Empty(original[r].head.clone()), edge));
+                }
+            }
+        }
+    }
+
+    /// The tails of an edge for the `r`-th rule with the given nodes for
+    /// the nonterminals of its body, followed by the nodes for the
+    /// derivations of its erased nonterminals.
+    fn tails(&self, chart: &Chart<Node<N, T>, W>, r: usize, mut tails: Vec<usize>) -> Vec<usize> {
+        tails.extend(self.rules[r].erased.iter().map(|n| chart.index[&Node::Empty(n.clone())]));
+        tails
+    }
+
+    /// Fills the chart for `word` span by span, shortest spans first.
+    fn chart(&self, word: &[T]) -> Chart<Node<N, T>, W> {
+        let mut lexical: HashMap<&T, Vec<usize>> = HashMap::new();
+        let mut binary: HashMap<&CNFNonterminal<N, T>, Vec<usize>> = HashMap::new();
+        let mut unary: HashMap<&CNFNonterminal<N, T>, Vec<usize>> = HashMap::new();
+        for (r, rule) in self.rules.iter().enumerate() {
+            match rule.body {
+                CNFBody::Lexical(ref t) => lexical.entry(t).or_insert_with(Vec::new).push(r),
+                CNFBody::Binary(ref left, _) => binary.entry(left).or_insert_with(Vec::new).push(r),
+                CNFBody::Unit(ref child) => unary.entry(child).or_insert_with(Vec::new).push(r),
+            }
+        }
+
+        let n = word.len();
+        let mut chart = Chart::new(BudgetTracker::unlimited());
+        self.empty(&mut chart);
+        // the nodes of the chart by their start and end position
+        let mut cells: Vec<Vec<Vec<usize>>> = vec![vec![Vec::new(); n + 1]; n + 1];
+
+        for length in 1..n + 1 {
+            for i in 0..n + 1 - length {
+                let j = i + length;
+                let mut new_edges = Vec::new();
+                if length == 1 {
+                    for &r in lexical.get(&word[i]).into_iter().flat_map(|rs| rs) {
+                        new_edges.push((r, self.tails(&chart, r, Vec::new())));
+                    }
+                }
+                for k in i + 1..j {
+                    for &left in &cells[i][k] {
+                        let symbol = match chart.nodes[left].node {
+                            Node::Span(ref symbol, _, _) => symbol.clone(),
+                            _ => unreachable!(),
+                        };
+                        for &r in binary.get(&symbol).into_iter().flat_map(|rs| rs) {
+                            if let CNFBody::Binary(_, ref right_symbol) = self.rules[r].body {
+                                let right = Node::Span(right_symbol.clone(), k, j);
+                                if let Some(&right) = chart.index.get(&right) {
+                                    new_edges.push((r, self.tails(&chart, r, vec![left, right])));
+                                }
+                            }
+                        }
+                    }
+                }
+
+                // close the cell under the unit rules in the order of Knuth's
+                // algorithm; unit rules whose head is finished already only
+                // add further derivations
+                let mut agenda = BinaryHeap::new();
+                for (r, tails) in new_edges {
+                    let node = Node::Span(self.rules[r].head.clone(), i, j);
+                    let edge = Edge { tails, label: Some(r), weight: self.rules[r].weight };
+                    agenda.extend(chart.propose(node, edge));
+                }
+                let mut cell = Vec::new();
+                while let Some((_, node)) = agenda.pop() {
+                    if chart.nodes[node].finished {
+                        continue;
+                    }
+                    chart.nodes[node].finished = true;
+                    cell.push(node);
+                    let symbol = match chart.nodes[node].node {
+                        Node::Span(ref symbol, _, _) => symbol.clone(),
+                        _ => unreachable!(),
+                    };
+                    for &r in unary.get(&symbol).into_iter().flat_map(|rs| rs) {
+                        let head = Node::Span(self.rules[r].head.clone(), i, j);
+                        let tails = self.tails(&chart, r, vec![node]);
+                        let edge = Edge { tails, label: Some(r), weight: self.rules[r].weight };
+                        agenda.extend(chart.propose(head, edge));
+                    }
+                }
+                cells[i][j] = cell;
+            }
+        }
+
+        for initial in &self.initial {
+            let root = if n == 0 {
+                Node::Empty(initial.clone())
+            } else {
+                Node::Span(CNFNonterminal::Original(initial.clone()), 0, n)
+            };
+            if let Some(&root) = chart.index.get(&root) {
+                chart.propose(Node::Goal, Edge { tails: vec![root], label: None, weight: W::one() });
+            }
+        }
+        if let Some(&goal) = chart.index.get(&Node::Goal) {
+            chart.nodes[goal].finished = true;
+        }
+
+        chart
+    }
+}
+
+/// Enumerates the derivations of a `CNF` best-first.
+struct CYKParses<'a, N, T, W>
+where
+    N: 'a + Clone + Eq + Hash,
+    T: 'a + Clone + Eq + Hash,
+    W: 'a + Ord,
+{
+    cnf: &'a CNF<N, T, W>,
+    chart: Chart<Node<N, T>, W>,
+    k: usize,
+}
+
+impl<'a, N, T, W> CYKParses<'a, N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Copy + Mul<Output = W> + One + Ord,
+{
+    /// Writes the `k`-th best derivation of a node to `address`.
+    fn read(&mut self, node: usize, k: usize, derivation: &mut GornTree<CNFLabel>, address: Vec<usize>) {
+        let (edge, ranks) = self.chart.derivation(node, k);
+        let label = match self.chart.nodes[node].node {
+            Node::Empty(_) => CNFLabel::Empty(edge.label.unwrap()),
+            _ => CNFLabel::Rule(edge.label.unwrap()),
+        };
+        derivation.insert(address.clone(), label);
+        for (c, (tail, rank)) in edge.tails.into_iter().zip(ranks).enumerate() {
+            self.read(tail, rank, derivation, child_address(&address, c));
+        }
+    }
+}
+
+impl<'a, N, T, W> Iterator for CYKParses<'a, N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Copy + Mul<Output = W> + One + Ord,
+{
+    type Item = (GornTree<CFGRule<N, T, W>>, W);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let goal = *self.chart.index.get(&Node::Goal)?;
+        let weight = self.chart.kth(goal, self.k)?;
+        let (edge, ranks) = self.chart.derivation(goal, self.k);
+        self.k += 1;
+
+        let mut derivation = GornTree::new();
+        self.read(edge.tails[0], ranks[0], &mut derivation, Vec::new());
+        Some((self.cnf.original_derivation(&derivation), weight))
+    }
+}
@@ -0,0 +1,282 @@
+use num_traits::One;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::hash::Hash;
+use std::ops::{AddAssign, MulAssign};
+
+use num_traits::Zero;
+use automata::push_down_automaton::{elementary_steps, PushDownAutomaton, PushDownInstruction, StepKind};
+use grammars::cfg::{CFG, CFGComposition, CFGRule, LetterT};
+use grammars::trim::{remove_unproductive, remove_unreachable};
+use recognisable::automaton::Automaton;
+
+/// Symbols of the pushdown of a `PushDownAutomaton` whose transitions are
+/// split into elementary steps.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum PDASymbol<A> {
+    /// below the initial pushdown
+    Bottom,
+    Original(A),
+    /// occurs only between the steps of a single transition
+    Intermediate(usize),
+}
+
+/// Nonterminals of a `CFG` created from a `PushDownAutomaton`.
+/// A *level* of the pushdown begins when a symbol is pushed and ends when
+/// this symbol (or the symbols that replaced it) is removed.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum PDANonterminal<A> {
+    Initial,
+    /// a level that began with the first symbol has the second one on top
+    Level(PDASymbol<A>, PDASymbol<A>),
+    /// a level that began with the symbol has been removed
+    Pop(PDASymbol<A>),
+    /// a level that began with the first symbol has been removed together
+    /// with the second symbol below it, which has been replaced by the third
+    /// one
+    PopSwap(PDASymbol<A>, PDASymbol<A>, PDASymbol<A>),
+}
+
+impl<A: fmt::Display> fmt::Display for PDASymbol<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PDASymbol::Bottom => write!(f, "⊥"),
+            PDASymbol::Original(ref a) => write!(f, "{}", a),
+            PDASymbol::Intermediate(i) => write!(f, "#{}", i),
+        }
+    }
+}
+
+impl<A: fmt::Display> fmt::Display for PDANonterminal<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PDANonterminal::Initial => write!(f, "initial"),
+            PDANonterminal::Level(ref b, ref c) => write!(f, "[{}, {}]", b, c),
+            PDANonterminal::Pop(ref b) => write!(f, "[{}]", b),
+            PDANonterminal::PopSwap(ref b, ref d, ref e) => write!(f, "[{}, {}/{}]", b, d, e),
+        }
+    }
+}
+
+/// Splits a transition that replaces `current_val` by `new_val` into
+/// elementary steps, only the first of which reads `word` and carries
+/// `weight`.
+fn steps<A, T, W>(
+    current_val: &[PDASymbol<A>],
+    new_val: &[PDASymbol<A>],
+    word: Vec<T>,
+    weight: W,
+    fresh: &mut usize,
+) -> Vec<(PDASymbol<A>, StepKind<PDASymbol<A>>, Vec<T>, W)>
+where
+    A: Clone,
+    W: One,
+{
+    let next_fresh = || {
+        *fresh += 1;
+        PDASymbol::Intermediate(*fresh - 1)
+    };
+    let mut word = Some(word);
+    let mut weight = Some(weight);
+    elementary_steps(current_val, new_val, next_fresh)
+        .into_iter()
+        .map(|(top, kind)| {
+            (top, kind, word.take().unwrap_or_default(), weight.take().unwrap_or_else(W::one))
+        })
+        .collect()
+}
+
+/// The left-hand side of a rule and the nonterminals of its right-hand side.
+fn nonterminals<N, T, W>(rule: &CFGRule<N, T, W>) -> (&N, Vec<&N>) {
+    let tail = rule.composition
+        .composition
+        .iter()
+        .filter_map(|symbol| match *symbol {
+            LetterT::Label(ref n) => Some(n),
+            LetterT::Value(_) => None,
+        })
+        .collect();
+    (&rule.head, tail)
+}
+
+/// Constructs a `CFG` whose derivations correspond to the runs of a
+/// `PushDownAutomaton`, with the same weights.
+/// The transitions are split into elementary steps that push or pop at most
+/// one symbol; the nonterminals then describe how a level of the pushdown
+/// evolves (the triple construction for automata with a single state).
+///
+/// ```
+/// extern crate log_domain;
+/// extern crate rustomata;
+///
+/// use std::str::FromStr;
+/// use log_domain::LogDomain;
+/// use rustomata::automata::push_down_automaton::PushDownAutomaton;
+/// use rustomata::grammars::cfg::CFG;
+/// use rustomata::grammars::cfg::cnf::CNF;
+///
+/// # fn main() {
+/// let automaton: PushDownAutomaton<String, String, LogDomain<f64>> = PushDownAutomaton::from_str(
+///     "initial: stack: [@ I], empty:@\n\n\
+///      Transition [\"a\"] (Replace \"(a)\" // ) # 1\n\
+///      Transition [\"b\"] (Replace \"(b)\" // ) # 1\n\
+///      Transition [] (Replace \"(S)\" // ) # 0.6\n\
+///      Transition [] (Replace \"(S)\" // \"(b)\", \"(S)\", \"(a)\") # 0.4\n\
+///      Transition [] (Replace \"I\" // \"(S)\") # 1"
+/// ).unwrap();
+/// let grammar = CFG::from(automaton);
+///
+/// let word: Vec<String> = vec!["a", "a", "b", "b"].into_iter().map(String::from).collect();
+/// let parses: Vec<_> = CNF::from(grammar).parse(&word).collect();
+/// assert_eq!(1, parses.len());
+/// assert!((parses[0].1.value() - 0.4 * 0.4 * 0.6).abs() < 1e-9);
+/// # }
+/// ```
+impl<A, T, W> From<PushDownAutomaton<A, T, W>> for CFG<PDANonterminal<A>, T, W>
+where
+    A: Clone + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: AddAssign + Clone + MulAssign + One + Ord + Zero,
+{
+    fn from(automaton: PushDownAutomaton<A, T, W>) -> Self {
+        let original = |a: &A| PDASymbol::Original(a.clone());
+        let mut fresh = 0;
+        let mut all_steps = Vec::new();
+        for t in automaton.list_transitions() {
+            let PushDownInstruction::Replace { current_val, new_val } = t.instruction;
+            let current_val: Vec<_> = current_val.iter().map(&original).collect();
+            let new_val: Vec<_> = new_val.iter().map(&original).collect();
+            all_steps.extend(steps(
+                &current_val,
+                &new_val,
+                t.word,
+                t.weight,
+                &mut fresh,
+            ));
+        }
+        // the initial pushdown is built on top of `Bottom`
+        let initial: Vec<_> = automaton.initial().iter().map(&original).collect();
+        all_steps.extend(steps(&[PDASymbol::Bottom], &initial, Vec::new(), W::one(), &mut fresh));
+
+        let mut steps_by_top = BTreeMap::new();
+        let mut pop_swaps = BTreeMap::new();
+        let mut starts = BTreeSet::new();
+        starts.insert(PDASymbol::Bottom);
+        for &(ref top, ref kind, _, _) in &all_steps {
+            steps_by_top.entry(top.clone()).or_insert_with(Vec::new).push(kind.clone());
+            match *kind {
+                StepKind::Push(_, ref pushed) => {
+                    starts.insert(pushed.clone());
+                }
+                StepKind::PopSwap(ref below, ref replaced) => {
+                    pop_swaps.entry(below.clone()).or_insert_with(BTreeSet::new).insert(replaced.clone());
+                }
+                _ => (),
+            }
+        }
+
+        // the pairs of the symbols a level begins with and the symbols it
+        // may have on top
+        let mut levels: BTreeSet<_> = starts.iter().map(|b| (b.clone(), b.clone())).collect();
+        let mut agenda: Vec<_> = levels.iter().cloned().collect();
+        while let Some((b, c)) = agenda.pop() {
+            for kind in steps_by_top.get(&c).into_iter().flat_map(|kinds| kinds) {
+                let tops = match *kind {
+                    StepKind::Swap(ref c1) => vec![c1.clone()],
+                    StepKind::Push(ref c1, _) => {
+                        let mut tops = vec![c1.clone()];
+                        tops.extend(pop_swaps.get(c1).into_iter().flat_map(|es| es).cloned());
+                        tops
+                    }
+                    _ => Vec::new(),
+                };
+                for c1 in tops {
+                    if levels.insert((b.clone(), c1.clone())) {
+                        agenda.push((b.clone(), c1));
+                    }
+                }
+            }
+        }
+
+        let level = |b: &PDASymbol<A>, c: &PDASymbol<A>| LetterT::Label(PDANonterminal::Level(b.clone(), c.clone()));
+        let rule = |head, mut composition: Vec<LetterT<_, T>>, word: &[T], tail: Option<LetterT<_, T>>, weight| {
+            composition.extend(word.iter().cloned().map(LetterT::Value));
+            composition.extend(tail);
+            CFGRule { head, composition: CFGComposition::from(composition), weight }
+        };
+
+        let mut rules = Vec::new();
+        for b in &starts {
+            rules.push(rule(PDANonterminal::Level(b.clone(), b.clone()), Vec::new(), &[], None, W::one()));
+        }
+        for &(ref top, ref kind, ref word, ref weight) in &all_steps {
+            for &(ref b, _) in levels.iter().filter(|&&(_, ref c)| c == top) {
+                let before = vec![level(b, top)];
+                match *kind {
+                    StepKind::Swap(ref c1) => {
+                        let head = PDANonterminal::Level(b.clone(), c1.clone());
+                        rules.push(rule(head, before, word, None, weight.clone()));
+                    }
+                    StepKind::Push(ref c1, ref pushed) => {
+                        let head = PDANonterminal::Level(b.clone(), c1.clone());
+                        let tail = LetterT::Label(PDANonterminal::Pop(pushed.clone()));
+                        rules.push(rule(head, before.clone(), word, Some(tail), weight.clone()));
+                        for e in pop_swaps.get(c1).into_iter().flat_map(|es| es) {
+                            let head = PDANonterminal::Level(b.clone(), e.clone());
+                            let tail = PDANonterminal::PopSwap(pushed.clone(), c1.clone(), e.clone());
+                            rules.push(rule(head, before.clone(), word, Some(LetterT::Label(tail)), weight.clone()));
+                        }
+                    }
+                    StepKind::Pop => {
+                        let head = PDANonterminal::Pop(b.clone());
+                        rules.push(rule(head, before, word, None, weight.clone()));
+                    }
+                    StepKind::PopSwap(ref below, ref replaced) => {
+                        let head = PDANonterminal::PopSwap(b.clone(), below.clone(), replaced.clone());
+                        rules.push(rule(head, before, word, None, weight.clone()));
+                    }
+                }
+            }
+        }
+        // the automaton accepts if only the bottom-most level is left
+        for &(ref b, ref c) in &levels {
+            if *b == PDASymbol::Bottom {
+                if let PDASymbol::Original(_) = *c {
+                    rules.push(rule(PDANonterminal::Initial, vec![level(b, c)], &[], None, W::one()));
+                }
+            }
+        }
+
+        let initial = vec![PDANonterminal::Initial];
+        let rules = remove_unproductive(rules, nonterminals);
+        CFG { rules: remove_unreachable(rules, &initial, nonterminals), initial }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_multiple_pops() {
+        // pushes an `x` for every `a` and pops two of them for every `b`
+        let automaton: PushDownAutomaton<String, String, LogDomain<f64>> = PushDownAutomaton::from_str(
+            "initial: stack: [@], empty:@\n\n\
+             Transition [\"a\"] (Replace \"@\" // \"@\", \"x\") # 0.5\n\
+             Transition [\"a\"] (Replace \"x\" // \"x\", \"x\") # 0.5\n\
+             Transition [\"b\"] (Replace \"x\", \"x\" // ) # 1",
+        ).unwrap();
+        let grammar = CFG::from(automaton);
+        let cnf = ::grammars::cfg::cnf::CNF::from(grammar);
+
+        let word = |w: &str| w.split_whitespace().map(|x| x.to_string()).collect::<Vec<_>>();
+        assert_eq!(1, cnf.parse(&word("a a b")).count());
+        assert_eq!(1, cnf.parse(&word("a a a b a b")).count());
+        assert_eq!(0, cnf.parse(&word("a b")).count());
+        assert_eq!(0, cnf.parse(&word("a a a b")).count());
+        let weight = cnf.parse(&word("a a a a b b")).next().unwrap().1;
+        assert!((weight.value() - 0.5f64.powi(4)).abs() < 1e-9);
+    }
+}
@@ -2,8 +2,14 @@ use std::fmt;
 use std::hash::{Hash, Hasher};
 
 mod from_str;
+mod from_pda;
 mod from_pmcfg;
 
+pub mod cnf;
+mod cyk;
+
+pub use self::from_pda::{PDANonterminal, PDASymbol};
+
 /// Variable or terminal symbol in a CFG.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
 pub enum LetterT<N, T> {
@@ -128,3 +134,26 @@ impl<N: fmt::Display, T: fmt::Display, W: fmt::Display> fmt::Display for CFGRule
         )
     }
 }
+
+impl<N: fmt::Display, T: fmt::Display, W: fmt::Display> fmt::Display for CFG<N, T, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut buffer = "".to_string();
+
+        let mut iter = self.initial.iter().peekable();
+
+        buffer.push_str("initial: [");
+        while let Some(nt) = iter.next() {
+            buffer.push_str(format!("\"{}\"", nt).as_str());
+            if iter.peek().is_some() {
+                buffer.push_str(", ");
+            }
+        }
+        buffer.push_str("]\n\n");
+
+        for r in &self.rules {
+            buffer.push_str(format!("{}\n", r).as_str());
+        }
+
+        write!(f, "{}", buffer)
+    }
+}
@@ -1,6 +1,7 @@
-use super::{StateT, RangeT};
-use std::mem::{zeroed};
+use super::StateT;
+use std::mem::{size_of, zeroed};
 use std::ops::{AddAssign};
+use std::slice::Iter;
 use num_traits::Zero;
 
 /// A chart for cfg parsing.
@@ -21,13 +22,11 @@ pub fn chart_size_with_states(n: usize, states: usize) -> usize {
     n * (n+1) * states / 2
 }
 
-pub fn index(i: RangeT, j: RangeT, n: usize) -> usize {
-    let (i, j) = (i as usize, j as usize);
+pub fn index(i: usize, j: usize, n: usize) -> usize {
     (n * (n+1) - (n - (j-i) + 1) * (n - (j-i) + 2)) / 2 + i
 }
-pub fn index_with_state(i: RangeT, j: RangeT, q: StateT, n: usize, states: usize) -> usize {
-    let (i, j, q) = (i as usize, j as usize, q as usize);
-    ((n * (n+1) - (n - (j-i) + 1) * (n - (j-i) + 2)) / 2 + i) * states + q
+pub fn index_with_state(i: usize, j: usize, q: StateT, n: usize, states: usize) -> usize {
+    index(i, j, n) * states + q as usize
 }
 
 impl<W: Copy> DenseChart<W> {
@@ -49,7 +48,7 @@ impl<W: Copy> DenseChart<W> {
     }
 
     /// Adds a constituent with viterbi weight to a span.
-    pub fn add_entry(&mut self, i: RangeT, j: RangeT, state: StateT, weight: W) {
+    pub fn add_entry(&mut self, i: usize, j: usize, state: StateT, weight: W) {
         let tri_index = index(i, j, self.3);
         let nts = &mut self.0[tri_index];
         if *nts < self.5 {
@@ -60,7 +59,7 @@ impl<W: Copy> DenseChart<W> {
     }
 
     /// Gets weight for specific constituent and span.
-    pub fn get_weight(&self, i: RangeT, j: RangeT, q: StateT) -> Option<W>
+    pub fn get_weight(&self, i: usize, j: usize, q: StateT) -> Option<W>
     where
         W: PartialEq + Zero
     {
@@ -69,7 +68,7 @@ impl<W: Copy> DenseChart<W> {
         else { Some(w) }
     }
 
-    pub fn get_best(&self, i: RangeT, j: RangeT) -> Option<(StateT, W)> where W: Zero + PartialEq {
+    pub fn get_best(&self, i: usize, j: usize) -> Option<(StateT, W)> where W: Zero + PartialEq {
         let index = index(i, j, self.3) * self.5 as usize;
         let (state, w) = self.1[index];
         if w == W::zero() { None }
@@ -85,10 +84,136 @@ impl<W> DenseChart<W> {
     }
 
     /// Iterates all constituents for a span.
-    pub fn iterate_nont<'a>(&'a self, i: RangeT, j: RangeT) -> impl 'a + Iterator<Item=&'a (StateT, W)> {
+    pub fn iterate_nont<'a>(&'a self, i: usize, j: usize) -> Iter<'a, (StateT, W)> {
         let tri_index = index(i, j, self.3);
         let first_index = tri_index * self.5 as usize;
         let last_index = first_index + self.0[tri_index] as usize;
         self.1[first_index..last_index].iter()
     }
-}
\ No newline at end of file
+}
+
+/// A chart for cfg parsing that only stores the constituents that were
+/// inserted. It is used if a `DenseChart` would be too large.
+#[derive(Debug, Clone)]
+pub struct SparseChart<W>(
+    Vec<Vec<(StateT, W)>>,  // nonterminals with viterbi weight per span (max <beam> entries)
+    Vec<Vec<(StateT, W)>>,  // nonterminals with viterbi weight per span, sorted by nonterminal
+    usize,                  // n
+    usize,                  // states
+    usize                   // max no. of constituents per span
+);
+
+impl<W: Copy> SparseChart<W> {
+    /// Allocates an empty list of constituents for each span.
+    pub fn new(n: usize, states: usize, bt_per_cell: usize) -> Self {
+        SparseChart(
+            vec![Vec::new(); chart_size(n)],
+            vec![Vec::new(); chart_size(n)],
+            n,
+            states,
+            bt_per_cell
+        )
+    }
+
+    /// Adds a constituent with viterbi weight to a span.
+    pub fn add_entry(&mut self, i: usize, j: usize, state: StateT, weight: W) {
+        let tri_index = index(i, j, self.2);
+        if self.0[tri_index].len() < self.4 {
+            self.0[tri_index].push((state, weight));
+            let sorted = &mut self.1[tri_index];
+            match sorted.binary_search_by_key(&state, |&(q, _)| q) {
+                Ok(pos) => sorted[pos] = (state, weight),
+                Err(pos) => sorted.insert(pos, (state, weight))
+            }
+        }
+    }
+
+    /// Gets weight for specific constituent and span.
+    pub fn get_weight(&self, i: usize, j: usize, q: StateT) -> Option<W>
+    where
+        W: PartialEq + Zero
+    {
+        let sorted = &self.1[index(i, j, self.2)];
+        let w = sorted.binary_search_by_key(&q, |&(q, _)| q).ok().map(|pos| sorted[pos].1)?;
+        if w == W::zero() { None }
+        else { Some(w) }
+    }
+}
+
+impl<W> SparseChart<W> {
+    /// Gives information about the size of the chart. Returns n, the state
+    /// count and the beam width.
+    pub fn get_meta(&self) -> (usize, usize, usize) {
+        (self.2, self.3, self.4)
+    }
+
+    /// Iterates all constituents for a span.
+    pub fn iterate_nont<'a>(&'a self, i: usize, j: usize) -> Iter<'a, (StateT, W)> {
+        self.0[index(i, j, self.2)].iter()
+    }
+}
+
+/// The (estimated) number of bytes up to which a `DenseChart` is used.
+pub const DENSE_CHART_LIMIT: usize = 1 << 27;
+
+/// A chart for cfg parsing, either dense or sparse.
+#[derive(Debug, Clone)]
+pub enum Chart<W> {
+    Dense(DenseChart<W>),
+    Sparse(SparseChart<W>)
+}
+
+impl<W: Copy> Chart<W> {
+    /// Allocates a `DenseChart` if its size does not exceed
+    /// `DENSE_CHART_LIMIT`, and a `SparseChart` otherwise.
+    pub fn new(n: usize, states: usize, bt_per_cell: usize) -> Self
+    where
+        W: Zero
+    {
+        let dense_size = chart_size(n) * (size_of::<u16>() + bt_per_cell * size_of::<(StateT, W)>())
+                       + chart_size_with_states(n, states) * size_of::<W>();
+        if dense_size <= DENSE_CHART_LIMIT {
+            Chart::Dense(DenseChart::new(n, states, bt_per_cell))
+        } else {
+            Chart::Sparse(SparseChart::new(n, states, bt_per_cell))
+        }
+    }
+
+    /// Adds a constituent with viterbi weight to a span.
+    pub fn add_entry(&mut self, i: usize, j: usize, state: StateT, weight: W) {
+        match *self {
+            Chart::Dense(ref mut chart) => chart.add_entry(i, j, state, weight),
+            Chart::Sparse(ref mut chart) => chart.add_entry(i, j, state, weight)
+        }
+    }
+
+    /// Gets weight for specific constituent and span.
+    pub fn get_weight(&self, i: usize, j: usize, q: StateT) -> Option<W>
+    where
+        W: PartialEq + Zero
+    {
+        match *self {
+            Chart::Dense(ref chart) => chart.get_weight(i, j, q),
+            Chart::Sparse(ref chart) => chart.get_weight(i, j, q)
+        }
+    }
+}
+
+impl<W> Chart<W> {
+    /// Gives information about the size of the chart. Returns n, the state
+    /// count and the beam width.
+    pub fn get_meta(&self) -> (usize, usize, usize) {
+        match *self {
+            Chart::Dense(ref chart) => chart.get_meta(),
+            Chart::Sparse(ref chart) => chart.get_meta()
+        }
+    }
+
+    /// Iterates all constituents for a span.
+    pub fn iterate_nont<'a>(&'a self, i: usize, j: usize) -> Iter<'a, (StateT, W)> {
+        match *self {
+            Chart::Dense(ref chart) => chart.iterate_nont(i, j),
+            Chart::Sparse(ref chart) => chart.iterate_nont(i, j)
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Automaton, RangeT, StateT};
+use super::{Automaton, StateT};
 use std::{ collections::BinaryHeap, hash::Hash, ops::Mul };
 use num_traits::{Zero, One};
 use std::mem::replace;
@@ -18,23 +18,23 @@ struct SxInside<W>(Vec<W>, usize);
 pub struct SxOutside<W>(Vec<W>, usize, usize);
 
 impl<W: Zero + Copy + Ord + Mul<Output=W>> SxInside<W> {
-    fn index(&self, q: StateT, range: RangeT) -> usize { self.1 * (range - 1) as usize + q as usize}
+    fn index(&self, q: StateT, range: usize) -> usize { self.1 * (range - 1) + q as usize }
 
     fn with_capacity(states: usize, maxrange: usize) -> Self { Self(vec![W::zero(); states * maxrange], states) }
 
-    fn insert(&mut self, q: StateT, range: RangeT, weight: W) -> bool {
+    fn insert(&mut self, q: StateT, range: usize, weight: W) -> bool {
         let index = self.index(q, range);
         let w = &mut self.0[index];
         if *w == W::zero() { *w = weight; true } else { false }
     }
 
-    fn get(&self, q: StateT, range: RangeT) -> Option<W> {
+    fn get(&self, q: StateT, range: usize) -> Option<W> {
         let w = self.0[self.index(q, range)];
         if w == W::zero() { None } else { Some(w) }
     }
 
-    fn iterate_states<'a>(&'a self, range_size: RangeT) -> impl 'a + Iterator<Item=(StateT, W)> {
-        let start = (range_size as usize - 1) * self.1;
+    fn iterate_states<'a>(&'a self, range_size: usize) -> impl 'a + Iterator<Item=(StateT, W)> {
+        let start = (range_size - 1) * self.1;
         self.0[start..(start+self.1)].iter().enumerate().filter_map(
             |(q, w)| if *w != W::zero() { Some((q as StateT, *w)) } else { None }
         )
@@ -42,8 +42,8 @@ impl<W: Zero + Copy + Ord + Mul<Output=W>> SxInside<W> {
 
     /// Constructs a structure storing the Sx inside estimate for the given cfg.
     /// The estimates are computed up to a given span.
-    fn from_automaton<T: Hash + Eq>(automaton: &Automaton<T, W>, maxrange: RangeT) -> Self {
-        let mut insides = Self::with_capacity(automaton.0.len(), maxrange as usize);
+    fn from_automaton<T: Hash + Eq>(automaton: &Automaton<T, W>, maxrange: usize) -> Self {
+        let mut insides = Self::with_capacity(automaton.0.len(), maxrange);
         let mut queue: BinaryHeap<(W, StateT)> = BinaryHeap::new();
 
         for range_size in 1..=maxrange {
@@ -79,10 +79,7 @@ impl<W: Zero + Copy + Ord + Mul<Output=W>> SxInside<W> {
 }
 
 impl<W: Copy + Ord + Mul<Output=W> + Zero> SxOutside<W> {
-    fn index(&self, q: StateT, i: RangeT, j: RangeT) -> usize {
-        ((self.1 * (self.1+1) - (self.1 - (j-i) as usize + 1) * (self.1 - (j-i) as usize + 2)) / 2 + i as usize) * self.2 + q as usize
-    }
-    fn index_usize(&self, q: StateT, i: usize, j: usize) -> usize {
+    fn index(&self, q: StateT, i: usize, j: usize) -> usize {
         ((self.1 * (self.1+1) - (self.1 - (j-i) + 1) * (self.1 - (j-i) + 2)) / 2 + i) * self.2 + q as usize
     }
 
@@ -90,13 +87,13 @@ impl<W: Copy + Ord + Mul<Output=W> + Zero> SxOutside<W> {
         Self(vec![W::zero(); states * maxrange * (maxrange + 1) / 2], maxrange, states)
     }
 
-    fn insert(&mut self, q: StateT, i: RangeT, j: RangeT, weight: W) -> bool {
+    fn insert(&mut self, q: StateT, i: usize, j: usize, weight: W) -> bool {
         let index = self.index(q, i, j);
         let w = &mut self.0[index];
         if *w == W::zero() { *w = weight; true } else { false }
     }
 
-    fn iterate_states<'a>(&'a self, left_front: RangeT, right_front: RangeT) -> impl 'a + Iterator<Item=(StateT, W)> {
+    fn iterate_states<'a>(&'a self, left_front: usize, right_front: usize) -> impl 'a + Iterator<Item=(StateT, W)> {
         let start = self.index(0, left_front, right_front);
         let end = start + self.2;
         self.0[start..end].iter().enumerate().filter_map(
@@ -114,18 +111,18 @@ impl<W: Copy + Ord + Mul<Output=W> + Zero> SxOutside<W> {
         W: One
     {
         if i + n - j >= self.1 { return Some(W::one()); }
-        let w = self.0[self.index_usize(q, i, j + self.1 - n)];
+        let w = self.0[self.index(q, i, j + self.1 - n)];
         if w == W::zero() { None } else { Some(w) }
     }
 
     /// Constructs a structure storing the Sx outside estimate for the given cfg.
     /// The estimates are computed up to a given span.
-    pub fn from_automaton<T: Hash + Eq>(automaton: &Automaton<T, W>, maxrange: RangeT) -> Self
+    pub fn from_automaton<T: Hash + Eq>(automaton: &Automaton<T, W>, maxrange: usize) -> Self
     where
         W: One
     {
         let insides = SxInside::from_automaton(automaton, maxrange);
-        let mut outsides = Self::with_capacity(automaton.0.len(), maxrange as usize);
+        let mut outsides = Self::with_capacity(automaton.0.len(), maxrange);
         let mut queue: BinaryHeap<(W, StateT)> = BinaryHeap::new();
         
         for range_size in (1..=maxrange).rev() {
@@ -191,7 +188,7 @@ mod test {
         let w1 = LogDomain::new(0.3f64).unwrap();
         let w2 = LogDomain::new(0.7f64).unwrap();
 
-        let g = Automaton::from_grammar(gmr.rules.iter().enumerate().map(|(i, r)| (i as u32, r)), gmr.init);
+        let g = Automaton::from_grammar(gmr.rules.iter().enumerate().map(|(i, r)| (i as u32, r)), gmr.init).unwrap();
         let inside = SxInside::from_automaton(&g, 3);
         assert_eq!(inside.get(0, 1), Some(w2));
         assert_eq!(inside.get(0, 2), Some(w1 * w2 * w2));
@@ -1,30 +1,31 @@
-use super::{RuleIdT, StateT, RangeT};
-use std::{ cmp::Ordering, hash::{Hash, Hasher} };
+use super::{BracketContent, RuleIdT, StateT, SuccessorT, TdBrackets};
+use std::{ cmp::Ordering, collections::{BTreeMap, btree_map::Entry}, hash::{Hash, Hasher} };
 
 /// This data structure represents a backtrace in cfg parsing and contains
 /// indices for each successor used in k-best enumeration.
+/// `R` is the type of positions in the word, see `Position`.
 #[derive(Debug, Clone, Copy)]
-pub enum IndexedBacktrace<W> {
-    Binary(RuleIdT, StateT, RangeT, StateT, W, u32, u32),
+pub enum IndexedBacktrace<W, R> {
+    Binary(RuleIdT, StateT, R, StateT, W, u32, u32),
     Unary(RuleIdT, StateT, W, u32),
     Nullary(RuleIdT, W)
 }
 // pub struct IndexedBacktrace<W>(RuleIdT, StateT, RangeT, StateT, W, u32, u32);
 
-impl<W> PartialEq for IndexedBacktrace<W> {
+impl<W, R: Eq> PartialEq for IndexedBacktrace<W, R> {
     fn eq(&self, other: &Self) -> bool {
         use self::IndexedBacktrace::*;
         match (self, other) {
             (&Nullary(r1, _), &Nullary(r2, _)) => r1 == r2,
             (&Unary(r1, _, _, i1), &Unary(r2, _, _, i2)) => (r1, i1) == (r2, i2),
-            (&Binary(r1, _, m1, _, _, i11, i12), &Binary(r2, _, m2, _, _, i21, i22))
+            (&Binary(r1, _, ref m1, _, _, i11, i12), &Binary(r2, _, ref m2, _, _, i21, i22))
                 => (r1, m1, i11, i12) == (r2, m2, i21, i22),
             _ => false
         }
     }
 }
-impl<W> Eq for IndexedBacktrace<W> {}
-impl<W> Hash for IndexedBacktrace<W> {
+impl<W, R: Eq> Eq for IndexedBacktrace<W, R> {}
+impl<W, R> Hash for IndexedBacktrace<W, R> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         use self::IndexedBacktrace::*;
         match *self {
@@ -34,12 +35,12 @@ impl<W> Hash for IndexedBacktrace<W> {
         } 
     }
 }
-impl<W> PartialOrd for IndexedBacktrace<W> {
+impl<W, R: Ord> PartialOrd for IndexedBacktrace<W, R> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
-impl<W> Ord for IndexedBacktrace<W> {
+impl<W, R: Ord> Ord for IndexedBacktrace<W, R> {
     fn cmp(&self, other: &Self) -> Ordering {
         use self::IndexedBacktrace::*;
         match (self, other) {
@@ -48,20 +49,20 @@ impl<W> Ord for IndexedBacktrace<W> {
             (_, Nullary(_, _)) => Ordering::Less,
             (&Unary(r1, _, _, i1), &Unary(r2, _, _, i2)) => (i2, r1).cmp(&(i1, r2)),
             (&Unary(_, _, _, _), _) => Ordering::Greater,
-            (&Binary(r1, _, m1, _, _, i11, i12), &Binary(r2, _, m2, _, _, i21, i22))
+            (&Binary(r1, _, ref m1, _, _, i11, i12), &Binary(r2, _, ref m2, _, _, i21, i22))
                 => (i21, i22, r1, m1).cmp(&(i11, i12, r2, m2)),
             (_, _) => Ordering::Less
         }
     }
 }
-impl<W: Copy> IndexedBacktrace<W> {
-    pub fn iter(&self) -> Iter<W> { self.into_iter() }
+impl<W: Copy, R: Copy> IndexedBacktrace<W, R> {
+    pub fn iter(&self) -> Iter<W, R> { self.into_iter() }
 }
 
-pub struct Iter<'a, W>(&'a IndexedBacktrace<W>, u8);
+pub struct Iter<'a, W: 'a, R: 'a>(&'a IndexedBacktrace<W, R>, u8);
 
-impl<'a, W: Copy> Iterator for Iter<'a, W> {
-    type Item = IndexedBacktrace<W>;
+impl<'a, W: Copy, R: Copy> Iterator for Iter<'a, W, R> {
+    type Item = IndexedBacktrace<W, R>;
     fn next(&mut self) -> Option<Self::Item> {
         use self::IndexedBacktrace::*;
         match *self {
@@ -73,13 +74,13 @@ impl<'a, W: Copy> Iterator for Iter<'a, W> {
     }
     fn size_hint(&self) -> (usize, Option<usize>) { (self.1 as usize, Some(self.1 as usize)) }
 }
-impl<'a, W: Copy> ExactSizeIterator for Iter<'a, W> {
+impl<'a, W: Copy, R: Copy> ExactSizeIterator for Iter<'a, W, R> {
     fn len(&self) -> usize { self.1 as usize }
 }
 
-impl<'a, W: Copy> IntoIterator for &'a IndexedBacktrace<W> {
-    type IntoIter = Iter<'a, W>;
-    type Item = IndexedBacktrace<W>;
+impl<'a, W: Copy, R: Copy> IntoIterator for &'a IndexedBacktrace<W, R> {
+    type IntoIter = Iter<'a, W, R>;
+    type Item = IndexedBacktrace<W, R>;
     fn into_iter(self) -> Self::IntoIter {
         use self::IndexedBacktrace::*;
         match *self {
@@ -88,4 +89,41 @@ impl<'a, W: Copy> IntoIterator for &'a IndexedBacktrace<W> {
             _ => Iter(self, 0)
         }
     }
-}
\ No newline at end of file
+}
+
+/// The rules at each position of a partial derivation, i.e. the part of a
+/// multiple Dyck word that is read off a backtrace; the positions are relative
+/// to the backtrace's root. It is used to reject backtraces that can not be
+/// part of a word in R ∩ D before they are read off.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PartialDerivation(BTreeMap<Vec<SuccessorT>, u32>);
+
+impl PartialDerivation {
+    /// Combines the partial derivations of the successors of a backtrace using
+    /// the brackets of its rule.
+    /// Fails if two rules occur at the same position.
+    pub fn combine((ob, lb, rb): TdBrackets, successors: &[&PartialDerivation]) -> Option<Self> {
+        let mut positions = BTreeMap::new();
+        if let BracketContent::Component(rule_id, _) = ob {
+            positions.insert(Vec::new(), rule_id);
+        }
+        for (bracket, successor) in [lb, rb].iter().zip(successors) {
+            for (position, &rule_id) in &successor.0 {
+                let position = match *bracket {
+                    BracketContent::Variable(_, i, _) => {
+                        let mut prefixed = Vec::with_capacity(position.len() + 1);
+                        prefixed.push(i);
+                        prefixed.extend(position);
+                        prefixed
+                    },
+                    _ => position.clone()
+                };
+                match positions.entry(position) {
+                    Entry::Vacant(ve) => { ve.insert(rule_id); },
+                    Entry::Occupied(oe) => if *oe.get() != rule_id { return None; }
+                }
+            }
+        }
+        Some(PartialDerivation(positions))
+    }
+}
@@ -1,27 +1,46 @@
-use super::{Automaton, Bracket, BracketContent, RangeT, StateT, TdBinary, TdUnary, TdNullary, TdBrackets, DenseChart, RuleIdT};
+use super::{Automaton, Bracket, BracketContent, Position, StateT, SuccessorT, TdBinary, TdUnary, TdNullary, TdBrackets, Chart, RuleIdT};
 use fnv::FnvHashMap;
 use unique_heap::FnvUniqueHeap;
 use num_traits::Zero;
-use std::{ ops::Mul, collections::hash_map::Entry, hash::Hash, cmp::min };
+use std::{ ops::Mul, hash::Hash, cmp::min, rc::Rc };
+use util::budget::BudgetTracker;
 
 mod backtrace;
-use self::backtrace::IndexedBacktrace;
-
-/// implements necessary structures for the lazy k-best algorithm
-pub struct ChartIterator<'a, W>
+use self::backtrace::{IndexedBacktrace, PartialDerivation};
+
+/// The cached derivations for a span and a state with their partial derivations,
+/// the candidates for the next derivations, and the last candidate whose
+/// successors were not enumerated yet.
+type Derivations<W, R> = (
+    Vec<(IndexedBacktrace<W, R>, W, Rc<PartialDerivation>)>,
+    FnvUniqueHeap<IndexedBacktrace<W, R>, W>,
+    Option<IndexedBacktrace<W, R>>
+);
+
+/// implements necessary structures for the lazy k-best algorithm;
+/// spans are stored with positions of type `R`
+pub struct ChartIterator<'a, W, R>
 where
     W: Ord,
+    R: Ord,
 {
-    chart: DenseChart<W>,
+    chart: Chart<W>,
     binaries: &'a [Vec<TdBinary<W>>],
     unaries: &'a [Vec<TdUnary<W>>],
     nullaries: &'a [Vec<TdNullary<W>>],
     rules_to_brackets: &'a [TdBrackets],
     rulefilter: Vec<bool>,
+    // the terminal rules that read the symbol at each position of the word
+    terminals: Vec<Vec<RuleIdT>>,
+    // each candidate popped from a heap counts as an agenda pop
+    budget: BudgetTracker,
+    // whether inconsistent candidates are rejected during the enumeration
+    check_consistency: bool,
     
     // caches already queried hyperpaths and holds the next candidates
-    // for each span and state
-    d: FnvHashMap<(RangeT, RangeT, StateT), (Vec<(IndexedBacktrace<W>, W)>, FnvUniqueHeap<IndexedBacktrace<W>, W>)>,
+    // for each span and state; only hyperpaths that are consistent w.r.t. the
+    // multiple Dyck language are cached
+    d: FnvHashMap<(R, R, StateT), Derivations<W, R>>,
     
     // current k for root
     k: usize,
@@ -29,9 +48,12 @@ where
     initial: StateT
 }
 
-impl<'a, W: Ord> ChartIterator<'a, W> {
-    pub fn new<T: Eq + Hash>(chart: DenseChart<W>, automaton: &'a Automaton<T, W>, rulefilter: Vec<bool>) -> Self {
+impl<'a, W: Ord, R: Position> ChartIterator<'a, W, R> {
+    pub fn new<T: Eq + Hash>(chart: Chart<W>, automaton: &'a Automaton<T, W>, rulefilter: Vec<bool>, word: &[T]) -> Self {
         let (n, states, beamwidth) = chart.get_meta();
+        let terminals = word.iter()
+                            .map(|t| automaton.2.get(t).map_or_else(Vec::new, |rules| rules.iter().map(|&(r, _)| r).collect()))
+                            .collect();
         Self {
             chart,
             binaries: &automaton.3,
@@ -39,6 +61,9 @@ impl<'a, W: Ord> ChartIterator<'a, W> {
             nullaries: &automaton.5,
             rules_to_brackets: &automaton.8,
             rulefilter,
+            terminals,
+            budget: BudgetTracker::unlimited(),
+            check_consistency: true,
             // we need at most `beam` entries for each span
             d: FnvHashMap::with_capacity_and_hasher(n * (n+1) * min(states, beamwidth) / 2, Default::default()),
             k: 0,
@@ -46,14 +71,29 @@ impl<'a, W: Ord> ChartIterator<'a, W> {
             initial: automaton.7
         }
     }
+
+    /// Stops the enumeration as soon as `budget` is exhausted.
+    pub fn with_budget(mut self, budget: BudgetTracker) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Sets whether inconsistent candidates are rejected during the
+    /// enumeration (the default); otherwise all well bracketed words are
+    /// enumerated, including those that are no multiple Dyck words.
+    pub fn with_consistency_check(mut self, check_consistency: bool) -> Self {
+        self.check_consistency = check_consistency;
+        self
+    }
 }
 
-impl<'a, W> ChartIterator<'a, W>
+impl<'a, W, R> ChartIterator<'a, W, R>
 where
     W: Mul<Output=W> + Ord + Copy + Zero,
+    R: Position,
 {
     /// Computes the weight of an item recursively and checks the existence of all predecessors.
-    fn weight(&mut self, i: RangeT, j: RangeT, ce: &IndexedBacktrace<W>) -> Option<W> {
+    fn weight(&mut self, i: R, j: R, ce: &IndexedBacktrace<W, R>) -> Option<W> {
         use self::IndexedBacktrace::*;
         
         match *ce {
@@ -72,12 +112,13 @@ where
 
     /// extracts the backtraces for a spand and a constituents in a
     /// top-down approach
-    fn backtraces(chart: &DenseChart<W>, binaries: &[Vec<TdBinary<W>>], unaries: &[Vec<TdUnary<W>>], nullaries: &[Vec<TdNullary<W>>], filter: &[bool], i: RangeT, j: RangeT, q: StateT) -> FnvUniqueHeap<IndexedBacktrace<W>, W> {
+    fn backtraces(chart: &Chart<W>, binaries: &[Vec<TdBinary<W>>], unaries: &[Vec<TdUnary<W>>], nullaries: &[Vec<TdNullary<W>>], filter: &[bool], terminals: &[Vec<RuleIdT>], i: R, j: R, q: StateT) -> FnvUniqueHeap<IndexedBacktrace<W, R>, W> {
         let mut heap = FnvUniqueHeap::default();
+        let (i, j) = (i.to_usize(), j.to_usize());
         for &(r, q1, q2, w) in binaries[q as usize].iter().filter(|&(r, _, _, _)| filter[*r as usize]) {
             for mid in (i+1)..j {
                 if let Some(sws) = chart.get_weight(i, mid, q1).and_then(|lew| chart.get_weight(mid, j, q2).map(move |riw| lew * riw)) {
-                    heap.push(IndexedBacktrace::Binary(r, q1, mid, q2, w, 0u32, 0u32), w * sws);
+                    heap.push(IndexedBacktrace::Binary(r, q1, R::from_usize(mid), q2, w, 0u32, 0u32), w * sws);
                 }
             }
         }
@@ -87,8 +128,8 @@ where
             }
         }
         if j-i == 1 {
-            for &(r, w) in nullaries[q as usize].iter().filter(|&(r, _)| filter[*r as usize]) {
-                // is is not correct in general, but ok for terminal-seperated rules
+            // only the rules that read the symbol at position i
+            for &(r, w) in nullaries[q as usize].iter().filter(|&(r, _)| filter[*r as usize] && terminals[i].contains(r)) {
                 heap.push(IndexedBacktrace::Nullary(r, w), w);
             }
         }
@@ -96,121 +137,184 @@ where
     }
     
     // Implementation of the lazy enumeration for hyperpaths in Better k-best Parsing.
-    fn kth(&mut self, i: RangeT, j: RangeT, q: StateT, k: usize) -> Option<(IndexedBacktrace<W>, W)> {
-        use std::cmp::Ordering;
+    // Candidates are checked for consistency (cf. `PartialDerivation`) when they
+    // are popped from the heap; the inconsistent ones are skipped, but their
+    // successors are enumerated nevertheless.
+    fn kth(&mut self, i: R, j: R, q: StateT, k: usize) -> Option<(IndexedBacktrace<W, R>, W)> {
         // initialize structures for span and state
-        // todo skip fetch if vec_len > k
-        let (mut vec_len, mut last_deriv, mut last_weight) = {
-            let ChartIterator{ ref mut d, ref chart, ref binaries, ref unaries, ref nullaries, ref rulefilter, .. } = *self;
-            match d.entry((i, j, q)) {
-                Entry::Vacant(ve) => {
-                    let mut bts = Self::backtraces(chart, binaries, unaries, nullaries, &rulefilter, i, j, q);
-                    if let Some((first, vit)) = bts.pop() {
-                        let mut vec = Vec::with_capacity(bts.len() + 1);
-                        vec.push((first, vit));
-                        ve.insert((vec, bts));
-                        (1, first, vit)
-                    } else {
-                        ve.insert((Vec::new(), FnvUniqueHeap::default()));
-                        return None
-                    }
-                }, Entry::Occupied(oe) => {
-                    let &(ref d, _) = oe.get();
-                    if let Some(&(deriv, w)) = d.last() { (d.len(), deriv, w) }
-                    else { return None }
-                }
-            }
-        };
+        if !self.d.contains_key(&(i, j, q)) {
+            let bts = {
+                let ChartIterator{ ref chart, ref binaries, ref unaries, ref nullaries, ref rulefilter, ref terminals, .. } = *self;
+                Self::backtraces(chart, binaries, unaries, nullaries, rulefilter, terminals, i, j, q)
+            };
+            self.d.insert((i, j, q), (Vec::new(), bts, None));
+        }
 
-        while vec_len < (k + 1) {
-            // there are at most 2 successors, so we collect them allocated in the stack
-            // and at the same time to avoid getting the entry for the insertion trice
-            let (wd1, wd2) = {
-                let mut worse_derivs = last_deriv.iter().filter_map(|bt| Some((bt, self.weight(i, j, &bt)?)));
-                (worse_derivs.next(), worse_derivs.next())
+        loop {
+            let last_deriv = {
+                let &mut (ref derivs, _, ref mut last_deriv) = self.d.get_mut(&(i, j, q)).unwrap();
+                if let Some(&(deriv, w, _)) = derivs.get(k) {
+                    return Some((deriv, w));
+                }
+                last_deriv.take()
             };
 
-            let &mut (ref mut derivs, ref mut candidates) = &mut self.d.get_mut(&(i, j, q)).unwrap();
-            if let Some((bt, w)) = wd1 { candidates.push(bt, w); }
-            if let Some((bt, w)) = wd2 { candidates.push(bt, w); }
+            if let Some(last_deriv) = last_deriv {
+                // there are at most 2 successors, so we collect them allocated in the stack
+                // and at the same time to avoid getting the entry for the insertion twice
+                let (wd1, wd2) = {
+                    let mut worse_derivs = last_deriv.iter().filter_map(|bt| Some((bt, self.weight(i, j, &bt)?)));
+                    (worse_derivs.next(), worse_derivs.next())
+                };
+                let candidates = &mut self.d.get_mut(&(i, j, q)).unwrap().1;
+                if let Some((bt, w)) = wd1 { candidates.push(bt, w); }
+                if let Some((bt, w)) = wd2 { candidates.push(bt, w); }
+            }
 
-            if candidates.is_empty() { break; }
+            if !self.budget.pop() { return None; }
+            let (candidate, estimate) = self.d.get_mut(&(i, j, q)).unwrap().1.pop()?;
             
-            let last_deriv_w = candidates.pop().unwrap();
-            derivs.push(last_deriv_w);
-            last_deriv = last_deriv_w.0;
-            last_weight = last_deriv_w.1;
-            vec_len += 1;
-        }
+            // The weights of the initial candidates are estimated using the
+            // chart; their successors may be worse if the best ones were
+            // rejected. If so, the candidate is queued with its actual weight.
+            match self.weight(i, j, &candidate) {
+                None => continue,
+                Some(w) if w < estimate => {
+                    self.d.get_mut(&(i, j, q)).unwrap().1.push(candidate, w);
+                    continue;
+                },
+                Some(_) => ()
+            }
 
-        match vec_len.cmp(&(k+1)) {
-            Ordering::Equal => Some((last_deriv, last_weight)),
-            Ordering::Less  => None,
-            Ordering::Greater => Some(self.d[&(i, j, q)].0[k])
+            let partial_derivation = if self.check_consistency {
+                self.partial_derivation(i, j, &candidate)
+            } else {
+                Some(Rc::new(PartialDerivation::default()))
+            };
+            let &mut (ref mut derivs, _, ref mut last_deriv) = self.d.get_mut(&(i, j, q)).unwrap();
+            *last_deriv = Some(candidate);
+            if let Some(pd) = partial_derivation {
+                derivs.push((candidate, estimate, pd));
+            }
         }
     }
 
+    /// Combines the partial derivations of the successors of a hyperpath;
+    /// fails if the hyperpath is not consistent.
+    /// Assumes that the successors were already enumerated.
+    fn partial_derivation(&self, i: R, j: R, ce: &IndexedBacktrace<W, R>) -> Option<Rc<PartialDerivation>> {
+        use self::IndexedBacktrace::*;
+        let successor = |i: R, j: R, q: StateT, k: u32| &self.d[&(i, j, q)].0[k as usize].2;
+
+        let pd = match *ce {
+            Nullary(rid, _) => PartialDerivation::combine(self.rules_to_brackets[rid as usize], &[]),
+            Unary(rid, q, _, k) => PartialDerivation::combine(self.rules_to_brackets[rid as usize], &[successor(i, j, q, k)]),
+            Binary(rid, ls, m, rs, _, lk, rk)
+                => PartialDerivation::combine(self.rules_to_brackets[rid as usize], &[successor(i, m, ls, lk), successor(m, j, rs, rk)])
+        };
+        pd.map(Rc::new)
+    }
+
     // Reads the bracket word for a hyperpath.
-    fn read(&mut self, i: RangeT, j: RangeT, ce: &IndexedBacktrace<W>) -> Vec<Bracket<BracketContent>> {
+    fn read(&mut self, i: R, j: R, ce: &IndexedBacktrace<W, R>) -> Vec<Bracket<BracketContent>> {
         use self::IndexedBacktrace::*;
 
         match *ce {
             Binary(rid, ls, m, rs, _, lk, rk) => {
                 let ce1 = self.kth(i, m, ls, lk as usize).unwrap().0;
                 let ce2 = self.kth(m, j, rs, rk as usize).unwrap().0;
-
-                let (ob, lb, rb) = self.rules_to_brackets[rid as usize];
-                let mut additional_elements = 2 + if ob.is_ignore() { 0 } else { 2 } + if lb.is_ignore() { 0 } else { 2 };
-                
-                let mut w1 = self.read(i, m, &ce1);
+                let w1 = self.read(i, m, &ce1);
                 let w2 = self.read(m, j, &ce2);
-                w1.reserve(w2.len() + additional_elements);
-                if !lb.is_ignore() {
-                    w1.insert(0, Bracket::Open(lb));
-                    w1.push(Bracket::Close(lb));
-                }
-                w1.push(Bracket::Open(rb));
-                w1.extend(w2);
-                w1.push(Bracket::Close(rb));
-                if !ob.is_ignore() {
-                    w1.insert(0, Bracket::Open(ob));
-                    w1.push(Bracket::Close(ob));
-                }
-                
-                w1
+                wrap(self.rules_to_brackets[rid as usize], w1, Some(w2))
             },
             Unary(rid, q, _, k) => {
                 let ice = self.kth(i, j, q, k as usize).unwrap().0;
-                let mut w = self.read(i, j, &ice);
-                w.reserve(4);
-                let (ob, ib, _) = self.rules_to_brackets[rid as usize];
-                
-                w.insert(0, Bracket::Open(ob));
-                w.insert(1, Bracket::Open(ib));
-                w.push(Bracket::Close(ib));
-                w.push(Bracket::Close(ob));
-                w
+                let w = self.read(i, j, &ice);
+                wrap(self.rules_to_brackets[rid as usize], w, None)
             },
-            Nullary(rid, _) => {
-                let (ob, ib, _) = self.rules_to_brackets[rid as usize];
-                vec![ Bracket::Open(ob)
-                    , Bracket::Open(ib)
-                    , Bracket::Close(ib)
-                    , Bracket::Close(ob)
-                    ]
+            Nullary(rid, _) => wrap(self.rules_to_brackets[rid as usize], Vec::new(), None)
+        }
+    }
+
+    /// Finds the best hyperpath for a span and a state whose successors have
+    /// hyperpaths themselves, without checking its consistency.
+    /// The result is memoised for each span and state in `best`; a span and
+    /// state that is currently visited counts as having no hyperpath, which
+    /// breaks cycles of chain rules.
+    fn best_backtrace(&self, i: R, j: R, q: StateT, best: &mut FnvHashMap<(R, R, StateT), Option<IndexedBacktrace<W, R>>>) -> Option<IndexedBacktrace<W, R>> {
+        use self::IndexedBacktrace::*;
+        if let Some(&bt) = best.get(&(i, j, q)) {
+            return bt;
+        }
+        best.insert((i, j, q), None);
+
+        let ChartIterator{ ref chart, binaries, unaries, nullaries, ref rulefilter, ref terminals, .. } = *self;
+        let mut bts = Self::backtraces(chart, binaries, unaries, nullaries, rulefilter, terminals, i, j, q);
+        let mut found = None;
+        while let Some((bt, _)) = bts.pop() {
+            let complete = match bt {
+                Binary(_, ls, m, rs, _, _, _)
+                    => self.best_backtrace(i, m, ls, best).is_some() && self.best_backtrace(m, j, rs, best).is_some(),
+                Unary(_, q1, _, _) => self.best_backtrace(i, j, q1, best).is_some(),
+                Nullary(_, _) => true
+            };
+            if complete {
+                found = Some(bt);
+                break;
             }
         }
+        best.insert((i, j, q), found);
+        found
+    }
+
+    /// Reads the bracket word for the hyperpaths found by `best_backtrace`.
+    fn read_best(&self, i: R, j: R, q: StateT, best: &FnvHashMap<(R, R, StateT), Option<IndexedBacktrace<W, R>>>) -> Vec<Bracket<BracketContent>> {
+        use self::IndexedBacktrace::*;
+        match best[&(i, j, q)].unwrap() {
+            Binary(rid, ls, m, rs, _, _, _) => {
+                let w1 = self.read_best(i, m, ls, best);
+                let w2 = self.read_best(m, j, rs, best);
+                wrap(self.rules_to_brackets[rid as usize], w1, Some(w2))
+            },
+            Unary(rid, q1, _, _) => wrap(self.rules_to_brackets[rid as usize], self.read_best(i, j, q1, best), None),
+            Nullary(rid, _) => wrap(self.rules_to_brackets[rid as usize], Vec::new(), None)
+        }
+    }
+
+    /// The bracket word of the best hyperpath in the chart, regardless of
+    /// its consistency; it is used as a fallback if there is no consistent one.
+    pub fn fallback(&self) -> Option<Vec<Bracket<BracketContent>>> {
+        let (i, j) = (R::from_usize(0), R::from_usize(self.n));
+        let mut best = FnvHashMap::default();
+        self.best_backtrace(i, j, self.initial, &mut best)?;
+        Some(self.read_best(i, j, self.initial, &best))
     }
 }
 
-impl<'a, W: Ord + Copy + Mul<Output=W> + Zero> Iterator for ChartIterator<'a, W> {
+/// Wraps the bracket words for the successors of a hyperpath in the brackets of its rule.
+fn wrap((ob, lb, rb): TdBrackets, w1: Vec<Bracket<BracketContent>>, w2: Option<Vec<Bracket<BracketContent>>>) -> Vec<Bracket<BracketContent>> {
+    let mut w = Vec::with_capacity(w1.len() + w2.as_ref().map_or(0, |w2| w2.len() + 2) + 4);
+    if !ob.is_ignore() { w.push(Bracket::Open(ob)); }
+    if !lb.is_ignore() { w.push(Bracket::Open(lb)); }
+    w.extend(w1);
+    if !lb.is_ignore() { w.push(Bracket::Close(lb)); }
+    if let Some(w2) = w2 {
+        w.push(Bracket::Open(rb));
+        w.extend(w2);
+        w.push(Bracket::Close(rb));
+    }
+    if !ob.is_ignore() { w.push(Bracket::Close(ob)); }
+    w
+}
+
+impl<'a, W: Ord + Copy + Mul<Output=W> + Zero, R: Position> Iterator for ChartIterator<'a, W, R> {
     type Item = Vec<Bracket<BracketContent>>;
     fn next(&mut self) -> Option<Self::Item> {
         let &mut ChartIterator{ initial, n, k, .. } = self;
         self.k += 1;
 
-        self.kth(0u8, n as u8, initial, k)
-            .map(|(backtrace, _)| self.read(0u8, n as u8, &backtrace))
+        self.kth(R::from_usize(0), R::from_usize(n), initial, k)
+            .map(|(backtrace, _)| self.read(R::from_usize(0), R::from_usize(n), &backtrace))
     }
 }
 
@@ -228,7 +332,7 @@ mod test {
             = "initial: [S]\n\n
                S → [[T a]] () # 0.7\n
                S → [[Var 0 0]] (S) # 0.3".parse().unwrap();
-        Automaton::from_grammar(g.rules.iter().enumerate().map(|(i, r)| (i as u32, r)), g.init)
+        Automaton::from_grammar(g.rules.iter().enumerate().map(|(i, r)| (i as u32, r)), g.init).unwrap()
     }
     
     #[test]
@@ -239,8 +343,8 @@ mod test {
 
         let automaton = example_automaton();
         let estimates = SxOutside::from_automaton(&automaton, 0);
-        let chart = automaton.fill_chart(&[String::from("a")], 1, zero, &estimates, &vec![true, true]);
-        let mut it = ChartIterator::new(chart, &automaton, vec![true, true]);
+        let chart = automaton.fill_chart(&[String::from("a")], 1, zero, &estimates, &vec![true, true], &BudgetTracker::unlimited());
+        let mut it = ChartIterator::<_, u8>::new(chart, &automaton, vec![true, true], &[String::from("a")]);
 
         assert_eq!(
             it.kth(0, 1, 0, 0),
@@ -272,18 +376,20 @@ mod test {
 
         let automaton = example_automaton();
         let estimates = SxOutside::from_automaton(&automaton, 0);
-        let chart = automaton.fill_chart(&[String::from("a")], 1, zero, &estimates, &[true, true]);
-        let mut it = ChartIterator::new(chart, &automaton, vec![true, true]);
+        let chart = automaton.fill_chart(&[String::from("a")], 1, zero, &estimates, &[true, true], &BudgetTracker::unlimited());
+        let mut it = ChartIterator::<_, u8>::new(chart, &automaton, vec![true, true], &[String::from("a")]);
 
         assert!(it.d.is_empty());
         assert_eq!(it.k, 0);
 
+        let derivs = |it: &ChartIterator<_, _>| it.d[&(0, 1, 0)].0.iter().map(|&(bt, w, _)| (bt, w)).collect::<Vec<_>>();
+
         assert!(it.next().is_some());
-        assert_eq!(it.d[&(0, 1, 0)].0, vec![(IndexedBacktrace::Nullary(0, w1), w1)]);
+        assert_eq!(derivs(&it), vec![(IndexedBacktrace::Nullary(0, w1), w1)]);
         assert_eq!(it.d[&(0, 1, 0)].1.clone().into_sorted_vec(), vec![(w1 * w2, IndexedBacktrace::Unary(1, 0, w2, 0))]);
 
         assert!(it.next().is_some());
-        assert_eq!(it.d[&(0, 1, 0)].0, vec![(IndexedBacktrace::Nullary(0, w1), w1), (IndexedBacktrace::Unary(1, 0, w2, 0), w1 * w2)]);
+        assert_eq!(derivs(&it), vec![(IndexedBacktrace::Nullary(0, w1), w1), (IndexedBacktrace::Unary(1, 0, w2, 0), w1 * w2)]);
         assert!(it.d[&(0, 1, 0)].1.is_empty());
 
         assert!(it.next().is_some());
@@ -294,14 +400,14 @@ mod test {
         let zero = LogDomain::zero();
         let automaton = example_automaton();
         let estimates = SxOutside::from_automaton(&automaton, 0);
-        let it = ChartIterator::new(automaton.fill_chart(&[String::from("a")], 1, zero, &estimates, &[true, true]), &automaton, vec![true, true]);
+        let it = ChartIterator::<_, u8>::new(automaton.fill_chart(&[String::from("a")], 1, zero, &estimates, &[true, true], &BudgetTracker::unlimited()), &automaton, vec![true, true], &[String::from("a")]);
         
         assert_eq!(
             it.take(10).count(),
             10
         );
 
-        let it = ChartIterator::new(automaton.fill_chart(&[String::from("a")], 1, zero, &estimates, &[true, true]), &automaton, vec![true, true]);
+        let it = ChartIterator::<_, u8>::new(automaton.fill_chart(&[String::from("a")], 1, zero, &estimates, &[true, true], &BudgetTracker::unlimited()), &automaton, vec![true, true], &[String::from("a")]);
         assert_eq!(
             it.take(4).collect::<Vec<_>>(),
             vec![
@@ -357,6 +463,82 @@ mod test {
         )
     }
 
+    #[test]
+    fn partial_derivations() {
+        use self::BracketContent::*;
+        let leaf = |rule_id| PartialDerivation::combine((Component(rule_id, 0), Terminal, Ignore), &[]).unwrap();
+        
+        // rule 0 with two successors of fanout 2, their components
+        // are read off from different spans
+        let a0 = PartialDerivation::combine((Component(1, 0), Variable(1, 0, 0), Ignore), &[&leaf(5)]).unwrap();
+        let a1 = PartialDerivation::combine((Component(1, 1), Variable(1, 0, 1), Ignore), &[&leaf(5)]).unwrap();
+        let b1 = PartialDerivation::combine((Component(2, 1), Variable(2, 0, 1), Ignore), &[&leaf(5)]).unwrap();
+        
+        assert!(PartialDerivation::combine((Component(0, 0), Variable(0, 0, 0), Variable(0, 0, 1)), &[&a0, &a1]).is_some());
+        assert!(PartialDerivation::combine((Component(0, 0), Variable(0, 0, 0), Variable(0, 0, 1)), &[&a0, &b1]).is_none());
+        // same rules at different positions
+        assert!(PartialDerivation::combine((Component(0, 0), Variable(0, 0, 0), Variable(0, 1, 0)), &[&a0, &b1]).is_some());
+        // the left successor is an intermediate state of the binarization
+        assert!(PartialDerivation::combine((Ignore, Ignore, Variable(0, 1, 1)), &[&a0, &b1]).is_some());
+        let inner = PartialDerivation::combine((Ignore, Variable(0, 0, 0), Variable(0, 1, 0)), &[&a0, &b1]).unwrap();
+        assert!(PartialDerivation::combine((Component(0, 0), Ignore, Variable(0, 0, 1)), &[&inner, &b1]).is_none());
+        assert!(PartialDerivation::combine((Component(0, 0), Ignore, Variable(0, 0, 1)), &[&inner, &a1]).is_some());
+    }
+
+    #[test]
+    fn dense_and_sparse_charts() {
+        use super::super::chart::{DenseChart, SparseChart};
+        let zero = LogDomain::zero();
+        let words: Vec<String> = vec!["a", "c", "b", "d"].into_iter().map(|s| s.to_owned()).collect();
+
+        for (automaton, word, rules) in vec![(example_automaton(), &words[0..1], 2), (example_automaton2(), &words[..], 15)] {
+            let estimates = SxOutside::from_automaton(&automaton, 0);
+            let filter = vec![true; rules];
+            let (n, states, beam) = (word.len(), automaton.states(), 10);
+
+            let dense = automaton.fill(Chart::Dense(DenseChart::new(n, states, beam)), word, beam, zero, &estimates, &filter, &BudgetTracker::unlimited());
+            let sparse = automaton.fill(Chart::Sparse(SparseChart::new(n, states, beam)), word, beam, zero, &estimates, &filter, &BudgetTracker::unlimited());
+            for i in 0..n {
+                for j in (i+1)..=n {
+                    assert_eq!(dense.iterate_nont(i, j).collect::<Vec<_>>(), sparse.iterate_nont(i, j).collect::<Vec<_>>());
+                    for q in 0..(states as StateT) {
+                        assert_eq!(dense.get_weight(i, j, q), sparse.get_weight(i, j, q));
+                    }
+                }
+            }
+
+            let dense_it = ChartIterator::<_, u8>::new(dense, &automaton, filter.clone(), word);
+            let sparse_it = ChartIterator::<_, u8>::new(sparse, &automaton, filter, word);
+            assert_eq!(dense_it.fallback(), sparse_it.fallback());
+            assert_eq!(dense_it.take(10).collect::<Vec<_>>(), sparse_it.take(10).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn consistency_check() {
+        let zero = LogDomain::zero();
+        let automaton = example_automaton2();
+        let estimates = SxOutside::from_automaton(&automaton, 0);
+        let filter = vec![true; 15];
+
+        for word in vec![vec!["a", "c", "b", "d"], vec!["a", "a", "c", "c", "b", "b", "d", "d"], vec!["a", "c", "b", "b", "d"]] {
+            let word: Vec<String> = word.into_iter().map(|s| s.to_owned()).collect();
+            let chart = automaton.fill_chart(&word, 10, zero, &estimates, &filter, &BudgetTracker::unlimited());
+            let unchecked: Vec<_> = ChartIterator::<_, u8>::new(chart, &automaton, filter.clone(), &word).with_consistency_check(false).collect();
+            let chart = automaton.fill_chart(&word, 10, zero, &estimates, &filter, &BudgetTracker::unlimited());
+            let checked: Vec<_> = ChartIterator::<_, u8>::new(chart, &automaton, filter.clone(), &word).collect();
+
+            // the consistency check only removes candidates
+            assert!(checked.len() <= unchecked.len());
+            assert!(checked.iter().all(|w| unchecked.contains(w)));
+        }
+
+        // only inconsistent candidates, which are all enumerated without the check
+        let word: Vec<String> = vec!["a", "c", "b", "b", "d"].into_iter().map(|s| s.to_owned()).collect();
+        let chart = automaton.fill_chart(&word, 10, zero, &estimates, &filter, &BudgetTracker::unlimited());
+        assert_eq!(ChartIterator::<_, u8>::new(chart, &automaton, filter.clone(), &word).with_consistency_check(false).count(), 1);
+    }
+
     #[test]
     fn kth2 () {
         let zero = LogDomain::zero();
@@ -364,11 +546,11 @@ mod test {
         let estimates = SxOutside::from_automaton(&automaton, 0);
         let filter = vec![true; 15];
         let words: Vec<String> = vec!["a", "c", "b", "b", "d"].into_iter().map(|s| s.to_owned()).collect();
-        let chart = automaton.fill_chart(&words, 10, zero, &estimates, &filter);
+        let chart = automaton.fill_chart(&words, 10, zero, &estimates, &filter, &BudgetTracker::unlimited());
 
         assert!(chart.get_weight(0, 5, 0).is_some());
         
-        let mut it = ChartIterator::new(chart, &automaton, filter);
+        let mut it = ChartIterator::<_, u8>::new(chart, &automaton, filter, &words);
 
         for i in 1..10 {
             assert!(it.kth(0, 5, 0, i).is_none(), "failed at {}", i);
@@ -382,25 +564,48 @@ mod test {
         let estimates = SxOutside::from_automaton(&automaton, 0);
         let words: Vec<String> = vec!["a", "c", "b", "b", "d"].into_iter().map(|s| s.to_owned()).collect();
         let filter = vec![true; 15];
-        let chart = automaton.fill_chart(&words, 10, zero, &estimates, &filter);
+        let chart = automaton.fill_chart(&words, 10, zero, &estimates, &filter, &BudgetTracker::unlimited());
 
+        // the only well bracketed word uses different rules at the same position
         assert_eq!(
-            ChartIterator::new(chart, &automaton, filter.clone()).take(10).count(),
-            1
+            ChartIterator::<_, u8>::new(chart, &automaton, filter.clone(), &words).take(10).count(),
+            0
         );
 
-        let chart = automaton.fill_chart(&words, 10, zero, &estimates, &filter);
-        let it = ChartIterator::new(chart, &automaton, filter);
+        let chart = automaton.fill_chart(&words, 10, zero, &estimates, &filter, &BudgetTracker::unlimited());
+        let it = ChartIterator::<_, u8>::new(chart, &automaton, filter, &words);
         
-        let some_words = it.collect::<Vec<_>>();
         let first = example_words2();
 
         assert_eq!(
-            some_words,
+            it.fallback().into_iter().collect::<Vec<_>>(),
             first
         );
     }
 
+    #[test]
+    fn terminals() {
+        use grammars::lcfrs::Lcfrs;
+        let zero = LogDomain::zero();
+        let Lcfrs{ rules, init }: Lcfrs<String, String, LogDomain<f64>>
+                    = "initial: [S]\n\n
+                       S → [[Var 0 0, Var 1 0]] (A, A) # 1\n
+                       A → [[T a]] () # 0.6\n
+                       A → [[T b]] () # 0.4".parse().unwrap();
+        let automaton = Automaton::from_grammar(rules.iter().enumerate().map(|(i, r)| (i as u32, r)), init).unwrap();
+        let estimates = SxOutside::from_automaton(&automaton, 0);
+        let filter = vec![true; 3];
+        let words: Vec<String> = vec!["b", "a"].into_iter().map(|s| s.to_owned()).collect();
+        let chart = automaton.fill_chart(&words, 10, zero, &estimates, &filter, &BudgetTracker::unlimited());
+
+        // both positions are reached by the same state, but only one rule
+        // reads the symbol at each of them
+        assert_eq!(
+            ChartIterator::<_, u8>::new(chart, &automaton, filter, &words).take(10).count(),
+            1
+        );
+    }
+
     fn example_automaton2 () -> Automaton<String, LogDomain<f64>> {
         use grammars::lcfrs::Lcfrs;
         let Lcfrs{ rules, init }: Lcfrs<String, String, LogDomain<f64>>
@@ -414,7 +619,7 @@ mod test {
                        X → [[T b]] () # 1\n
                        Y → [[T c]] () # 1\n
                        Z → [[T d]] () # 1".parse().unwrap();
-        Automaton::from_grammar(rules.iter().enumerate().map(|(i, r)| (i as u32, r)), init)
+        Automaton::from_grammar(rules.iter().enumerate().map(|(i, r)| (i as u32, r)), init).unwrap()
     }
 
     fn example_words2 () -> Vec<Vec<Bracket<BracketContent>>> {
@@ -1,9 +1,9 @@
 use num_traits::One;
-use std::{collections::{BinaryHeap}, ops::Mul, mem::replace, hash::Hash, default::Default};
+use std::{collections::{BinaryHeap}, fmt::{Debug, Display}, ops::Mul, mem::replace, hash::Hash, default::Default};
 use vecmultimap::VecMultiMap;
 use integeriser::{HashIntegeriser, Integeriser};
-use grammars::{pmcfg::{PMCFGRule, VarT}, lcfrs::csparsing::{BracketContent, Bracket}};
-use util::factorizable::Factorizable;
+use grammars::{pmcfg::{PMCFGRule, VarT}, lcfrs::csparsing::{BracketContent, Bracket, ComponentT, SuccessorT}};
+use util::{budget::BudgetTracker, dot::escape, factorizable::Factorizable};
 use fnv::FnvHashMap;
 use num_traits::Zero;
 
@@ -12,14 +12,39 @@ mod kbest;
 mod estimates;
 mod rulemask;
 
-use self::chart::DenseChart;
+use self::chart::Chart;
 use self::kbest::ChartIterator;
 pub use self::estimates::SxOutside;
 pub use self::rulemask::RuleMaskBuilder;
 
 pub type RuleIdT = u32;
 pub type StateT = u32;
-pub type RangeT = u8;
+
+/// Types for positions in a word, i.e. for the boundaries of spans.
+/// They are used as keys in the k-best enumeration, where smaller types are
+/// faster; `Automaton::generate` picks the smallest type that fits the word.
+pub trait Position: Copy + Debug + Eq + Hash + Ord {
+    fn from_usize(i: usize) -> Self;
+    fn to_usize(self) -> usize;
+    /// The maximum length of a word whose positions fit into this type.
+    fn max_len() -> usize;
+}
+
+macro_rules! impl_position {
+    ($t:ty) => {
+        impl Position for $t {
+            #[inline(always)]
+            fn from_usize(i: usize) -> Self { i as $t }
+            #[inline(always)]
+            fn to_usize(self) -> usize { self as usize }
+            fn max_len() -> usize { <$t>::max_value() as usize }
+        }
+    };
+}
+
+impl_position!(u8);
+impl_position!(u16);
+impl_position!(u32);
 
 /// stores a lhs state and weight of a rule
 pub type BuRule<W> = (W, StateT);
@@ -66,8 +91,11 @@ pub struct Automaton<T: Eq + Hash, W> (
 enum State<Nt> { Non(Nt, usize), Unique(RuleIdT, usize, usize) }
 
 impl<T: Eq + Hash, W> Automaton<T, W> {
-    /// extracts an `Automaton` from a given grammar with integerized rules
-    pub fn from_grammar<'a, N>(rules: impl Iterator<Item=(RuleIdT, &'a PMCFGRule<N, T, W>)>, init: N) -> Self
+    /// extracts an `Automaton` from a given grammar with integerized rules.
+    /// Will return `Err` if the fanout or the arity of a rule exceeds the
+    /// range of `ComponentT` or `SuccessorT`, respectively,
+    /// or if a rule has an empty component or is not terminal-separated.
+    pub fn from_grammar<'a, N>(rules: impl Iterator<Item=(RuleIdT, &'a PMCFGRule<N, T, W>)>, init: N) -> Result<Self, String>
     where
         T: 'a + Clone,
         W: 'a + Factorizable + Mul<Output=W> + One + Copy,
@@ -87,9 +115,21 @@ impl<T: Eq + Hash, W> Automaton<T, W> {
         let mut state_integerizer = HashIntegeriser::new();
 
         for (rule_id, rule) in rules {
+            if rule.composition.len() > ComponentT::max_value() as usize + 1 {
+                return Err(format!("rule {} has fanout {}, but at most {} is supported", rule_id, rule.composition.len(), ComponentT::max_value() as usize + 1));
+            }
+            if rule.tail.len() > SuccessorT::max_value() as usize + 1 {
+                return Err(format!("rule {} has {} successors, but at most {} are supported", rule_id, rule.tail.len(), SuccessorT::max_value() as usize + 1));
+            }
+            if rule.composition.iter().any(|component| component.is_empty()) {
+                return Err(format!("rule {} has an empty component, which is not supported", rule_id));
+            }
+            if rule.composition.iter().any(|component| component.len() > 1 && component.iter().any(VarT::is_t)) {
+                return Err(format!("rule {} is not terminal-separated, which is not supported", rule_id));
+            }
             let weight_factors = rule.weight.factorize(rule.composition.len());
             for (component_id, (component, component_weight)) in rule.composition.iter().zip(weight_factors).enumerate() {
-                let component_t = Component(rule_id as u32, component_id as u8);
+                let component_t = Component(rule_id as u32, component_id as ComponentT);
                 let state_i = state_integerizer.integerise(Non(&rule.head, component_id)) as StateT;
 
                 // handle terminal rules and chain rules
@@ -101,7 +141,7 @@ impl<T: Eq + Hash, W> Automaton<T, W> {
                             rule_to_brackets.push((component_t, Terminal, Ignore));
                         },
                         VarT::Var(i, j) => {
-                            let variable_t = Variable(rule_id as u32, i as u8, j as u8);
+                            let variable_t = Variable(rule_id as u32, i as SuccessorT, j as ComponentT);
                             let successor_state_i = state_integerizer.integerise(Non(&rule.tail[i], j)) as StateT;
                             bu_unaries.push_to(successor_state_i as usize, (rule_to_brackets.len() as RuleIdT, (component_weight, state_i)));
                             td_unaries.push_to(state_i as usize, (rule_to_brackets.len() as RuleIdT, successor_state_i, component_weight));
@@ -115,8 +155,8 @@ impl<T: Eq + Hash, W> Automaton<T, W> {
                     let successors = ( state_integerizer.integerise(Non(&rule.tail[v1.0], v1.1)) as StateT
                                      , state_integerizer.integerise(Non(&rule.tail[v2.0], v2.1)) as StateT
                                      );
-                    let vt1 = Variable(rule_id as u32, v1.0 as u8, v1.1 as u8);
-                    let vt2 = Variable(rule_id as u32, v2.0 as u8, v2.1 as u8);
+                    let vt1 = Variable(rule_id as u32, v1.0 as SuccessorT, v1.1 as ComponentT);
+                    let vt2 = Variable(rule_id as u32, v2.0 as SuccessorT, v2.1 as ComponentT);
                     // set lhs nontermal as target state or create unique
                     // state if there are nontermals left
                     let (mut targetstate, mut outer_t) = if component.len() == 2 {
@@ -131,7 +171,7 @@ impl<T: Eq + Hash, W> Automaton<T, W> {
                     for succ_offset in 1..=(component.len()-2) {
                         let l_successor = targetstate;
                         let v = component[1+succ_offset].unwrap_var();
-                        let vt = Variable(rule_id as u32, v.0 as u8, v.1 as u8);
+                        let vt = Variable(rule_id as u32, v.0 as SuccessorT, v.1 as ComponentT);
                         let r_successor = state_integerizer.integerise(Non(&rule.tail[v.0], v.1)) as StateT;
                         if component.len() == 2+succ_offset { 
                             targetstate = state_i;
@@ -153,7 +193,7 @@ impl<T: Eq + Hash, W> Automaton<T, W> {
         for (ql, qr, w, q0) in binaries.iter().enumerate().flat_map(|(ql, v)| v.iter().map(move |&(_, qr, (w, q0))| (ql, qr, w, q0))) {
             binaries_mirrorred.push_to(qr as usize, (ql as StateT, w, q0));
         }
-        Automaton(
+        Ok(Automaton(
             binaries,
             bu_unaries.into_vec_with_size(state_integerizer.size()),
             bu_initials,
@@ -163,38 +203,118 @@ impl<T: Eq + Hash, W> Automaton<T, W> {
             binaries_mirrorred.into_vec_with_size(state_integerizer.size()),
             state_integerizer.integerise(Non(&init, 0)) as StateT,
             rule_to_brackets
-        )
+        ))
     }
 
     /// Create an Iteator for well bracketed words in the  language of the
-    /// context-free approximation
-    pub fn generate<'a>(&'a self, word: &[T], beam: usize, delta: W, estimates: &SxOutside<W>, rulefilter: Vec<bool>) -> ChartIterator<'a, W>
+    /// context-free approximation that are consistent w.r.t. the multiple
+    /// Dyck language; also returns the best well bracketed word as fallback.
+    /// Both stop as soon as the `budget` is exhausted.
+    pub fn generate<'a>(&'a self, word: &[T], beam: usize, delta: W, estimates: &SxOutside<W>, rulefilter: Vec<bool>, budget: BudgetTracker, check_consistency: bool) -> (Box<Iterator<Item=Vec<Bracket<BracketContent>>> + 'a>, Option<Vec<Bracket<BracketContent>>>)
     where
         W: Ord + Copy + Mul<Output=W> + Zero + One,
     {
-        let chart = self.fill_chart(word, beam, delta, estimates, &rulefilter);
-        ChartIterator::new(chart, self, rulefilter)
+        let chart = self.fill_chart(word, beam, delta, estimates, &rulefilter, &budget);
+        if word.len() <= u8::max_len() {
+            let it = ChartIterator::<W, u8>::new(chart, self, rulefilter, word).with_budget(budget).with_consistency_check(check_consistency);
+            let fallback = it.fallback();
+            (Box::new(it), fallback)
+        } else if word.len() <= u16::max_len() {
+            let it = ChartIterator::<W, u16>::new(chart, self, rulefilter, word).with_budget(budget).with_consistency_check(check_consistency);
+            let fallback = it.fallback();
+            (Box::new(it), fallback)
+        } else {
+            assert!(word.len() <= u32::max_len());
+            let it = ChartIterator::<W, u32>::new(chart, self, rulefilter, word).with_budget(budget).with_consistency_check(check_consistency);
+            let fallback = it.fallback();
+            (Box::new(it), fallback)
+        }
     }
 
     pub fn states(&self) -> usize {
         self.0.len()
     }
+
+    /// Draws the binarized context-free rules in the DOT language, each state
+    /// is labelled by `label`.
+    /// Binary rules are drawn as points with edges to both successors, chain
+    /// and terminal rules as single edges; the initial state is drawn with a
+    /// double circle.
+    pub fn to_dot<F: Fn(StateT) -> String>(&self, label: F) -> String
+    where
+        T: Display,
+        W: Display,
+    {
+        let mut terminals = FnvHashMap::default();
+        for (t, rules) in &self.2 {
+            for &(rule, _) in rules {
+                terminals.insert(rule, t);
+            }
+        }
+
+        let mut dot = String::from("digraph {\n    node [shape=circle];\n");
+        for q in 0..self.states() {
+            let shape = if q as StateT == self.7 { ", shape=doublecircle" } else { "" };
+            dot.push_str(&format!("    q{} [label=\"{}\"{}];\n", q, escape(&label(q as StateT)), shape));
+        }
+        for (q, rules) in self.3.iter().enumerate() {
+            for &(rule, left, right, ref weight) in rules {
+                dot.push_str(&format!("    r{} [shape=point];\n", rule));
+                dot.push_str(&format!("    q{} -> r{} [label=\"{}\", arrowhead=none];\n", q, rule, weight));
+                dot.push_str(&format!("    r{} -> q{} [label=\"1\"];\n    r{} -> q{} [label=\"2\"];\n", rule, left, rule, right));
+            }
+        }
+        for (q, rules) in self.4.iter().enumerate() {
+            for &(_, successor, ref weight) in rules {
+                dot.push_str(&format!("    q{} -> q{} [label=\"{}\"];\n", q, successor, weight));
+            }
+        }
+        for (q, rules) in self.5.iter().enumerate() {
+            for &(rule, ref weight) in rules {
+                let terminal = escape(&terminals[&rule].to_string());
+                dot.push_str(&format!("    \"t {}\" [shape=none, label=\"{}\"];\n", terminal, terminal));
+                dot.push_str(&format!("    q{} -> \"t {}\" [label=\"{}\"];\n", q, terminal, weight));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Finds the rule and component that a state stands for; states that
+    /// were introduced for the binarization of rules yield `None`.
+    pub fn state_component(&self, q: StateT) -> Option<(RuleIdT, ComponentT)> {
+        let q = q as usize;
+        let rule = self.3[q].first().map(|&(r, ..)| r)
+                       .or_else(|| self.4[q].first().map(|&(r, ..)| r))
+                       .or_else(|| self.5[q].first().map(|&(r, _)| r))?;
+        match self.8[rule as usize].0 {
+            BracketContent::Component(rule_id, component) => Some((rule_id, component)),
+            _ => None
+        }
+    }
 }
 
 impl<T: Eq + Hash, W: Ord + Mul<Output=W> + Copy + Zero + One> Automaton<T, W> {
-    /// implements the CKY algorithm with chain rules
-    pub fn fill_chart(&self, word: &[T], beam: usize, delta: W, outsides: &SxOutside<W>, rule_filter: &[bool]) -> DenseChart<W> {
+    /// implements the CKY algorithm with chain rules; the kind of chart is
+    /// chosen by its estimated size, see `Chart::new`
+    pub fn fill_chart(&self, word: &[T], beam: usize, delta: W, outsides: &SxOutside<W>, rule_filter: &[bool], budget: &BudgetTracker) -> Chart<W> {
+        let chart = Chart::new(word.len(), self.0.len(), beam);
+        self.fill(chart, word, beam, delta, outsides, rule_filter, budget)
+    }
+
+    /// Fills an empty chart for a word; stops early if there is no time
+    /// left in the `budget`.
+    fn fill(&self, mut chart: Chart<W>, word: &[T], beam: usize, delta: W, outsides: &SxOutside<W>, rule_filter: &[bool], budget: &BudgetTracker) -> Chart<W> {
         let n = word.len();
-        let nonterminals = self.0.len();
 
         // contains the constituents ordered by weight
         let mut heap_of_nonterminals: BinaryHeap<(W, StateT)> = BinaryHeap::with_capacity(beam);
-        let mut chart = DenseChart::new(n, nonterminals, beam);
 
         for range in 1..=n {
             for l in 0..=(n-range) {
                 let r = l + range;
 
+                if !budget.check() { return chart; }
                 heap_of_nonterminals.clear();
 
                 // initial predictions for each position in word
@@ -212,14 +332,14 @@ impl<T: Eq + Hash, W: Ord + Mul<Output=W> + Copy + Zero + One> Automaton<T, W> {
                 
                 // binary step
                 for mid in l+1..r {
-                    for &(lnt, lew) in chart.iterate_nont(l as u8, mid as u8) {
+                    for &(lnt, lew) in chart.iterate_nont(l, mid) {
                         let available_rules = &self.0[lnt as usize];
                         let mut cache = (NOSTATE, None);
                         heap_of_nonterminals.extend(available_rules.iter().filter_map(
                             |&(rid, rnt, (ruw, lhs))| {
                                 if !rule_filter[rid as usize] { return None; }
                                 let riw = if cache.0 == rnt { cache.1 }
-                                          else { chart.get_weight(mid as u8, r as u8, rnt) }?;
+                                          else { chart.get_weight(mid, r, rnt) }?;
                                 let _ = outsides.get(lhs, l, r, n)?;
                                 Some((lew * ruw * riw, lhs))
                             }
@@ -233,7 +353,7 @@ impl<T: Eq + Hash, W: Ord + Mul<Output=W> + Copy + Zero + One> Automaton<T, W> {
                 let mut worst_weight = delta * heap_of_nonterminals.peek().map_or(W::zero(), |&(w, _)| w);
                 while let Some((w, q)) = heap_of_nonterminals.pop() {
                     if replace(&mut skip[q as usize], true) { continue; }
-                    chart.add_entry(l as u8, r as u8, q, w);
+                    chart.add_entry(l, r, q, w);
                     heap_of_nonterminals.extend(self.1[q as usize].iter().filter_map(
                         |&(rid, (rw, q))| {
                             if !rule_filter[rid as usize] { return None; }
@@ -1,6 +1,6 @@
 use super::*;
 use grammars::pmcfg::{PMCFGRule, VarT, Composition};
-use util::{tree::GornTree, IntMap };
+use util::{tree::{child_address, GornTree}, IntMap };
 use std::collections::{BTreeSet, BTreeMap};
 use dyck::Bracket;
 use fnv::{FnvHashMap, FnvHashSet};
@@ -12,10 +12,10 @@ pub struct FailedParseTreeNode {
     // the applied grammar rule
     rule: u32,
     // the components this grammar rule was used in
-    components: FnvHashSet<u8>,
+    components: FnvHashSet<ComponentT>,
     
     // successor -> rule -> tree
-    children: BTreeMap< u8, FnvHashMap<u32, usize> >
+    children: BTreeMap< SuccessorT, FnvHashMap<u32, usize> >
 }
 
 /// Represents a failed attempt to parse a word using the Chomsky-Schützenberger parser.
@@ -142,9 +142,7 @@ impl FailedParseTree {
                 );
 
                 for (s_pos, child_tree_nodes) in child_tree_nodess.into_iter().enumerate() {
-                    let mut child_tree_node_position = pt_pos.clone();
-                    child_tree_node_position.push(s_pos);
-                    execution_stack.push((child_tree_nodes.values().cloned().collect(), child_tree_node_position));
+                    execution_stack.push((child_tree_nodes.values().cloned().collect(), child_address(&pt_pos, s_pos)));
                 }
 
                 tree.insert(pt_pos, rule);
@@ -158,31 +156,31 @@ impl FailedParseTree {
 /// Analyzes some of the components in the composition of a rule and returns
 /// * a map that assigns a new successor index to the variables in the composition components, and
 /// * an iterator over the old successor indices filtered by occurrence in the given components.
-fn successors_used_in_comps<'a, N, T, W>(rule: &'a PMCFGRule<N, T, W>, comps: &'a FnvHashSet<u8>) -> (FnvHashMap<u8, u8>, impl Iterator<Item=u8>)
+fn successors_used_in_comps<'a, N, T, W>(rule: &'a PMCFGRule<N, T, W>, comps: &'a FnvHashSet<ComponentT>) -> (FnvHashMap<SuccessorT, SuccessorT>, impl Iterator<Item=SuccessorT>)
 where
     N: Clone
 {
     let successors = rule.composition.composition.iter().enumerate().filter_map(
-        |(c,v)| if comps.contains(&(c as u8)) { Some(v) } else { None }
+        |(c,v)| if comps.contains(&(c as ComponentT)) { Some(v) } else { None }
     ).flat_map(
         |v| v.iter().filter_map(
-            |x| match *x { VarT::Var(i, _) => Some(i as u8), _ => None }
+            |x| match *x { VarT::Var(i, _) => Some(i as SuccessorT), _ => None }
         )
-    ).collect::<BTreeSet<u8>>();
+    ).collect::<BTreeSet<SuccessorT>>();
     
-    ( successors.iter().enumerate().map(|(i, j)| (*j as u8, i as u8)).collect(),
+    ( successors.iter().enumerate().map(|(i, j)| (*j, i as SuccessorT)).collect(),
       successors.into_iter()
     )
 }
 
 /// Changes the first index of all Variables according to `reordering` and adds `offset`.
-fn reorder_successors<'a, T>(component: &'a [VarT<T>], reordering: &'a FnvHashMap<u8, u8>, offset: u8) -> impl Iterator<Item=VarT<T>> + 'a
+fn reorder_successors<'a, T>(component: &'a [VarT<T>], reordering: &'a FnvHashMap<SuccessorT, SuccessorT>, offset: SuccessorT) -> impl Iterator<Item=VarT<T>> + 'a
 where
     T: Clone + 'a
 {
     component.iter().map(
         move |s| match s { 
-            &VarT::Var(i, j) => VarT::Var(*reordering.get(&(i as u8)).unwrap() as usize + offset as usize, j),
+            &VarT::Var(i, j) => VarT::Var(*reordering.get(&(i as SuccessorT)).unwrap() as usize + offset as usize, j),
             t => t.clone()
         }
     )
@@ -196,7 +194,7 @@ where
 /// It returns
 /// * a constructed grammar rule with weight 0, and
 /// * a sorted list of maps (rule -> node_id) for each child with occuring variable in the components.
-fn merge_rules<'a, 'b, N, T, W>(nodes: impl Iterator<Item=(&'a FnvHashSet<u8>, &'b PMCFGRule<N, T, W>, &'a BTreeMap<u8, FnvHashMap<u32, usize>>)>) -> (PMCFGRule<N, T, W>, Vec<&'a FnvHashMap<u32, usize>>)
+fn merge_rules<'a, 'b, N, T, W>(nodes: impl Iterator<Item=(&'a FnvHashSet<ComponentT>, &'b PMCFGRule<N, T, W>, &'a BTreeMap<SuccessorT, FnvHashMap<u32, usize>>)>) -> (PMCFGRule<N, T, W>, Vec<&'a FnvHashMap<u32, usize>>)
 where
     'b: 'a,
     N: Clone + 'b,
@@ -217,7 +215,7 @@ where
         heads.push(rule.head.clone());
         for c in components {
             VecMultiMapAdapter(&mut composition)[*c as usize]
-                .extend(reorder_successors(&rule.composition.composition[*c as usize], &ordering, successors.len() as u8));
+                .extend(reorder_successors(&rule.composition.composition[*c as usize], &ordering, successors.len() as SuccessorT));
         }
 
         for successor_index in succ_indices {
@@ -247,14 +245,14 @@ mod test {
         use self::VarT::*;
         let one: LogDomain<f64> = LogDomain::one();
 
-        let rules: Vec<(PMCFGRule<char, &str, _>, FnvHashSet<u8>)> = vec![
+        let rules: Vec<(PMCFGRule<char, &str, _>, FnvHashSet<ComponentT>)> = vec![
             (   PMCFGRule{
                     head: 'A',
                     tail: vec![ 'B', 'C' ],
                     weight: one,
                     composition: Composition{ composition: vec![vec![Var(1, 0)], vec![Var(0, 0)]] }
                 },
-                vec![0u8].into_iter().collect()
+                vec![0].into_iter().collect()
             ),
             (   PMCFGRule{
                     head: 'A',
@@ -262,13 +260,13 @@ mod test {
                     weight: one,
                     composition: Composition{ composition: vec![vec![], vec![Var(0, 0), Var(1, 0)]] }
                 },
-                vec![1u8].into_iter().collect()
+                vec![1].into_iter().collect()
             )
         ];
 
-        let maps: Vec<BTreeMap<u8, FnvHashMap<u32, usize>>> = vec![
-            vec![ (0u8, vec![(1u32, 1)].into_iter().collect()), (1u8, vec![(2u32, 2)].into_iter().collect()) ].into_iter().collect(),
-            vec![ (0u8, vec![(3u32, 3)].into_iter().collect()), (1u8, vec![(4u32, 4)].into_iter().collect()) ].into_iter().collect()
+        let maps: Vec<BTreeMap<SuccessorT, FnvHashMap<u32, usize>>> = vec![
+            vec![ (0, vec![(1u32, 1)].into_iter().collect()), (1, vec![(2u32, 2)].into_iter().collect()) ].into_iter().collect(),
+            vec![ (0, vec![(3u32, 3)].into_iter().collect()), (1, vec![(4u32, 4)].into_iter().collect()) ].into_iter().collect()
         ];
 
         assert_eq!(
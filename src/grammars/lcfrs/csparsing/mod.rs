@@ -5,16 +5,30 @@ use super::Lcfrs;
 
 use dyck::Bracket;
 use grammars::pmcfg::PMCFGRule;
-use util::{ tree::GornTree, factorizable::Factorizable };
+use util::{ budget::{Budget, BudgetTracker, Exhaustion}, dot::ToDot, tree::GornTree, factorizable::Factorizable };
 use std::{ collections::{BTreeMap}, fmt::{Display, Error, Formatter}, hash::Hash, ops::Mul };
 use num_traits::{Zero, One};
 use std::time::{Instant, Duration};
 
 use self::automaton::{Automaton, SxOutside, RuleMaskBuilder};
 
+/// Version of the serialised format of a `CSRepresentation`, which precedes
+/// it in files (e.g. those written by `csparsing extract`). It must be
+/// incremented whenever the serialised data structures change, e.g. the
+/// widths of `ComponentT` and `SuccessorT`, so that outdated files are
+/// rejected instead of being misread.
+/// Its value is large, so that it is not mistaken for the length that starts
+/// files without a version.
+pub const FORMAT_VERSION: u32 = 0x4353_0002;
+
+/// Index of a component of a rule; limits the fanout of the grammar.
+pub type ComponentT = u16;
+/// Index of a successor of a rule; limits the arity of the grammar.
+pub type SuccessorT = u16;
+
 /// The indices of a bracket in a CS representation for an lcfrs.
 /// Assumes integerized an itergerized set of (at most 2^32) rules and fanouts
-/// and arities ≤ 2^8.
+/// and arities ≤ 2^16.
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum BracketContent {
     /// We construe `Ignore` as a parenthesis without index; it is introduced
@@ -22,8 +36,8 @@ pub enum BracketContent {
     Ignore,
     /// We do not store the specific terminal as bracket index.
     Terminal,
-    Component(u32, u8),
-    Variable(u32, u8, u8),
+    Component(u32, ComponentT),
+    Variable(u32, SuccessorT, ComponentT),
 }
 
 impl BracketContent {
@@ -63,10 +77,11 @@ where
 
 pub struct GeneratorBuilder<'a, N, T: Eq + Hash, W> {
     grammar: &'a CSRepresentation<N, T, W>,
-    candidates: Option<usize>,
+    budget: Budget,
     beam: Option<usize>,
     delta: W,
     root_prediction: bool,
+    consistency_check: bool,
 }
 
 impl<'a, N, T, W> GeneratorBuilder<'a, N, T, W>
@@ -75,41 +90,82 @@ where
     W: Zero + Ord + Copy + One + Mul<Output=W>,
     N: Clone
 {
-    pub fn set_candidates(&mut self, c: usize) { self.candidates = Some(c); }
+    pub fn set_candidates(&mut self, c: usize) { self.budget.candidates = Some(c); }
+    /// Sets the limits for parsing a single word; overrides `set_candidates`.
+    pub fn set_budget(&mut self, budget: Budget) { self.budget = budget; }
     pub fn set_beam(&mut self, b: usize) { self.beam = Some(b); }
     pub fn set_delta(&mut self, d: W) { self.delta = d; }
     pub fn allow_root_prediction(&mut self) { self.root_prediction = true; }
+    /// Enumerates all well bracketed words instead of rejecting those that
+    /// are no multiple Dyck words while they are enumerated; e.g. to count
+    /// the candidates that the check saves.
+    pub fn disable_consistency_check(&mut self) { self.consistency_check = false; }
 
-    pub fn with_fallback(&self, word: &[T]) -> (impl Iterator<Item=GornTree<&'a PMCFGRule<N, T, W>>> + 'a, Option<GornTree<PMCFGRule<N, T, W>>>) {
-        let &Self { grammar, mut candidates, beam, delta, .. } = self;
+    /// Enumerates the parse trees for a word and computes a fallback tree.
+    /// Also returns a handle to the budget that is spent for the word, which
+    /// tells why the enumeration stopped early (if it did).
+    pub fn with_fallback(&self, word: &[T]) -> (impl Iterator<Item=GornTree<&'a PMCFGRule<N, T, W>>> + 'a, Option<GornTree<PMCFGRule<N, T, W>>>, BudgetTracker) {
+        let &Self { grammar, budget, beam, delta, consistency_check, .. } = self;
+        let budget = BudgetTracker::new(budget);
         let realbeam = beam.unwrap_or(grammar.generator.states());
         let rulemask = grammar.rulemaskbuilder.build(word);
-        let mut word_iterator = grammar.generator.generate(word, realbeam, delta, &grammar.estimates, rulemask).peekable();
-        let first = word_iterator.peek().map(|w| fallback::FailedParseTree::new(w).merge(&grammar.rules));
+        let (word_iterator, fallback_word) = grammar.generator.generate(word, realbeam, delta, &grammar.estimates, rulemask, budget.clone(), consistency_check);
+        let first = fallback_word.map(|w| fallback::FailedParseTree::new(&w).merge(&grammar.rules));
 
-        let count_candidates = move |_: &Vec<Delta>| -> bool {
-            candidates.as_mut().map_or(true, |c| if *c == 0 { false } else { *c -= 1; true } )
-        };
-        
-        ( word_iterator.take_while(count_candidates).filter_map(move |bs| grammar.toderiv(&bs))
+        let candidates = budget.clone();
+        ( word_iterator.take_while(move |_| candidates.candidate()).filter_map(move |bs| grammar.toderiv(&bs))
         , first
+        , budget
         )
     }
 
+    /// The number of rules of the underlying grammar.
+    pub fn rules(&self) -> usize {
+        self.grammar.rules.len()
+    }
+
+    /// Fills the chart for a word and lists the states of the automaton that
+    /// were visited for each span, with their viterbi weights and the
+    /// nonterminal and component they stand for (if they stand for one).
+    /// The spans are ordered by length.
+    pub fn chart(&self, word: &[T]) -> Vec<(usize, usize, u32, Option<(&'a N, ComponentT)>, W)> {
+        let &Self { grammar, budget, beam, delta, .. } = self;
+        let budget = BudgetTracker::new(budget);
+        let rulemask = grammar.rulemaskbuilder.build(word);
+        let realbeam = beam.unwrap_or(grammar.generator.states());
+        let chart = grammar.generator.fill_chart(word, realbeam, delta, &grammar.estimates, &rulemask, &budget);
+
+        let n = word.len();
+        let mut constituents = Vec::new();
+        for length in 1..=n {
+            for i in 0..=(n - length) {
+                for &(q, w) in chart.iterate_nont(i, i + length) {
+                    let nonterminal = grammar.generator.state_component(q)
+                                             .map(|(rule, component)| (&grammar.rules[rule as usize].head, component));
+                    constituents.push((i, i + length, q, nonterminal, w));
+                }
+            }
+        }
+        constituents
+    }
+
+    /// Parses a word and returns the number of rules, the length of the word,
+    /// the parse time and the result with the number of enumerated bracket
+    /// words. Unless the consistency check is disabled, bracket words that are
+    /// no multiple Dyck words are rejected during the enumeration and not
+    /// counted.
     pub fn debug(&self, word: &[T]) -> (usize, usize, Duration, DebugResult<N, T, W>) {
         let starting_time = Instant::now();
-        let &Self { grammar, mut candidates, beam, delta, .. } = self;
+        let &Self { grammar, budget, beam, delta, consistency_check, .. } = self;
+        let budget = BudgetTracker::new(budget);
         let rulemask = grammar.rulemaskbuilder.build(word);
         let realbeam = beam.unwrap_or(grammar.generator.states());
-        let count_candidates = move |_: &Vec<Delta>| -> bool {
-            candidates.as_mut().map_or(true, |c| if *c == 0 { false } else { *c -= 1; true } )
-        };
-        let word_iterator = grammar.generator.generate(word, realbeam, delta, &grammar.estimates, rulemask);
-        let mut word_iterator = word_iterator.take_while(count_candidates).peekable();
+        let (word_iterator, o_fallback_word) = grammar.generator.generate(word, realbeam, delta, &grammar.estimates, rulemask, budget.clone(), consistency_check);
+        let candidates = budget.clone();
+        let word_iterator = word_iterator.take_while(move |_| candidates.candidate());
 
         let mut enumerated_words = 0;
 
-        let o_fallback_word = word_iterator.peek().cloned();
         let o_parse_tree = word_iterator.filter_map(
             |cfg_deriv| {
                 enumerated_words += 1;
@@ -121,10 +177,10 @@ where
                     => DebugResult::Parse(t.cloned(), enumerated_words),
                 (None, Some(w)) => {
                     let tree = fallback::FailedParseTree::new(&w).merge(&grammar.rules);
-                    DebugResult::Fallback(tree, enumerated_words)
+                    DebugResult::Fallback(tree, enumerated_words, budget.exhausted())
                 },
                 (None, None)
-                    => DebugResult::Noparse
+                    => DebugResult::Noparse(budget.exhausted())
         };
         
         ( grammar.rules.len()
@@ -135,10 +191,23 @@ where
     }
 }
 
+/// Draws the automaton of the context-free approximation; each state is
+/// labelled by the nonterminal and component it stands for (if any).
+impl<N: Display, T: Eq + Hash + Display, W: Display> ToDot for CSRepresentation<N, T, W> {
+    fn to_dot(&self) -> String {
+        self.generator.to_dot(|q| match self.generator.state_component(q) {
+            Some((rule, component)) => format!("{}/{}", self.rules[rule as usize].head, component),
+            None => q.to_string(),
+        })
+    }
+}
+
+/// The result of `GeneratorBuilder::debug`; a fallback tree or no parse is
+/// given with the reason for giving up early if the budget was exhausted.
 pub enum DebugResult<N, T, W> {
     Parse(GornTree<PMCFGRule<N, T, W>>, usize),
-    Fallback(GornTree<PMCFGRule<N, T, W>>, usize),
-    Noparse
+    Fallback(GornTree<PMCFGRule<N, T, W>>, usize, Option<Exhaustion>),
+    Noparse(Option<Exhaustion>)
 } 
 
 impl<N, T, W> CSRepresentation<N, T, W>
@@ -148,22 +217,29 @@ where
     N: Clone,
 {
     /// Instantiates a CS representation for an `LCFRS`.
-    pub fn new<M>(grammar: M, estimates_max_width: usize) -> Self
+    /// Will return `Err` if the fanout or the arity of a rule exceeds the
+    /// range of `ComponentT` or `SuccessorT`, respectively,
+    /// or if a rule has an empty component or is not terminal-separated.
+    pub fn new<M>(grammar: M, estimates_max_width: usize) -> Result<Self, String>
     where
         M: Into<Lcfrs<N, T, W>>,
         W: Factorizable + Zero,
         N: Hash + Eq
     {
-        assert!(estimates_max_width <= u8::max_value() as usize);
         let (rules, initial) = grammar.into().destruct();
         let generator = {
             let mut rules_with_id = rules.iter().enumerate().map(|(i, r)| (i as u32, r));
-            Automaton::from_grammar(rules_with_id, initial.clone())
+            Automaton::from_grammar(rules_with_id, initial.clone())?
         };
         let rulemaskbuilder = RuleMaskBuilder::new(rules.iter(), &initial);
-        let estimates = SxOutside::from_automaton(&generator, estimates_max_width as u8);
+        let estimates = SxOutside::from_automaton(&generator, estimates_max_width);
         
-        CSRepresentation { generator, rulemaskbuilder, estimates, rules }
+        Ok(CSRepresentation { generator, rulemaskbuilder, estimates, rules })
+    }
+
+    /// The rules of the grammar.
+    pub fn rules(&self) -> &[PMCFGRule<N, T, W>] {
+        &self.rules
     }
 
     pub fn build_generator<'a>(&'a self) -> GeneratorBuilder<'a, N, T, W>
@@ -174,8 +250,9 @@ where
             grammar: self,
             beam: None,
             delta: W::zero(),
-            candidates: None,
-            root_prediction: false
+            budget: Budget::default(),
+            root_prediction: false,
+            consistency_check: true,
         }
     }
 }
@@ -215,7 +292,7 @@ impl<N, T, W> CSRepresentation<N, T, W> where T: Hash + Eq {
 #[cfg(test)]
 mod test {
     use grammars::pmcfg::{VarT, PMCFGRule, Composition};
-    use super::{CSRepresentation, Lcfrs};
+    use super::{CSRepresentation, Lcfrs, SuccessorT};
     use log_domain::LogDomain;
 
     #[test]
@@ -229,7 +306,7 @@ mod test {
         ].into_iter()
             .collect();
 
-        let cs = CSRepresentation::new(grammar.clone(), 0);
+        let cs = CSRepresentation::new(grammar.clone(), 0).unwrap();
         assert_eq!(cs.build_generator().with_fallback(&['A']).0.next(), Some(d1));
         assert_eq!(
             cs.build_generator().with_fallback(&['A', 'A']).0.next(),
@@ -237,6 +314,58 @@ mod test {
         );
     }
 
+    #[test]
+    fn long_sentences() {
+        let cs = CSRepresentation::new(lcfrs(), 0).unwrap();
+        let mut generator = cs.build_generator();
+        generator.set_beam(1);
+
+        let word = vec!['A'; 300];
+        let tree = generator.with_fallback(&word).0.next().unwrap();
+        assert_eq!(tree.values().filter(|r| r.tail.is_empty()).count(), 300);
+    }
+
+    #[test]
+    fn too_many_successors() {
+        let mut grammar = lcfrs();
+        grammar.rules[0].tail = vec!["S"; SuccessorT::max_value() as usize + 2];
+        assert!(CSRepresentation::new(grammar, 0).is_err());
+    }
+
+    #[test]
+    fn budgets() {
+        use util::budget::{Budget, Exhaustion};
+        let cs = CSRepresentation::new(lcfrs(), 0).unwrap();
+        let word = vec!['A'; 4];
+
+        let mut generator = cs.build_generator();
+        generator.set_candidates(2);
+        let (parses, _, budget) = generator.with_fallback(&word);
+        assert_eq!(parses.count(), 2);
+        assert_eq!(budget.exhausted(), Some(Exhaustion::Candidates));
+
+        generator.set_budget(Budget { pops: Some(1), ..Budget::default() });
+        let (mut parses, fallback, budget) = generator.with_fallback(&word);
+        assert!(parses.next().is_none());
+        assert!(fallback.is_some());
+        assert_eq!(budget.exhausted(), Some(Exhaustion::Pops));
+    }
+
+    #[test]
+    fn chart() {
+        let cs = CSRepresentation::new(lcfrs(), 0).unwrap();
+        let word = vec!['A'; 3];
+        let constituents = cs.build_generator().chart(&word);
+
+        // the only nonterminal is recognised for each span
+        for length in 1..=3 {
+            for i in 0..=(3 - length) {
+                assert!(constituents.iter().any(|&(l, r, _, nt, _)| (l, r, nt) == (i, i + length, Some((&"S", 0)))));
+            }
+        }
+        assert!(constituents.windows(2).all(|cs| cs[0].1 - cs[0].0 <= cs[1].1 - cs[1].0));
+    }
+
     fn lcfrs() -> Lcfrs<&'static str, char, LogDomain<f64>> {
         Lcfrs {
             init: "S",
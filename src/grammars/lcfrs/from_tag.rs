@@ -0,0 +1,301 @@
+/// This module contains the compilation of a TAG into an LCFRS of fanout at
+/// most 2.
+
+use num_traits::One;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+
+use grammars::pmcfg::{Composition, PMCFGRule, VarT};
+use grammars::tag::{Adjunction, ElementaryTree, Shape, TAG, TAGNode, TAGNonterminal};
+use super::Lcfrs;
+
+/// Compiles a `TAG` into an `Lcfrs` such that each derivation of the `TAG`
+/// corresponds to exactly one derivation of the `Lcfrs` with the same
+/// weight.
+///
+/// There is a rule for every elementary tree and every choice of the nodes
+/// and shapes of the auxiliary trees that are adjoined to it.
+/// The shape of an auxiliary tree tells which sides of its foot node have
+/// terminal symbols, so that no component of the `Lcfrs` is empty.
+/// The rule is reached by a chain rule from the nonterminal of the
+/// substitution or adjunction site, which is the same for all elementary
+/// trees with the same root label.
+/// Terminal symbols are derived by separate rules unless they make up a
+/// whole component.
+/// The resulting `Lcfrs` can thus be used for parsing with a
+/// `CSRepresentation`.
+/// Note that the number of rules for an elementary tree grows exponentially
+/// with the number of its nodes at which adjunction is possible.
+impl<N, T, W> From<TAG<N, T, W>> for Lcfrs<TAGNonterminal<N, T>, T, W>
+where
+    N: Clone + Hash + Eq,
+    T: Clone + Hash + Eq,
+    W: Clone + One,
+{
+    fn from(tag: TAG<N, T, W>) -> Self {
+        // find the labels of derivable initial trees and the shapes of
+        // derivable auxiliary trees
+        let mut substitutable: HashSet<&N> = HashSet::new();
+        let mut shapes: HashMap<&N, BTreeSet<Shape>> = HashMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for tree in tag.trees() {
+                for adjunctions in instances(tree, &substitutable, &shapes) {
+                    changed |= match instantiate(tree, &adjunctions).2 {
+                        Some(shape) => shapes.entry(tree.root_label()).or_default().insert(shape),
+                        None => substitutable.insert(tree.root_label()),
+                    };
+                }
+            }
+        }
+
+        let mut rules = Vec::new();
+        let mut terminals = Vec::new();
+        let mut known_terminals = HashSet::new();
+        for (i, tree) in tag.trees().iter().enumerate() {
+            for adjunctions in instances(tree, &substitutable, &shapes) {
+                let (composition, tail, shape) = instantiate(tree, &adjunctions);
+                let head = match shape {
+                    Some(shape) => TAGNonterminal::Adjunction(tree.root_label().clone(), shape),
+                    None => TAGNonterminal::Substitution(tree.root_label().clone()),
+                };
+                let elementary = TAGNonterminal::Elementary(i, adjunctions);
+
+                for nonterminal in &tail {
+                    if let TAGNonterminal::Terminal(ref terminal) = *nonterminal {
+                        if known_terminals.insert(terminal.clone()) {
+                            terminals.push(terminal.clone());
+                        }
+                    }
+                }
+
+                rules.push(PMCFGRule {
+                    head,
+                    tail: vec![elementary.clone()],
+                    composition: Composition::from(
+                        (0..composition.len()).map(|j| vec![VarT::Var(0, j)]).collect::<Vec<_>>()
+                    ),
+                    weight: W::one(),
+                });
+                rules.push(PMCFGRule {
+                    head: elementary,
+                    tail,
+                    composition: Composition::from(composition),
+                    weight: tree.weight().clone(),
+                });
+            }
+        }
+
+        for terminal in terminals {
+            rules.push(PMCFGRule {
+                head: TAGNonterminal::Terminal(terminal.clone()),
+                tail: Vec::new(),
+                composition: Composition::from(vec![vec![VarT::T(terminal)]]),
+                weight: W::one(),
+            });
+        }
+
+        Lcfrs {
+            rules,
+            init: TAGNonterminal::Substitution(tag.initial().clone()),
+        }
+    }
+}
+
+/// The ways to adjoin derivable auxiliary trees to `tree`, each given by the
+/// addresses of the nodes at which they are adjoined and their shapes.
+/// There is none if a substitution node cannot be filled or no auxiliary
+/// tree can be adjoined at a node with obligatory adjunction.
+fn instances<N, T, W>(
+    tree: &ElementaryTree<N, T, W>,
+    substitutable: &HashSet<&N>,
+    shapes: &HashMap<&N, BTreeSet<Shape>>,
+) -> Vec<Vec<(Vec<usize>, Shape)>>
+where
+    N: Hash + Eq,
+{
+    let mut instances = vec![Vec::new()];
+    for (address, node) in tree.tree() {
+        match *node {
+            TAGNode::Substitution(ref label) if !substitutable.contains(label) => return Vec::new(),
+            TAGNode::Inner(ref label, adjunction) if adjunction != Adjunction::Null => {
+                let mut extended = if adjunction == Adjunction::Optional {
+                    instances.clone()
+                } else {
+                    Vec::new()
+                };
+                for shape in shapes.get(label).into_iter().flat_map(|shapes| shapes) {
+                    for instance in &instances {
+                        let mut instance = instance.clone();
+                        instance.push((address.clone(), *shape));
+                        extended.push(instance);
+                    }
+                }
+                instances = extended;
+            }
+            _ => (),
+        }
+    }
+    instances
+}
+
+/// A symbol in the yield of an elementary tree.
+enum Item<T> {
+    Terminal(T),
+    Variable(usize, usize),
+    Foot,
+}
+
+/// The composition and the tail of the rule for `tree` with the given
+/// `adjunctions`, and the shape of its yield if it is an auxiliary tree.
+fn instantiate<N, T, W>(
+    tree: &ElementaryTree<N, T, W>,
+    adjunctions: &[(Vec<usize>, Shape)],
+) -> (Vec<Vec<VarT<T>>>, Vec<TAGNonterminal<N, T>>, Option<Shape>)
+where
+    N: Clone,
+    T: Clone,
+{
+    let sites = tree.sites(adjunctions);
+    let successors: BTreeMap<&Vec<usize>, (usize, Option<Shape>)> = sites
+        .iter()
+        .enumerate()
+        .map(|(k, &(address, shape))| (address, (k, shape)))
+        .collect();
+    let mut tail: Vec<TAGNonterminal<N, T>> = sites
+        .iter()
+        .map(|&(address, shape)| match (tree.tree().get(address), shape) {
+            (Some(&TAGNode::Inner(ref label, _)), Some(shape)) => TAGNonterminal::Adjunction(label.clone(), shape),
+            (Some(&TAGNode::Substitution(ref label)), None) => TAGNonterminal::Substitution(label.clone()),
+            _ => unreachable!(),
+        })
+        .collect();
+
+    let mut items = Vec::new();
+    read_yield(tree, &[], &successors, &mut items);
+    let mut components = vec![Vec::new()];
+    for item in items {
+        match item {
+            Item::Foot => components.push(Vec::new()),
+            item => components.last_mut().unwrap().push(item),
+        }
+    }
+    let shape = if components.len() == 1 {
+        None
+    } else {
+        let right = components.pop().unwrap();
+        let left = components.pop().unwrap();
+        match (left.is_empty(), right.is_empty()) {
+            (false, true) => {
+                components.push(left);
+                Some(Shape::Left)
+            }
+            (true, false) => {
+                components.push(right);
+                Some(Shape::Right)
+            }
+            (false, false) => {
+                components.push(left);
+                components.push(right);
+                Some(Shape::Wrap)
+            }
+            (true, true) => unreachable!("elementary trees have a terminal or a substitution node"),
+        }
+    };
+
+    let composition = components
+        .into_iter()
+        .map(|component| match component.as_slice() {
+            &[Item::Terminal(ref terminal)] => vec![VarT::T(terminal.clone())],
+            _ => component
+                .iter()
+                .map(|item| match *item {
+                    Item::Variable(i, j) => VarT::Var(i, j),
+                    Item::Terminal(ref terminal) => {
+                        tail.push(TAGNonterminal::Terminal(terminal.clone()));
+                        VarT::Var(tail.len() - 1, 0)
+                    }
+                    Item::Foot => unreachable!(),
+                })
+                .collect(),
+        })
+        .collect();
+
+    (composition, tail, shape)
+}
+
+/// Appends the yield of the subtree at `address` to `items`, where the
+/// yields at the `successors` are represented by variables.
+fn read_yield<N, T, W>(
+    tree: &ElementaryTree<N, T, W>,
+    address: &[usize],
+    successors: &BTreeMap<&Vec<usize>, (usize, Option<Shape>)>,
+    items: &mut Vec<Item<T>>,
+) where
+    T: Clone,
+{
+    let address = address.to_vec();
+    match *tree.tree().get(&address).unwrap() {
+        TAGNode::Terminal(ref terminal) => items.push(Item::Terminal(terminal.clone())),
+        TAGNode::Foot(_) => items.push(Item::Foot),
+        TAGNode::Substitution(_) => items.push(Item::Variable(successors[&address].0, 0)),
+        TAGNode::Inner(_, _) => {
+            let adjunction = successors.get(&address).cloned();
+            match adjunction {
+                Some((k, Some(Shape::Left))) | Some((k, Some(Shape::Wrap))) => items.push(Item::Variable(k, 0)),
+                _ => (),
+            }
+            for child in tree.tree().children(&address) {
+                read_yield(tree, &child, successors, items);
+            }
+            match adjunction {
+                Some((k, Some(Shape::Right))) => items.push(Item::Variable(k, 0)),
+                Some((k, Some(Shape::Wrap))) => items.push(Item::Variable(k, 1)),
+                _ => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+
+    #[test]
+    fn test_shapes() {
+        let tag: TAG<String, String, LogDomain<f64>> = "initial: [S]\n\
+             (S NP↓ (VP (V sleeps)))\n\
+             (NP (N John))\n\
+             (VP (ADV often) VP*)\n\
+             (VP:NA VP* (ADV soundly))\n\
+             (N:NA (A big) N* (PP (P from) NP↓))"
+            .parse()
+            .unwrap();
+        let lcfrs = Lcfrs::from(tag);
+
+        let heads: BTreeSet<String> = lcfrs.rules.iter().map(|rule| rule.head.to_string()).collect();
+        for head in &["S↓", "NP↓", "VP*left", "VP*right", "VP*wrap", "N*wrap"] {
+            assert!(heads.contains(*head), "{} is missing", head);
+        }
+        // the only auxiliary tree for N forbids adjunction at its root
+        assert!(!heads.contains("N*left"));
+
+        // two rules for each instance of an elementary tree, i.e. with each
+        // shape of VP at the inner VPs or without, and a rule for each of
+        // the terminals `sleeps`, `John`, `often` and `from`
+        assert_eq!(2 * (4 + 2 + 4 + 1 + 1) + 4, lcfrs.rules.len());
+        assert!(Lcfrs::new(lcfrs.rules.clone(), lcfrs.init.clone()).is_some());
+
+        for rule in &lcfrs.rules {
+            for component in &rule.composition {
+                assert!(
+                    component.len() == 1 || component.iter().all(VarT::is_var),
+                    "{} mixes terminals and variables",
+                    rule
+                );
+            }
+        }
+    }
+}
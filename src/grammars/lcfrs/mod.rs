@@ -1,11 +1,15 @@
-use grammars::pmcfg::PMCFGRule;
+use automata::tree_automaton::TreeAutomaton;
+use grammars::pmcfg::{PMCFG, PMCFGRule};
 use grammars::pmcfg::VarT;
+use grammars::trim::remove_unreachable;
 use std::hash::Hash;
 use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
+use std::ops::Mul;
 
 mod conversion;
 mod from_str;
+mod from_tag;
 pub mod from_discodop;
 pub mod from_rparse;
 pub mod csparsing;
@@ -59,11 +63,59 @@ impl<N, T, W> Lcfrs<N, T, W> {
     }
 }
 
+impl<N, T, W> Lcfrs<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Copy + Mul<Output = W>,
+{
+    /// Constructs an LCFRS whose derivations are the derivations of this
+    /// LCFRS that are accepted by `automaton`, like `PMCFG::intersect`.
+    /// Its nonterminals are pairs of a nonterminal and `Some` state, except
+    /// for the fresh initial nonterminal `(init, None)`, which has a copy of
+    /// each rule for `(init, q)` with a final state `q`.
+    /// Will return `None` if no derivation is accepted.
+    pub fn intersect<Q>(
+        &self,
+        automaton: &TreeAutomaton<Q, PMCFGRule<N, T, W>, W>,
+    ) -> Option<Lcfrs<(N, Option<Q>), T, W>>
+    where
+        N: Ord,
+        Q: Clone + Eq + Hash + Ord,
+    {
+        let grammar = PMCFG {
+            initial: vec![self.init.clone()],
+            rules: self.rules.clone(),
+        };
+        let PMCFG { initial, rules } = grammar.intersect(automaton);
+        if initial.is_empty() {
+            return None;
+        }
+
+        let lift = |&(ref n, ref q): &(N, Q)| (n.clone(), Some(q.clone()));
+        let finals: Vec<_> = initial.iter().map(&lift).collect();
+        let init = (self.init.clone(), None);
+        let mut rules: Vec<_> = rules.iter().map(|rule| rule.map_nonterminals(&lift)).collect();
+        let copies: Vec<_> = rules
+            .iter()
+            .filter(|rule| finals.contains(&rule.head))
+            .map(|rule| PMCFGRule { head: init.clone(), ..rule.clone() })
+            .collect();
+        rules.extend(copies);
+
+        // the rules for `(init, q)` are only kept if `init` occurs on some
+        // right-hand side
+        Lcfrs::new(remove_unreachable(rules, &[init.clone()], nonterminals), init)
+    }
+}
+
 impl<N: Display, T: Display, W: Display> Display for Lcfrs<N, T, W> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         let mut buffer = "".to_string();
 
-        buffer.push_str(format!("initial: [{}]\n\n", self.init).as_str());
+        // quoted like the heads of the rules, such that nonterminals that
+        // contain delimiters, e.g. `-` in `NP-SBJ`, can be read again
+        buffer.push_str(format!("initial: [\"{}\"]\n\n", self.init).as_str());
 
         for r in &self.rules {
             buffer.push_str(format!("{}\n", r).as_str());
@@ -73,6 +125,11 @@ impl<N: Display, T: Display, W: Display> Display for Lcfrs<N, T, W> {
     }
 }
 
+/// The left-hand side and the nonterminals of the right-hand side of a rule.
+fn nonterminals<N, T, W>(rule: &PMCFGRule<N, T, W>) -> (&N, Vec<&N>) {
+    (&rule.head, rule.tail.iter().collect())
+}
+
 /// Reads the fanout off `PMCFGRules`.
 /// Will return `None` if rule fanouts are inconsistent for same nonterminals.
 fn read_fanouts<'a, R, N, T, W>(rules: R) -> Option<HashMap<&'a N, usize>>
@@ -191,6 +248,22 @@ mod tests {
         )
     }
 
+    #[test]
+    fn display_from_str() {
+        let rules = vec![
+            PMCFGRule {
+                weight: 1.0,
+                head: String::from("NP-SBJ"),
+                tail: vec![],
+                composition: Composition { composition: vec![vec![VarT::T(String::from("a"))]] },
+            },
+        ];
+        let lcfrs: Lcfrs<String, String, f64> = Lcfrs::new(rules, String::from("NP-SBJ")).unwrap();
+        let parsed: Lcfrs<String, String, f64> = lcfrs.to_string().parse().unwrap();
+        assert_eq!(lcfrs.to_string(), parsed.to_string());
+        assert_eq!("NP-SBJ", parsed.init);
+    }
+
     fn mcfg_rules() -> Vec<PMCFGRule<usize, usize, ()>> {
         vec![
             PMCFGRule {
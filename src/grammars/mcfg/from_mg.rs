@@ -0,0 +1,349 @@
+/// This module contains the compilation of a minimalist grammar into an
+/// MCFG.
+
+use num_traits::One;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use grammars::mg::{Feature, MG, MGNonterminal};
+use grammars::pmcfg::{Composition, PMCFGRule, VarT};
+use super::Mcfg;
+
+/// Compiles an `MG` into an `Mcfg` in the manner of Michaelis (2001), such
+/// that each derivation of the `MG` corresponds to exactly one derivation
+/// of the `Mcfg` with the same weight.
+///
+/// The nonterminals are the types of expressions, i.e. whether they are
+/// lexical and which features are left on their chains.
+/// Each lexical item becomes a terminal rule and each application of
+/// `merge` or `move` to types becomes a rule with weight one.
+/// By the shortest move constraint there are only finitely many types.
+///
+/// Since moving chains are put in front of their heads, the components of
+/// a type are ordered such that every rule visits the components of its
+/// successors from left to right, as is needed by the construction of a
+/// `TreeStackAutomaton`.
+/// Only the types and orders that are reachable from the initial
+/// nonterminal are kept.
+impl<T, F, W> From<MG<T, F, W>> for Mcfg<MGNonterminal<F>, T, W>
+where
+    T: Clone,
+    F: Clone + Ord + Hash,
+    W: Clone + One,
+{
+    fn from(mg: MG<T, F, W>) -> Self {
+        // the rules for types whose head chain is followed by the sorted
+        // moving chains
+        let mut type_rules = Vec::new();
+        let mut types = Vec::new();
+        let mut known_types = HashSet::new();
+
+        for item in mg.lexicon() {
+            let head = MGNonterminal::Lexical(item.features.clone());
+            type_rules.push(PMCFGRule {
+                head: head.clone(),
+                tail: Vec::new(),
+                composition: Composition::from(vec![item.word.iter().cloned().map(VarT::T).collect::<Vec<_>>()]),
+                weight: item.weight.clone(),
+            });
+            if known_types.insert(head.clone()) {
+                types.push(head);
+            }
+        }
+
+        // each pair of types is combined once the later one of them is
+        // reached
+        let mut i = 0;
+        while i < types.len() {
+            let mut derived = Vec::new();
+            for j in 0..=i {
+                derived.extend(merge(&types[i], &types[j]));
+                if j != i {
+                    derived.extend(merge(&types[j], &types[i]));
+                }
+            }
+            derived.extend(move_(&types[i]));
+
+            for (head, tail, composition) in derived {
+                if known_types.insert(head.clone()) {
+                    types.push(head.clone());
+                }
+                type_rules.push(PMCFGRule {
+                    head,
+                    tail,
+                    composition: Composition::from(composition),
+                    weight: W::one(),
+                });
+            }
+            i += 1;
+        }
+
+        let mut rule_map: HashMap<&MGNonterminal<F>, Vec<&PMCFGRule<MGNonterminal<F>, T, W>>> = HashMap::new();
+        for rule in &type_rules {
+            rule_map.entry(&rule.head).or_insert_with(Vec::new).push(rule);
+        }
+
+        let mut rules = Vec::new();
+        let mut agenda = Vec::new();
+        let mut known_orders = HashSet::new();
+
+        let complete = vec![Feature::Category(mg.initial().clone())];
+        for nonterminal in &types {
+            let is_complete = match *nonterminal {
+                MGNonterminal::Lexical(ref features) => *features == complete,
+                MGNonterminal::Derived(ref chains, _) => chains.len() == 1 && chains[0] == complete,
+                MGNonterminal::Initial => false,
+            };
+            if is_complete {
+                rules.push(PMCFGRule {
+                    head: MGNonterminal::Initial,
+                    tail: vec![nonterminal.clone()],
+                    composition: Composition::from(vec![vec![VarT::Var(0, 0)]]),
+                    weight: W::one(),
+                });
+                known_orders.insert((nonterminal, vec![0]));
+                agenda.push((nonterminal, vec![0]));
+            }
+        }
+
+        while let Some((nonterminal, order)) = agenda.pop() {
+            for rule in rule_map.get(nonterminal).unwrap_or(&Vec::new()) {
+                let composition: Vec<Vec<VarT<T>>> = order
+                    .iter()
+                    .map(|&j| rule.composition.composition[j].clone())
+                    .collect();
+
+                // the components of each successor in the order of their
+                // first occurrence
+                let mut orders = vec![Vec::new(); rule.tail.len()];
+                for symbol in composition.iter().flat_map(|component| component) {
+                    if let VarT::Var(i, j) = *symbol {
+                        if !orders[i].contains(&j) {
+                            orders[i].push(j);
+                        }
+                    }
+                }
+
+                let composition: Vec<Vec<VarT<T>>> = composition
+                    .into_iter()
+                    .map(|component| {
+                        component
+                            .into_iter()
+                            .map(|symbol| match symbol {
+                                VarT::Var(i, j) => VarT::Var(i, orders[i].iter().position(|&k| k == j).unwrap()),
+                                terminal => terminal,
+                            })
+                            .collect()
+                    })
+                    .collect();
+
+                let tail = rule.tail.iter().zip(&orders).map(|(successor, o)| reorder(successor, o)).collect();
+                for (successor, o) in rule.tail.iter().zip(orders) {
+                    if known_orders.insert((successor, o.clone())) {
+                        agenda.push((successor, o));
+                    }
+                }
+
+                rules.push(PMCFGRule {
+                    head: reorder(nonterminal, &order),
+                    tail,
+                    composition: Composition::from(composition),
+                    weight: rule.weight.clone(),
+                });
+            }
+        }
+
+        Mcfg::new(rules, MGNonterminal::Initial)
+    }
+}
+
+type DerivedRule<F, T> = (MGNonterminal<F>, Vec<MGNonterminal<F>>, Vec<Vec<VarT<T>>>);
+
+/// The nonterminal for a type whose components are put in the given
+/// order.
+fn reorder<F: Clone>(nonterminal: &MGNonterminal<F>, order: &[usize]) -> MGNonterminal<F> {
+    match *nonterminal {
+        MGNonterminal::Derived(ref chains, head) => MGNonterminal::Derived(
+            order.iter().map(|&j| chains[j].clone()).collect(),
+            order.iter().position(|&j| j == head).unwrap(),
+        ),
+        _ => nonterminal.clone(),
+    }
+}
+
+/// Whether the type is lexical, and the features of its chains, starting
+/// with the head chain.
+fn chains<F>(nonterminal: &MGNonterminal<F>) -> Option<(bool, &[Vec<Feature<F>>])> {
+    match *nonterminal {
+        MGNonterminal::Lexical(ref features) => Some((true, ::std::slice::from_ref(features))),
+        MGNonterminal::Derived(ref chains, _) => Some((false, &chains[..])),
+        MGNonterminal::Initial => None,
+    }
+}
+
+/// The rule for merging an expression of type `selector` with one of type
+/// `selected`, if their first features match.
+/// The selected expression is a complement to the right of a lexical head,
+/// a specifier to the left of a derived head, or a new moving chain if it
+/// has features left.
+fn merge<F, T>(selector: &MGNonterminal<F>, selected: &MGNonterminal<F>) -> Option<DerivedRule<F, T>>
+where
+    F: Clone + Ord,
+{
+    let (lexical, s_chains) = chains(selector)?;
+    let (_, t_chains) = chains(selected)?;
+    match (s_chains[0].first(), t_chains[0].first()) {
+        (Some(&Feature::Selector(ref x)), Some(&Feature::Category(ref y))) if x == y => (),
+        _ => return None,
+    }
+
+    let mut moving: Vec<_> = s_chains[1..]
+        .iter()
+        .enumerate()
+        .map(|(k, chain)| (chain.clone(), vec![VarT::Var(0, k + 1)]))
+        .chain(
+            t_chains[1..]
+                .iter()
+                .enumerate()
+                .map(|(k, chain)| (chain.clone(), vec![VarT::Var(1, k + 1)]))
+        )
+        .collect();
+    let rest = &t_chains[0][1..];
+    let head_string = if !rest.is_empty() {
+        moving.push((rest.to_vec(), vec![VarT::Var(1, 0)]));
+        vec![VarT::Var(0, 0)]
+    } else if lexical {
+        vec![VarT::Var(0, 0), VarT::Var(1, 0)]
+    } else {
+        vec![VarT::Var(1, 0), VarT::Var(0, 0)]
+    };
+
+    let (head, composition) = derive(s_chains[0][1..].to_vec(), head_string, moving)?;
+    Some((head, vec![selector.clone(), selected.clone()], composition))
+}
+
+/// The rule for moving the chain of an expression of type `nonterminal`
+/// whose licensee matches the first feature of the head, if there is one.
+/// The chain lands in the specifier to the left of the head unless it has
+/// features left, in which case it keeps moving.
+fn move_<F, T>(nonterminal: &MGNonterminal<F>) -> Option<DerivedRule<F, T>>
+where
+    F: Clone + Ord,
+{
+    let (_, s_chains) = chains(nonterminal)?;
+    let f = match s_chains[0].first() {
+        Some(&Feature::Licensor(ref f)) => f,
+        _ => return None,
+    };
+    let k = s_chains.iter().position(|chain| match chain.first() {
+        Some(&Feature::Licensee(ref g)) => f == g,
+        _ => false,
+    })?;
+
+    let mut moving: Vec<_> = s_chains
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|&(l, _)| l != k)
+        .map(|(l, chain)| (chain.clone(), vec![VarT::Var(0, l)]))
+        .collect();
+    let rest = &s_chains[k][1..];
+    let head_string = if rest.is_empty() {
+        vec![VarT::Var(0, k), VarT::Var(0, 0)]
+    } else {
+        moving.push((rest.to_vec(), vec![VarT::Var(0, k)]));
+        vec![VarT::Var(0, 0)]
+    };
+
+    let (head, composition) = derive(s_chains[0][1..].to_vec(), head_string, moving)?;
+    Some((head, vec![nonterminal.clone()], composition))
+}
+
+/// The type and composition of a derived expression with the given head
+/// and moving chains, unless the head has no features left or the shortest
+/// move constraint is violated.
+fn derive<F, T>(
+    head_features: Vec<Feature<F>>,
+    head_string: Vec<VarT<T>>,
+    mut moving: Vec<(Vec<Feature<F>>, Vec<VarT<T>>)>,
+) -> Option<(MGNonterminal<F>, Vec<Vec<VarT<T>>>)>
+where
+    F: Ord,
+{
+    if head_features.is_empty() {
+        return None;
+    }
+    // chains are sorted by their features such that equal types have equal
+    // nonterminals; chains with the same first feature become adjacent
+    moving.sort_by(|a, b| a.0.cmp(&b.0));
+    if moving.windows(2).any(|pair| pair[0].0.first() == pair[1].0.first()) {
+        return None;
+    }
+
+    let mut chains = vec![head_features];
+    let mut composition = vec![head_string];
+    for (features, string) in moving {
+        chains.push(features);
+        composition.push(string);
+    }
+    Some((MGNonterminal::Derived(chains, 0), composition))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grammars::mg::LexicalItem;
+    use grammars::pmcfg::PMCFG;
+
+    fn features(s: &str) -> Vec<Feature<String>> {
+        let item: LexicalItem<String, String, f64> = format!("ε :: {}", s).parse().unwrap();
+        item.features
+    }
+
+    #[test]
+    fn test_from_mg() {
+        let mg: MG<String, String, f64> = "initial: [c]\n\
+                                           who :: d -wh\n\
+                                           John :: d\n\
+                                           sleeps :: =d v\n\
+                                           ε :: =v +wh c\n\
+                                           ε :: =v c"
+            .parse()
+            .unwrap();
+        let (rules, initial) = Mcfg::from(mg).destruct();
+        assert_eq!(MGNonterminal::Initial, initial);
+
+        // 5 lexical items, 4 merges, 1 move and 1 initial rule; the merge of
+        // `ε :: =v c` with `who` is not reachable
+        assert_eq!(5 + 4 + 1 + 1, rules.len());
+
+        // `who` is moved in front of the head, so the components of its
+        // type are swapped
+        let moving = MGNonterminal::Derived(vec![features("-wh"), features("+wh c")], 1);
+        let rule = rules.iter().find(|rule| rule.tail == vec![moving.clone()]).unwrap();
+        assert_eq!(
+            Composition::from(vec![vec![VarT::Var(0, 0), VarT::Var(0, 1)]]),
+            rule.composition
+        );
+        assert!(rules.iter().any(|rule| rule.head == moving));
+
+        assert!(Mcfg::from_pmcfg(PMCFG { initial: vec![initial], rules }).is_some());
+    }
+
+    #[test]
+    fn test_shortest_move_constraint() {
+        let selector = MGNonterminal::Derived(vec![features("=d +wh v"), features("-wh")], 0);
+        let selected = MGNonterminal::Lexical(features("d -wh"));
+        assert_eq!(None, merge::<_, String>(&selector, &selected));
+
+        let selected = MGNonterminal::Lexical(features("d -case"));
+        assert_eq!(
+            Some((
+                MGNonterminal::Derived(vec![features("+wh v"), features("-case"), features("-wh")], 0),
+                vec![selector.clone(), selected.clone()],
+                vec![vec![VarT::Var(0, 0)], vec![VarT::Var(1, 0)], vec![VarT::Var(0, 1)]],
+            )),
+            merge::<_, String>(&selector, &selected)
+        );
+    }
+}
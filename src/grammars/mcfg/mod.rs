@@ -1,6 +1,8 @@
 use grammars::pmcfg::{PMCFG, PMCFGRule};
 use grammars::lcfrs::Lcfrs;
 
+mod from_mg;
+
 /// A mutliple context-free grammar.
 #[derive(Clone, Debug)]
 pub struct Mcfg<N, T, W> {
@@ -0,0 +1,93 @@
+use num_traits::One;
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use grammars::mg::{Feature, LexicalItem, MG};
+use util::parsing::initial_rule_grammar_from_str;
+
+impl<T, F, W> FromStr for MG<T, F, W>
+where
+    T: FromStr,
+    T::Err: Debug,
+    F: FromStr,
+    F::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut initials, lexicon) = initial_rule_grammar_from_str(s)?;
+        if initials.len() != 1 {
+            Err("multiple initial categories".to_owned())
+        } else {
+            Ok(MG::new(initials.remove(0), lexicon))
+        }
+    }
+}
+
+/// Parses a lexical item of the form `⟨words⟩ :: ⟨features⟩`, followed by an
+/// optional weight, e.g. `sees :: =d =d v # 0.5`.
+/// The words are separated by whitespace and `ε` stands for the empty word.
+/// Selectors, licensors and licensees are prefixed with `=`, `+` and `-`,
+/// respectively; features without a prefix are categories.
+impl<T, F, W> FromStr for LexicalItem<T, F, W>
+where
+    T: FromStr,
+    T::Err: Debug,
+    F: FromStr,
+    F::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let item = s.splitn(2, '%').next().unwrap();
+        let mut parts = item.splitn(2, '#');
+        let mut sides = parts.next().unwrap().splitn(2, "::");
+        let word_s = sides.next().unwrap();
+        let features_s = match sides.next() {
+            Some(features_s) => features_s,
+            None => return Err(format!("Could not parse {}: missing '::'", s)),
+        };
+
+        let mut word = Vec::new();
+        for t in word_s.split_whitespace().filter(|&t| t != "ε") {
+            word.push(t.parse().map_err(|e| format!("Could not parse {}: {:?}", s, e))?);
+        }
+
+        let mut features = Vec::new();
+        for f in features_s.split_whitespace() {
+            features.push(parse_feature(f).map_err(|e| format!("Could not parse {}: {}", s, e))?);
+        }
+        if features.is_empty() {
+            return Err(format!("Could not parse {}: no features", s));
+        }
+
+        let weight = match parts.next().map(|w| w.trim()) {
+            Some(weight_s) => weight_s.parse().map_err(|e| format!("Could not parse {}: {:?}", s, e))?,
+            None => W::one(),
+        };
+
+        Ok(LexicalItem { word, features, weight })
+    }
+}
+
+fn parse_feature<F>(s: &str) -> Result<Feature<F>, String>
+where
+    F: FromStr,
+    F::Err: Debug,
+{
+    let name = if s.starts_with(|c: char| "=+-".contains(c)) { &s[1..] } else { s };
+    if name.is_empty() {
+        return Err(format!("the feature {} has no name", s));
+    }
+    let name = name.parse().map_err(|e| format!("{:?}", e))?;
+    Ok(match s.as_bytes()[0] {
+        b'=' => Feature::Selector(name),
+        b'+' => Feature::Licensor(name),
+        b'-' => Feature::Licensee(name),
+        _ => Feature::Category(name),
+    })
+}
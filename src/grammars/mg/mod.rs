@@ -0,0 +1,312 @@
+use std::borrow::Borrow;
+use std::collections::BTreeSet;
+use std::fmt;
+
+use grammars::pmcfg::{PMCFGRule, VarT};
+use util::tree::{child_address, GornTree};
+
+mod from_str;
+
+/// A syntactic feature of a minimalist grammar.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum Feature<F> {
+    /// a category `x`
+    Category(F),
+    /// a selector `=x`, which is checked by merging with a category `x`
+    Selector(F),
+    /// a licensor `+f`, which is checked by moving a licensee `-f`
+    Licensor(F),
+    /// a licensee `-f`
+    Licensee(F),
+}
+
+impl<F: fmt::Display> fmt::Display for Feature<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Feature::Category(ref x) => write!(f, "{}", x),
+            Feature::Selector(ref x) => write!(f, "={}", x),
+            Feature::Licensor(ref x) => write!(f, "+{}", x),
+            Feature::Licensee(ref x) => write!(f, "-{}", x),
+        }
+    }
+}
+
+/// An entry of the lexicon of a weighted minimalist grammar, e.g.
+/// `sees :: =d =d v # 0.5`; the word may be empty, which is written `ε`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub struct LexicalItem<T, F, W> {
+    pub word: Vec<T>,
+    pub features: Vec<Feature<F>>,
+    pub weight: W,
+}
+
+impl<T: fmt::Display, F: fmt::Display, W: fmt::Display> fmt::Display for LexicalItem<T, F, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_item(f, &self.word, &self.features)?;
+        write!(f, "  # {}", self.weight)
+    }
+}
+
+fn fmt_item<T, F>(f: &mut fmt::Formatter, word: &[T], features: &[Feature<F>]) -> fmt::Result
+where
+    T: fmt::Display,
+    F: fmt::Display,
+{
+    if word.is_empty() {
+        write!(f, "ε")?;
+    }
+    let words: Vec<String> = word.iter().map(|t| t.to_string()).collect();
+    write!(f, "{} ::", words.join(" "))?;
+    for feature in features {
+        write!(f, " {}", feature)?;
+    }
+    Ok(())
+}
+
+/// A weighted minimalist grammar (MG) in the sense of Stabler (1997) with a
+/// lexicon and a category that complete derivations must have.
+/// Expressions are built by `merge` and `move`, where the latter obeys the
+/// shortest move constraint, i.e. no two moving subexpressions may wait for
+/// the same licensor.
+/// The weight of a derivation is the product of the weights of its lexical
+/// items.
+///
+/// An `MG` is parsed by compiling it into an `Mcfg` whose derivations are
+/// mapped back to derivation trees of the MG by `to_derivation_tree`.
+///
+/// ```
+/// # extern crate log_domain;
+/// # extern crate rustomata;
+/// use log_domain::LogDomain;
+/// use rustomata::automata::tree_stack_automaton::{to_abstract_syntax_tree, TreeStackAutomaton};
+/// use rustomata::grammars::mcfg::Mcfg;
+/// use rustomata::grammars::mg::{to_derivation_tree, MG};
+/// use rustomata::grammars::pmcfg::PMCFG;
+/// use rustomata::recognisable::Recognisable;
+///
+/// # fn main() {
+/// let mg: MG<String, String, LogDomain<f64>> = "initial: [c]\n\
+///                                               who :: d -wh\n\
+///                                               sleeps :: =d v\n\
+///                                               ε :: =v +wh c".parse().unwrap();
+///
+/// let automaton = TreeStackAutomaton::from(PMCFG::from(Mcfg::from(mg)));
+/// let word = vec![String::from("who"), String::from("sleeps")];
+/// let parse = automaton.recognise(word).next().unwrap();
+/// let derivation = to_abstract_syntax_tree(parse.0.storage.to_tree());
+///
+/// assert_eq!(
+///     "move\n\
+///      └── merge\n    \
+///          ├── ε :: =v +wh c\n    \
+///          └── merge\n        \
+///              ├── sleeps :: =d v\n        \
+///              └── who :: d -wh",
+///     to_derivation_tree(&derivation).unwrap().to_string()
+/// );
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MG<T, F, W> {
+    initial: F,
+    lexicon: Vec<LexicalItem<T, F, W>>,
+}
+
+impl<T, F, W> MG<T, F, W> {
+    pub fn new(initial: F, lexicon: Vec<LexicalItem<T, F, W>>) -> Self {
+        MG { initial, lexicon }
+    }
+
+    pub fn initial(&self) -> &F {
+        &self.initial
+    }
+
+    pub fn lexicon(&self) -> &[LexicalItem<T, F, W>] {
+        &self.lexicon
+    }
+}
+
+impl<T: fmt::Display, F: fmt::Display, W: fmt::Display> fmt::Display for MG<T, F, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "initial: [\"{}\"]\n\n", self.initial)?;
+        for item in &self.lexicon {
+            writeln!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+/// Nonterminals of the `Mcfg` that is compiled from an `MG`.
+/// Each component of a nonterminal is the string of a chain, i.e. of the
+/// head of an expression or of one of its moving subexpressions, and the
+/// nonterminal records the features that are left on each chain.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum MGNonterminal<F> {
+    /// derives the complete expressions of the initial category
+    Initial,
+    /// derives the lexical items with the given features
+    Lexical(Vec<Feature<F>>),
+    /// derives the expressions that were built by `merge` or `move`, with
+    /// the features of their chains in the order of the components and the
+    /// index of the head chain
+    Derived(Vec<Vec<Feature<F>>>, usize),
+}
+
+impl<F: fmt::Display> fmt::Display for MGNonterminal<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fmt_chain = |chain: &[Feature<F>]| -> String {
+            chain.iter().map(|feature| feature.to_string()).collect::<Vec<_>>().join(" ")
+        };
+        match *self {
+            MGNonterminal::Initial => write!(f, "initial"),
+            MGNonterminal::Lexical(ref features) => write!(f, "::{}", fmt_chain(features)),
+            MGNonterminal::Derived(ref chains, head) => {
+                let chains: Vec<String> = chains
+                    .iter()
+                    .enumerate()
+                    .map(|(i, chain)| if i == head {
+                        format!("[{}]", fmt_chain(chain))
+                    } else {
+                        fmt_chain(chain)
+                    })
+                    .collect();
+                write!(f, ":{}", chains.join(", "))
+            }
+        }
+    }
+}
+
+/// A node of the derivation tree of a minimalist grammar.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum MGNode<T, F> {
+    /// merges the selecting (first child) and the selected expression
+    /// (second child)
+    Merge,
+    /// moves a subexpression of its only child to its specifier
+    Move,
+    /// a lexical item, given by its word and features
+    Item(Vec<T>, Vec<Feature<F>>),
+}
+
+impl<T: fmt::Display, F: fmt::Display> fmt::Display for MGNode<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MGNode::Merge => write!(f, "merge"),
+            MGNode::Move => write!(f, "move"),
+            MGNode::Item(ref word, ref features) => fmt_item(f, word, features),
+        }
+    }
+}
+
+/// Nonterminals of a grammar that was obtained from an `MG`, i.e. of the
+/// `Mcfg` that was compiled from it and of the `Lcfrs` that is converted
+/// from this `Mcfg`, whose nonterminals are paired with their deleted
+/// components.
+pub trait ToMGNonterminal<F> {
+    fn to_mg_nonterminal(&self) -> &MGNonterminal<F>;
+}
+
+impl<F> ToMGNonterminal<F> for MGNonterminal<F> {
+    fn to_mg_nonterminal(&self) -> &MGNonterminal<F> {
+        self
+    }
+}
+
+impl<F> ToMGNonterminal<F> for (MGNonterminal<F>, BTreeSet<usize>) {
+    fn to_mg_nonterminal(&self) -> &MGNonterminal<F> {
+        &self.0
+    }
+}
+
+/// Reads off the derivation tree of a minimalist grammar from a derivation
+/// of the `Mcfg` that was compiled from it, or of the `Lcfrs` that is
+/// obtained from this `Mcfg`.
+pub fn to_derivation_tree<R, N, T, F, W>(derivation: &GornTree<R>) -> Result<GornTree<MGNode<T, F>>, String>
+where
+    R: Borrow<PMCFGRule<N, T, W>>,
+    N: ToMGNonterminal<F>,
+    T: Clone,
+    F: Clone,
+{
+    let mut tree = GornTree::new();
+    let mut agenda = vec![(Vec::new(), Vec::new())];
+    while let Some((address, node_address)) = agenda.pop() {
+        let rule = match derivation.get(&address) {
+            Some(rule) => rule.borrow(),
+            None => return Err(format!("the derivation has no node at {:?}", address)),
+        };
+
+        match (rule.head.to_mg_nonterminal(), rule.tail.len()) {
+            (&MGNonterminal::Initial, 1) => agenda.push((child_address(&address, 0), node_address)),
+            (&MGNonterminal::Lexical(ref features), 0) => {
+                let word = rule.composition
+                    .iter()
+                    .flat_map(|component| component)
+                    .filter_map(|symbol| match *symbol {
+                        VarT::T(ref t) => Some(t.clone()),
+                        VarT::Var(_, _) => None,
+                    })
+                    .collect();
+                tree.insert(node_address, MGNode::Item(word, features.clone()));
+            }
+            (&MGNonterminal::Derived(..), 1) => {
+                agenda.push((child_address(&address, 0), child_address(&node_address, 0)));
+                tree.insert(node_address, MGNode::Move);
+            }
+            (&MGNonterminal::Derived(..), 2) => {
+                for i in 0..2 {
+                    agenda.push((child_address(&address, i), child_address(&node_address, i)));
+                }
+                tree.insert(node_address, MGNode::Merge);
+            }
+            _ => return Err(format!("the rule at {:?} was not compiled from a minimalist grammar", address)),
+        }
+    }
+    Ok(tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let item: LexicalItem<String, String, f64> = "sees :: =d =d v  # 0.5 % comment".parse().unwrap();
+        assert_eq!(vec![String::from("sees")], item.word);
+        assert_eq!(
+            vec![
+                Feature::Selector(String::from("d")),
+                Feature::Selector(String::from("d")),
+                Feature::Category(String::from("v")),
+            ],
+            item.features
+        );
+        assert_eq!("sees :: =d =d v  # 0.5", item.to_string());
+
+        let item: LexicalItem<String, String, f64> = "ε :: =v +wh c".parse().unwrap();
+        assert!(item.word.is_empty());
+        assert_eq!(Feature::Licensor(String::from("wh")), item.features[1]);
+        assert_eq!("ε :: =v +wh c  # 1", item.to_string());
+
+        let mg: MG<String, String, f64> = "initial: [c]\n\
+                                           % a comment\n\
+                                           who :: d -wh"
+            .parse()
+            .unwrap();
+        assert_eq!("c", mg.initial());
+        assert_eq!(Feature::Licensee(String::from("wh")), mg.lexicon()[0].features[1]);
+    }
+
+    #[test]
+    fn test_invalid_items() {
+        let invalid = vec!["sees =d v", "sees ::", "sees :: =d =", "sees :: v # x"];
+
+        for item in invalid {
+            assert!(
+                item.parse::<LexicalItem<String, String, f64>>().is_err(),
+                "{} was accepted",
+                item
+            );
+        }
+    }
+}
@@ -1,4 +1,7 @@
 pub mod cfg;
 pub mod lcfrs;
+pub mod mg;
 pub mod mcfg;
 pub mod pmcfg;
+pub mod tag;
+pub mod trim;
@@ -1,9 +1,10 @@
 use nom::{IResult, digit, is_space};
 use num_traits::One;
 use std::fmt::Debug;
+use std::hash::Hash;
 use std::str::{FromStr, from_utf8};
 
-use grammars::pmcfg::{Composition, PMCFG, PMCFGRule, VarT};
+use grammars::pmcfg::{Composition, PMCFG, PMCFGRule, SynchronousPMCFG, SynchronousPMCFGRule, VarT};
 use util::parsing::*;
 
 impl<N, T, W> FromStr for PMCFG<N, T, W>
@@ -43,6 +44,46 @@ where
     }
 }
 
+impl<N, T, W> FromStr for SynchronousPMCFG<N, T, W>
+where
+    N: FromStr + Eq + Hash,
+    N::Err: Debug,
+    T: Clone + FromStr,
+    T::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (initial, rules) = initial_rule_grammar_from_str(s)?;
+
+        SynchronousPMCFG::new(initial, rules)
+    }
+}
+
+/// Parses a synchronous rule, which is written like a `PMCFGRule` with the
+/// target composition after the source composition, e.g.
+/// `A → [[T a, Var 0 0]] [[Var 0 0, T x]] (A) # 0.5`.
+impl<N, T, W> FromStr for SynchronousPMCFGRule<N, T, W>
+where
+    N: FromStr,
+    N::Err: Debug,
+    T: Clone + FromStr,
+    T::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_synchronous_rule(s.as_bytes()) {
+            IResult::Done(_, result) => Ok(result),
+            _ => Err(format!("Could not parse {}", s)),
+        }
+    }
+}
+
 fn parse_successors<N>(input: &[u8]) -> IResult<&[u8], Vec<N>>
 where
     N: FromStr,
@@ -92,6 +133,51 @@ where
     )
 }
 
+fn parse_synchronous_rule<N, T, W>(input: &[u8]) -> IResult<&[u8], SynchronousPMCFGRule<N, T, W>>
+where
+    N: FromStr,
+    N::Err: Debug,
+    T: FromStr,
+    T::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    do_parse!(
+        input,
+        head: parse_token >>
+            take_while!(is_space) >>
+            alt!(tag!("→") | tag!("->") | tag!("=>")) >>
+            take_while!(is_space) >>
+            source: parse_composition >>
+            take_while!(is_space) >>
+            target: parse_composition >>
+            take_while!(is_space) >>
+            tail: parse_successors >>
+            take_while!(is_space) >>
+            weight_o: opt!(
+                complete!(
+                    do_parse!(
+                        tag!("#") >>
+                            take_while!(is_space) >>
+                            weight_s: map_res!(is_not!(" "), from_utf8) >>
+                            weight: expr_res!(weight_s.parse()) >>
+                            (weight)
+                    )
+                )
+            ) >>
+            take_while!(is_space) >>
+            many0!(tag!("%")) >>
+            take_while!(|_| true) >>
+            (SynchronousPMCFGRule {
+                head,
+                tail,
+                source: Composition::from(source),
+                target: Composition::from(target),
+                weight: weight_o.unwrap_or_else(W::one),
+            })
+    )
+}
+
 fn parse_var_t<T>(input: &[u8]) -> IResult<&[u8], VarT<T>>
 where
     T: FromStr,
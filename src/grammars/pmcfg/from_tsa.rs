@@ -0,0 +1,373 @@
+use num_traits::One;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Mul;
+
+use automata::tree_stack_automaton::{TreeStackAutomaton, TreeStackInstruction};
+use grammars::pmcfg::{Composition, PMCFG, PMCFGRule, VarT};
+use grammars::trim::remove_unproductive;
+
+/// Nonterminals of a `PMCFG` created from a `TreeStackAutomaton`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum TSANonterminal<A> {
+    /// the root of the tree stack
+    Initial,
+    /// a node of the tree stack that is visited several times; each visit
+    /// is given by the values of the node when it is entered and when it is
+    /// left
+    Visits(Vec<(A, A)>),
+}
+
+impl<A: fmt::Display> fmt::Display for TSANonterminal<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TSANonterminal::Initial => write!(f, "initial"),
+            TSANonterminal::Visits(ref visits) => {
+                write!(f, "[")?;
+                for (i, &(ref entry, ref exit)) in visits.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "({}, {})", entry, exit)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// The transitions of a `TreeStackAutomaton`, sorted by what they do during
+/// a visit of a node.
+struct Moves<A, T, W> {
+    /// `Push` transitions by their current value
+    pushes: BTreeMap<A, Vec<(usize, A, Vec<T>, W)>>,
+    /// `Up` transitions by their current value
+    ups: BTreeMap<A, Vec<(usize, A, A, Vec<T>, W)>>,
+    /// `Down` transitions by the value of the node they return to
+    downs: BTreeMap<A, Vec<(A, A, Vec<T>, W)>>,
+    /// the values that a node may have at the end of a visit that it entered
+    /// with the given value
+    reachable: BTreeMap<A, BTreeSet<A>>,
+}
+
+impl<A, T, W> Moves<A, T, W>
+where
+    A: Clone + Ord,
+{
+    /// The values reachable from `value` by `Down` transitions that return
+    /// to the node.
+    fn changes(&self, value: &A) -> BTreeSet<A> {
+        let mut reachable = BTreeSet::new();
+        reachable.insert(value.clone());
+        let mut agenda = vec![value.clone()];
+        while let Some(a) = agenda.pop() {
+            for &(_, ref new_val, _, _) in self.downs.get(&a).into_iter().flat_map(|ds| ds) {
+                if reachable.insert(new_val.clone()) {
+                    agenda.push(new_val.clone());
+                }
+            }
+        }
+        reachable
+    }
+}
+
+/// A rule for a node, before its children are sorted into a tail; the
+/// variables refer to children by their position in the tree stack.
+type PartialRule<A, T, W> = (Vec<Vec<VarT<T>>>, BTreeMap<usize, Vec<(A, A)>>, W);
+
+/// Enumerates the ways in which a node can be visited, either as the root
+/// (`visits == None`), whose only visit may end anywhere after the first
+/// transition, or with the given `visits`.
+fn explore<A, T, W>(
+    moves: &Moves<A, T, W>,
+    visits: Option<&[(A, A)]>,
+    visit: usize,
+    value: A,
+    components: Vec<Vec<VarT<T>>>,
+    children: BTreeMap<usize, Vec<(A, A)>>,
+    weight: W,
+    rules: &mut Vec<PartialRule<A, T, W>>,
+) where
+    A: Clone + Ord,
+    T: Clone,
+    W: Clone + Mul<Output = W>,
+{
+    match visits {
+        None => if !children.is_empty() {
+            rules.push((components.clone(), children.clone(), weight.clone()));
+        },
+        Some(visits) => if value == visits[visit].1 {
+            if visit + 1 == visits.len() {
+                rules.push((components.clone(), children.clone(), weight.clone()));
+            } else {
+                let mut components = components.clone();
+                components.push(Vec::new());
+                let entry = visits[visit + 1].0.clone();
+                explore(moves, Some(visits), visit + 1, entry, components, children.clone(), weight.clone(), rules);
+            }
+        },
+    }
+
+    // the transitions that enter a child, with the value the child has
+    // afterwards
+    let mut entries = Vec::new();
+    for &(n, ref entry, ref word, ref w) in moves.pushes.get(&value).into_iter().flat_map(|ps| ps) {
+        if !children.contains_key(&n) {
+            entries.push((n, entry, word, w));
+        }
+    }
+    for &(n, ref old_val, ref entry, ref word, ref w) in moves.ups.get(&value).into_iter().flat_map(|us| us) {
+        if children.get(&n).map_or(false, |child| child.last().unwrap().1 == *old_val) {
+            entries.push((n, entry, word, w));
+        }
+    }
+
+    for (n, entry, word, w) in entries {
+        for &(ref exit, ref new_val, ref down_word, ref down_w) in moves.downs.get(&value).into_iter().flat_map(|ds| ds) {
+            if !moves.reachable[entry].contains(exit) {
+                continue;
+            }
+            let mut children = children.clone();
+            children.entry(n).or_insert_with(Vec::new).push((entry.clone(), exit.clone()));
+            let mut components = components.clone();
+            {
+                let component = components.last_mut().unwrap();
+                component.extend(word.iter().cloned().map(VarT::T));
+                component.push(VarT::Var(n, children[&n].len() - 1));
+                component.extend(down_word.iter().cloned().map(VarT::T));
+            }
+            let weight = weight.clone() * w.clone() * down_w.clone();
+            explore(moves, visits, visit, new_val.clone(), components, children, weight, rules);
+        }
+    }
+}
+
+/// The left-hand side of a rule and the nonterminals of its right-hand side.
+fn nonterminals<N, T, W>(rule: &PMCFGRule<N, T, W>) -> (&N, Vec<&N>) {
+    (&rule.head, rule.tail.iter().collect())
+}
+
+impl<A, T, W> PMCFG<TSANonterminal<A>, T, W>
+where
+    A: Clone + fmt::Debug + Hash + Ord,
+    T: Clone + Hash + Ord,
+    W: Clone + Mul<Output = W> + One + Ord,
+{
+    /// Constructs a `PMCFG` whose derivations correspond to the runs of a
+    /// `TreeStackAutomaton`, with the same weights.
+    /// Each node of the tree stack becomes a node of the derivation, and
+    /// each visit of the node becomes a component of its nonterminal.
+    ///
+    /// This requires that the number of visits of the nodes is bounded:
+    /// after an `Up` transition entered a node, the node must not get the
+    /// value that this transition expects again, neither by returning from
+    /// its children nor during further visits.
+    /// Furthermore, the initial tree stack must consist of its root only.
+    ///
+    /// Like in `TreeStackAutomaton::from`, the run without any transition,
+    /// which every `TreeStackAutomaton` has, does not correspond to a
+    /// derivation.
+    ///
+    /// ```
+    /// extern crate log_domain;
+    /// extern crate rustomata;
+    ///
+    /// use std::str::FromStr;
+    /// use log_domain::LogDomain;
+    /// use rustomata::automata::tree_stack_automaton::TreeStackAutomaton;
+    /// use rustomata::grammars::pmcfg::PMCFG;
+    /// use rustomata::recognisable::Recognisable;
+    ///
+    /// # fn main() {
+    /// let grammar: PMCFG<String, String, LogDomain<f64>> = PMCFG::from_str(
+    ///     "initial: [S]\n\n\
+    ///      S → [[Var 0 0, Var 0 1]] (A) # 1\n\
+    ///      A → [[T a, Var 0 0], [T b, Var 0 1]] (A) # 0.5\n\
+    ///      A → [[], []] () # 0.5"
+    /// ).unwrap();
+    /// let automaton = TreeStackAutomaton::from(grammar);
+    ///
+    /// let grammar = PMCFG::from_tree_stack_automaton(&automaton).unwrap();
+    /// // the nonterminals of A have two components, one for each visit
+    /// assert!(grammar.rules.iter().all(|r| r.composition.len() <= 2));
+    ///
+    /// let word: Vec<String> = vec!["a", "a", "b", "b"].into_iter().map(String::from).collect();
+    /// let run = TreeStackAutomaton::from(grammar).recognise(word).next().unwrap();
+    /// assert!(((run.0).weight.value() - 0.125).abs() < 1e-9);
+    /// # }
+    /// ```
+    pub fn from_tree_stack_automaton(automaton: &TreeStackAutomaton<A, T, W>) -> Result<Self, String> {
+        let (initial_tree, _) = automaton.initial().to_tree();
+        if initial_tree.len() != 1 {
+            return Err(String::from("the initial tree stack must consist of its root only"));
+        }
+        let root = initial_tree.get(&Vec::new()).unwrap().clone();
+
+        let mut moves = Moves {
+            pushes: BTreeMap::new(),
+            ups: BTreeMap::new(),
+            downs: BTreeMap::new(),
+            reachable: BTreeMap::new(),
+        };
+        let mut entries = BTreeSet::new();
+        let mut reentries = Vec::new();
+        for t in automaton.list_transitions() {
+            match t.instruction {
+                TreeStackInstruction::Push { n, current_val, new_val } => {
+                    entries.insert(new_val.clone());
+                    moves.pushes.entry(current_val).or_insert_with(Vec::new).push((n, new_val, t.word, t.weight));
+                }
+                TreeStackInstruction::Up { n, current_val, old_val, new_val } => {
+                    entries.insert(new_val.clone());
+                    reentries.push((old_val.clone(), new_val.clone()));
+                    moves.ups.entry(current_val).or_insert_with(Vec::new).push((n, old_val, new_val, t.word, t.weight));
+                }
+                TreeStackInstruction::Down { current_val, old_val, new_val } => {
+                    moves.downs.entry(old_val).or_insert_with(Vec::new).push((current_val, new_val, t.word, t.weight));
+                }
+            }
+        }
+        for entry in entries {
+            let reachable = moves.changes(&entry);
+            moves.reachable.insert(entry, reachable);
+        }
+
+        // the values that a node may have after one of its visits began
+        // with `value`, including all further visits
+        let mut later: BTreeMap<A, BTreeSet<A>> = BTreeMap::new();
+        for (_, value) in &reentries {
+            let mut reachable = BTreeSet::new();
+            let mut agenda = vec![value.clone()];
+            while let Some(a) = agenda.pop() {
+                for b in moves.changes(&a) {
+                    if reachable.insert(b.clone()) {
+                        for &(ref old_val, ref new_val) in &reentries {
+                            if *old_val == b {
+                                agenda.push(new_val.clone());
+                            }
+                        }
+                    }
+                }
+            }
+            later.insert(value.clone(), reachable);
+        }
+        for (old_val, new_val) in &reentries {
+            if later[new_val].contains(old_val) {
+                return Err(format!(
+                    "the number of visits of a node is not bounded: a node that is entered with {:?} \
+                     may be left with {:?} and then entered with {:?} again",
+                    new_val, old_val, new_val
+                ));
+            }
+        }
+
+        let mut rules = Vec::new();
+        let mut agenda: Vec<TSANonterminal<A>> = vec![TSANonterminal::Initial];
+        let mut seen: BTreeSet<_> = agenda.iter().cloned().collect();
+        while let Some(head) = agenda.pop() {
+            let mut partial_rules = Vec::new();
+            match head {
+                TSANonterminal::Initial => {
+                    let components = vec![Vec::new()];
+                    explore(&moves, None, 0, root.clone(), components, BTreeMap::new(), W::one(), &mut partial_rules);
+                }
+                TSANonterminal::Visits(ref visits) => {
+                    let components = vec![Vec::new()];
+                    let entry = visits[0].0.clone();
+                    explore(&moves, Some(visits), 0, entry, components, BTreeMap::new(), W::one(), &mut partial_rules);
+                }
+            }
+
+            for (components, children, weight) in partial_rules {
+                let positions: BTreeMap<usize, usize> = children.keys().enumerate().map(|(i, &n)| (n, i)).collect();
+                let composition = components
+                    .into_iter()
+                    .map(|component| {
+                        component
+                            .into_iter()
+                            .map(|symbol| match symbol {
+                                VarT::Var(n, j) => VarT::Var(positions[&n], j),
+                                VarT::T(t) => VarT::T(t),
+                            })
+                            .collect()
+                    })
+                    .collect();
+                let tail: Vec<_> = children.into_iter().map(|(_, visits)| TSANonterminal::Visits(visits)).collect();
+                for nonterminal in &tail {
+                    if seen.insert(nonterminal.clone()) {
+                        agenda.push(nonterminal.clone());
+                    }
+                }
+                rules.push(PMCFGRule {
+                    head: head.clone(),
+                    tail,
+                    composition: Composition { composition },
+                    weight,
+                });
+            }
+        }
+
+        Ok(PMCFG {
+            initial: vec![TSANonterminal::Initial],
+            rules: remove_unproductive(rules, nonterminals),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_unbounded_visits() {
+        // returns from the child with 2 and enters it again with 1, which
+        // can be left with 2 again
+        let automaton: TreeStackAutomaton<String, String, LogDomain<f64>> = TreeStackAutomaton::from_str(
+            "initial: 0\n\n\
+             Transition [\"a\"] (Push 0 0 1) # 1\n\
+             Transition [] (Down 1 0 0) # 1\n\
+             Transition [\"b\"] (Up 0 0 1 1) # 1",
+        ).unwrap();
+        assert!(PMCFG::from_tree_stack_automaton(&automaton).is_err());
+    }
+
+    #[test]
+    fn test_copying_visits() {
+        // the only child is visited twice and reads an `a` each time
+        let automaton: TreeStackAutomaton<String, String, LogDomain<f64>> = TreeStackAutomaton::from_str(
+            "initial: 0\n\n\
+             Transition [] (Push 0 0 1) # 0.5\n\
+             Transition [\"a\"] (Down 1 0 2) # 1\n\
+             Transition [] (Up 0 2 1 3) # 1\n\
+             Transition [\"a\"] (Down 3 2 4) # 0.5",
+        ).unwrap();
+        let grammar = PMCFG::from_tree_stack_automaton(&automaton).unwrap();
+
+        // the root may stop after each of the visits of its child, and the
+        // child has rules for one and for two visits
+        assert_eq!(4, grammar.rules.len());
+        let visits = vec![("1".to_string(), "1".to_string()), ("3".to_string(), "3".to_string())];
+        let rule = grammar
+            .rules
+            .iter()
+            .find(|r| r.tail == vec![TSANonterminal::Visits(visits.clone())])
+            .unwrap();
+        assert_eq!(TSANonterminal::Initial, rule.head);
+        assert_eq!(
+            vec![vec![
+                VarT::Var(0, 0),
+                VarT::T("a".to_string()),
+                VarT::Var(0, 1),
+                VarT::T("a".to_string()),
+            ]],
+            rule.composition.composition
+        );
+        assert!((rule.weight.value() - 0.25).abs() < 1e-9);
+
+        let child = grammar.rules.iter().find(|r| r.head == TSANonterminal::Visits(visits.clone())).unwrap();
+        assert_eq!(vec![Vec::<VarT<String>>::new(), Vec::new()], child.composition.composition);
+    }
+}
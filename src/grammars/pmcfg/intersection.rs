@@ -0,0 +1,182 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::Mul;
+
+use automata::tree_automaton::{TreeAutomaton, TreeTransition};
+use grammars::pmcfg::{PMCFG, PMCFGRule};
+
+impl<N, T, W> PMCFG<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Copy + Mul<Output = W>,
+{
+    /// Constructs a PMCFG whose derivations are the derivations of this
+    /// PMCFG that are accepted by `automaton`, e.g. to enforce constraints
+    /// on derivations before parsing.
+    /// Its nonterminals are pairs of a nonterminal and a state; for each
+    /// rule `A → f(B₁, …, Bₖ)` and each transition
+    /// `(A → f(B₁, …, Bₖ))(q₁, …, qₖ) → q`, it has the rule
+    /// `(A, q) → f((B₁, q₁), …, (Bₖ, qₖ))` whose weight is the product of the
+    /// weights of the rule and of the transition.
+    /// Hence the weights of this PMCFG are preserved if `automaton` has no
+    /// other weights than `W::one()`.
+    /// Only nonterminals that occur in some derivation are kept.
+    ///
+    /// The derivations of the resulting PMCFG are mapped to derivations of
+    /// this PMCFG with `map_nonterminals(|&(ref n, _)| n.clone())`.
+    ///
+    /// ```
+    /// use rustomata::automata::tree_automaton::{TreeAutomaton, TreeTransition};
+    /// use rustomata::grammars::pmcfg::PMCFG;
+    ///
+    /// let grammar: PMCFG<char, char, usize> = "initial: [S]\n\
+    ///                                          S → [[T a, Var 0 0]] (S)\n\
+    ///                                          S → [[]] ()".parse().unwrap();
+    ///
+    /// // derivations in which the first rule is used at most once
+    /// let automaton = TreeAutomaton::new(
+    ///     vec![
+    ///         TreeTransition { symbol: grammar.rules[0].clone(), source_states: vec![0], target_state: 1, weight: 1 },
+    ///         TreeTransition { symbol: grammar.rules[1].clone(), source_states: vec![], target_state: 0, weight: 1 },
+    ///     ],
+    ///     vec![0, 1],
+    /// );
+    ///
+    /// let intersection = grammar.intersect(&automaton);
+    /// assert_eq!(vec![('S', 0), ('S', 1)], intersection.initial);
+    /// assert_eq!(2, intersection.rules.len());
+    /// ```
+    pub fn intersect<Q>(&self, automaton: &TreeAutomaton<Q, PMCFGRule<N, T, W>, W>) -> PMCFG<(N, Q), T, W>
+    where
+        Q: Clone + Eq + Hash + Ord,
+    {
+        let rules: HashMap<&PMCFGRule<N, T, W>, &PMCFGRule<N, T, W>> =
+            self.rules.iter().map(|rule| (rule, rule)).collect();
+        let transitions: Vec<(&PMCFGRule<N, T, W>, &TreeTransition<Q, PMCFGRule<N, T, W>, W>)> = automaton
+            .transitions()
+            .iter()
+            .filter_map(|transition| {
+                rules
+                    .get(&transition.symbol)
+                    .filter(|rule| rule.tail.len() == transition.source_states.len())
+                    .map(|&rule| (rule, transition))
+            })
+            .collect();
+
+        // bottom-up: the pairs of nonterminals and states that derive some
+        // derivation tree
+        let mut productive: HashSet<(&N, &Q)> = HashSet::new();
+        let mut used = vec![false; transitions.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (i, &(rule, transition)) in transitions.iter().enumerate() {
+                if !used[i] &&
+                    rule.tail
+                        .iter()
+                        .zip(&transition.source_states)
+                        .all(|pair| productive.contains(&pair))
+                {
+                    used[i] = true;
+                    productive.insert((&rule.head, &transition.target_state));
+                    changed = true;
+                }
+            }
+        }
+
+        let mut by_head: HashMap<(&N, &Q), Vec<usize>> = HashMap::new();
+        for (i, &(rule, transition)) in transitions.iter().enumerate().filter(|&(i, _)| used[i]) {
+            by_head.entry((&rule.head, &transition.target_state)).or_insert_with(Vec::new).push(i);
+        }
+
+        // top-down: the productive pairs that are reachable from an initial
+        // pair
+        let initial: Vec<(&N, &Q)> = self.initial
+            .iter()
+            .flat_map(|n| automaton.final_states().iter().map(move |q| (n, q)))
+            .filter(|pair| productive.contains(pair))
+            .collect();
+        let mut reachable: HashSet<(&N, &Q)> = initial.iter().cloned().collect();
+        let mut stack = initial.clone();
+        while let Some(pair) = stack.pop() {
+            for &i in by_head.get(&pair).unwrap_or(&Vec::new()) {
+                let (rule, transition) = transitions[i];
+                for successor in rule.tail.iter().zip(&transition.source_states) {
+                    if reachable.insert(successor) {
+                        stack.push(successor);
+                    }
+                }
+            }
+        }
+
+        let rules = transitions
+            .iter()
+            .enumerate()
+            .filter(|&(i, &(rule, transition))| {
+                used[i] && reachable.contains(&(&rule.head, &transition.target_state))
+            })
+            .map(|(_, &(rule, transition))| {
+                PMCFGRule {
+                    head: (rule.head.clone(), transition.target_state.clone()),
+                    tail: rule.tail.iter().cloned().zip(transition.source_states.iter().cloned()).collect(),
+                    composition: rule.composition.clone(),
+                    weight: rule.weight * transition.weight,
+                }
+            })
+            .collect();
+
+        PMCFG {
+            initial: initial.into_iter().map(|(n, q)| (n.clone(), q.clone())).collect(),
+            rules,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+    use num_traits::One;
+
+    #[test]
+    fn test_intersect() {
+        let grammar: PMCFG<String, String, LogDomain<f64>> = "initial: [S]\n\
+             S → [[Var 0 0, Var 1 0]] (A, A)\n\
+             A → [[T a, Var 0 0]] (A) # 0.5\n\
+             A → [[T b]] () # 0.5"
+            .parse()
+            .unwrap();
+
+        // derivations with an even number of occurrences of `a`
+        let one = LogDomain::one();
+        let parity = |rule: &PMCFGRule<String, String, LogDomain<f64>>, sources: Vec<bool>, target| {
+            TreeTransition { symbol: rule.clone(), source_states: sources, target_state: target, weight: one }
+        };
+        let automaton = TreeAutomaton::new(
+            vec![
+                parity(&grammar.rules[0], vec![false, false], false),
+                parity(&grammar.rules[0], vec![true, true], false),
+                parity(&grammar.rules[0], vec![false, true], true),
+                parity(&grammar.rules[0], vec![true, false], true),
+                parity(&grammar.rules[1], vec![false], true),
+                parity(&grammar.rules[1], vec![true], false),
+                parity(&grammar.rules[2], vec![], false),
+            ],
+            vec![false],
+        );
+
+        let intersection = grammar.intersect(&automaton);
+        assert_eq!(vec![("S".to_string(), false)], intersection.initial);
+        // the transitions to `(S, true)` are not reachable
+        assert_eq!(5, intersection.rules.len());
+        for rule in &intersection.rules {
+            let original = rule.map_nonterminals(|&(ref n, _)| n.clone());
+            assert_eq!(grammar.rules.iter().find(|r| **r == original).unwrap().weight, rule.weight);
+        }
+
+        let empty = grammar.intersect(&TreeAutomaton::new(Vec::new(), vec![false]));
+        assert!(empty.initial.is_empty());
+        assert!(empty.rules.is_empty());
+    }
+}
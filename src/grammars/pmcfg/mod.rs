@@ -5,12 +5,18 @@ use std::iter::Extend;
 use std::slice;
 use std::vec;
 
-use util::tree::GornTree;
+use util::tree::{child_address, GornTree};
 use grammars::mcfg::Mcfg;
 
 mod from_str;
+mod from_tsa;
+mod intersection;
+mod synchronous;
 pub mod negra;
 
+pub use self::from_tsa::TSANonterminal;
+pub use self::synchronous::{SynchronousPMCFG, SynchronousPMCFGRule};
+
 /// Variable or terminal symbol in a PMCFG.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
 pub enum VarT<T> {
@@ -140,6 +146,36 @@ where
     }
 }
 
+impl<N: fmt::Display, T: fmt::Display, W> PMCFGRule<N, T, W> {
+    /// A short label for the rule in a derivation tree: its left-hand side,
+    /// followed by its terminals (if there are any).
+    ///
+    /// ```
+    /// use std::str::FromStr;
+    /// use rustomata::grammars::pmcfg::PMCFGRule;
+    ///
+    /// let rule: PMCFGRule<String, String, f64> =
+    ///     PMCFGRule::from_str("A → [[T a, Var 0 0, T b], [T c, Var 0 1]] (A)").unwrap();
+    /// assert_eq!("A a b c", rule.label());
+    /// ```
+    pub fn label(&self) -> String {
+        let terminals: Vec<String> = self.composition
+            .composition
+            .iter()
+            .flat_map(|component| component.iter())
+            .filter_map(|symbol| match *symbol {
+                VarT::T(ref t) => Some(t.to_string()),
+                VarT::Var(..) => None,
+            })
+            .collect();
+        if terminals.is_empty() {
+            self.head.to_string()
+        } else {
+            format!("{} {}", self.head, terminals.join(" "))
+        }
+    }
+}
+
 /// A weighted, parallel multiple context-free grammar (PMCFG) with a set of initial nonterminal
 /// symbols and a set of PMCFG rules.
 ///
@@ -304,9 +340,7 @@ where
             match variable {
                 &VarT::Var(num_nonter, num_compon) => {
                     if !expanded_nonterminals.contains_key(&num_nonter) {
-                        let mut child_address = address.clone();
-                        child_address.push(num_nonter);
-                        let nonter_compos = evaluate_pos(term_map, child_address).composition;
+                        let nonter_compos = evaluate_pos(term_map, child_address(&address, num_nonter)).composition;
                         expanded_nonterminals.insert(num_nonter, nonter_compos);
                     }
 
@@ -1,6 +1,7 @@
 use super::*;
 use std::collections::{BTreeMap, VecDeque};
 use std::fmt;
+use util::tree::parent_address;
 
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 struct TermId {
@@ -182,16 +183,15 @@ where
                     let address = terminal_id.address;
                     let rule_label = nonterminal_map.get(&address).unwrap();
 
-                    let mut parent_address = address;
-                    let parent_number = if let None = parent_address.pop() {
-                        panic!("Terminals must have a nonterminal-only rule as their parent!");
-                    } else {
+                    let parent_number = if let Some(parent) = parent_address(&address) {
                         get_rule_number(
-                            parent_address,
+                            parent,
                             &mut rule_queue,
                             &mut rule_number_map,
                             &mut rule_counter,
                         )
+                    } else {
+                        panic!("Terminals must have a nonterminal-only rule as their parent!");
                     };
                     negra_vector.push((
                         terminal_string,
@@ -204,11 +204,9 @@ where
     }
 
     while let Some((address, rule_number)) = rule_queue.pop_front() {
-        let mut parent_address = address.clone();
-
-        if let Some(_) = parent_address.pop() {
+        if let Some(parent) = parent_address(&address) {
             let parent_number = get_rule_number(
-                parent_address,
+                parent,
                 &mut rule_queue,
                 &mut rule_number_map,
                 &mut rule_counter,
@@ -0,0 +1,373 @@
+use num_traits::One;
+use std::borrow::Borrow;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Mul;
+
+use grammars::pmcfg::{evaluate, Composition, PMCFG, PMCFGRule, VarT};
+use util::tree::GornTree;
+
+/// A rule of a weighted synchronous PMCFG, which derives a tuple of strings
+/// of the source language and a tuple of strings of the target language at
+/// the same time.
+/// The variables of both compositions refer to the same tail, but a
+/// nonterminal may have a different fanout on each side.
+///
+/// ```
+/// use std::str::FromStr;
+/// use rustomata::grammars::pmcfg::{Composition, SynchronousPMCFGRule, VarT};
+///
+/// let rule: SynchronousPMCFGRule<char, char, f64> = SynchronousPMCFGRule {
+///     head: 'P',
+///     tail: vec!['V', 'N'],
+///     source: Composition::from(vec![vec![VarT::Var(0, 0), VarT::Var(1, 0)]]),
+///     target: Composition::from(vec![vec![VarT::Var(0, 0)], vec![VarT::Var(1, 0)]]),
+///     weight: 1.0,
+/// };
+///
+/// assert_eq!(
+///     rule,
+///     SynchronousPMCFGRule::from_str("P → [[Var 0 0, Var 1 0]] [[Var 0 0], [Var 1 0]] (V, N)").unwrap()
+/// );
+/// ```
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct SynchronousPMCFGRule<N, T, W> {
+    pub head: N,
+    pub tail: Vec<N>,
+    pub source: Composition<T>,
+    pub target: Composition<T>,
+    pub weight: W,
+}
+
+impl<N: Clone, T: Clone, W: Clone> SynchronousPMCFGRule<N, T, W> {
+    /// The rule with the source composition.
+    pub fn source_rule(&self) -> PMCFGRule<N, T, W> {
+        PMCFGRule {
+            head: self.head.clone(),
+            tail: self.tail.clone(),
+            composition: self.source.clone(),
+            weight: self.weight.clone(),
+        }
+    }
+
+    /// The rule with the target composition.
+    pub fn target_rule(&self) -> PMCFGRule<N, T, W> {
+        PMCFGRule {
+            head: self.head.clone(),
+            tail: self.tail.clone(),
+            composition: self.target.clone(),
+            weight: self.weight.clone(),
+        }
+    }
+}
+
+impl<N: fmt::Display, T: fmt::Display, W: fmt::Display> fmt::Display for SynchronousPMCFGRule<N, T, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tail: Vec<String> = self.tail.iter().map(|nt| format!("\"{}\"", nt)).collect();
+        write!(
+            f,
+            "\"{}\" → {} {} ({})  # {}",
+            self.head,
+            self.source,
+            self.target,
+            tail.join(", "),
+            self.weight
+        )
+    }
+}
+
+/// A weighted synchronous PMCFG, which translates words of a source
+/// language into words of a target language.
+///
+/// A word is translated by parsing it with the PMCFG of the `source`
+/// language, e.g. with a `TreeStackAutomaton` or a `CSRepresentation`, and
+/// by evaluating the resulting derivations with the target compositions.
+///
+/// ```
+/// use std::str::FromStr;
+/// use rustomata::grammars::pmcfg::SynchronousPMCFG;
+/// use rustomata::util::tree::GornTree;
+///
+/// let grammar: SynchronousPMCFG<String, String, usize> =
+///     SynchronousPMCFG::from_str("initial: [S]\n\
+///                                 S → [[Var 0 0, T and, Var 0 1]] [[Var 0 1, T und, Var 0 0]] (A)\n\
+///                                 A → [[T cats], [T dogs]] [[T Katzen], [T Hunde]] () # 2").unwrap();
+///
+/// let source = grammar.source();
+/// let mut derivation = GornTree::new();
+/// derivation.insert(vec![], source.rules[0].clone());
+/// derivation.insert(vec![0], source.rules[1].clone());
+///
+/// let target: Vec<String> = "Hunde und Katzen".split_whitespace().map(String::from).collect();
+/// assert_eq!(Ok(vec![(target, 2)]), grammar.translate(&derivation, 1));
+/// ```
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct SynchronousPMCFG<N, T, W> {
+    pub initial: Vec<N>,
+    pub rules: Vec<SynchronousPMCFGRule<N, T, W>>,
+}
+
+impl<N: Eq + Hash, T, W> SynchronousPMCFG<N, T, W> {
+    /// Instantiates a synchronous PMCFG and checks that, separately for the
+    /// source and the target compositions,
+    /// * each nonterminal has the same fanout in all of its rules, and
+    /// * each variable refers to a successor of its rule and to one of the
+    ///   components of this successor.
+    pub fn new(initial: Vec<N>, rules: Vec<SynchronousPMCFGRule<N, T, W>>) -> Result<Self, String> {
+        check_compositions(&rules, "source", |rule| &rule.source)?;
+        check_compositions(&rules, "target", |rule| &rule.target)?;
+        Ok(SynchronousPMCFG { initial, rules })
+    }
+}
+
+/// Checks the fanouts and the variables of either the source or the target
+/// compositions of `rules`, which are selected by `side`.
+fn check_compositions<N, T, W, F>(rules: &[SynchronousPMCFGRule<N, T, W>], name: &str, side: F) -> Result<(), String>
+where
+    N: Eq + Hash,
+    F: Fn(&SynchronousPMCFGRule<N, T, W>) -> &Composition<T>,
+{
+    let mut fanouts: HashMap<&N, usize> = HashMap::new();
+    for (r, rule) in rules.iter().enumerate() {
+        let fanout = side(rule).composition.len();
+        if *fanouts.entry(&rule.head).or_insert(fanout) != fanout {
+            return Err(format!("the head of rule {} has another {} fanout than in a previous rule", r, name));
+        }
+    }
+
+    for (r, rule) in rules.iter().enumerate() {
+        for symbol in side(rule).composition.iter().flat_map(|component| component) {
+            if let VarT::Var(i, j) = *symbol {
+                match rule.tail.get(i) {
+                    None => return Err(format!("the {} composition of rule {} refers to a missing successor {}", name, r, i)),
+                    Some(successor) => match fanouts.get(successor) {
+                        Some(&fanout) if j >= fanout => {
+                            return Err(format!(
+                                "the {} composition of rule {} refers to component {} of successor {}, whose {} fanout is {}",
+                                name, r, j, i, name, fanout
+                            ))
+                        }
+                        _ => (),
+                    },
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl<N, T, W> SynchronousPMCFG<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Clone + PartialOrd,
+{
+    /// The PMCFG of the source language.
+    /// Rules that only differ in their target compositions are merged into
+    /// a single rule with the greatest of their weights.
+    pub fn source(&self) -> PMCFG<N, T, W> {
+        let mut rules: Vec<PMCFGRule<N, T, W>> = Vec::new();
+        let mut indices: HashMap<PMCFGRule<N, T, W>, usize> = HashMap::new();
+        for rule in &self.rules {
+            let source = rule.source_rule();
+            if let Some(&i) = indices.get(&source) {
+                if source.weight > rules[i].weight {
+                    rules[i].weight = source.weight;
+                }
+            } else {
+                indices.insert(source.clone(), rules.len());
+                rules.push(source);
+            }
+        }
+
+        PMCFG {
+            initial: self.initial.clone(),
+            rules,
+        }
+    }
+}
+
+impl<N, T, W> SynchronousPMCFG<N, T, W>
+where
+    N: Eq,
+    T: Clone + Ord + Hash + fmt::Display,
+    W: Copy + Ord + Mul<Output = W> + One,
+{
+    /// The `n` best translations for a derivation of the `source` PMCFG.
+    /// They are obtained by replacing each rule of the derivation with a
+    /// synchronous rule that has the same source composition, and by
+    /// evaluating the target compositions.
+    /// The weight of a translation is the weight of its synchronous
+    /// derivation.
+    pub fn translate<R>(&self, derivation: &GornTree<R>, n: usize) -> Result<Vec<(Vec<T>, W)>, String>
+    where
+        R: Borrow<PMCFGRule<N, T, W>>,
+    {
+        let mut nodes = Vec::new();
+        for (address, rule) in derivation {
+            let rule = rule.borrow();
+            let mut alternatives: Vec<&SynchronousPMCFGRule<N, T, W>> = self.rules
+                .iter()
+                .filter(|r| r.head == rule.head && r.tail == rule.tail && r.source == rule.composition)
+                .collect();
+            if alternatives.is_empty() {
+                return Err(format!("the rule at {:?} has no synchronous rule", address));
+            }
+            alternatives.sort_by(|a, b| b.weight.cmp(&a.weight));
+            nodes.push((address, alternatives));
+        }
+
+        let weight = |choice: &[usize]| {
+            choice
+                .iter()
+                .zip(&nodes)
+                .fold(W::one(), |w, (&i, &(_, ref alternatives))| w * alternatives[i].weight)
+        };
+
+        // choices of alternatives are enumerated best-first, where the
+        // successors of a choice take the next alternative at one node
+        let mut translations = Vec::new();
+        let start = vec![0; nodes.len()];
+        let mut agenda = BinaryHeap::new();
+        let mut known = HashSet::new();
+        agenda.push((weight(&start), start.clone()));
+        known.insert(start);
+
+        while let Some((w, choice)) = agenda.pop() {
+            if translations.len() >= n {
+                break;
+            }
+
+            let mut term_map = GornTree::new();
+            for (&i, &(address, ref alternatives)) in choice.iter().zip(&nodes) {
+                term_map.insert(address.clone(), alternatives[i].target.clone());
+            }
+            let word = evaluate(&term_map)
+                .into_iter()
+                .flat_map(|component| component)
+                .filter_map(|symbol| match symbol {
+                    VarT::T(t) => Some(t),
+                    VarT::Var(_, _) => None,
+                })
+                .collect();
+            translations.push((word, w));
+
+            for (j, &(_, ref alternatives)) in nodes.iter().enumerate() {
+                if choice[j] + 1 < alternatives.len() {
+                    let mut successor = choice.clone();
+                    successor[j] += 1;
+                    if known.insert(successor.clone()) {
+                        agenda.push((weight(&successor), successor));
+                    }
+                }
+            }
+        }
+
+        Ok(translations)
+    }
+
+    /// The `n` best translations for the given derivations of the `source`
+    /// PMCFG, e.g. for the `n` best parses of a word.
+    /// Translations that are obtained from several synchronous derivations
+    /// are only returned once with the greatest of their weights.
+    pub fn translate_all<I, R>(&self, derivations: I, n: usize) -> Result<Vec<(Vec<T>, W)>, String>
+    where
+        I: IntoIterator<Item = GornTree<R>>,
+        R: Borrow<PMCFGRule<N, T, W>>,
+    {
+        let mut best: HashMap<Vec<T>, W> = HashMap::new();
+        for derivation in derivations {
+            for (word, weight) in self.translate(&derivation, n)? {
+                let entry = best.entry(word).or_insert(weight);
+                if weight > *entry {
+                    *entry = weight;
+                }
+            }
+        }
+
+        let mut translations: Vec<_> = best.into_iter().collect();
+        translations.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        translations.truncate(n);
+        Ok(translations)
+    }
+}
+
+impl<N: fmt::Display, T: fmt::Display, W: fmt::Display> fmt::Display for SynchronousPMCFG<N, T, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let initial: Vec<String> = self.initial.iter().map(|nt| format!("\"{}\"", nt)).collect();
+        writeln!(f, "initial: [{}]\n", initial.join(", "))?;
+        for rule in &self.rules {
+            writeln!(f, "{}", rule)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+
+    #[test]
+    fn test_translate() {
+        let grammar: SynchronousPMCFG<String, String, LogDomain<f64>> = "initial: [S]\n\
+             S → [[Var 0 0, Var 1 0]] [[Var 1 0, Var 0 0]] (A, A)\n\
+             A → [[T a]] [[T x]] () # 0.6\n\
+             A → [[T a]] [[T y]] () # 0.4"
+            .parse()
+            .unwrap();
+
+        let source = grammar.source();
+        assert_eq!(2, source.rules.len());
+        assert_eq!(LogDomain::new(0.6).unwrap(), source.rules[1].weight);
+
+        let mut derivation = GornTree::new();
+        derivation.insert(vec![], &source.rules[0]);
+        derivation.insert(vec![0], &source.rules[1]);
+        derivation.insert(vec![1], &source.rules[1]);
+
+        let translations: Vec<(String, f64)> = grammar
+            .translate(&derivation, 3)
+            .unwrap()
+            .into_iter()
+            .map(|(word, weight)| (word.join(" "), weight.value()))
+            .collect();
+        assert_eq!(3, translations.len());
+        assert_eq!("x x", translations[0].0);
+        assert!((translations[0].1 - 0.36).abs() < 1e-9);
+        assert!((translations[1].1 - 0.24).abs() < 1e-9);
+        assert!((translations[2].1 - 0.24).abs() < 1e-9);
+
+        let unknown: PMCFGRule<String, String, LogDomain<f64>> = "A → [[T b]] ()".parse().unwrap();
+        derivation.insert(vec![1], &unknown);
+        assert!(grammar.translate(&derivation, 1).is_err());
+    }
+
+    #[test]
+    fn test_invalid_compositions() {
+        let invalid = vec![
+            // A has the target fanouts 1 and 2
+            "initial: [S]\n\
+             S → [[Var 0 0]] [[Var 0 0, Var 0 1]] (A)\n\
+             A → [[T a]] [[T x]] ()\n\
+             A → [[T b]] [[T y], [T z]] ()",
+            // the second component of A does not exist in the target
+            "initial: [S]\n\
+             S → [[Var 0 0]] [[Var 0 1]] (A)\n\
+             A → [[T a]] [[T x]] ()",
+            // there is no second successor
+            "initial: [S]\n\
+             S → [[Var 0 0]] [[Var 1 0]] (A)\n\
+             A → [[T a]] [[T x]] ()",
+        ];
+
+        for grammar in invalid {
+            assert!(
+                grammar.parse::<SynchronousPMCFG<String, String, f64>>().is_err(),
+                "{} was accepted",
+                grammar
+            );
+        }
+    }
+}
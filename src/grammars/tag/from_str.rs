@@ -0,0 +1,198 @@
+use nom::{ErrorKind, IResult, is_space};
+use num_traits::One;
+use std::fmt::Debug;
+use std::str::{FromStr, from_utf8};
+
+use grammars::tag::{Adjunction, ElementaryTree, TAG, TAGNode};
+use util::parsing::{initial_rule_grammar_from_str, unescape};
+use util::tree::GornTree;
+
+impl<N, T, W> FromStr for TAG<N, T, W>
+where
+    N: FromStr + PartialEq,
+    N::Err: Debug,
+    T: FromStr,
+    T::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut initials, trees) = initial_rule_grammar_from_str(s)?;
+        if initials.len() != 1 {
+            Err("multiple initial nonterminals".to_owned())
+        } else {
+            Ok(TAG::new(initials.remove(0), trees))
+        }
+    }
+}
+
+/// Parses an elementary tree in bracket notation, followed by an optional
+/// weight, e.g. `(S NP↓ (VP:NA (V sleeps))) # 0.5`.
+/// Leaves are substitution nodes if they are marked with `↓`, foot nodes if
+/// they are marked with `*`, and terminal symbols otherwise.
+impl<N, T, W> FromStr for ElementaryTree<N, T, W>
+where
+    N: FromStr + PartialEq,
+    N::Err: Debug,
+    T: FromStr,
+    T::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_elementary_tree(s.as_bytes()) {
+            IResult::Done(_, (tree, weight)) => ElementaryTree::new(tree, weight),
+            _ => Err(format!("Could not parse {}", s)),
+        }
+    }
+}
+
+fn parse_elementary_tree<N, T, W>(input: &[u8]) -> IResult<&[u8], (GornTree<TAGNode<N, T>>, W)>
+where
+    N: FromStr,
+    N::Err: Debug,
+    T: FromStr,
+    T::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    do_parse!(
+        input,
+        tree: parse_subtree >>
+        take_while!(is_space) >>
+        weight_o: opt!(
+            complete!(
+                do_parse!(
+                    tag!("#") >>
+                    take_while!(is_space) >>
+                    weight_s: map_res!(is_not!(" "), from_utf8) >>
+                    weight: expr_res!(weight_s.parse()) >>
+                    (weight)
+                )
+            )
+        ) >>
+        take_while!(is_space) >>
+        many0!(tag!("%")) >>
+        take_while!(|_| true) >>
+        ((tree, weight_o.unwrap_or_else(W::one)))
+    )
+}
+
+fn parse_subtree<N, T>(input: &[u8]) -> IResult<&[u8], GornTree<TAGNode<N, T>>>
+where
+    N: FromStr,
+    N::Err: Debug,
+    T: FromStr,
+    T::Err: Debug,
+{
+    alt!(
+        input,
+        do_parse!(
+            tag!("(") >>
+            take_while!(is_space) >>
+            label_s: parse_label >>
+            label: expr_res!(label_s.parse()) >>
+            adjunction: parse_adjunction >>
+            children: many0!(
+                do_parse!(
+                    take_while!(is_space) >>
+                    child: parse_subtree >>
+                    (child)
+                )
+            ) >>
+            take_while!(is_space) >>
+            tag!(")") >>
+            (inner_node(TAGNode::Inner(label, adjunction), children))
+        ) |
+        do_parse!(
+            label_s: parse_label >>
+            leaf: alt!(
+                do_parse!(
+                    tag!("↓") >>
+                    label: expr_res!(label_s.parse()) >>
+                    (TAGNode::Substitution(label))
+                ) |
+                do_parse!(
+                    tag!("*") >>
+                    label: expr_res!(label_s.parse()) >>
+                    (TAGNode::Foot(label))
+                ) |
+                do_parse!(
+                    terminal: expr_res!(label_s.parse()) >>
+                    (TAGNode::Terminal(terminal))
+                )
+            ) >>
+            (vec![(Vec::new(), leaf)].into_iter().collect())
+        )
+    )
+}
+
+/// Parses the adjunction constraint after the label of an inner node.
+fn parse_adjunction(input: &[u8]) -> IResult<&[u8], Adjunction> {
+    do_parse!(
+        input,
+        constraint: opt!(
+            complete!(
+                preceded!(
+                    tag!(":"),
+                    alt!(
+                        tag!("OA") => { |_| Adjunction::Obligatory } |
+                        tag!("NA") => { |_| Adjunction::Null }
+                    )
+                )
+            )
+        ) >>
+        (constraint.unwrap_or(Adjunction::Optional))
+    )
+}
+
+/// Parses a label, which is either delimited by `'"'` on both sides (with
+/// `'\\'` and `'"'` escaped inside, see `util::parsing::escape`) or contains neither whitespace nor any
+/// of the symbols `'('`, `')'`, `'"'`, `'↓'`, `'*'`, `':'`, `'#'`, `'%'`.
+fn parse_label(input: &[u8]) -> IResult<&[u8], String> {
+    alt!(
+        input,
+        map!(
+            map_res!(
+                delimited!(
+                    tag!("\""),
+                    escaped!(is_not!("\"\\"), '\\', one_of!("\\\"")),
+                    tag!("\"")
+                ),
+                from_utf8
+            ),
+            unescape
+        ) |
+        map!(map_res!(parse_bare_label, from_utf8), String::from)
+    )
+}
+
+fn parse_bare_label(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let end = (0..input.len())
+        .find(|&i| {
+            is_space(input[i]) || b"()\"*:#%".contains(&input[i]) || input[i..].starts_with("↓".as_bytes())
+        })
+        .unwrap_or_else(|| input.len());
+    if end == 0 {
+        IResult::Error(error_position!(ErrorKind::IsNot, input))
+    } else {
+        IResult::Done(&input[end..], &input[..end])
+    }
+}
+
+/// Puts `children` below an inner node.
+fn inner_node<N, T>(node: TAGNode<N, T>, children: Vec<GornTree<TAGNode<N, T>>>) -> GornTree<TAGNode<N, T>> {
+    let mut tree: GornTree<_> = vec![(Vec::new(), node)].into_iter().collect();
+    for (i, child) in children.into_iter().enumerate() {
+        for (address, child_node) in child {
+            let mut new_address = vec![i];
+            new_address.extend(address);
+            tree.insert(new_address, child_node);
+        }
+    }
+    tree
+}
@@ -0,0 +1,440 @@
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use grammars::pmcfg::PMCFGRule;
+use util::parsing::escape;
+use util::tree::{child_address, GornTree};
+
+mod from_str;
+
+/// Whether an auxiliary tree can be adjoined at an inner node of an
+/// elementary tree.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum Adjunction {
+    /// adjunction is allowed, but not required (written `A`)
+    Optional,
+    /// adjunction is required (written `A:OA`)
+    Obligatory,
+    /// adjunction is not allowed (written `A:NA`)
+    Null,
+}
+
+/// A node of an elementary tree.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum TAGNode<N, T> {
+    /// an inner node, written `(A …)`
+    Inner(N, Adjunction),
+    /// a leaf at which an initial tree is substituted, written `A↓`
+    Substitution(N),
+    /// the foot node of an auxiliary tree, written `A*`
+    Foot(N),
+    /// a leaf that is labelled with a terminal symbol
+    Terminal(T),
+}
+
+impl<N: fmt::Display, T: fmt::Display> fmt::Display for TAGNode<N, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TAGNode::Inner(ref label, Adjunction::Optional) => write!(f, "{}", label),
+            TAGNode::Inner(ref label, Adjunction::Obligatory) => write!(f, "{}:OA", label),
+            TAGNode::Inner(ref label, Adjunction::Null) => write!(f, "{}:NA", label),
+            TAGNode::Substitution(ref label) => write!(f, "{}↓", label),
+            TAGNode::Foot(ref label) => write!(f, "{}*", label),
+            TAGNode::Terminal(ref terminal) => write!(f, "{}", terminal),
+        }
+    }
+}
+
+/// The parts of the yield of a derived auxiliary tree that are not empty,
+/// i.e. whether there are terminal symbols to the left of its foot node, to
+/// the right of it, or on both sides.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum Shape {
+    Left,
+    Right,
+    Wrap,
+}
+
+impl Shape {
+    /// The number of components of the nonterminal that derives auxiliary
+    /// trees of this shape.
+    pub fn fanout(self) -> usize {
+        match self {
+            Shape::Left | Shape::Right => 1,
+            Shape::Wrap => 2,
+        }
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Shape::Left => write!(f, "left"),
+            Shape::Right => write!(f, "right"),
+            Shape::Wrap => write!(f, "wrap"),
+        }
+    }
+}
+
+/// Nonterminals of the `Lcfrs` that is compiled from a `TAG`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum TAGNonterminal<N, T> {
+    /// derives the yield of an initial tree with the given root label
+    Substitution(N),
+    /// derives the yield of an auxiliary tree with the given root label and
+    /// shape
+    Adjunction(N, Shape),
+    /// derives the yield of the elementary tree with the given index, where
+    /// auxiliary trees of the given shapes are adjoined at the given
+    /// addresses
+    Elementary(usize, Vec<(Vec<usize>, Shape)>),
+    /// derives a terminal symbol
+    Terminal(T),
+}
+
+impl<N: fmt::Display, T: fmt::Display> fmt::Display for TAGNonterminal<N, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TAGNonterminal::Substitution(ref label) => write!(f, "{}↓", label),
+            TAGNonterminal::Adjunction(ref label, shape) => write!(f, "{}*{}", label, shape),
+            TAGNonterminal::Elementary(i, ref adjunctions) => {
+                write!(f, "tree{}", i)?;
+                if !adjunctions.is_empty() {
+                    write!(f, "[")?;
+                    for (k, &(ref address, shape)) in adjunctions.iter().enumerate() {
+                        if k > 0 {
+                            write!(f, ", ")?;
+                        }
+                        if address.is_empty() {
+                            write!(f, "ε")?;
+                        }
+                        let positions: Vec<String> = address.iter().map(|i| i.to_string()).collect();
+                        write!(f, "{}:{}", positions.join("."), shape)?;
+                    }
+                    write!(f, "]")?;
+                }
+                Ok(())
+            }
+            TAGNonterminal::Terminal(ref terminal) => write!(f, "'{}'", terminal),
+        }
+    }
+}
+
+/// An elementary tree of a weighted TAG.
+/// It is an auxiliary tree if it has a foot node, and an initial tree
+/// otherwise.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ElementaryTree<N, T, W> {
+    tree: GornTree<TAGNode<N, T>>,
+    weight: W,
+}
+
+impl<N: PartialEq, T, W> ElementaryTree<N, T, W> {
+    /// Instantiates an elementary tree and checks that
+    /// * its root is an inner node,
+    /// * only inner nodes have children,
+    /// * it has at most one foot node, which is labelled like the root, and
+    /// * it has a terminal or a substitution node, i.e. derived trees never
+    ///   have an empty yield.
+    pub fn new(tree: GornTree<TAGNode<N, T>>, weight: W) -> Result<Self, String> {
+        tree.validate()?;
+        let root = match tree.get(&Vec::new()) {
+            Some(&TAGNode::Inner(ref label, _)) => label,
+            _ => return Err(String::from("the root of an elementary tree must be an inner node")),
+        };
+
+        let mut feet = 0;
+        let mut anchored = false;
+        for (address, node) in &tree {
+            match tree.parent(address) {
+                None | Some(&TAGNode::Inner(_, _)) => (),
+                Some(_) => return Err(format!("the leaf at {:?} has children", address)),
+            }
+            match *node {
+                TAGNode::Foot(ref label) if label != root => {
+                    return Err(format!("the foot node at {:?} is not labelled like the root", address));
+                }
+                TAGNode::Foot(_) => feet += 1,
+                TAGNode::Substitution(_) | TAGNode::Terminal(_) => anchored = true,
+                TAGNode::Inner(_, _) => (),
+            }
+        }
+
+        if feet > 1 {
+            Err(String::from("an elementary tree must not have more than one foot node"))
+        } else if !anchored {
+            Err(String::from("an elementary tree needs a terminal or a substitution node"))
+        } else {
+            Ok(ElementaryTree { tree, weight })
+        }
+    }
+}
+
+impl<N, T, W> ElementaryTree<N, T, W> {
+    pub fn tree(&self) -> &GornTree<TAGNode<N, T>> {
+        &self.tree
+    }
+
+    pub fn weight(&self) -> &W {
+        &self.weight
+    }
+
+    pub fn root_label(&self) -> &N {
+        match self.tree.get(&Vec::new()) {
+            Some(&TAGNode::Inner(ref label, _)) => label,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The address of the foot node, if this is an auxiliary tree.
+    pub fn foot(&self) -> Option<&Vec<usize>> {
+        self.tree.pre_order().find(|&(_, node)| match *node {
+            TAGNode::Foot(_) => true,
+            _ => false,
+        }).map(|(address, _)| address)
+    }
+
+    pub fn is_auxiliary(&self) -> bool {
+        self.foot().is_some()
+    }
+
+    /// The nodes at which other elementary trees are substituted or, if
+    /// they are among the `adjunctions`, adjoined, in pre-order.
+    /// These are the successors of the `Lcfrs` rule for this tree.
+    pub fn sites(&self, adjunctions: &[(Vec<usize>, Shape)]) -> Vec<(&Vec<usize>, Option<Shape>)> {
+        let adjunctions: BTreeMap<&Vec<usize>, Shape> =
+            adjunctions.iter().map(|&(ref address, shape)| (address, shape)).collect();
+        self.tree
+            .pre_order()
+            .filter_map(|(address, node)| match *node {
+                TAGNode::Substitution(_) => Some((address, None)),
+                TAGNode::Inner(_, _) => adjunctions.get(address).map(|&shape| (address, Some(shape))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn fmt_subtree(&self, f: &mut fmt::Formatter, address: &[usize]) -> fmt::Result
+    where
+        N: fmt::Display,
+        T: fmt::Display,
+    {
+        let node = &self.tree.get(&address.to_vec()).unwrap();
+        match **node {
+            TAGNode::Inner(_, _) => {
+                write!(f, "({}", node)?;
+                for child in self.tree.children(address) {
+                    write!(f, " ")?;
+                    self.fmt_subtree(f, &child)?;
+                }
+                write!(f, ")")
+            }
+            TAGNode::Terminal(ref terminal) => write!(f, "\"{}\"", escape(&terminal.to_string())),
+            _ => write!(f, "{}", node),
+        }
+    }
+}
+
+impl<N: fmt::Display, T: fmt::Display, W: fmt::Display> fmt::Display for ElementaryTree<N, T, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_subtree(f, &[])?;
+        write!(f, "  # {}", self.weight)
+    }
+}
+
+/// A weighted tree-adjoining grammar (TAG) with an initial nonterminal and
+/// a list of elementary trees.
+/// The weight of a derivation is the product of the weights of the
+/// elementary trees that it uses.
+///
+/// A `TAG` is parsed by compiling it into an `Lcfrs` of fanout at most 2,
+/// whose derivations are mapped back to derived trees with `derived_tree`.
+///
+/// ```
+/// # extern crate log_domain;
+/// # extern crate rustomata;
+/// use log_domain::LogDomain;
+/// use rustomata::grammars::lcfrs::Lcfrs;
+/// use rustomata::grammars::lcfrs::csparsing::CSRepresentation;
+/// use rustomata::grammars::tag::TAG;
+///
+/// # fn main() {
+/// let tag: TAG<String, String, LogDomain<f64>> = "initial: [S]\n\
+///                                        (S NP↓ (VP (V sleeps)))\n\
+///                                        (NP (N John))\n\
+///                                        (VP (ADV often) VP*)".parse().unwrap();
+///
+/// let cs = CSRepresentation::new(Lcfrs::from(tag.clone()), 0).unwrap();
+/// let word: Vec<String> = "John often sleeps".split_whitespace().map(String::from).collect();
+/// let derivation = cs.build_generator().with_fallback(&word).0.next().unwrap();
+///
+/// assert_eq!(
+///     "S\n\
+///      ├── NP\n\
+///      │   └── N\n\
+///      │       └── John\n\
+///      └── VP\n    \
+///          ├── ADV\n    \
+///          │   └── often\n    \
+///          └── VP\n        \
+///              └── V\n            \
+///                  └── sleeps",
+///     tag.derived_tree(&derivation).unwrap().to_string()
+/// );
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct TAG<N, T, W> {
+    initial: N,
+    trees: Vec<ElementaryTree<N, T, W>>,
+}
+
+impl<N, T, W> TAG<N, T, W> {
+    pub fn new(initial: N, trees: Vec<ElementaryTree<N, T, W>>) -> Self {
+        TAG { initial, trees }
+    }
+
+    pub fn initial(&self) -> &N {
+        &self.initial
+    }
+
+    pub fn trees(&self) -> &[ElementaryTree<N, T, W>] {
+        &self.trees
+    }
+}
+
+impl<N: Clone, T: Clone, W> TAG<N, T, W> {
+    /// Reads off the derived tree from a derivation of the `Lcfrs` that was
+    /// compiled from this grammar.
+    /// The adjunction constraints of inner nodes are dropped from the tree.
+    pub fn derived_tree<R>(&self, derivation: &GornTree<R>) -> Result<GornTree<TAGNode<N, T>>, String>
+    where
+        R: Borrow<PMCFGRule<TAGNonterminal<N, T>, T, W>>,
+    {
+        let mut tree = self.derive(derivation, Vec::new())?;
+        for node in tree.values_mut() {
+            if let TAGNode::Inner(_, ref mut adjunction) = *node {
+                *adjunction = Adjunction::Optional;
+            }
+        }
+        Ok(tree)
+    }
+
+    /// The derived tree for the subderivation at `address`; it keeps the
+    /// foot node of an auxiliary tree such that it can be adjoined.
+    fn derive<R>(&self, derivation: &GornTree<R>, address: Vec<usize>) -> Result<GornTree<TAGNode<N, T>>, String>
+    where
+        R: Borrow<PMCFGRule<TAGNonterminal<N, T>, T, W>>,
+    {
+        let rule = match derivation.get(&address) {
+            Some(rule) => rule.borrow(),
+            None => return Err(format!("the derivation has no node at {:?}", address)),
+        };
+
+        match rule.head {
+            TAGNonterminal::Substitution(_) | TAGNonterminal::Adjunction(_, _) => {
+                self.derive(derivation, child_address(&address, 0))
+            }
+            TAGNonterminal::Elementary(i, ref adjunctions) => {
+                let elementary = match self.trees.get(i) {
+                    Some(elementary) => elementary,
+                    None => return Err(format!("the grammar has no elementary tree {}", i)),
+                };
+                let mut tree = elementary.tree.clone();
+
+                // sites are replaced from right to left and bottom to top,
+                // so that the addresses of the remaining ones do not change
+                for (k, (site, shape)) in elementary.sites(adjunctions).into_iter().enumerate().rev() {
+                    let subtree = self.derive(derivation, child_address(&address, k))?;
+                    if shape.is_some() {
+                        let foot = match subtree.pre_order().find(|&(_, node)| match *node {
+                            TAGNode::Foot(_) => true,
+                            _ => false,
+                        }) {
+                            Some((foot, _)) => foot.clone(),
+                            None => return Err(format!("the tree adjoined at {:?} has no foot node", site)),
+                        };
+                        let below = tree.graft(site, subtree);
+                        let mut foot_address = site.clone();
+                        foot_address.extend(foot);
+                        tree.graft(&foot_address, below);
+                    } else {
+                        tree.graft(site, subtree);
+                    }
+                }
+
+                Ok(tree)
+            }
+            TAGNonterminal::Terminal(_) => {
+                Err(format!("the derivation has a terminal symbol at {:?}", address))
+            }
+        }
+    }
+}
+
+impl<N: fmt::Display, T: fmt::Display, W: fmt::Display> fmt::Display for TAG<N, T, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "initial: [\"{}\"]\n\n", self.initial)?;
+        for tree in &self.trees {
+            writeln!(f, "{}", tree)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let tree: ElementaryTree<String, String, f64> =
+            "(VP:NA (ADV \"very often\") VP*)  # 0.5 % comment".parse().unwrap();
+        assert!(tree.is_auxiliary());
+        assert_eq!(Some(&vec![1]), tree.foot());
+        assert_eq!("VP", tree.root_label());
+        assert_eq!(&0.5, tree.weight());
+        assert_eq!(
+            Some(&TAGNode::Terminal(String::from("very often"))),
+            tree.tree().get(&vec![0, 0])
+        );
+        assert_eq!("(VP:NA (ADV \"very often\") VP*)  # 0.5", tree.to_string());
+        assert_eq!(Ok(tree.clone()), tree.to_string().parse());
+
+        let tree: ElementaryTree<String, String, f64> = "(NP (N \"\\\"quoted\\\"\"))".parse().unwrap();
+        assert_eq!(
+            Some(&TAGNode::Terminal(String::from("\"quoted\""))),
+            tree.tree().get(&vec![0, 0])
+        );
+        assert_eq!(Ok(tree.clone()), tree.to_string().parse());
+
+        let tree: ElementaryTree<String, String, f64> = "(S:OA NP↓ (VP (V sleeps)))".parse().unwrap();
+        assert!(!tree.is_auxiliary());
+        assert_eq!(
+            vec![(&vec![0], None), (&vec![1], Some(Shape::Left))],
+            tree.sites(&[(vec![1], Shape::Left)])
+        );
+        assert_eq!("(S:OA NP↓ (VP (V \"sleeps\")))  # 1", tree.to_string());
+    }
+
+    #[test]
+    fn test_invalid_trees() {
+        let invalid = vec![
+            "NP↓",
+            "(VP VP* (ADV often) VP*)",
+            "(VP NP* (ADV often))",
+            "(VP VP*)",
+            "(S (NP))",
+            "(S NP↓",
+        ];
+
+        for tree in invalid {
+            assert!(
+                tree.parse::<ElementaryTree<String, String, f64>>().is_err(),
+                "{} was accepted",
+                tree
+            );
+        }
+    }
+}
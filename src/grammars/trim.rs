@@ -0,0 +1,80 @@
+use std::collections::BTreeSet;
+
+/// Removes the rules that contain nonterminals from which no word can be
+/// derived.
+/// `nonterminals` returns the left-hand side of a rule and the nonterminals
+/// of its right-hand side.
+pub fn remove_unproductive<R, N, F>(rules: Vec<R>, nonterminals: F) -> Vec<R>
+where
+    N: Clone + Ord,
+    F: Fn(&R) -> (&N, Vec<&N>),
+{
+    let mut productive = BTreeSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for rule in &rules {
+            let (head, tail) = nonterminals(rule);
+            if !productive.contains(head) && tail.iter().all(|n| productive.contains(*n)) {
+                productive.insert(head.clone());
+                changed = true;
+            }
+        }
+    }
+    rules.into_iter().filter(|rule| nonterminals(rule).1.iter().all(|n| productive.contains(*n))).collect()
+}
+
+/// Removes the rules for nonterminals that cannot be reached from the
+/// `initial` nonterminals.
+/// `nonterminals` returns the left-hand side of a rule and the nonterminals
+/// of its right-hand side.
+pub fn remove_unreachable<R, N, F>(rules: Vec<R>, initial: &[N], nonterminals: F) -> Vec<R>
+where
+    N: Clone + Ord,
+    F: Fn(&R) -> (&N, Vec<&N>),
+{
+    let mut reachable: BTreeSet<N> = initial.iter().cloned().collect();
+    let mut agenda: Vec<N> = initial.to_vec();
+    while let Some(n) = agenda.pop() {
+        for rule in &rules {
+            let (head, tail) = nonterminals(rule);
+            if *head != n {
+                continue;
+            }
+            for m in tail {
+                if reachable.insert(m.clone()) {
+                    agenda.push(m.clone());
+                }
+            }
+        }
+    }
+    rules.into_iter().filter(|rule| reachable.contains(nonterminals(rule).0)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rules are given as their left-hand side and the nonterminals of their
+    /// right-hand side.
+    fn nonterminals(rule: &(char, Vec<char>)) -> (&char, Vec<&char>) {
+        (&rule.0, rule.1.iter().collect())
+    }
+
+    #[test]
+    fn test_trim() {
+        let rules = vec![
+            ('S', vec!['A']),
+            ('S', vec!['B']),
+            ('A', vec![]),
+            ('B', vec!['B']),
+            ('C', vec!['A']),
+        ];
+        let productive = remove_unproductive(rules, nonterminals);
+        assert_eq!(vec![('S', vec!['A']), ('A', vec![]), ('C', vec!['A'])], productive);
+        assert_eq!(
+            vec![('S', vec!['A']), ('A', vec![])],
+            remove_unreachable(productive, &['S'], nonterminals)
+        );
+    }
+}
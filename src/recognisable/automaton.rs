@@ -2,7 +2,7 @@ use std::{ collections::{BinaryHeap, HashMap}, hash::Hash, ops::MulAssign, rc::R
 use num_traits::One;
 
 use recognisable::{Configuration, Instruction, Item, Transition};
-use util::{  push_down::Pushdown };
+use util::{ budget::BudgetTracker, push_down::Pushdown };
 use search::{Search, agenda::limited_heap::weighted::LimitedHeap, Agenda };
 
 // map from key to transition
@@ -111,23 +111,21 @@ where
 }
 
 
-pub fn recognise<'a, A, T, W>(
-    a: &'a A,
+/// The initial item for a word, if all of its symbols are known to the automaton.
+fn initial_item<A, T, W>(
+    a: &A,
     word: Vec<T>,
-) -> impl Iterator<Item = Item<<A::I as Instruction>::Storage, A::I, T, W>> + 'a
+) -> Option<Item<<A::IInt as Instruction>::Storage, A::IInt, A::TInt, W>>
 where
     A: Automaton<T, W>,
-    A::I: Clone + Eq + Instruction,
-    <A::I as Instruction>::Storage: Clone + Eq,
-    A::IInt: Ord + 'a,
-    <A::IInt as Instruction>::Storage: Clone + Eq + Ord,
-    T: Clone + Eq + Ord,
-    A::TInt: Clone + Eq + Ord + 'a,
-    W: Copy + MulAssign + One + Ord + 'a,
+    A::I: Clone + Instruction,
+    A::IInt: Clone + Eq + Instruction,
+    T: Clone,
+    W: Clone + MulAssign + One,
 {
     let the_words: Option<_> = word.iter().map(|t| a.terminal_to_int(t)).collect();
 
-    let init_confs = the_words.map(|the_word| {
+    the_words.map(|the_word| {
         Item(
             Configuration {
                 word: the_word,
@@ -136,9 +134,30 @@ where
             },
             Pushdown::new(),
         )
-    });
+    })
+}
+
+/// The items (in their internal representation) that are reachable from the initial item,
+/// in the order they are popped from the agenda.
+/// If a `beam` is given, the agenda is limited to `beam` items.
+fn explore<'a, A, T, W>(
+    a: &'a A,
+    beam: Option<usize>,
+    word: Vec<T>,
+) -> Box<Iterator<Item = Item<<A::IInt as Instruction>::Storage, A::IInt, A::TInt, W>> + 'a>
+where
+    A: Automaton<T, W>,
+    A::I: Clone + Eq + Instruction,
+    <A::I as Instruction>::Storage: Clone + Eq,
+    A::IInt: Ord + 'a,
+    <A::IInt as Instruction>::Storage: Clone + Eq + Ord,
+    T: Clone + Eq + Ord,
+    A::TInt: Clone + Eq + Ord + 'a,
+    W: Copy + MulAssign + One + Ord + 'a,
+{
+    let init_confs = initial_item(a, word);
 
-    Search::weighted(init_confs, move |Item(conf, run)| {
+    let successors = move |&Item(ref conf, ref run): &Item<_, A::IInt, _, W>| {
         let key = A::extract_key(conf);
         let trans_map = a.transition_map();
         let bh = BinaryHeap::new();
@@ -151,7 +170,35 @@ where
                 })
             })
             .collect::<Vec<_>>()
-    }).filter(move |Item(c, _)| a.is_terminal(c))
+    };
+
+    match beam {
+        Some(beam) => {
+            let mut agenda = LimitedHeap::with_capacity(beam);
+            agenda.extend(init_confs);
+            Box::new(Search::with_agenda(agenda, successors))
+        }
+        None => Box::new(Search::weighted(init_confs, successors)),
+    }
+}
+
+
+pub fn recognise<'a, A, T, W>(
+    a: &'a A,
+    word: Vec<T>,
+) -> impl Iterator<Item = Item<<A::I as Instruction>::Storage, A::I, T, W>> + 'a
+where
+    A: Automaton<T, W>,
+    A::I: Clone + Eq + Instruction,
+    <A::I as Instruction>::Storage: Clone + Eq,
+    A::IInt: Ord + 'a,
+    <A::IInt as Instruction>::Storage: Clone + Eq + Ord,
+    T: Clone + Eq + Ord,
+    A::TInt: Clone + Eq + Ord + 'a,
+    W: Copy + MulAssign + One + Ord + 'a,
+{
+    explore(a, None, word)
+        .filter(move |Item(c, _)| a.is_terminal(c))
         .map(move |i| a.item_map(&i))
 }
 
@@ -171,35 +218,35 @@ where
     A::TInt: Clone + Eq + Ord + 'a,
     W: Copy + MulAssign + One + Ord + 'a,
 {
-    let the_words: Option<_> = word.iter().map(|t| a.terminal_to_int(t)).collect();
-
-    let init_confs = the_words.map(|the_word| {
-        Item(
-            Configuration {
-                word: the_word,
-                storage: a.initial_int(),
-                weight: W::one(),
-            },
-            Pushdown::new(),
-        )
-    });
+    explore(a, Some(beam), word)
+        .filter(move |Item(c, _)| a.is_terminal(c))
+        .map(move |i| a.item_map(&i))
+}
 
-    let mut agenda = LimitedHeap::with_capacity(beam);
-    agenda.extend(init_confs);
 
-    Search::with_agenda(agenda, move |Item(conf, run)| {
-        let key = A::extract_key(conf);
-        let trans_map = a.transition_map();
-        let bh = BinaryHeap::new();
-        let rules = trans_map.get(key).unwrap_or(&bh);
-        rules
-            .iter()
-            .flat_map(|r| {
-                r.apply(conf).into_iter().map(move |conf1| {
-                    Item(conf1, run.clone().push(r.clone()))
-                })
-            })
-            .collect::<Vec<_>>()
-    }).filter(move |Item(c, _)| a.is_terminal(c))
+/// Like `recognise` (or `recognise_beam` if a `beam` is given), but the iterator ends as soon as
+/// the `budget` is exhausted. Each explored item counts as an agenda pop and each recognised item
+/// as a candidate.
+pub fn recognise_with_budget<'a, A, T, W>(
+    a: &'a A,
+    beam: Option<usize>,
+    budget: BudgetTracker,
+    word: Vec<T>,
+) -> impl Iterator<Item = Item<<A::I as Instruction>::Storage, A::I, T, W>> + 'a
+where
+    A: Automaton<T, W>,
+    A::I: Clone + Eq + Instruction,
+    <A::I as Instruction>::Storage: Clone + Eq,
+    A::IInt: Ord + 'a,
+    <A::IInt as Instruction>::Storage: Clone + Eq + Ord,
+    T: Clone + Eq + Ord,
+    A::TInt: Clone + Eq + Ord + 'a,
+    W: Copy + MulAssign + One + Ord + 'a,
+{
+    let candidate_budget = budget.clone();
+    explore(a, beam, word)
+        .take_while(move |_| budget.pop())
+        .filter(move |Item(c, _)| a.is_terminal(c))
+        .take_while(move |_| candidate_budget.candidate())
         .map(move |i| a.item_map(&i))
 }
@@ -0,0 +1,227 @@
+use num_traits::One;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::Mul;
+
+use util::budget::BudgetTracker;
+
+/// A hyperedge: the node it belongs to can be derived from the `tails`
+/// (which happened in that order) by applying the operation `label`.
+#[derive(Debug, Clone)]
+pub struct Edge<W> {
+    pub tails: Vec<usize>,
+    pub label: Option<usize>,
+    pub weight: W,
+}
+
+#[derive(Debug)]
+pub struct NodeData<N, W> {
+    pub node: N,
+    pub weight: W,
+    pub best: usize,
+    pub edges: Vec<Edge<W>>,
+    pub finished: bool,
+}
+
+/// The derivations of a node that were enumerated so far, the candidates
+/// for the next ones, and the last derivation whose successors were not
+/// enumerated yet.
+pub struct Derivations<W: Ord> {
+    pub derivations: Vec<(usize, Vec<usize>, W)>,
+    candidates: BinaryHeap<(W, usize, Vec<usize>)>,
+    seen: HashSet<(usize, Vec<usize>)>,
+    last: Option<(usize, Vec<usize>)>,
+    in_progress: bool,
+}
+
+/// The hypergraph of the items that tabular recognisers find for a word.
+/// Nodes are finished in the order of Knuth's algorithm, which is driven by
+/// the recogniser; derivations are then enumerated lazily best-first.
+pub struct Chart<N, W: Ord> {
+    pub nodes: Vec<NodeData<N, W>>,
+    pub index: HashMap<N, usize>,
+    pub derivations: HashMap<usize, Derivations<W>>,
+    budget: BudgetTracker,
+}
+
+impl<N, W> Chart<N, W>
+where
+    N: Clone + Eq + Hash,
+    W: Copy + Mul<Output = W> + One + Ord,
+{
+    pub fn new(budget: BudgetTracker) -> Self {
+        Chart { nodes: Vec::new(), index: HashMap::new(), derivations: HashMap::new(), budget }
+    }
+
+    pub fn node(&mut self, node: N) -> usize {
+        if let Some(&i) = self.index.get(&node) {
+            return i;
+        }
+        let i = self.nodes.len();
+        self.nodes.push(NodeData { node: node.clone(), weight: W::one(), best: 0, edges: Vec::new(), finished: false });
+        self.index.insert(node, i);
+        i
+    }
+
+    /// Adds an edge whose tails are finished and returns the weight of the
+    /// node if it improved.
+    pub fn propose(&mut self, node: N, edge: Edge<W>) -> Option<(W, usize)> {
+        let weight = edge.tails.iter().fold(edge.weight, |w, &t| w * self.nodes[t].weight);
+        let i = self.node(node);
+        let data = &mut self.nodes[i];
+        data.edges.push(edge);
+        if !data.finished && (data.edges.len() == 1 || weight > data.weight) {
+            data.weight = weight;
+            data.best = data.edges.len() - 1;
+            Some((weight, i))
+        } else {
+            None
+        }
+    }
+
+    /// The weight of the derivation that uses the `ranks`-th derivations of
+    /// the tails of an edge, if they exist.
+    fn weight(&mut self, node: usize, edge: usize, ranks: &[usize]) -> Option<W> {
+        let (tails, mut weight) = {
+            let edge = &self.nodes[node].edges[edge];
+            (edge.tails.clone(), edge.weight)
+        };
+        for (&tail, &rank) in tails.iter().zip(ranks) {
+            weight = weight * self.kth(tail, rank)?;
+        }
+        Some(weight)
+    }
+
+    /// The weight of the `k`-th best derivation of a finished node, following
+    /// Algorithm 3 of Huang and Chiang (2005): Better k-best Parsing.
+    /// The best derivation of each node is the one found by Knuth's algorithm.
+    ///
+    /// The hypergraph may have cycles (e.g. for ε- or unary cycles of a
+    /// grammar), as the weights of the edges are not greater than
+    /// `W::one()`: each derivation of a node that occurs in its `k`-th best
+    /// derivation comes before the latter, hence the successors of the
+    /// `k`-th best derivation only require derivations of the node up to the
+    /// `k`-th one, which are known.
+    /// Cycles whose weight is `W::one()` lead to infinitely many derivations
+    /// of the same weight, which are enumerated one after another.
+    pub fn kth(&mut self, node: usize, k: usize) -> Option<W> {
+        if !self.derivations.contains_key(&node) {
+            let data = &self.nodes[node];
+            let mut derivations = Derivations {
+                derivations: vec![(data.best, vec![0; data.edges[data.best].tails.len()], data.weight)],
+                candidates: BinaryHeap::new(),
+                seen: HashSet::new(),
+                last: None,
+                in_progress: false,
+            };
+            let (best, ref ranks, _) = derivations.derivations[0];
+            derivations.last = Some((best, ranks.clone()));
+            derivations.seen.insert((best, ranks.clone()));
+            for (e, edge) in data.edges.iter().enumerate().filter(|&(e, _)| e != data.best) {
+                let ranks = vec![0; edge.tails.len()];
+                let weight = edge.tails.iter().fold(edge.weight, |w, &t| w * self.nodes[t].weight);
+                derivations.seen.insert((e, ranks.clone()));
+                derivations.candidates.push((weight, e, ranks));
+            }
+            self.derivations.insert(node, derivations);
+        }
+
+        {
+            let derivations = &self.derivations[&node];
+            if let Some(&(_, _, weight)) = derivations.derivations.get(k) {
+                return Some(weight);
+            }
+            // The derivation is needed for a successor of a derivation of
+            // this node, which can only happen if weights are greater than
+            // `W::one()` (see above); it is skipped instead of looping.
+            if derivations.in_progress {
+                return None;
+            }
+        }
+
+        self.derivations.get_mut(&node).unwrap().in_progress = true;
+        let result = loop {
+            if let Some((e, ranks)) = self.derivations.get_mut(&node).unwrap().last.take() {
+                for i in 0..ranks.len() {
+                    let mut successor = ranks.clone();
+                    successor[i] += 1;
+                    if self.derivations[&node].seen.contains(&(e, successor.clone())) {
+                        continue;
+                    }
+                    if let Some(weight) = self.weight(node, e, &successor) {
+                        let derivations = self.derivations.get_mut(&node).unwrap();
+                        derivations.seen.insert((e, successor.clone()));
+                        derivations.candidates.push((weight, e, successor));
+                    }
+                }
+            }
+
+            if !self.budget.pop() {
+                break None;
+            }
+            let derivations = self.derivations.get_mut(&node).unwrap();
+            match derivations.candidates.pop() {
+                Some((weight, e, ranks)) => {
+                    derivations.derivations.push((e, ranks.clone(), weight));
+                    derivations.last = Some((e, ranks));
+                    if derivations.derivations.len() > k {
+                        break Some(weight);
+                    }
+                }
+                None => break None,
+            }
+        };
+        self.derivations.get_mut(&node).unwrap().in_progress = false;
+        result
+    }
+
+    /// The edge of the `k`-th best derivation of a node, together with the
+    /// ranks of the derivations of its tails; the derivation must exist.
+    pub fn derivation(&mut self, node: usize, k: usize) -> (Edge<W>, Vec<usize>) {
+        self.kth(node, k);
+        let (e, ref ranks, _) = self.derivations[&node].derivations[k];
+        (self.nodes[node].edges[e].clone(), ranks.clone())
+    }
+
+    /// Appends the labels of the `k`-th best derivation of a node in the
+    /// order in which they are applied; the derivation must exist.
+    pub fn read(&mut self, node: usize, k: usize, labels: &mut Vec<usize>) {
+        let (edge, ranks) = self.derivation(node, k);
+        for (tail, rank) in edge.tails.into_iter().zip(ranks) {
+            self.read(tail, rank, labels);
+        }
+        labels.extend(edge.label);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+
+    #[test]
+    fn test_cycles() {
+        // S → a, S → A and A → S, where the cycle through A has the weight
+        // of the rule A → S
+        for &(cycle, ratio) in &[(0.5, 0.5), (1.0, 1.0)] {
+            let mut chart = Chart::new(BudgetTracker::unlimited());
+            let a: LogDomain<f64> = LogDomain::new(0.5).unwrap();
+            chart.propose("S", Edge { tails: Vec::new(), label: Some(0), weight: a });
+            chart.nodes[0].finished = true;
+            chart.propose("A", Edge { tails: vec![0], label: Some(2), weight: LogDomain::new(cycle).unwrap() });
+            chart.nodes[1].finished = true;
+            chart.propose("S", Edge { tails: vec![1], label: Some(1), weight: LogDomain::one() });
+
+            let mut derivations = HashSet::new();
+            let mut weight = a;
+            for k in 0..10 {
+                assert!((chart.kth(0, k).unwrap().ln() - weight.ln()).abs() < 1e-9);
+                let mut labels = Vec::new();
+                chart.read(0, k, &mut labels);
+                assert_eq!(2 * k + 1, labels.len());
+                assert!(derivations.insert(labels));
+                weight = weight * LogDomain::new(ratio).unwrap();
+            }
+        }
+    }
+}
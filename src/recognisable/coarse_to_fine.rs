@@ -7,6 +7,7 @@ use approximation::{ApproximationInstance, ApproximationStrategy};
 use recognisable::{Instruction, Item, Recognisable};
 use recognisable::automaton::Automaton;
 use search::agenda::weighted::Weighted;
+use util::budget::BudgetTracker;
 
 pub struct CoarseToFineRecogniser<Rec, SubRec, Strategy, T, W>
     where Rec: Automaton<T, W>,
@@ -127,6 +128,26 @@ where
     ) -> Box<Iterator<Item = Self::Parse> + 'a> {
         unimplemented!()
     }
+
+    /// The `budget` is spent by the recogniser for the coarsest approximation, which is also
+    /// the only one that uses the `beam`.
+    fn recognise_with_budget<'a>(
+        &'a self,
+        beam: Option<usize>,
+        budget: BudgetTracker,
+        word: Vec<T>,
+    ) -> Box<Iterator<Item = Self::Parse> + 'a>
+    where
+        Self::Parse: 'a,
+    {
+        Box::new(CoarseToFineParseForest {
+            sublevel_parses: self.sublevel.recognise_with_budget(beam, budget, word),
+            recogniser: self.recogniser.clone(),
+            approximation_instance: self.approximation_instance.clone(),
+            input_buffer: None,
+            output_buffer: BinaryHeap::new(),
+        })
+    }
 }
 
 #[macro_export]
@@ -41,7 +41,7 @@ where
             take_while!(is_space) >>
             word: parse_word >>
             take_while!(is_space) >>
-            instruction: map_res!(delimited!(tag!("("), take_until!(")"), tag!(")")), from_utf8) >>
+            instruction: map_res!(delimited!(tag!("("), parse_instruction, tag!(")")), from_utf8) >>
             take_while!(is_space) >>
             weight_o: opt!(
                 complete!(
@@ -63,6 +63,26 @@ where
     )
 }
 
+/// Consumes the body of an instruction, i.e. everything up to the closing `')'`.
+/// Parentheses inside of `'"'`-delimited tokens do not close the instruction.
+fn parse_instruction(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    recognize!(
+        input,
+        many0!(
+            alt!(
+                recognize!(
+                    delimited!(
+                        tag!("\""),
+                        escaped!(is_not!("\"\\"), '\\', one_of!("\\\"")),
+                        tag!("\"")
+                    )
+                ) |
+                is_not!("\")")
+            )
+        )
+    )
+}
+
 fn parse_word<T>(input: &[u8]) -> IResult<&[u8], Vec<T>>
 where
     T: FromStr,
@@ -139,3 +159,34 @@ fn test_parse_transitions() {
         );
     }
 }
+
+#[test]
+fn test_parse_transitions_quoted_parentheses() {
+    let legal_inputs = vec![
+        (
+            "Transition [\"a\"] (Replace \"(a)\" // ) # 2",
+            "",
+            Transition {
+                word: vec![String::from("a")],
+                weight: 2usize,
+                instruction: String::from("Replace \"(a)\" // "),
+            }
+        ),
+        (
+            "Transition [] (Replace \"(S)\" // \"(b)\", \"(S)\")",
+            "",
+            Transition {
+                word: vec![],
+                weight: 1usize,
+                instruction: String::from("Replace \"(S)\" // \"(b)\", \"(S)\""),
+            }
+        ),
+    ];
+
+    for (legal_input, control_rest, control_parsed) in legal_inputs {
+        assert_eq!(
+            IResult::Done(control_rest.as_bytes(), control_parsed),
+            parse_transition::<String, String, usize>(legal_input.as_bytes())
+        );
+    }
+}
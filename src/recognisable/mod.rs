@@ -2,12 +2,14 @@ mod configuration;
 mod transition;
 
 pub mod automaton;
+pub mod chart;
 pub mod from_str;
 #[macro_use]
 pub mod coarse_to_fine;
 
 use std::vec::Vec;
 use search::agenda::weighted::Weighted;
+use util::budget::BudgetTracker;
 use util::push_down::Pushdown;
 
 pub use self::configuration::Configuration;
@@ -49,4 +51,64 @@ pub trait Recognisable<T, W> {
         beam: usize,
         word: Vec<T>,
     ) -> Box<Iterator<Item = Self::Parse> + 'a>;
+
+    /// Like `recognise` (or `recognise_beam_search` if a `beam` is given), but stops as soon as
+    /// the `budget` is exhausted; the reason can then be queried from (a clone of) the `budget`.
+    ///
+    /// The default implementation only checks the `budget` before each parse, i.e. it counts
+    /// parses as candidates but no agenda pops, and it cannot interrupt the search for a parse.
+    fn recognise_with_budget<'a>(
+        &'a self,
+        beam: Option<usize>,
+        budget: BudgetTracker,
+        word: Vec<T>,
+    ) -> Box<Iterator<Item = Self::Parse> + 'a>
+    where
+        Self::Parse: 'a,
+    {
+        let parses = match beam {
+            Some(beam) => self.recognise_beam_search(beam, word),
+            None => self.recognise(word),
+        };
+        Box::new(parses.take_while(move |_| budget.candidate()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::budget::{Budget, Exhaustion};
+
+    /// Recognises every word as many times as it has symbols.
+    struct Repeat;
+
+    impl Recognisable<char, usize> for Repeat {
+        type Parse = Vec<char>;
+
+        fn recognise<'a>(&'a self, word: Vec<char>) -> Box<Iterator<Item = Vec<char>> + 'a> {
+            Box::new(::std::iter::repeat(word.clone()).take(word.len()))
+        }
+
+        fn recognise_beam_search<'a>(
+            &'a self,
+            beam: usize,
+            word: Vec<char>,
+        ) -> Box<Iterator<Item = Vec<char>> + 'a> {
+            Box::new(self.recognise(word).take(beam))
+        }
+    }
+
+    #[test]
+    fn test_default_recognise_with_budget() {
+        let word = vec!['a', 'b', 'c'];
+
+        let unlimited = BudgetTracker::unlimited();
+        assert_eq!(3, Repeat.recognise_with_budget(None, unlimited.clone(), word.clone()).count());
+        assert_eq!(2, Repeat.recognise_with_budget(Some(2), unlimited.clone(), word.clone()).count());
+        assert_eq!(None, unlimited.exhausted());
+
+        let tracker = BudgetTracker::new(Budget { candidates: Some(1), ..Budget::default() });
+        assert_eq!(1, Repeat.recognise_with_budget(None, tracker.clone(), word).count());
+        assert_eq!(Some(Exhaustion::Candidates), tracker.exhausted());
+    }
 }
@@ -0,0 +1,155 @@
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Limits for the work that is spent on a single word.
+/// Each limit that is `None` is not checked.
+/// There is no limit on memory; it is only bounded indirectly by limiting the
+/// number of agenda pops.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Budget {
+    /// wall-clock time
+    pub time: Option<Duration>,
+    /// number of enumerated candidates
+    pub candidates: Option<usize>,
+    /// number of items that are popped from an agenda
+    pub pops: Option<usize>,
+}
+
+/// The reason why a `Budget` is exhausted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exhaustion {
+    Time,
+    Candidates,
+    Pops,
+}
+
+impl fmt::Display for Exhaustion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Exhaustion::Time => write!(f, "time limit exceeded"),
+            Exhaustion::Candidates => write!(f, "candidate limit exceeded"),
+            Exhaustion::Pops => write!(f, "agenda pop limit exceeded"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BudgetState {
+    budget: Budget,
+    start: Instant,
+    candidates: Cell<usize>,
+    pops: Cell<usize>,
+    exhausted: Cell<Option<Exhaustion>>,
+}
+
+/// Keeps track of the work that is spent w.r.t. a `Budget`, starting at its
+/// construction.
+/// Clones share their state, so a clone can be handed to an iterator while
+/// the original is used to query the reason why the iterator stopped.
+#[derive(Clone, Debug)]
+pub struct BudgetTracker(Rc<BudgetState>);
+
+impl BudgetTracker {
+    pub fn new(budget: Budget) -> Self {
+        BudgetTracker(Rc::new(BudgetState {
+            budget,
+            start: Instant::now(),
+            candidates: Cell::new(0),
+            pops: Cell::new(0),
+            exhausted: Cell::new(None),
+        }))
+    }
+
+    /// A `BudgetTracker` that is never exhausted.
+    pub fn unlimited() -> Self {
+        BudgetTracker::new(Budget::default())
+    }
+
+    /// Returns the reason why the budget is exhausted, if it is.
+    pub fn exhausted(&self) -> Option<Exhaustion> {
+        self.0.exhausted.get()
+    }
+
+    /// The number of candidates that were registered so far.
+    pub fn candidates(&self) -> usize {
+        self.0.candidates.get()
+    }
+
+    /// The time since the construction of the tracker.
+    pub fn elapsed(&self) -> Duration {
+        self.0.start.elapsed()
+    }
+
+    fn exhaust(&self, reason: Exhaustion) -> bool {
+        self.0.exhausted.set(Some(reason));
+        false
+    }
+
+    /// Checks whether there is time left.
+    pub fn check(&self) -> bool {
+        if self.0.exhausted.get().is_some() {
+            return false;
+        }
+        match self.0.budget.time {
+            Some(limit) if self.0.start.elapsed() > limit => self.exhaust(Exhaustion::Time),
+            _ => true,
+        }
+    }
+
+    /// Registers an agenda pop; returns `false` if the budget is exhausted.
+    pub fn pop(&self) -> bool {
+        if !self.check() {
+            return false;
+        }
+        let pops = self.0.pops.get();
+        if self.0.budget.pops.map_or(false, |max| pops >= max) {
+            return self.exhaust(Exhaustion::Pops);
+        }
+        self.0.pops.set(pops + 1);
+        true
+    }
+
+    /// Registers an enumerated candidate; returns `false` if the budget is
+    /// exhausted.
+    pub fn candidate(&self) -> bool {
+        if !self.check() {
+            return false;
+        }
+        let candidates = self.0.candidates.get();
+        if self.0.budget.candidates.map_or(false, |max| candidates >= max) {
+            return self.exhaust(Exhaustion::Candidates);
+        }
+        self.0.candidates.set(candidates + 1);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_tracker() {
+        let unlimited = BudgetTracker::unlimited();
+        assert!((0..1000).all(|_| unlimited.pop() && unlimited.candidate()));
+        assert_eq!(None, unlimited.exhausted());
+
+        let tracker = BudgetTracker::new(Budget { pops: Some(3), ..Budget::default() });
+        let shared = tracker.clone();
+        assert_eq!(3, (0..10).take_while(|_| shared.pop()).count());
+        assert_eq!(Some(Exhaustion::Pops), tracker.exhausted());
+        assert!(!tracker.candidate());
+
+        let tracker = BudgetTracker::new(Budget { candidates: Some(2), ..Budget::default() });
+        assert_eq!(2, (0..10).take_while(|_| tracker.candidate()).count());
+        assert_eq!(2, tracker.candidates());
+        assert_eq!(Some(Exhaustion::Candidates), tracker.exhausted());
+
+        let tracker = BudgetTracker::new(Budget { time: Some(Duration::from_millis(0)), ..Budget::default() });
+        ::std::thread::sleep(Duration::from_millis(1));
+        assert!(!tracker.pop());
+        assert_eq!(Some(Exhaustion::Time), tracker.exhausted());
+    }
+}
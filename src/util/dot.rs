@@ -0,0 +1,169 @@
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+use util::parsing;
+use util::tree::{parent_address, GornTree};
+
+/// Objects that can be drawn with Graphviz.
+pub trait ToDot {
+    /// Describes `self` as a graph in the DOT language.
+    fn to_dot(&self) -> String;
+}
+
+/// Escapes a string such that it can be used in a quoted DOT identifier,
+/// i.e. like `util::parsing::escape` and additionally with line breaks as
+/// `\n`.
+pub fn escape(s: &str) -> String {
+    parsing::escape(s).replace('\n', "\\n")
+}
+
+/// Draws a graph whose nodes are identified by their labels; edges are
+/// given as `(source, target, label)`.
+/// Each `initial` node gets an incoming arrow, each `final` node is drawn
+/// with a double circle.
+/// Nodes and edges are sorted, so the output does not depend on the order of
+/// `edges`.
+pub fn graph_to_dot<I>(initial: &[String], finals: &[String], edges: I) -> String
+where
+    I: IntoIterator<Item = (String, String, String)>,
+{
+    let mut dot = String::from("digraph {\n    rankdir=LR;\n    node [shape=circle];\n");
+    let mut nodes = BTreeSet::new();
+    let mut edge_lines = String::new();
+    let mut edges: Vec<_> = edges.into_iter().collect();
+    edges.sort();
+    for (source, target, label) in edges {
+        edge_lines.push_str(&format!(
+            "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+            escape(&source),
+            escape(&target),
+            escape(&label)
+        ));
+        nodes.insert(source);
+        nodes.insert(target);
+    }
+    nodes.extend(initial.iter().cloned());
+    nodes.extend(finals.iter().cloned());
+
+    for node in &nodes {
+        if finals.contains(node) {
+            dot.push_str(&format!("    \"{}\" [shape=doublecircle];\n", escape(node)));
+        } else {
+            dot.push_str(&format!("    \"{}\";\n", escape(node)));
+        }
+    }
+    for (i, node) in initial.iter().enumerate() {
+        dot.push_str(&format!(
+            "    __initial{} [shape=point];\n    __initial{} -> \"{}\";\n",
+            i,
+            i,
+            escape(node)
+        ));
+    }
+    dot.push_str(&edge_lines);
+    dot.push_str("}\n");
+    dot
+}
+
+/// Labels a transition with the word it reads (`ε` if it is empty), its
+/// instruction (if it is not empty) and its weight.
+pub fn transition_label<T: Display, W: Display>(word: &[T], instruction: &str, weight: &W) -> String {
+    let word = if word.is_empty() {
+        String::from("ε")
+    } else {
+        word.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(" ")
+    };
+    if instruction.is_empty() {
+        format!("{} # {}", word, weight)
+    } else {
+        format!("{} / {} # {}", word, instruction, weight)
+    }
+}
+
+fn node_name(address: &[usize]) -> String {
+    let mut name = String::from("n");
+    for i in address {
+        name.push_str(&format!("_{}", i));
+    }
+    name
+}
+
+/// Draws a tree whose nodes are labelled by `label`; the node at the address
+/// `highlight` (if given) is drawn bold.
+pub fn tree_to_dot<V, F>(tree: &GornTree<V>, label: F, highlight: Option<&[usize]>) -> String
+where
+    F: Fn(&V) -> String,
+{
+    let mut dot = String::from("digraph {\n    node [shape=none];\n");
+    for (address, value) in tree {
+        let style = if highlight == Some(address.as_slice()) { ", style=bold, shape=box" } else { "" };
+        dot.push_str(&format!(
+            "    {} [label=\"{}\"{}];\n",
+            node_name(address),
+            escape(&label(value)),
+            style
+        ));
+    }
+    for address in tree.keys() {
+        if let Some(parent) = parent_address(address) {
+            if tree.contains_key(&parent) {
+                dot.push_str(&format!("    {} -> {};\n", node_name(&parent), node_name(address)));
+            }
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+impl<V: Display> ToDot for GornTree<V> {
+    fn to_dot(&self) -> String {
+        tree_to_dot(self, |v| v.to_string(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_to_dot() {
+        let edges = vec![
+            ("q0".to_string(), "q1".to_string(), "a # 0.5".to_string()),
+            ("q1".to_string(), "q1".to_string(), "\"b\"".to_string()),
+        ];
+        assert_eq!(
+            "digraph {\n    rankdir=LR;\n    node [shape=circle];\n\
+             \x20   \"q0\";\n\
+             \x20   \"q1\" [shape=doublecircle];\n\
+             \x20   __initial0 [shape=point];\n    __initial0 -> \"q0\";\n\
+             \x20   \"q0\" -> \"q1\" [label=\"a # 0.5\"];\n\
+             \x20   \"q1\" -> \"q1\" [label=\"\\\"b\\\"\"];\n\
+             }\n",
+            graph_to_dot(&["q0".to_string()], &["q1".to_string()], edges)
+        );
+    }
+
+    #[test]
+    fn test_transition_label() {
+        assert_eq!("a b # 1", transition_label(&["a", "b"], "", &1));
+        assert_eq!("ε / push # 0.5", transition_label::<char, _>(&[], "push", &0.5));
+    }
+
+    #[test]
+    fn test_tree_to_dot() {
+        let tree: GornTree<&str> = vec![(vec![], "S"), (vec![0], "A"), (vec![1], "B"), (vec![1, 0], "b")]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            "digraph {\n    node [shape=none];\n\
+             \x20   n [label=\"S\"];\n\
+             \x20   n_0 [label=\"A\"];\n\
+             \x20   n_1 [label=\"B\", style=bold, shape=box];\n\
+             \x20   n_1_0 [label=\"b\"];\n\
+             \x20   n -> n_0;\n    n -> n_1;\n    n_1 -> n_1_0;\n\
+             }\n",
+            tree_to_dot(&tree, |v| v.to_string(), Some(&[1]))
+        );
+        assert_eq!(tree_to_dot(&tree, |v| v.to_string(), None), tree.to_dot());
+    }
+}
@@ -5,6 +5,8 @@ pub mod parsing;
 pub mod reverse;
 pub mod tree;
 pub mod factorizable;
+pub mod budget;
+pub mod dot;
 
 use fnv::{FnvHashMap, FnvHashSet};
 
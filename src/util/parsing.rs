@@ -36,6 +36,40 @@ where
     )
 }
 
+/// Parses a token like `parse_token`, but removes the escaping `'\\'`s of a token that is
+/// delimited by `'"'`, i.e. it is the inverse of writing `escape(…)` between `'"'`s.
+pub fn parse_unescaped_token<A>(input: &[u8]) -> IResult<&[u8], A>
+where
+    A: FromStr,
+    A::Err: Debug,
+{
+    do_parse!(
+        input,
+        output: call!(parse_token::<String>) >>
+        token: expr_res!(unescape(&output).parse()) >>
+        (token)
+    )
+}
+
+/// Escapes each occurrence of `'\\'` and `'"'` in `s` with a `'\\'`, such that `s` can be
+/// written as a token that is delimited by `'"'`.
+pub fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Removes the escaping `'\\'`s from `s`, i.e. the inverse of `escape`.
+pub fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.extend(chars.next()),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
 /// Parses the `input` into a `Vec<A>` given an `inner_parser` for type `A`, an `opening` delimiter, a `closing` delimiter, and a `separator`.
 /// The `inner_parser` must not consume the `separator`s or the `closing` delimiter of the given `input`.
 pub fn parse_vec<'a, A, P>(
@@ -217,6 +251,17 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_escape_parse_unescaped_token_inverse() {
+        for token in vec!["abc", "a b", "a\"b", "a\\b", "\\\"", ""] {
+            let escaped = format!("\"{}\"", escape(token));
+            assert_eq!(
+                ("".as_bytes(), String::from(token)),
+                parse_unescaped_token::<String>(escaped.as_bytes()).unwrap()
+            );
+        }
+    }
+
     #[test]
     fn test_parse_token_illegal_input() {
         let illegal_inputs = vec![
@@ -1,6 +1,9 @@
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::collections::btree_map;
+use std::fmt;
 use std::iter::FromIterator;
+use std::vec;
 
 /// A tree map where each node has a _Gorn address_, i.e. a sequence of integers that describes the
 /// path one has to follow to get from the root to the particular node.
@@ -69,6 +72,169 @@ impl<V> GornTree<V> {
     pub fn is_empty(&self) -> bool {
         self.map.is_empty()
     }
+
+    /// The addresses of the children of the node at `key`, from left to right.
+    pub fn children(&self, key: &[usize]) -> Vec<Vec<usize>> {
+        self.map
+            .range(key.to_vec()..)
+            .map(|(address, _)| address)
+            .take_while(|address| address.starts_with(key))
+            .filter(|address| address.len() == key.len() + 1)
+            .cloned()
+            .collect()
+    }
+
+    /// The value at the parent of the node at `key`, if `key` is not the root.
+    pub fn parent(&self, key: &[usize]) -> Option<&V> {
+        parent_address(key).and_then(|address| self.map.get(&address))
+    }
+
+    /// Iterates over the nodes in pre-order, i.e. each node is visited before
+    /// its children and children are visited from left to right.
+    pub fn pre_order<'a>(&'a self) -> btree_map::Iter<'a, Vec<usize>, V> {
+        // the lexicographic order of Gorn addresses is the pre-order
+        self.map.iter()
+    }
+
+    /// Iterates over the nodes in post-order, i.e. each node is visited after
+    /// its children and children are visited from left to right.
+    pub fn post_order<'a>(&'a self) -> vec::IntoIter<(&'a Vec<usize>, &'a V)> {
+        let mut nodes: Vec<_> = self.map.iter().collect();
+        nodes.sort_by(|&(a, _), &(b, _)| post_order_cmp(a, b));
+        nodes.into_iter()
+    }
+
+    /// The length of the longest address in the tree, i.e. `0` for a tree
+    /// that consists of the root only (or of nothing at all).
+    pub fn depth(&self) -> usize {
+        self.map.keys().map(|address| address.len()).max().unwrap_or(0)
+    }
+
+    /// Applies `f` to every value and keeps the shape of the tree.
+    pub fn map_values<B, F>(&self, mut f: F) -> GornTree<B>
+    where
+        F: FnMut(&V) -> B,
+    {
+        self.map.iter().map(|(address, value)| (address.clone(), f(value))).collect()
+    }
+
+    /// The subtree below `key`, re-rooted such that `key` becomes `[]`.
+    pub fn subtree<'a>(&'a self, key: &[usize]) -> GornTree<&'a V> {
+        self.map
+            .range(key.to_vec()..)
+            .take_while(|&(address, _)| address.starts_with(key))
+            .map(|(address, value)| (address[key.len()..].to_vec(), value))
+            .collect()
+    }
+
+    /// Like `subtree`, but consumes the tree and thus needs no references.
+    pub fn into_subtree(self, key: &[usize]) -> GornTree<V> {
+        self.map
+            .into_iter()
+            .filter(|&(ref address, _)| address.starts_with(key))
+            .map(|(address, value)| (address[key.len()..].to_vec(), value))
+            .collect()
+    }
+
+    /// Replaces the subtree below `key` by `subtree`, whose root is put at
+    /// `key`, and returns the replaced subtree (re-rooted).
+    pub fn graft(&mut self, key: &[usize], subtree: GornTree<V>) -> GornTree<V> {
+        let addresses: Vec<Vec<usize>> = self.map
+            .range(key.to_vec()..)
+            .map(|(address, _)| address)
+            .take_while(|address| address.starts_with(key))
+            .cloned()
+            .collect();
+        let replaced = addresses
+            .into_iter()
+            .map(|address| {
+                let value = self.map.remove(&address).unwrap();
+                (address[key.len()..].to_vec(), value)
+            })
+            .collect();
+
+        for (address, value) in subtree {
+            let mut new_address = key.to_vec();
+            new_address.extend(address);
+            self.map.insert(new_address, value);
+        }
+
+        replaced
+    }
+
+    /// Checks that the addresses form a tree domain: unless the tree is
+    /// empty, it has a root, and with every node `u·i` it contains `u` and
+    /// all left siblings `u·j` with `j < i`.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.map.is_empty() && !self.map.contains_key(&Vec::new()) {
+            return Err(String::from("the tree has no root"));
+        }
+        for address in self.map.keys() {
+            if let Some((&i, parent)) = address.split_last() {
+                if !self.map.contains_key(&parent.to_vec()) {
+                    return Err(format!("the node at {:?} has no parent", address));
+                }
+                if i > 0 && !self.map.contains_key(&child_address(parent, i - 1)) {
+                    return Err(format!("the node at {:?} has no left sibling", address));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the tree with box-drawing characters, one node per line, where
+    /// each node is labelled by `label`:
+    ///
+    /// ```text
+    /// S
+    /// ├── A
+    /// │   └── a
+    /// └── B
+    /// ```
+    pub fn to_ascii<F>(&self, label: F) -> String
+    where
+        F: Fn(&V) -> String,
+    {
+        let has_right_sibling = |address: &[usize]| {
+            let mut sibling = address.to_vec();
+            *sibling.last_mut().unwrap() += 1;
+            self.map.contains_key(&sibling)
+        };
+
+        let mut lines = Vec::new();
+        for (address, value) in &self.map {
+            let mut line = String::new();
+            for depth in 1..address.len() {
+                line.push_str(if has_right_sibling(&address[..depth]) { "│   " } else { "    " });
+            }
+            if !address.is_empty() {
+                line.push_str(if has_right_sibling(address) { "├── " } else { "└── " });
+            }
+            line.push_str(&label(value));
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+/// The address of the parent of the node at `address`, if it is not the root.
+pub fn parent_address(address: &[usize]) -> Option<Vec<usize>> {
+    address.split_last().map(|(_, parent)| parent.to_vec())
+}
+
+/// The address of the `i`-th child of the node at `address`.
+pub fn child_address(address: &[usize], i: usize) -> Vec<usize> {
+    let mut child = address.to_vec();
+    child.push(i);
+    child
+}
+
+/// Compares addresses such that descendants come before their ancestors.
+fn post_order_cmp(a: &[usize], b: &[usize]) -> Ordering {
+    match a.iter().zip(b).map(|(i, j)| i.cmp(j)).find(|o| o != &Ordering::Equal) {
+        Some(ordering) => ordering,
+        None => b.len().cmp(&a.len()),
+    }
 }
 
 impl<'a, V: 'a + Clone> GornTree<&'a V> {
@@ -110,6 +276,12 @@ impl<V: PartialEq> PartialEq for GornTree<V> {
     }
 }
 
+impl<V: fmt::Display> fmt::Display for GornTree<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_ascii(|v| v.to_string()))
+    }
+}
+
 impl<V> FromIterator<(Vec<usize>, V)> for GornTree<V> {
     fn from_iter<T>(iter: T) -> Self
     where
@@ -118,3 +290,89 @@ impl<V> FromIterator<(Vec<usize>, V)> for GornTree<V> {
         GornTree { map: iter.into_iter().collect() }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> GornTree<&'static str> {
+        vec![
+            (vec![], "S"),
+            (vec![0], "A"),
+            (vec![0, 0], "a"),
+            (vec![1], "B"),
+            (vec![1, 0], "b"),
+            (vec![1, 1], "c"),
+        ].into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_navigation() {
+        let tree = example();
+        assert_eq!(vec![vec![0], vec![1]], tree.children(&[]));
+        assert_eq!(vec![vec![1, 0], vec![1, 1]], tree.children(&[1]));
+        assert!(tree.children(&[0, 0]).is_empty());
+        assert_eq!(Some(&"B"), tree.parent(&[1, 1]));
+        assert_eq!(None, tree.parent(&[]));
+        assert_eq!(Some(vec![1]), parent_address(&[1, 0]));
+        assert_eq!(vec![1, 2], child_address(&[1], 2));
+        assert_eq!(2, tree.depth());
+        assert_eq!(0, GornTree::<()>::new().depth());
+    }
+
+    #[test]
+    fn test_traversal() {
+        let tree = example();
+        assert_eq!(
+            vec!["S", "A", "a", "B", "b", "c"],
+            tree.pre_order().map(|(_, v)| *v).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["a", "A", "b", "c", "B", "S"],
+            tree.post_order().map(|(_, v)| *v).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_subtree_and_graft() {
+        let mut tree = example();
+        let subtree: GornTree<&str> = vec![(vec![], "B"), (vec![0], "b"), (vec![1], "c")]
+            .into_iter()
+            .collect();
+        assert_eq!(subtree, tree.subtree(&[1]).cloned());
+        assert_eq!(subtree, tree.clone().into_subtree(&[1]));
+        assert!(tree.subtree(&[2]).is_empty());
+
+        let replaced = tree.graft(&[0], subtree.clone());
+        assert_eq!(vec![(vec![], "A"), (vec![0], "a")], replaced.into_iter().collect::<Vec<_>>());
+        assert_eq!(subtree, tree.subtree(&[0]).cloned());
+        assert_eq!(7, tree.len());
+        assert_eq!(
+            vec![(vec![], 1), (vec![0], 1), (vec![1], 1)],
+            tree.subtree(&[0]).map_values(|v| v.len()).into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut tree = example();
+        assert_eq!(Ok(()), tree.validate());
+        assert_eq!(Ok(()), GornTree::<()>::new().validate());
+        tree.insert(vec![1, 3], "d");
+        assert!(tree.validate().is_err());
+        tree.remove(&vec![1, 3]);
+        tree.insert(vec![2, 0], "e");
+        assert!(tree.validate().is_err());
+        tree.remove(&vec![]);
+        assert!(tree.validate().is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            "S\n├── A\n│   └── a\n└── B\n    ├── b\n    └── c",
+            example().to_string()
+        );
+    }
+}
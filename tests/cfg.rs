@@ -3,10 +3,11 @@ extern crate num_traits;
 extern crate rustomata;
 
 use log_domain::LogDomain;
-// TODO: Uncomment once PushDownAutomaton::FromStr has been implemented
-// use std::collections::HashSet;
+use num_traits::One;
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::Read;
+use std::str::FromStr;
 
 use rustomata::approximation::ApproximationStrategy;
 use rustomata::approximation::equivalence_classes::EquivalenceRelation;
@@ -14,8 +15,8 @@ use rustomata::approximation::relabel::RlbElement;
 use rustomata::grammars::cfg::*;
 use rustomata::automata::push_down_automaton::*;
 use rustomata::recognisable::*;
-// TODO: Uncomment once PushDownAutomaton::FromStr has been implemented
-// use rustomata::recognisable::automaton::Automaton;
+use rustomata::recognisable::automaton::Automaton;
+use rustomata::util::tree::{child_address, GornTree};
 
 fn cfg_from_file(grammar_file_path: &str) -> CFG<String, String, LogDomain<f64>> {
     let mut grammar_file = File::open(grammar_file_path).unwrap();
@@ -99,8 +100,6 @@ fn test_cfg_from_str_correctness() {
     }
 }
 
-// TODO: Uncomment once PushDownAutomaton::FromStr has been implemented
-/*
 #[test]
 fn test_pushdown_automaton_from_str() {
     use PushDownInstruction::Replace;
@@ -123,7 +122,7 @@ fn test_pushdown_automaton_from_str() {
             weight: LogDomain::new(weight).unwrap()
         });
     }
-    let control_automaton = PushDownAutomaton::new(transitions, PushDown::new("I".to_string(), "@".to_string()));
+    let control_automaton = PushDownAutomaton::new(transitions, PushDown::new("@".to_string(), "I".to_string()));
 
     let mut automaton_file = File::open("examples/example.pda").unwrap();
     let mut automaton_string = String::new();
@@ -139,7 +138,20 @@ fn test_pushdown_automaton_from_str() {
         automaton.list_transitions().collect::<HashSet<_>>()
     );
 }
-*/
+
+#[test]
+fn test_pushdown_automaton_from_str_recognise() {
+    let mut automaton_file = File::open("examples/example.pda").unwrap();
+    let mut automaton_string = String::new();
+    let _ = automaton_file.read_to_string(&mut automaton_string);
+    let automaton: PushDownAutomaton<String, String, LogDomain<f64>> = automaton_string.parse().unwrap();
+    let inputs = vec![("", true), ("ab", true), ("aabb", true), ("aab", false), ("ba", false)];
+
+    for (input, control_acceptance) in inputs {
+        let word: Vec<_> = String::from(input).chars().map(|x| x.to_string()).collect();
+        assert_eq!(control_acceptance, automaton.recognise(word).next().is_some());
+    }
+}
 
 #[test]
 fn test_cfg_recognise_legal_terminal_symbols() {
@@ -171,3 +183,176 @@ fn test_cfg_recognise_illegal_terminal_symbols() {
         assert!(automaton.recognise(illegal_word).next().is_none());
     }
 }
+
+/// Checks that `TabularRecogniser` finds the same best runs as the agenda-based recogniser.
+fn assert_tabular_agrees<A>(automaton: PushDownAutomaton<A, String, LogDomain<f64>>)
+where
+    A: Clone + std::fmt::Debug + std::hash::Hash + Ord,
+{
+    let tabular = TabularRecogniser::new(automaton.clone());
+    for input in vec!["", "ab", "aabb", "aaabbb", "aab", "ba", "aacc"] {
+        let word: Vec<_> = String::from(input).chars().map(|x| x.to_string()).collect();
+        assert_eq!(
+            automaton.recognise(word.clone()).next(),
+            tabular.recognise(word.clone()).next()
+        );
+        assert_eq!(
+            automaton.recognise_beam_search(10, word.clone()).next().is_some(),
+            tabular.recognise_beam_search(10, word).next().is_some()
+        );
+    }
+}
+
+#[test]
+fn test_tabular_recognise() {
+    let mut automaton_file = File::open("examples/example.pda").unwrap();
+    let mut automaton_string = String::new();
+    let _ = automaton_file.read_to_string(&mut automaton_string);
+    let automaton: PushDownAutomaton<String, String, LogDomain<f64>> = automaton_string.parse().unwrap();
+
+    assert_tabular_agrees(automaton);
+    assert_tabular_agrees(PushDownAutomaton::from(cfg_from_file("examples/example.cfg")));
+}
+
+/// The word that a derivation of a CFG yields below `address`.
+fn derivation_yield(tree: &GornTree<CFGRule<String, String, LogDomain<f64>>>, address: &[usize]) -> Vec<String> {
+    let mut word = Vec::new();
+    let mut child = 0;
+    for letter in &tree.get(&address.to_vec()).unwrap().composition.composition {
+        match *letter {
+            LetterT::Value(ref t) => word.push(t.clone()),
+            LetterT::Label(_) => {
+                word.extend(derivation_yield(tree, &child_address(address, child)));
+                child += 1;
+            }
+        }
+    }
+    word
+}
+
+/// Derives each binary tree whose leaves are labelled with `a`.
+fn binary_trees() -> CFG<String, String, LogDomain<f64>> {
+    CFG::from_str(
+        "initial: [S]\n\n\
+         S → [Nt S, Nt S] # 0.5\n\
+         S → [T a] # 0.5"
+    ).unwrap()
+}
+
+#[test]
+fn test_cyk_parse() {
+    // S derives b by S → B, by S → A B with A → ε, and by S → A B with
+    // A → B and B → ε
+    let ambiguous: CFG<String, String, LogDomain<f64>> = CFG::from_str(
+        "initial: [S]\n\n\
+         S → [Nt A, Nt B] # 0.6\n\
+         S → [Nt B] # 0.4\n\
+         A → [Nt B] # 0.5\n\
+         A → [] # 0.5\n\
+         B → [T b] # 0.9\n\
+         B → [] # 0.1"
+    ).unwrap();
+    let inputs = vec![
+        (cfg_from_file("examples/example.cfg"), vec!["", "a b", "a a b b", "a a b", "b a"]),
+        (cfg_from_file("examples/example2.cfg"), vec!["a", "b", "a a b", "a a b a", "b b a a b", "c"]),
+        (cfg_from_file("examples/example3.cfg"), vec!["John loves Mary", "Mary loves Mary", "loves John"]),
+        (ambiguous, vec!["", "b", "b b", "b b b"]),
+        (binary_trees(), vec!["a", "a a a", "a a a a"]),
+    ];
+
+    for (grammar, words) in inputs {
+        let cnf = cnf::CNF::from(grammar.clone());
+        let tabular = TabularRecogniser::new(PushDownAutomaton::from(grammar));
+
+        for input in words {
+            let word: Vec<_> = input.split_whitespace().map(|x| x.to_string()).collect();
+            let parses: Vec<_> = cnf.parse(&word).take(5).collect();
+            let runs: Vec<_> = tabular.recognise(word.clone()).take(5).collect();
+
+            assert_eq!(runs.len(), parses.len());
+            for (i, &(ref tree, _)) in parses.iter().enumerate() {
+                assert!(parses[..i].iter().all(|&(ref other, _)| other != tree));
+            }
+            for ((tree, weight), run) in parses.into_iter().zip(runs) {
+                assert!((weight.ln() - run.0.weight.ln()).abs() < 1e-9);
+                assert_eq!(Ok(()), tree.validate());
+                assert_eq!(word, derivation_yield(&tree, &[]));
+                let product = tree.values().fold(LogDomain::one(), |w, rule| w * rule.weight);
+                assert!((weight.ln() - product.ln()).abs() < 1e-9);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_lr_automaton() {
+    let inputs = vec![
+        ("examples/example.cfg", vec!["", "a b", "a a b b", "a a b", "b a"]),
+        ("examples/example2.cfg", vec!["a", "b", "a a b", "a a b a", "b b a a b", "c"]),
+        ("examples/example3.cfg", vec!["John loves Mary", "Mary loves Mary", "loves John"]),
+    ];
+
+    for (grammar_file, words) in inputs {
+        let grammar = cfg_from_file(grammar_file);
+        let cnf = cnf::CNF::from(grammar.clone());
+
+        for kind in vec![LRKind::LR0, LRKind::SLR, LRKind::LALR1] {
+            let recogniser = TabularRecogniser::new(LRTable::new(grammar.clone(), kind).automaton());
+
+            for input in &words {
+                let word: Vec<_> = input.split_whitespace().map(|x| x.to_string()).collect();
+                let parses: Vec<_> = cnf.parse(&word).take(5).collect();
+                let runs: Vec<_> = recogniser.recognise(word).take(5).collect();
+
+                assert_eq!(parses.len(), runs.len());
+                for ((_, weight), run) in parses.into_iter().zip(runs) {
+                    assert!((weight.ln() - run.0.weight.ln()).abs() < 1e-9);
+                }
+            }
+        }
+    }
+}
+
+/// Checks that the best derivations of the grammar constructed from `automaton` have the same
+/// weights as the best runs of `automaton`.
+fn assert_grammar_agrees<A>(automaton: PushDownAutomaton<A, String, LogDomain<f64>>, words: &[&str])
+where
+    A: Clone + std::fmt::Debug + std::hash::Hash + Ord,
+{
+    // the grammar is left-recursive, hence its derivations are enumerated
+    // with the tabular recogniser of its pushdown automaton
+    let grammar = TabularRecogniser::new(PushDownAutomaton::from(CFG::from(automaton.clone())));
+    for input in words {
+        let word: Vec<_> = input.split_whitespace().map(|x| x.to_string()).collect();
+        let runs: Vec<_> = automaton.recognise(word.clone()).take(5).collect();
+        let derivations: Vec<_> = grammar.recognise(word).take(5).collect();
+
+        assert_eq!(runs.len(), derivations.len(), "different numbers of parses for {:?}", input);
+        for (run, derivation) in runs.into_iter().zip(derivations) {
+            assert!((run.0.weight.ln() - derivation.0.weight.ln()).abs() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn test_cfg_from_pushdown_automaton() {
+    let mut automaton_file = File::open("examples/example.pda").unwrap();
+    let mut automaton_string = String::new();
+    let _ = automaton_file.read_to_string(&mut automaton_string);
+    let automaton: PushDownAutomaton<String, String, LogDomain<f64>> = automaton_string.parse().unwrap();
+    assert_grammar_agrees(automaton, &["", "a b", "a a b b", "a a b", "b a"]);
+
+    assert_grammar_agrees(
+        PushDownAutomaton::from(cfg_from_file("examples/example3.cfg")),
+        &["John loves Mary", "Mary loves Mary", "loves John"],
+    );
+
+    let words = ["a a b", "b b a", "a a a b b", "a a b b a", "a a a", "b b b", "a b a a a"];
+    let automaton = PushDownAutomaton::from(cfg_from_file("examples/example2.cfg"));
+    let rel = example_equivalence_relation();
+    let mapping = |ps: &PushState<_, _>| ps.map(|nt| rel.project(nt));
+    let rlb = RlbElement::new(&mapping);
+    let (relabelled_automaton, _) = rlb.approximate_automaton(&automaton);
+    assert_grammar_agrees(automaton, &words);
+    assert_grammar_agrees(relabelled_automaton, &words);
+}
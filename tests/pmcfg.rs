@@ -8,15 +8,27 @@ use std::fs::File;
 use std::io::Read;
 use std::rc::Rc;
 
-use rustomata::approximation::ApproximationStrategy;
+use rustomata::approximation::{ApproximationStrategy, SubsetApproximation};
 use rustomata::approximation::equivalence_classes::EquivalenceRelation;
+use rustomata::approximation::ptk::PDTopKElement;
 use rustomata::approximation::relabel::RlbElement;
 use rustomata::approximation::tts::TTSElement;
+use rustomata::approximation::tsu::TSUElement;
+use rustomata::grammars::lcfrs::Lcfrs;
+use rustomata::grammars::lcfrs::csparsing::CSRepresentation;
+use rustomata::grammars::mcfg::Mcfg;
+use rustomata::grammars::mg::{self, MGNode, MG};
 use rustomata::grammars::pmcfg::*;
 use rustomata::grammars::pmcfg::negra::{to_negra, DumpMode};
+use rustomata::grammars::tag::{TAG, TAGNode};
 use rustomata::recognisable::*;
+use rustomata::recognisable::automaton::Automaton;
 use rustomata::recognisable::coarse_to_fine::CoarseToFineRecogniser;
+use rustomata::automata::push_down_automaton::TabularRecogniser;
+use rustomata::automata::tree_automaton::{TreeAutomaton, TreeTransition};
+use rustomata::automata::tree_stack_automaton;
 use rustomata::automata::tree_stack_automaton::*;
+use rustomata::util::tree::GornTree;
 
 fn pmcfg_from_file(grammar_file_path: &str) -> PMCFG<String, String, LogDomain<f64>> {
     let mut grammar_file = File::open(grammar_file_path).unwrap();
@@ -84,6 +96,21 @@ fn test_coarse_to_fine_recogniser_correctness() {
     }
 }
 
+#[test]
+fn test_coarse_to_fine_recogniser_with_ptk() {
+    let automaton = TreeStackAutomaton::from(pmcfg_from_file("examples/example.pmcfg"));
+    let recogniser = coarse_to_fine_recogniser!(automaton.clone(); TTSElement::new(), PDTopKElement::new(8));
+
+    let inputs = vec!["abcd", "aabccd", "aaabcccd"];
+
+    for input in inputs {
+        let word: Vec<_> = String::from(input).chars().map(|x| x.to_string()).collect();
+        let run = recogniser.recognise(word.clone()).next();
+        assert!(run.is_some());
+        assert_eq!(automaton.recognise(word).next(), run);
+    }
+}
+
 #[test]
 fn test_tts_correctness() {
     let automaton = TreeStackAutomaton::from(pmcfg_from_file("examples/example.pmcfg"));
@@ -109,6 +136,262 @@ fn test_tts_correctness() {
     }
 }
 
+#[test]
+fn test_tabular_tts_recogniser() {
+    let automaton = TreeStackAutomaton::from(pmcfg_from_file("examples/example.pmcfg"));
+    let (tts_ed_automaton, tts_instance) = TTSElement::new().approximate_automaton(&automaton);
+    let tabular = TabularRecogniser::new(tts_ed_automaton.clone());
+
+    let inputs = vec!["", "abcd", "aabccd", "aaabcccd", "aabcd", "aabbcd", "abbcccdddd", "abce"];
+
+    for input in inputs.clone() {
+        let word: Vec<_> = String::from(input).chars().map(|x| x.to_string()).collect();
+        assert_eq!(
+            tts_ed_automaton.recognise(word.clone()).next(),
+            tabular.recognise(word).next()
+        );
+    }
+
+    // the tabular recogniser can be the coarsest level of a coarse-to-fine recogniser
+    let recogniser = CoarseToFineRecogniser {
+        recogniser: Rc::new(automaton.clone()),
+        sublevel: Rc::new(tabular),
+        approximation_instance: Rc::new(tts_instance),
+    };
+    for input in inputs {
+        let word: Vec<_> = String::from(input).chars().map(|x| x.to_string()).collect();
+        assert_eq!(
+            automaton.recognise(word.clone()).next(),
+            recogniser.recognise(word).next()
+        );
+    }
+}
+
+#[test]
+fn test_tabular_tsa_recogniser() {
+    let automaton = TreeStackAutomaton::from(pmcfg_from_file("examples/example.pmcfg"));
+    let tabular = tree_stack_automaton::TabularRecogniser::new(automaton.clone()).unwrap();
+
+    let inputs = vec!["", "abcd", "aabccd", "aaabcccd", "aabcd", "aabbcd", "abbcccdddd", "abce"];
+
+    for input in inputs {
+        let word: Vec<_> = String::from(input).chars().map(|x| x.to_string()).collect();
+        let expected: Vec<_> = automaton.recognise(word.clone()).collect();
+        let found: Vec<_> = tabular.recognise(word).collect();
+
+        assert_eq!(expected.len(), found.len());
+        for (e, f) in expected.iter().zip(&found) {
+            assert_eq!(e.0.storage, f.0.storage);
+            assert!((e.0.weight.ln() - f.0.weight.ln()).abs() < 1e-9);
+        }
+        let runs: HashSet<_> = found.into_iter().map(|i| i.1.to_vec()).collect();
+        assert!(expected.into_iter().all(|i| runs.contains(&i.1.to_vec())));
+    }
+
+    let mut automaton_file = File::open("examples/example.tsa").unwrap();
+    let mut automaton_string = String::new();
+    let _ = automaton_file.read_to_string(&mut automaton_string);
+    let automaton: TreeStackAutomaton<String, String, LogDomain<f64>> = automaton_string.parse().unwrap();
+    let tabular = tree_stack_automaton::TabularRecogniser::new(automaton.clone()).unwrap();
+
+    for input in vec!["", "abcd", "aabbccdd", "aabbcd"] {
+        let word: Vec<_> = String::from(input).chars().map(|x| x.to_string()).collect();
+        let expected: Vec<_> = automaton.recognise(word.clone()).collect();
+        let found: Vec<_> = tabular.recognise(word).collect();
+        assert_eq!(expected, found);
+    }
+}
+
+/// Checks that the automaton constructed from the grammar of `automaton` has runs with the same
+/// weights as `automaton`.
+fn assert_round_trip<A>(automaton: TreeStackAutomaton<A, String, LogDomain<f64>>, inputs: &[&str])
+where
+    A: Clone + std::fmt::Debug + std::hash::Hash + Ord,
+{
+    let grammar = PMCFG::from_tree_stack_automaton(&automaton).unwrap();
+    let round_trip = TreeStackAutomaton::from(grammar);
+
+    for input in inputs {
+        let word: Vec<_> = String::from(*input).chars().map(|x| x.to_string()).collect();
+        let expected: Vec<_> = automaton.recognise(word.clone()).map(|i| i.0.weight).collect();
+        let found: Vec<_> = round_trip.recognise(word).map(|i| i.0.weight).collect();
+
+        assert_eq!(expected.len(), found.len());
+        for (e, f) in expected.iter().zip(&found) {
+            assert!((e.ln() - f.ln()).abs() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn test_pmcfg_from_tree_stack_automaton() {
+    let automaton = TreeStackAutomaton::from(pmcfg_from_file("examples/example.pmcfg"));
+    assert_round_trip(automaton, &["", "abcd", "aabccd", "aaabcccd", "aabcd", "aabbcd", "abce"]);
+
+    let mut automaton_file = File::open("examples/example.tsa").unwrap();
+    let mut automaton_string = String::new();
+    let _ = automaton_file.read_to_string(&mut automaton_string);
+    let automaton: TreeStackAutomaton<String, String, LogDomain<f64>> = automaton_string.parse().unwrap();
+    assert_round_trip(automaton, &["", "abcd", "aabbccdd", "aabbcd", "abcdd"]);
+}
+
+#[test]
+fn test_tag_parsing() {
+    let mut grammar_file = File::open("examples/example.tag").unwrap();
+    let mut grammar_string = String::new();
+    let _ = grammar_file.read_to_string(&mut grammar_string);
+    let grammar: TAG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+
+    let cs = CSRepresentation::new(Lcfrs::from(grammar.clone()), 0).unwrap();
+    let automaton = TreeStackAutomaton::from(PMCFG::from(Mcfg::from(Lcfrs::from(grammar.clone()))));
+
+    for sentence in &["the big dog often sleeps", "John sees the dog with John"] {
+        let word: Vec<String> = sentence.split_whitespace().map(|x| x.to_string()).collect();
+
+        let derivation = cs.build_generator().with_fallback(&word).0.next().unwrap();
+        let tree = grammar.derived_tree(&derivation).unwrap();
+        let terminals: Vec<String> = tree.values()
+            .filter_map(|node| match *node {
+                TAGNode::Terminal(ref terminal) => Some(terminal.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(word, terminals);
+
+        let parse = automaton.recognise(word).next().unwrap();
+        let derivation = tree_stack_automaton::to_abstract_syntax_tree(parse.0.storage.to_tree());
+        assert_eq!(tree, grammar.derived_tree(&derivation).unwrap());
+    }
+}
+
+#[test]
+fn test_mg_parsing() {
+    let mut grammar_file = File::open("examples/example.mg").unwrap();
+    let mut grammar_string = String::new();
+    let _ = grammar_file.read_to_string(&mut grammar_string);
+    let grammar: MG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+
+    let cs = CSRepresentation::new(Lcfrs::from(Mcfg::from(grammar.clone())), 0).unwrap();
+    let automaton = TreeStackAutomaton::from(PMCFG::from(Mcfg::from(grammar)));
+
+    for (sentence, moves) in vec![("John sees Mary", 0), ("who sleeps", 1), ("who John sees", 1)] {
+        let word: Vec<String> = sentence.split_whitespace().map(|x| x.to_string()).collect();
+
+        let parse = automaton.recognise(word.clone()).next().unwrap();
+        let derivation = tree_stack_automaton::to_abstract_syntax_tree(parse.0.storage.to_tree());
+        let tree = mg::to_derivation_tree(&derivation).unwrap();
+
+        let mut words: Vec<String> = tree.values()
+            .flat_map(|node| match *node {
+                MGNode::Item(ref item_word, _) => item_word.clone(),
+                _ => Vec::new(),
+            })
+            .collect();
+        let mut expected = word.clone();
+        words.sort();
+        expected.sort();
+        assert_eq!(expected, words);
+        assert_eq!(moves, tree.values().filter(|&node| *node == MGNode::Move).count());
+
+        let derivation = cs.build_generator().with_fallback(&word).0.next().unwrap();
+        assert_eq!(tree, mg::to_derivation_tree(&derivation).unwrap());
+    }
+
+    for sentence in &["John sleeps Mary", "Mary who sees"] {
+        let word: Vec<String> = sentence.split_whitespace().map(|x| x.to_string()).collect();
+        assert!(automaton.recognise(word).next().is_none());
+    }
+}
+
+#[test]
+fn test_synchronous_translation() {
+    let mut grammar_file = File::open("examples/example.spmcfg").unwrap();
+    let mut grammar_string = String::new();
+    let _ = grammar_file.read_to_string(&mut grammar_string);
+    let grammar: SynchronousPMCFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+
+    let automaton = TreeStackAutomaton::from(grammar.source());
+    let word: Vec<String> = "I know that John sees Mary".split_whitespace().map(|x| x.to_string()).collect();
+    let derivations = automaton
+        .recognise(word)
+        .take(2)
+        .map(|parse| tree_stack_automaton::to_abstract_syntax_tree(parse.0.storage.to_tree()));
+    let translations = grammar.translate_all(derivations, 3).unwrap();
+
+    let expected = vec![
+        ("ich weiß dass John Mary sieht", 0.175),
+        ("ich weiß dass John Mary erblickt", 0.075),
+    ];
+    assert_eq!(expected.len(), translations.len());
+    for (&(e, e_weight), &(ref f, f_weight)) in expected.iter().zip(&translations) {
+        assert_eq!(e, f.join(" "));
+        assert!((e_weight - f_weight.value()).abs() < 1e-9);
+    }
+}
+
+/// Checks that every run of the approximated automaton is mapped to a valid run of `automaton`.
+fn assert_subset_approximation<Strategy>(
+    automaton: &TreeStackAutomaton<PosState<PMCFGRule<String, String, LogDomain<f64>>>, String, LogDomain<f64>>,
+    strategy: Strategy,
+    inputs: &[&str],
+) where
+    Strategy: SubsetApproximation<
+        String,
+        LogDomain<f64>,
+        I1 = TreeStackInstruction<PosState<PMCFGRule<String, String, LogDomain<f64>>>>,
+        A1 = TreeStackAutomaton<PosState<PMCFGRule<String, String, LogDomain<f64>>>, String, LogDomain<f64>>,
+    >,
+    Strategy::A2: Recognisable<
+        String,
+        LogDomain<f64>,
+        Parse = Item<<Strategy::I2 as Instruction>::Storage, Strategy::I2, String, LogDomain<f64>>,
+    >,
+{
+    let (approximated_automaton, instance) = strategy.approximate_automaton(automaton);
+
+    for input in inputs {
+        let word: Vec<_> = input.split_whitespace().map(|x| x.to_string()).collect();
+        for Item(_, run2) in approximated_automaton.recognise(word).take(10) {
+            let valid = instance.unapproximate_run(run2).into_iter().any(|run1| {
+                automaton
+                    .check_run(run1)
+                    .into_iter()
+                    .any(|Item(c, _)| c.storage.is_at_bottom())
+            });
+            assert!(valid, "invalid run for {:?}", input);
+        }
+    }
+}
+
+#[test]
+fn test_tsu_subset_approximation() {
+    let samples = vec![
+        (
+            "examples/example.pmcfg",
+            vec!["", "a b c d", "a a b c c d", "a a b b c c d d", "a a a b c c c d", "a a b c d"],
+        ),
+        ("examples/example2.pmcfg", vec!["What should I do ?", "What I should do ?"]),
+    ];
+
+    for (grammar_file_path, inputs) in samples {
+        let automaton = TreeStackAutomaton::from(pmcfg_from_file(grammar_file_path));
+
+        for depth in 0..5 {
+            assert_subset_approximation(&automaton, TSUElement::new(depth), &inputs);
+        }
+
+        // runs whose derivations are shallow enough are preserved
+        let (tsu_ed_automaton, _) = TSUElement::new(4).approximate_automaton(&automaton);
+        for input in inputs {
+            let word: Vec<_> = input.split_whitespace().map(|x| x.to_string()).collect();
+            assert_eq!(
+                automaton.recognise(word.clone()).next().is_some(),
+                tsu_ed_automaton.recognise(word).next().is_some()
+            );
+        }
+    }
+}
+
 #[test]
 fn test_pmcfg_from_str_correctness() {
     let rule_s0 = PMCFGRule {
@@ -564,3 +847,159 @@ fn test_pmcfg_recognise_illegal_terminal_symbols() {
         assert!(automaton.recognise(illegal_word).next().is_none());
     }
 }
+
+#[test]
+fn test_pmcfg_recognise_with_budget() {
+    use rustomata::util::budget::{Budget, BudgetTracker, Exhaustion};
+
+    let automaton = TreeStackAutomaton::from(pmcfg_from_file("examples/example.pmcfg"));
+    let word: Vec<_> = String::from("aabccd")
+        .chars()
+        .map(|x| x.to_string())
+        .collect();
+
+    let unlimited = BudgetTracker::unlimited();
+    assert_eq!(
+        automaton.recognise(word.clone()).next(),
+        automaton.recognise_with_budget(None, unlimited.clone(), word.clone()).next()
+    );
+    assert_eq!(
+        automaton.recognise_beam_search(10, word.clone()).next(),
+        automaton.recognise_with_budget(Some(10), unlimited.clone(), word.clone()).next()
+    );
+    assert_eq!(None, unlimited.exhausted());
+
+    let tracker = BudgetTracker::new(Budget { pops: Some(3), ..Budget::default() });
+    assert!(automaton.recognise_with_budget(None, tracker.clone(), word.clone()).next().is_none());
+    assert_eq!(Some(Exhaustion::Pops), tracker.exhausted());
+
+    // the budget is also spent by the coarsest recogniser of a coarse-to-fine recogniser
+    let ctf = coarse_to_fine_recogniser!(automaton; TTSElement::new());
+    let tracker = BudgetTracker::new(Budget { pops: Some(3), ..Budget::default() });
+    assert!(ctf.recognise_with_budget(None, tracker.clone(), word.clone()).next().is_none());
+    assert_eq!(Some(Exhaustion::Pops), tracker.exhausted());
+}
+
+fn relative_clause_grammar() -> PMCFG<String, String, LogDomain<f64>> {
+    "initial: [S]\n\
+     S → [[Var 0 0, Var 1 0]] (NP, VP)\n\
+     NP → [[T dogs]] () # 0.6\n\
+     NP → [[T dogs, T that, Var 0 0]] (VP) # 0.4\n\
+     VP → [[T bark]] () # 0.5\n\
+     VP → [[T see, Var 0 0]] (NP) # 0.5"
+        .parse()
+        .unwrap()
+}
+
+/// A tree automaton for the derivations of `grammar` in which relative clauses are nested at most
+/// `limit` times; its states are the depths of nesting.
+fn nesting_automaton(
+    grammar: &PMCFG<String, String, LogDomain<f64>>,
+    limit: usize,
+) -> TreeAutomaton<usize, PMCFGRule<String, String, LogDomain<f64>>, LogDomain<f64>> {
+    let mut transitions = Vec::new();
+    for rule in &grammar.rules {
+        let relative_clause = VarT::T("that".to_string());
+        let increment = if rule.composition.composition[0].contains(&relative_clause) { 1 } else { 0 };
+
+        let mut sources = vec![Vec::new()];
+        for _ in &rule.tail {
+            sources = sources
+                .into_iter()
+                .flat_map(|source_states: Vec<usize>| {
+                    (0..limit + 1).map(move |q| {
+                        let mut source_states = source_states.clone();
+                        source_states.push(q);
+                        source_states
+                    })
+                })
+                .collect();
+        }
+
+        for source_states in sources {
+            let target_state = source_states.iter().cloned().max().unwrap_or(0) + increment;
+            if target_state <= limit {
+                transitions.push(TreeTransition {
+                    symbol: rule.clone(),
+                    source_states,
+                    target_state,
+                    weight: LogDomain::new(1.0).unwrap(),
+                });
+            }
+        }
+    }
+
+    TreeAutomaton::new(transitions, 0..limit + 1)
+}
+
+#[test]
+fn test_tree_automaton_constrained_parsing() {
+    let grammar = relative_clause_grammar();
+
+    let inputs = vec![
+        ("dogs bark", 0, Some(0.3)),
+        ("dogs that bark bark", 0, None),
+        ("dogs that bark bark", 1, Some(0.1)),
+        ("dogs see dogs that bark", 1, Some(0.06)),
+        ("dogs that see dogs that bark bark", 1, None),
+        ("dogs that see dogs that bark bark", 2, Some(0.02)),
+    ];
+
+    // every depth of nesting up to the limit is a final state, so the LCFRS needs a fresh initial
+    // nonterminal
+    let lcfrs = Lcfrs::new(grammar.rules.clone(), "S".to_string()).unwrap();
+
+    for (input, limit, control_weight) in inputs {
+        let nesting = nesting_automaton(&grammar, limit);
+        let word: Vec<_> = input.split_whitespace().map(|x| x.to_string()).collect();
+
+        let automaton = TreeStackAutomaton::from(grammar.intersect(&nesting));
+        let pmcfg_weight = automaton.recognise(word.clone()).next().map(|parse| (parse.0).weight.value());
+        let automaton = TreeStackAutomaton::from(PMCFG::from(Mcfg::from(lcfrs.intersect(&nesting).unwrap())));
+        let lcfrs_weight = automaton.recognise(word).next().map(|parse| (parse.0).weight.value());
+
+        for weight in vec![pmcfg_weight, lcfrs_weight] {
+            match (control_weight, weight) {
+                (Some(control_weight), Some(weight)) => assert!((control_weight - weight).abs() < 1e-9),
+                (None, None) => (),
+                _ => panic!("{} was parsed with {:?} for the limit {}", input, weight, limit),
+            }
+        }
+    }
+}
+
+/// A tree automaton that accepts exactly `tree`; its states are the addresses of `tree`.
+fn skeleton_automaton<A>(tree: &GornTree<A>) -> TreeAutomaton<Vec<usize>, A, LogDomain<f64>>
+where
+    A: Clone + Eq + std::hash::Hash,
+{
+    let transitions = tree.into_iter().map(|(address, symbol)| {
+        TreeTransition {
+            symbol: symbol.clone(),
+            source_states: tree.children(address),
+            target_state: address.clone(),
+            weight: LogDomain::new(1.0).unwrap(),
+        }
+    });
+    TreeAutomaton::new(transitions, vec![Vec::new()])
+}
+
+#[test]
+fn test_lcfrs_intersect_with_skeleton() {
+    let grammar = relative_clause_grammar();
+    let automaton = TreeStackAutomaton::from(grammar.clone());
+    let word: Vec<_> = "dogs see dogs that bark".split_whitespace().map(|x| x.to_string()).collect();
+    let gold = to_abstract_syntax_tree(automaton.recognise(word.clone()).next().unwrap().0.storage.to_tree());
+
+    let lcfrs = Lcfrs::new(grammar.rules, "S".to_string()).unwrap();
+    let intersection = lcfrs.intersect(&skeleton_automaton(&gold)).unwrap();
+    let automaton = TreeStackAutomaton::from(PMCFG::from(Mcfg::from(intersection)));
+
+    let parses: Vec<_> = automaton.recognise(word).collect();
+    assert_eq!(1, parses.len());
+    let derivation = to_abstract_syntax_tree(parses[0].0.storage.to_tree());
+    assert_eq!(gold, derivation.map_values(|rule| rule.map_nonterminals(|&(ref n, _)| n.clone())));
+
+    let other: Vec<_> = "dogs bark".split_whitespace().map(|x| x.to_string()).collect();
+    assert!(automaton.recognise(other).next().is_none());
+}
@@ -0,0 +1,49 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs a session of `repl` on the given file with the given input and returns its output.
+fn session(file: &str, input: &str) -> String {
+    let mut repl = Command::new(env!("CARGO_BIN_EXE_rustomata"))
+        .args(&["repl", file])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    repl.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = repl.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_repl_settings() {
+    let output = session(
+        "examples/example.pmcfg",
+        ":k 2\n:beam 10\n:candidates 5\n:candidates off\n:beam x\n:k\n:settings\n:states\n",
+    );
+    assert!(output.contains("usage: :beam <n>|off"));
+    assert!(output.contains("usage: :k <n>"));
+    assert!(output.contains("k: 2, beam: 10, candidates: off, states: off"));
+    assert!(output.contains("states: on"));
+}
+
+#[test]
+fn test_repl_rules() {
+    let output = session("examples/example.pmcfg", ":rules\n:rules A\n:rules C\n:frobnicate\n");
+    assert!(output.contains("A\t2 rule(s)"));
+    assert!(output.contains("B\t2 rule(s)"));
+    assert!(output.contains("S\t1 rule(s)"));
+    assert_eq!(2, output.matches("\"A\" →").count());
+    assert!(output.contains("there are no rules for C"));
+    assert!(output.contains("unknown command :frobnicate"));
+}
+
+#[test]
+fn test_repl_parse() {
+    let output = session("examples/example.pmcfg", "a b c d\n:quit\na b c\n");
+    assert_eq!(1, output.matches("weight").count());
+    assert!(!output.contains("no parse"));
+
+    let output = session("examples/example.pmcfg", ":candidates 0\na b c d\n");
+    assert!(output.contains("no parse (candidate limit exceeded)"));
+}
@@ -0,0 +1,66 @@
+extern crate serde_json;
+
+use serde_json::Value;
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+/// Extracts the CS representation of `examples/example2.pmcfg` into a temporary file.
+fn extract_example(file_name: &str) -> String {
+    let path = env::temp_dir().join(file_name);
+    let output = Command::new(env!("CARGO_BIN_EXE_rustomata"))
+        .args(&["csparsing", "extract", "examples/example2.pmcfg"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    File::create(&path).unwrap().write_all(&output.stdout).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_serve_parse_and_reload() {
+    let path = extract_example("rustomata_test_serve.cs");
+    let mut server = Command::new(env!("CARGO_BIN_EXE_rustomata"))
+        .args(&["csparsing", "serve", &format!("ex={}", path)])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut requests = server.stdin.take().unwrap();
+    let mut responses = BufReader::new(server.stdout.take().unwrap()).lines();
+    let mut request = move |line: &str| -> Value {
+        writeln!(requests, "{}", line).unwrap();
+        requests.flush().unwrap();
+        serde_json::from_str(&responses.next().unwrap().unwrap()).unwrap()
+    };
+
+    let parse = r#"{"grammar": "ex", "sentence": "What should I do ?", "id": 7, "k": 2}"#;
+    let response = request(parse);
+    assert_eq!(response["id"], 7);
+    assert_eq!(response["parses"].as_array().unwrap().len(), 1);
+    assert_eq!(response["fallback"], false);
+
+    let response = request(r#"{"grammar": "ex", "sentence": "What I should do ?", "candidates": 100}"#);
+    assert!(response["parses"].as_array().unwrap().is_empty());
+
+    assert_eq!(request(r#"{"command": "grammars"}"#)["grammars"], json_names(&["ex"]));
+    assert_eq!(request(r#"{"command": "reload", "grammar": "ex"}"#)["reloaded"], "ex");
+    assert_eq!(request(parse)["parses"].as_array().unwrap().len(), 1);
+
+    // the old grammar is kept if the file cannot be read anymore
+    fs::remove_file(&path).unwrap();
+    assert!(request(r#"{"command": "reload", "grammar": "ex"}"#)["error"].is_string());
+    assert_eq!(request(parse)["parses"].as_array().unwrap().len(), 1);
+
+    assert!(request(r#"{"grammar": "unknown", "sentence": "a"}"#)["error"].is_string());
+    assert!(request("not json")["error"].is_string());
+
+    // closes the input of the server
+    drop(request);
+    assert!(server.wait().unwrap().success());
+}
+
+fn json_names(names: &[&str]) -> Value {
+    Value::Array(names.iter().map(|name| Value::String(name.to_string())).collect())
+}